@@ -0,0 +1,3 @@
+-- Replace the user_id index with one matching the keyset pagination order
+DROP INDEX idx_transactions_user_id;
+CREATE INDEX idx_transactions_user_id_created_at_id ON transactions(user_id, created_at DESC, id DESC);
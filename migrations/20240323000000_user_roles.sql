@@ -0,0 +1,5 @@
+-- Create user_role enum
+CREATE TYPE user_role AS ENUM ('user', 'admin');
+
+-- Add role to users; admins are promoted manually by operators
+ALTER TABLE users ADD COLUMN role user_role NOT NULL DEFAULT 'user';
@@ -0,0 +1,31 @@
+-- Create adjustment_reason enum, the controlled vocabulary for manual adjustments
+CREATE TYPE adjustment_reason AS ENUM (
+    'goodwill_credit',
+    'fee_refund',
+    'chargeback',
+    'duplicate_correction',
+    'fraud_recovery',
+    'system_error'
+);
+
+-- Create adjustment_status enum
+CREATE TYPE adjustment_status AS ENUM ('pending_approval', 'posted', 'rejected');
+
+-- Create adjustments table; each row is the audit record of a support-initiated adjustment
+CREATE TABLE adjustments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id),
+    amount DECIMAL(19,4) NOT NULL CHECK (amount > 0),
+    transaction_type transaction_type NOT NULL,
+    reason_code adjustment_reason NOT NULL,
+    note TEXT NOT NULL,
+    status adjustment_status NOT NULL,
+    requested_by UUID NOT NULL REFERENCES users(id),
+    decided_by UUID REFERENCES users(id),
+    transaction_id UUID REFERENCES transactions(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    decided_at TIMESTAMPTZ
+);
+
+-- Create index on user_id for listing a user's adjustments
+CREATE INDEX idx_adjustments_user_id ON adjustments(user_id);
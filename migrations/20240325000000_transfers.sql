@@ -0,0 +1,16 @@
+-- Create transfers table
+CREATE TABLE transfers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    from_user_id UUID NOT NULL REFERENCES users(id),
+    to_user_id UUID NOT NULL REFERENCES users(id),
+    amount DECIMAL(19,4) NOT NULL CHECK (amount > 0),
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (from_user_id <> to_user_id)
+);
+
+-- Link both legs of a transfer in the ledger
+ALTER TABLE transactions ADD COLUMN transfer_id UUID REFERENCES transfers(id);
+
+-- Create index on transfer_id for looking up the legs of a transfer
+CREATE INDEX idx_transactions_transfer_id ON transactions(transfer_id);
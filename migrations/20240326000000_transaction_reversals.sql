@@ -0,0 +1,9 @@
+-- Create transaction status enum
+CREATE TYPE transaction_status AS ENUM ('posted', 'reversed');
+
+-- Track reversals: the compensating entry points at the original via `reverses`,
+-- and the original points back via `reversed_by`
+ALTER TABLE transactions
+    ADD COLUMN status transaction_status NOT NULL DEFAULT 'posted',
+    ADD COLUMN reverses UUID UNIQUE REFERENCES transactions(id),
+    ADD COLUMN reversed_by UUID REFERENCES transactions(id);
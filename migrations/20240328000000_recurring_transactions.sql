@@ -0,0 +1,31 @@
+-- Create recurrence frequency enum, the FREQ part of an RRULE-like schedule
+CREATE TYPE recurrence_frequency AS ENUM ('daily', 'weekly', 'monthly', 'yearly');
+
+-- Create recurring transaction status enum
+CREATE TYPE recurring_status AS ENUM ('active', 'paused', 'completed');
+
+-- Create recurring_transactions table; the scheduler posts an occurrence whenever next_run_at is due
+CREATE TABLE recurring_transactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id),
+    amount DECIMAL(19,4) NOT NULL CHECK (amount > 0),
+    currency CHAR(3) NOT NULL CHECK (currency ~ '^[A-Z]{3}$'),
+    transaction_type transaction_type NOT NULL,
+    description TEXT,
+    frequency recurrence_frequency NOT NULL,
+    interval_count INTEGER NOT NULL DEFAULT 1 CHECK (interval_count > 0),
+    remaining_occurrences INTEGER CHECK (remaining_occurrences >= 0),
+    until TIMESTAMPTZ,
+    next_run_at TIMESTAMPTZ NOT NULL,
+    status recurring_status NOT NULL DEFAULT 'active',
+    last_run_at TIMESTAMPTZ,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create index on user_id for listing a user's schedules
+CREATE INDEX idx_recurring_transactions_user_id ON recurring_transactions(user_id);
+
+-- Create partial index for the scheduler's due-occurrence scan
+CREATE INDEX idx_recurring_transactions_due ON recurring_transactions(next_run_at) WHERE status = 'active';
@@ -0,0 +1,10 @@
+-- Add lifecycle states for future-dated transactions
+ALTER TYPE transaction_status ADD VALUE 'scheduled';
+ALTER TYPE transaction_status ADD VALUE 'cancelled';
+ALTER TYPE transaction_status ADD VALUE 'failed';
+
+-- When a scheduled transaction is due to be posted
+ALTER TABLE transactions ADD COLUMN execute_at TIMESTAMPTZ;
+
+-- Create partial index for the scheduler's due-transaction scan
+CREATE INDEX idx_transactions_execute_at ON transactions(execute_at) WHERE execute_at IS NOT NULL;
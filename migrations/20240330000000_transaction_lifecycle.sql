@@ -0,0 +1,5 @@
+-- Posted transactions are now called settled, to contrast with pending ones
+ALTER TYPE transaction_status RENAME VALUE 'posted' TO 'settled';
+
+-- Add the pending state for transactions awaiting settlement
+ALTER TYPE transaction_status ADD VALUE 'pending';
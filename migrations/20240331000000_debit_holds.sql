@@ -0,0 +1,27 @@
+-- Add lifecycle states for debits queued for review by a hold
+ALTER TYPE transaction_status ADD VALUE 'held';
+ALTER TYPE transaction_status ADD VALUE 'denied';
+
+-- Create debit_holds table; while a hold is active, debits matching every criterion it sets are
+-- queued for review instead of posting
+CREATE TABLE debit_holds (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    counterparty_id UUID REFERENCES users(id),
+    description_pattern TEXT,
+    reason TEXT NOT NULL,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    lifted_at TIMESTAMPTZ,
+    CHECK (counterparty_id IS NOT NULL OR description_pattern IS NOT NULL)
+);
+
+-- Create held_debits table, the review queue linking each held debit to the hold that caught it
+CREATE TABLE held_debits (
+    transaction_id UUID PRIMARY KEY REFERENCES transactions(id),
+    hold_id UUID NOT NULL REFERENCES debit_holds(id),
+    decided_by UUID REFERENCES users(id),
+    decided_at TIMESTAMPTZ
+);
+
+-- Create partial index for the review queue
+CREATE INDEX idx_held_debits_undecided ON held_debits(hold_id) WHERE decided_at IS NULL;
@@ -0,0 +1,12 @@
+-- Create fx_rates table; every rate fetched from the FX provider is kept so past conversions can be explained
+CREATE TABLE fx_rates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    base_currency CHAR(3) NOT NULL CHECK (base_currency ~ '^[A-Z]{3}$'),
+    quote_currency CHAR(3) NOT NULL CHECK (quote_currency ~ '^[A-Z]{3}$'),
+    rate NUMERIC NOT NULL CHECK (rate > 0),
+    source TEXT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create index for looking up a currency pair's rates over time
+CREATE INDEX idx_fx_rates_pair_fetched_at ON fx_rates(base_currency, quote_currency, fetched_at DESC);
@@ -0,0 +1,25 @@
+-- Create notification mode enum; digested events are batched into one webhook delivery per period
+CREATE TYPE notification_mode AS ENUM ('instant', 'hourly', 'daily');
+
+-- Create notification_preferences table; event types without a row are delivered instantly
+CREATE TABLE notification_preferences (
+    user_id UUID NOT NULL REFERENCES users(id),
+    event_type TEXT NOT NULL,
+    mode notification_mode NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, event_type)
+);
+
+-- Create digest_events table; events held back until the digest period they fall in ends
+CREATE TABLE digest_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id),
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create index on period_end for finding digests that are due
+CREATE INDEX idx_digest_events_period_end ON digest_events(period_end);
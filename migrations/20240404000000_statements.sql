@@ -0,0 +1,9 @@
+-- Create statements table; the rendered PDF for each user and calendar month (UTC), kept as issued
+CREATE TABLE statements (
+    user_id UUID NOT NULL REFERENCES users(id),
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL CHECK (month BETWEEN 1 AND 12),
+    pdf BYTEA NOT NULL,
+    generated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, year, month)
+);
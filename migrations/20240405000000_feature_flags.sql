@@ -0,0 +1,8 @@
+-- Create feature_flags table; flags without a row use their default, and kill switches default to enabled
+CREATE TABLE feature_flags (
+    name TEXT PRIMARY KEY,
+    enabled BOOLEAN NOT NULL,
+    reason TEXT,
+    updated_by UUID NOT NULL REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,5 @@
+-- Add integrator-defined attributes to users; always a JSON object
+ALTER TABLE users ADD COLUMN metadata JSONB NOT NULL DEFAULT '{}' CHECK (jsonb_typeof(metadata) = 'object');
+
+-- Create index for admin searches by metadata containment
+CREATE INDEX idx_users_metadata ON users USING GIN (metadata jsonb_path_ops);
@@ -0,0 +1,7 @@
+-- Count data-heavy requests (statement downloads, CSV imports) per user per UTC day, for the daily limit
+CREATE TABLE data_export_usage (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (user_id, day)
+);
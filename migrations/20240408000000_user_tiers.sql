@@ -0,0 +1,5 @@
+-- Create user_tier enum; each tier unlocks higher usage limits
+CREATE TYPE user_tier AS ENUM ('free', 'plus', 'business');
+
+-- Add tier to users; changed by admins
+ALTER TABLE users ADD COLUMN tier user_tier NOT NULL DEFAULT 'free';
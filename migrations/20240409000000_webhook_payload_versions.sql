@@ -0,0 +1,5 @@
+-- Create webhook_payload_version enum; each endpoint receives events in the version it subscribed with
+CREATE TYPE webhook_payload_version AS ENUM ('v1', 'v2');
+
+-- Add version to webhook_endpoints; existing endpoints keep the payloads they were built against
+ALTER TABLE webhook_endpoints ADD COLUMN version webhook_payload_version NOT NULL DEFAULT 'v1';
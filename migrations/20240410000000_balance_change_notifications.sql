@@ -0,0 +1,17 @@
+-- Announce each user whose ledger changed on the balance_changed channel, so cached balances are
+-- dropped once the change commits
+CREATE FUNCTION notify_balance_changed() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        PERFORM pg_notify('balance_changed', OLD.user_id::text);
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        PERFORM pg_notify('balance_changed', NEW.user_id::text);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_balance_changed
+    AFTER INSERT OR UPDATE OR DELETE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION notify_balance_changed();
@@ -0,0 +1,8 @@
+-- API keys can be created with an expiry, after which they are refused. Owners are warned once
+-- shortly before, and `expiry_warned_at` records that they were.
+ALTER TABLE api_keys ADD COLUMN expires_at TIMESTAMPTZ;
+ALTER TABLE api_keys ADD COLUMN expiry_warned_at TIMESTAMPTZ;
+
+-- Create partial index for the expiry warning sweep
+CREATE INDEX idx_api_keys_unwarned_expiry ON api_keys(expires_at)
+    WHERE expires_at IS NOT NULL AND expiry_warned_at IS NULL AND revoked_at IS NULL;
@@ -0,0 +1,75 @@
+-- Create account_balances, each user's ledger totals per currency, kept current by a trigger on
+-- transactions so reading a balance doesn't sum the user's whole history
+CREATE TABLE account_balances (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    currency CHAR(3) NOT NULL,
+    -- Settled and reversed entries, signed
+    balance DECIMAL(19,4) NOT NULL DEFAULT 0,
+    -- Pending entries, signed
+    pending DECIMAL(19,4) NOT NULL DEFAULT 0,
+    -- Pending debits, held against the available balance
+    held DECIMAL(19,4) NOT NULL DEFAULT 0,
+    -- Entries counted in the totals above; a currency with none isn't shown as a wallet
+    entries INTEGER NOT NULL DEFAULT 0,
+    -- Newest creation time among counted entries
+    last_updated TIMESTAMPTZ,
+    PRIMARY KEY (user_id, currency)
+);
+
+-- Applies one entry's contribution to its balance row, with `sign` -1 to take it back out
+CREATE FUNCTION apply_balance_entry(entry transactions, sign INTEGER) RETURNS void AS $$
+DECLARE
+    signed_amount DECIMAL(19,4) := CASE WHEN entry.transaction_type = 'credit' THEN entry.amount ELSE -entry.amount END;
+BEGIN
+    IF entry.status NOT IN ('settled', 'reversed', 'pending') THEN
+        RETURN;
+    END IF;
+
+    INSERT INTO account_balances AS b (user_id, currency, balance, pending, held, entries, last_updated)
+    VALUES (
+        entry.user_id,
+        entry.currency,
+        CASE WHEN entry.status IN ('settled', 'reversed') THEN sign * signed_amount ELSE 0 END,
+        CASE WHEN entry.status = 'pending' THEN sign * signed_amount ELSE 0 END,
+        CASE WHEN entry.status = 'pending' AND entry.transaction_type = 'debit' THEN sign * entry.amount ELSE 0 END,
+        sign,
+        CASE WHEN sign > 0 THEN entry.created_at END
+    )
+    ON CONFLICT (user_id, currency) DO UPDATE SET
+        balance = b.balance + EXCLUDED.balance,
+        pending = b.pending + EXCLUDED.pending,
+        held = b.held + EXCLUDED.held,
+        entries = b.entries + EXCLUDED.entries,
+        last_updated = GREATEST(b.last_updated, EXCLUDED.last_updated);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION update_account_balances() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        PERFORM apply_balance_entry(OLD, -1);
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        PERFORM apply_balance_entry(NEW, 1);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_account_balances
+    AFTER INSERT OR UPDATE OR DELETE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION update_account_balances();
+
+-- Backfill from the existing ledger
+INSERT INTO account_balances (user_id, currency, balance, pending, held, entries, last_updated)
+SELECT
+    user_id,
+    currency,
+    COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END) FILTER (WHERE status IN ('settled', 'reversed')), 0),
+    COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END) FILTER (WHERE status = 'pending'), 0),
+    COALESCE(SUM(amount) FILTER (WHERE status = 'pending' AND transaction_type = 'debit'), 0),
+    COUNT(*),
+    MAX(created_at)
+FROM transactions
+WHERE status IN ('settled', 'reversed', 'pending')
+GROUP BY user_id, currency;
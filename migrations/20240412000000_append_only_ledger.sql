@@ -0,0 +1,43 @@
+-- Posted entries (settled or reversed) are immutable: their amount, currency, direction and owner
+-- can't change and they can't be deleted. The one change allowed is marking a settled entry
+-- reversed, pointing at the entry that offsets it; every other correction is a new entry, a
+-- reversal or an adjustment. Violations fail with SQLSTATE LD001.
+CREATE FUNCTION protect_posted_transactions() RETURNS trigger AS $$
+BEGIN
+    IF OLD.status NOT IN ('settled', 'reversed') THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        -- Only for removing whole accounts' data on purpose, e.g. test fixtures
+        IF current_setting('dodo.allow_ledger_purge', true) = 'on' THEN
+            RETURN OLD;
+        END IF;
+        RAISE EXCEPTION 'posted transaction % cannot be deleted', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    IF NEW.user_id IS DISTINCT FROM OLD.user_id
+        OR NEW.amount IS DISTINCT FROM OLD.amount
+        OR NEW.currency IS DISTINCT FROM OLD.currency
+        OR NEW.transaction_type IS DISTINCT FROM OLD.transaction_type
+        OR NEW.transfer_id IS DISTINCT FROM OLD.transfer_id
+        OR NEW.reverses IS DISTINCT FROM OLD.reverses
+        OR NEW.created_at IS DISTINCT FROM OLD.created_at
+        OR (NEW.status <> OLD.status AND NOT (OLD.status = 'settled' AND NEW.status = 'reversed'))
+        OR (OLD.reversed_by IS NOT NULL AND NEW.reversed_by IS DISTINCT FROM OLD.reversed_by)
+    THEN
+        RAISE EXCEPTION 'posted transaction % cannot be changed', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_append_only
+    BEFORE UPDATE OR DELETE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION protect_posted_transactions();
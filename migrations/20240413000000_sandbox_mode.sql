@@ -0,0 +1,81 @@
+-- Sandbox API keys work on test data kept apart from the live ledger: entries they create are
+-- stored with livemode = false and only ever counted in sandbox balances
+ALTER TABLE api_keys ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+-- Room for the longer dodo_test_ prefix in the stored key prefix
+ALTER TABLE api_keys ALTER COLUMN key_prefix TYPE VARCHAR(24);
+ALTER TABLE transactions ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+
+-- Replaces the keyset index, as history is now listed per mode
+DROP INDEX idx_transactions_user_id_created_at_id;
+CREATE INDEX idx_transactions_user_id_livemode_created_at_id ON transactions(user_id, livemode, created_at DESC, id DESC);
+
+-- Live and sandbox totals are kept in separate rows
+ALTER TABLE account_balances ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE account_balances DROP CONSTRAINT account_balances_pkey;
+ALTER TABLE account_balances ADD PRIMARY KEY (user_id, currency, livemode);
+
+CREATE OR REPLACE FUNCTION apply_balance_entry(entry transactions, sign INTEGER) RETURNS void AS $$
+DECLARE
+    signed_amount DECIMAL(19,4) := CASE WHEN entry.transaction_type = 'credit' THEN entry.amount ELSE -entry.amount END;
+BEGIN
+    IF entry.status NOT IN ('settled', 'reversed', 'pending') THEN
+        RETURN;
+    END IF;
+
+    INSERT INTO account_balances AS b (user_id, currency, livemode, balance, pending, held, entries, last_updated)
+    VALUES (
+        entry.user_id,
+        entry.currency,
+        entry.livemode,
+        CASE WHEN entry.status IN ('settled', 'reversed') THEN sign * signed_amount ELSE 0 END,
+        CASE WHEN entry.status = 'pending' THEN sign * signed_amount ELSE 0 END,
+        CASE WHEN entry.status = 'pending' AND entry.transaction_type = 'debit' THEN sign * entry.amount ELSE 0 END,
+        sign,
+        CASE WHEN sign > 0 THEN entry.created_at END
+    )
+    ON CONFLICT (user_id, currency, livemode) DO UPDATE SET
+        balance = b.balance + EXCLUDED.balance,
+        pending = b.pending + EXCLUDED.pending,
+        held = b.held + EXCLUDED.held,
+        entries = b.entries + EXCLUDED.entries,
+        last_updated = GREATEST(b.last_updated, EXCLUDED.last_updated);
+END;
+$$ LANGUAGE plpgsql;
+
+-- An entry can't move between the live and sandbox ledgers once posted
+CREATE OR REPLACE FUNCTION protect_posted_transactions() RETURNS trigger AS $$
+BEGIN
+    IF OLD.status NOT IN ('settled', 'reversed') THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        -- Only for removing whole accounts' data on purpose, e.g. test fixtures
+        IF current_setting('dodo.allow_ledger_purge', true) = 'on' THEN
+            RETURN OLD;
+        END IF;
+        RAISE EXCEPTION 'posted transaction % cannot be deleted', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    IF NEW.user_id IS DISTINCT FROM OLD.user_id
+        OR NEW.amount IS DISTINCT FROM OLD.amount
+        OR NEW.currency IS DISTINCT FROM OLD.currency
+        OR NEW.transaction_type IS DISTINCT FROM OLD.transaction_type
+        OR NEW.livemode IS DISTINCT FROM OLD.livemode
+        OR NEW.transfer_id IS DISTINCT FROM OLD.transfer_id
+        OR NEW.reverses IS DISTINCT FROM OLD.reverses
+        OR NEW.created_at IS DISTINCT FROM OLD.created_at
+        OR (NEW.status <> OLD.status AND NOT (OLD.status = 'settled' AND NEW.status = 'reversed'))
+        OR (OLD.reversed_by IS NOT NULL AND NEW.reversed_by IS DISTINCT FROM OLD.reversed_by)
+    THEN
+        RAISE EXCEPTION 'posted transaction % cannot be changed', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,2 @@
+-- Optional spending category set by the client when posting a transaction, for analytics
+ALTER TABLE transactions ADD COLUMN category VARCHAR(64);
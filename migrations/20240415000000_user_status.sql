@@ -0,0 +1,7 @@
+-- Deactivated users can't sign in or use their sessions and API keys; their data is kept and they
+-- can be reactivated
+CREATE TYPE user_status AS ENUM ('active', 'deactivated');
+
+ALTER TABLE users
+    ADD COLUMN status user_status NOT NULL DEFAULT 'active',
+    ADD COLUMN deactivated_at TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- Users who delete their account are deactivated rather than removed, so the ledger keeps its
+-- owner; `deleted_at` tells a self-service deletion apart from an admin's deactivation
+ALTER TABLE users
+    ADD COLUMN deleted_at TIMESTAMPTZ;
@@ -0,0 +1,21 @@
+-- Right-to-erasure requests. A request is confirmed with a one-time token, then carried out once its
+-- grace period has passed: the user's personal details are anonymized while their ledger is kept.
+-- The row stays behind as the record of the erasure.
+CREATE TYPE erasure_status AS ENUM ('pending', 'confirmed', 'completed', 'cancelled');
+
+CREATE TABLE erasure_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id),
+    status erasure_status NOT NULL DEFAULT 'pending',
+    confirmation_token_hash TEXT NOT NULL,
+    token_expires_at TIMESTAMPTZ NOT NULL,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    confirmed_at TIMESTAMPTZ,
+    scheduled_for TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    cancelled_at TIMESTAMPTZ
+);
+
+-- At most one open request per user
+CREATE UNIQUE INDEX erasure_requests_open_idx ON erasure_requests (user_id) WHERE status IN ('pending', 'confirmed');
+CREATE INDEX erasure_requests_due_idx ON erasure_requests (scheduled_for) WHERE status = 'confirmed';
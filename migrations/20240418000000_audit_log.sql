@@ -0,0 +1,21 @@
+-- Who did what: one row per mutating operation, with the affected record before and after. Actions
+-- are stored by name so new ones don't need a migration. Users aren't referenced by foreign key so
+-- the log outlives anything it describes.
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Who performed the action
+    actor_id UUID,
+    -- Whose account it affected, if anyone's
+    user_id UUID,
+    action VARCHAR(64) NOT NULL,
+    -- The record acted on, such as a transaction or adjustment
+    target_id UUID,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_log_created_idx ON audit_log (created_at DESC, id DESC);
+CREATE INDEX audit_log_user_idx ON audit_log (user_id, created_at DESC);
+CREATE INDEX audit_log_actor_idx ON audit_log (actor_id, created_at DESC);
+CREATE INDEX audit_log_action_idx ON audit_log (action, created_at DESC);
@@ -0,0 +1,17 @@
+-- Events for downstream systems, written in the same DB transaction as the change they describe and
+-- relayed to the message broker once committed. `sequence` keeps the relay in commit order; `id` is
+-- sent as the message ID so consumers and the broker can drop a redelivered event.
+CREATE TABLE outbox_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    sequence BIGINT GENERATED ALWAYS AS IDENTITY UNIQUE,
+    event_type TEXT NOT NULL,
+    user_id UUID,
+    payload JSONB NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    published_at TIMESTAMPTZ
+);
+
+-- The relay's scan for events still to publish
+CREATE INDEX idx_outbox_events_unpublished ON outbox_events(sequence) WHERE published_at IS NULL;
@@ -0,0 +1,26 @@
+-- Postgres-backed job queue. Workers claim due jobs with FOR UPDATE SKIP LOCKED and hold the row lock
+-- while the job runs, so a crashed worker's job becomes due again when its connection drops.
+CREATE TYPE job_status AS ENUM ('queued', 'completed', 'dead');
+
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    -- Jobs of a kind with the same key are only ever queued once
+    unique_key TEXT,
+    status job_status NOT NULL DEFAULT 'queued',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX idx_jobs_unique_key ON jobs(kind, unique_key);
+
+-- The workers' scan for due jobs
+CREATE INDEX idx_jobs_due ON jobs(run_at) WHERE status = 'queued';
+
+-- Listing jobs by status for the admin API
+CREATE INDEX idx_jobs_status ON jobs(status, created_at DESC);
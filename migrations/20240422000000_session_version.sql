@@ -0,0 +1,3 @@
+-- Session tokens carry the version current when they were issued; bumping it, as changing the
+-- password does, signs out every existing session
+ALTER TABLE users ADD COLUMN session_version INTEGER NOT NULL DEFAULT 0;
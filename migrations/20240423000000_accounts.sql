@@ -0,0 +1,97 @@
+-- Accounts split a user's money in one currency into separate balances, e.g. checking and savings.
+-- Every entry belongs to one account; entries posted without one go to the user's main account in
+-- their currency, which is created the first time it's needed.
+CREATE TYPE account_type AS ENUM ('checking', 'savings', 'wallet');
+
+CREATE TABLE accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    account_type account_type NOT NULL,
+    currency CHAR(3) NOT NULL,
+    is_primary BOOLEAN NOT NULL DEFAULT false,
+    -- Closed accounts keep their history but take no new entries
+    closed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_accounts_user_id ON accounts(user_id);
+-- One main account per user and currency
+CREATE UNIQUE INDEX idx_accounts_primary ON accounts(user_id, currency) WHERE is_primary;
+
+-- Main accounts for the ledger so far
+INSERT INTO accounts (user_id, name, account_type, currency, is_primary)
+SELECT DISTINCT user_id, 'Main', 'checking'::account_type, currency, true FROM transactions;
+
+ALTER TABLE transactions ADD COLUMN account_id UUID REFERENCES accounts(id) ON DELETE CASCADE;
+UPDATE transactions t SET account_id = a.id
+FROM accounts a
+WHERE a.user_id = t.user_id AND a.currency = t.currency AND a.is_primary;
+ALTER TABLE transactions ALTER COLUMN account_id SET NOT NULL;
+
+CREATE INDEX idx_transactions_account_id_livemode_created_at_id ON transactions(account_id, livemode, created_at DESC, id DESC);
+
+-- Files an entry without an account under the user's main account in its currency, and makes sure
+-- an entry given one is posted to an account of the same user and currency
+CREATE FUNCTION assign_transaction_account() RETURNS trigger AS $$
+BEGIN
+    IF NEW.account_id IS NULL THEN
+        INSERT INTO accounts (user_id, name, account_type, currency, is_primary)
+        VALUES (NEW.user_id, 'Main', 'checking', NEW.currency, true)
+        ON CONFLICT (user_id, currency) WHERE is_primary DO NOTHING;
+
+        SELECT id INTO NEW.account_id FROM accounts
+        WHERE user_id = NEW.user_id AND currency = NEW.currency AND is_primary;
+    ELSIF NOT EXISTS (
+        SELECT 1 FROM accounts WHERE id = NEW.account_id AND user_id = NEW.user_id AND currency = NEW.currency
+    ) THEN
+        RAISE EXCEPTION 'account % does not hold % for user %', NEW.account_id, NEW.currency, NEW.user_id
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_assign_account
+    BEFORE INSERT ON transactions
+    FOR EACH ROW EXECUTE FUNCTION assign_transaction_account();
+
+-- A posted entry can't move between accounts either
+CREATE OR REPLACE FUNCTION protect_posted_transactions() RETURNS trigger AS $$
+BEGIN
+    IF OLD.status NOT IN ('settled', 'reversed') THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        -- Only for removing whole accounts' data on purpose, e.g. test fixtures
+        IF current_setting('dodo.allow_ledger_purge', true) = 'on' THEN
+            RETURN OLD;
+        END IF;
+        RAISE EXCEPTION 'posted transaction % cannot be deleted', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    IF NEW.user_id IS DISTINCT FROM OLD.user_id
+        OR NEW.account_id IS DISTINCT FROM OLD.account_id
+        OR NEW.amount IS DISTINCT FROM OLD.amount
+        OR NEW.currency IS DISTINCT FROM OLD.currency
+        OR NEW.transaction_type IS DISTINCT FROM OLD.transaction_type
+        OR NEW.livemode IS DISTINCT FROM OLD.livemode
+        OR NEW.transfer_id IS DISTINCT FROM OLD.transfer_id
+        OR NEW.reverses IS DISTINCT FROM OLD.reverses
+        OR NEW.created_at IS DISTINCT FROM OLD.created_at
+        OR (NEW.status <> OLD.status AND NOT (OLD.status = 'settled' AND NEW.status = 'reversed'))
+        OR (OLD.reversed_by IS NOT NULL AND NEW.reversed_by IS DISTINCT FROM OLD.reversed_by)
+    THEN
+        RAISE EXCEPTION 'posted transaction % cannot be changed', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,10 @@
+-- Transfers between two accounts of the same user. Such a transfer has the user on both sides and
+-- names the accounts; transfers between users keep going to the recipient's main account.
+ALTER TABLE transfers ADD COLUMN from_account_id UUID REFERENCES accounts(id) ON DELETE CASCADE;
+ALTER TABLE transfers ADD COLUMN to_account_id UUID REFERENCES accounts(id) ON DELETE CASCADE;
+
+ALTER TABLE transfers DROP CONSTRAINT transfers_check;
+ALTER TABLE transfers ADD CONSTRAINT transfers_check CHECK (
+    from_user_id <> to_user_id
+    OR (from_account_id IS NOT NULL AND to_account_id IS NOT NULL AND from_account_id <> to_account_id)
+);
@@ -0,0 +1,31 @@
+-- Savings goals: a target amount, optionally by a deadline, saved up in one of the user's accounts.
+-- Progress is the account's balance, so it is never stored.
+CREATE TABLE savings_goals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    target_amount DECIMAL(19,4) NOT NULL CHECK (target_amount > 0),
+    deadline TIMESTAMPTZ,
+    -- Optional rule the scheduler follows to move a fixed amount into the goal's account whenever
+    -- next_auto_transfer_at is due
+    auto_transfer_from_account_id UUID REFERENCES accounts(id) ON DELETE CASCADE,
+    auto_transfer_amount DECIMAL(19,4) CHECK (auto_transfer_amount > 0),
+    auto_transfer_frequency recurrence_frequency,
+    next_auto_transfer_at TIMESTAMPTZ,
+    last_auto_transfer_at TIMESTAMPTZ,
+    last_auto_transfer_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (
+        (auto_transfer_from_account_id IS NULL) = (auto_transfer_amount IS NULL)
+        AND (auto_transfer_amount IS NULL) = (auto_transfer_frequency IS NULL)
+        AND (next_auto_transfer_at IS NULL OR auto_transfer_amount IS NOT NULL)
+    )
+);
+
+CREATE INDEX idx_savings_goals_user_id ON savings_goals(user_id);
+-- An account saves towards one goal at a time
+CREATE UNIQUE INDEX idx_savings_goals_account_id ON savings_goals(account_id);
+-- Partial index for the scheduler's due auto-transfer scan
+CREATE INDEX idx_savings_goals_auto_transfer_due ON savings_goals(next_auto_transfer_at) WHERE next_auto_transfer_at IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Round-up savings: when enabled, each settled debit is rounded up to the next whole unit of its
+-- currency and the difference is moved into the chosen savings account
+CREATE TABLE round_up_rules (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,19 @@
+-- Spending limits per category and currency over a week, month or year
+CREATE TYPE budget_period AS ENUM ('weekly', 'monthly', 'yearly');
+
+CREATE TABLE budgets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category VARCHAR(64) NOT NULL,
+    currency CHAR(3) NOT NULL CHECK (currency ~ '^[A-Z]{3}$'),
+    period budget_period NOT NULL,
+    limit_amount DECIMAL(19,4) NOT NULL CHECK (limit_amount > 0),
+    -- The highest alert threshold (a percentage of the limit) already reported, and the period it
+    -- was reported in, so each threshold is reported once per period
+    alerted_period_start TIMESTAMPTZ,
+    alerted_threshold SMALLINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_budgets_user_id_category_currency_period ON budgets(user_id, category, currency, period);
@@ -0,0 +1,11 @@
+-- Per-user velocity limits, set by admins; a null limit doesn't apply. Amount limits are in the
+-- currency of the transaction being checked.
+CREATE TABLE velocity_limits (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    max_transaction_amount NUMERIC CHECK (max_transaction_amount > 0),
+    max_daily_debit_total NUMERIC CHECK (max_daily_debit_total > 0),
+    max_transactions_per_hour INTEGER CHECK (max_transactions_per_hour > 0),
+    updated_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,34 @@
+-- Add a lifecycle state for large transactions waiting for a second person's approval
+ALTER TYPE transaction_status ADD VALUE 'awaiting_approval';
+
+-- Create approval_status enum
+CREATE TYPE approval_status AS ENUM ('pending', 'approved', 'rejected');
+
+-- Create transaction_approvers table; besides admins, the users someone names here can approve their
+-- transactions
+CREATE TABLE transaction_approvers (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    approver_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, approver_id),
+    CHECK (user_id <> approver_id)
+);
+
+CREATE INDEX idx_transaction_approvers_approver ON transaction_approvers(approver_id);
+
+-- Create transaction_approvals table, one per transaction that needed approval
+CREATE TABLE transaction_approvals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Whether the transaction was requested as pending, and so opens as pending once approved
+    pending BOOLEAN NOT NULL,
+    status approval_status NOT NULL DEFAULT 'pending',
+    decided_by UUID REFERENCES users(id),
+    decided_at TIMESTAMPTZ,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create partial index for the pending approvals listing
+CREATE INDEX idx_transaction_approvals_pending ON transaction_approvals(user_id, created_at) WHERE status = 'pending';
@@ -0,0 +1,38 @@
+-- Create risk_action enum, ordered from least to most severe
+CREATE TYPE risk_action AS ENUM ('flag', 'hold', 'block');
+
+-- Create login_devices table, each device a user has signed in from, keyed by a hash of its user agent
+CREATE TABLE login_devices (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    fingerprint VARCHAR(64) NOT NULL,
+    user_agent TEXT,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, fingerprint)
+);
+
+-- Create risk_events table, one per fraud rule a transaction tripped. Blocked transactions were never
+-- stored, so their events carry what was attempted instead of a transaction.
+CREATE TABLE risk_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    transaction_id UUID REFERENCES transactions(id) ON DELETE CASCADE,
+    rule VARCHAR(64) NOT NULL,
+    action risk_action NOT NULL,
+    reason TEXT NOT NULL,
+    amount DECIMAL(19,4) NOT NULL,
+    currency CHAR(3) NOT NULL,
+    transaction_type transaction_type NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_by UUID REFERENCES users(id),
+    reviewed_at TIMESTAMPTZ,
+    review_note TEXT
+);
+
+-- Create partial index for the review queue
+CREATE INDEX idx_risk_events_unreviewed ON risk_events(created_at) WHERE reviewed_at IS NULL;
+
+-- Debits a fraud rule holds join the held debit review queue, linked to the risk event instead of a hold
+ALTER TABLE held_debits ALTER COLUMN hold_id DROP NOT NULL;
+ALTER TABLE held_debits ADD COLUMN risk_event_id UUID REFERENCES risk_events(id) ON DELETE CASCADE;
+ALTER TABLE held_debits ADD CONSTRAINT held_debits_reason_check CHECK (hold_id IS NOT NULL OR risk_event_id IS NOT NULL);
@@ -0,0 +1,27 @@
+-- Create dispute_status enum; resolved disputes were decided against the user, refunded ones for them
+CREATE TYPE dispute_status AS ENUM ('open', 'under_review', 'resolved', 'refunded');
+
+-- Create disputes table, at most one per transaction. The amount and currency are the disputed
+-- debit's; a refunded dispute links the reversal that paid it back.
+CREATE TABLE disputes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    livemode BOOLEAN NOT NULL,
+    amount DECIMAL(19,4) NOT NULL,
+    currency CHAR(3) NOT NULL,
+    reason TEXT NOT NULL,
+    status dispute_status NOT NULL DEFAULT 'open',
+    reviewed_by UUID REFERENCES users(id),
+    resolved_by UUID REFERENCES users(id),
+    resolved_at TIMESTAMPTZ,
+    resolution_note TEXT,
+    refund_transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_disputes_user_id_created_at ON disputes(user_id, created_at DESC);
+
+-- Create partial index for the admin queue of undecided disputes
+CREATE INDEX idx_disputes_undecided ON disputes(created_at) WHERE status IN ('open', 'under_review');
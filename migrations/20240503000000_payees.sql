@@ -0,0 +1,23 @@
+-- Create payees table, a user's saved recipients. A payee is either another Dodo user or an
+-- outside account, identified by its normalized account number or IBAN; a user can save each
+-- recipient once.
+CREATE TABLE payees (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    recipient_user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    account_identifier VARCHAR(34),
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((recipient_user_id IS NULL) <> (account_identifier IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_payees_user_id_recipient_user_id ON payees(user_id, recipient_user_id)
+    WHERE recipient_user_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_payees_user_id_account_identifier ON payees(user_id, account_identifier)
+    WHERE account_identifier IS NOT NULL;
+
+-- Record which saved payee a transaction or transfer was made to
+ALTER TABLE transactions ADD COLUMN payee_id UUID REFERENCES payees(id) ON DELETE SET NULL;
+ALTER TABLE transfers ADD COLUMN payee_id UUID REFERENCES payees(id) ON DELETE SET NULL;
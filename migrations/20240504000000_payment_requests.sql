@@ -0,0 +1,26 @@
+-- Create payment_request_status enum; pending requests end accepted, declined by the payer,
+-- cancelled by the requester, or expired
+CREATE TYPE payment_request_status AS ENUM ('pending', 'accepted', 'declined', 'cancelled', 'expired');
+
+-- Create payment_requests table. An accepted request links the transfer that paid it.
+CREATE TABLE payment_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    requester_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    payer_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount DECIMAL(19,4) NOT NULL CHECK (amount > 0),
+    currency CHAR(3) NOT NULL,
+    description TEXT,
+    status payment_request_status NOT NULL DEFAULT 'pending',
+    expires_at TIMESTAMPTZ NOT NULL,
+    transfer_id UUID REFERENCES transfers(id) ON DELETE SET NULL,
+    decided_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (requester_id <> payer_id)
+);
+
+CREATE INDEX idx_payment_requests_requester_id_created_at ON payment_requests(requester_id, created_at DESC);
+CREATE INDEX idx_payment_requests_payer_id_created_at ON payment_requests(payer_id, created_at DESC);
+
+-- Create partial index for the expiry sweep
+CREATE INDEX idx_payment_requests_pending_expires_at ON payment_requests(expires_at) WHERE status = 'pending';
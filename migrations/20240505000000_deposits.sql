@@ -0,0 +1,27 @@
+-- Create deposit_status enum; pending deposits wait for the card payment to succeed or fail
+CREATE TYPE deposit_status AS ENUM ('pending', 'succeeded', 'failed', 'cancelled');
+
+-- Create deposits table, card top-ups paid through Stripe. A succeeded deposit links the credit
+-- that added it to the user's balance.
+CREATE TABLE deposits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount DECIMAL(19,4) NOT NULL CHECK (amount > 0),
+    currency CHAR(3) NOT NULL,
+    status deposit_status NOT NULL DEFAULT 'pending',
+    payment_intent_id TEXT UNIQUE,
+    transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    failure_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_deposits_user_id_created_at ON deposits(user_id, created_at DESC);
+
+-- Create stripe_events table, the ids of webhook events already processed. Stripe delivers each
+-- event at least once, so a redelivery is recognized here and acknowledged without effect.
+CREATE TABLE stripe_events (
+    id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,52 @@
+-- Create linked_items table, users' logins at outside banks made through Plaid Link. The access
+-- token lets the server read the item's accounts; it is stored encrypted with PLAID_TOKEN_KEY and
+-- is never returned to clients. transactions_cursor is where the next transactions sync picks up.
+CREATE TABLE linked_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider_item_id TEXT NOT NULL UNIQUE,
+    access_token TEXT NOT NULL,
+    transactions_cursor TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_linked_items_user_id ON linked_items(user_id);
+
+-- Create linked_accounts table, the accounts of each linked item with their last pulled balances
+CREATE TABLE linked_accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_id UUID NOT NULL REFERENCES linked_items(id) ON DELETE CASCADE,
+    provider_account_id TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    mask TEXT,
+    account_type TEXT NOT NULL,
+    subtype TEXT,
+    currency CHAR(3),
+    available_balance DECIMAL(19,4),
+    current_balance DECIMAL(19,4),
+    balances_updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_linked_accounts_user_id ON linked_accounts(user_id);
+CREATE INDEX idx_linked_accounts_item_id ON linked_accounts(item_id);
+
+-- Create linked_account_transactions table, transactions pulled from linked accounts. They are
+-- a copy of the bank's records and never touch the Dodo ledger. Positive amounts left the account.
+CREATE TABLE linked_account_transactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    linked_account_id UUID NOT NULL REFERENCES linked_accounts(id) ON DELETE CASCADE,
+    provider_transaction_id TEXT NOT NULL UNIQUE,
+    amount DECIMAL(19,4) NOT NULL,
+    currency CHAR(3),
+    description TEXT NOT NULL,
+    merchant_name TEXT,
+    posted_on DATE NOT NULL,
+    pending BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_linked_account_transactions_account_posted ON linked_account_transactions(linked_account_id, posted_on DESC);
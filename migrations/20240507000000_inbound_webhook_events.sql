@@ -0,0 +1,15 @@
+-- Create inbound_webhook_events table, the events received from each webhook provider that have
+-- been processed. Providers deliver each event at least once, so a redelivery is recognized here
+-- by the provider's event id and acknowledged without effect. It replaces stripe_events.
+CREATE TABLE inbound_webhook_events (
+    provider TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (provider, event_id)
+);
+
+INSERT INTO inbound_webhook_events (provider, event_id, event_type, processed_at)
+SELECT 'stripe', id, event_type, processed_at FROM stripe_events;
+
+DROP TABLE stripe_events;
@@ -0,0 +1,7 @@
+-- Record the conversion on transfers whose recipient is credited in another currency: the amount
+-- and currency credited, and the rate applied after the spread
+ALTER TABLE transfers ADD COLUMN converted_amount DECIMAL(19,4) CHECK (converted_amount > 0);
+ALTER TABLE transfers ADD COLUMN converted_currency CHAR(3);
+ALTER TABLE transfers ADD COLUMN fx_rate NUMERIC CHECK (fx_rate > 0);
+ALTER TABLE transfers ADD CONSTRAINT transfers_conversion_complete
+    CHECK ((converted_amount IS NULL) = (converted_currency IS NULL) AND (converted_amount IS NULL) = (fx_rate IS NULL));
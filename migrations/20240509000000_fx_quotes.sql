@@ -0,0 +1,71 @@
+-- A rate locked for a short while so the customer sees exactly what they'll get before transferring
+CREATE TABLE fx_quotes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    from_currency CHAR(3) NOT NULL,
+    to_currency CHAR(3) NOT NULL CHECK (to_currency <> from_currency),
+    amount DECIMAL(19,4) NOT NULL CHECK (amount > 0),
+    converted_amount DECIMAL(19,4) NOT NULL CHECK (converted_amount > 0),
+    mid_rate NUMERIC NOT NULL CHECK (mid_rate > 0),
+    rate NUMERIC NOT NULL CHECK (rate > 0),
+    spread_bps INTEGER NOT NULL CHECK (spread_bps >= 0),
+    expires_at TIMESTAMPTZ NOT NULL,
+    -- Set once the quote is used; a quote pays for one transfer only
+    used_at TIMESTAMPTZ,
+    transfer_id UUID REFERENCES transfers(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((used_at IS NULL) = (transfer_id IS NULL))
+);
+
+CREATE INDEX fx_quotes_user_id_idx ON fx_quotes (user_id, created_at);
+
+-- A user exchanging between their own wallets transfers to themselves, from one main account to another
+ALTER TABLE transfers DROP CONSTRAINT transfers_check;
+ALTER TABLE transfers ADD CONSTRAINT transfers_check CHECK (
+    from_user_id <> to_user_id
+    OR (from_account_id IS NOT NULL AND to_account_id IS NOT NULL AND from_account_id <> to_account_id)
+    OR converted_currency <> currency
+);
+
+-- The rate a converting transfer's entries were posted at, on both the debit and the credit
+ALTER TABLE transactions ADD COLUMN fx_rate NUMERIC CHECK (fx_rate > 0);
+
+-- The rate is part of the posted entry
+CREATE OR REPLACE FUNCTION protect_posted_transactions() RETURNS trigger AS $$
+BEGIN
+    IF OLD.status NOT IN ('settled', 'reversed') THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        -- Only for removing whole accounts' data on purpose, e.g. test fixtures
+        IF current_setting('dodo.allow_ledger_purge', true) = 'on' THEN
+            RETURN OLD;
+        END IF;
+        RAISE EXCEPTION 'posted transaction % cannot be deleted', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    IF NEW.user_id IS DISTINCT FROM OLD.user_id
+        OR NEW.account_id IS DISTINCT FROM OLD.account_id
+        OR NEW.amount IS DISTINCT FROM OLD.amount
+        OR NEW.currency IS DISTINCT FROM OLD.currency
+        OR NEW.transaction_type IS DISTINCT FROM OLD.transaction_type
+        OR NEW.livemode IS DISTINCT FROM OLD.livemode
+        OR NEW.transfer_id IS DISTINCT FROM OLD.transfer_id
+        OR NEW.reverses IS DISTINCT FROM OLD.reverses
+        OR NEW.fx_rate IS DISTINCT FROM OLD.fx_rate
+        OR NEW.created_at IS DISTINCT FROM OLD.created_at
+        OR (NEW.status <> OLD.status AND NOT (OLD.status = 'settled' AND NEW.status = 'reversed'))
+        OR (OLD.reversed_by IS NOT NULL AND NEW.reversed_by IS DISTINCT FROM OLD.reversed_by)
+    THEN
+        RAISE EXCEPTION 'posted transaction % cannot be changed', OLD.id
+            USING ERRCODE = 'LD001', HINT = 'Reverse it instead';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
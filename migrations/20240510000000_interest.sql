@@ -0,0 +1,37 @@
+-- Interest paid on account balances, configured per account type
+CREATE TYPE compounding_period AS ENUM ('daily', 'monthly');
+
+CREATE TABLE interest_rates (
+    account_type account_type PRIMARY KEY,
+    -- Annual percentage yield, in percent
+    apy NUMERIC(7,4) NOT NULL CHECK (apy >= 0 AND apy <= 100),
+    compounding compounding_period NOT NULL,
+    -- Interest accrues from this day on; moved forward when a rate of zero is raised, so switching
+    -- interest on never pays for the time it was off
+    effective_from DATE NOT NULL,
+    updated_by UUID NOT NULL REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One row per account and period interest was worked out for, whether or not any was due. The
+-- unique period keeps a restarted or concurrent run from paying a period twice.
+CREATE TABLE interest_accruals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- First day of the period and the day after its last, both UTC
+    period_start DATE NOT NULL,
+    period_end DATE NOT NULL CHECK (period_end > period_start),
+    -- Average end-of-day balance over the period, counting negative balances as zero
+    average_balance DECIMAL(19,4) NOT NULL,
+    apy NUMERIC(7,4) NOT NULL,
+    compounding compounding_period NOT NULL,
+    amount DECIMAL(19,4) NOT NULL CHECK (amount >= 0),
+    currency CHAR(3) NOT NULL,
+    -- The interest credit; none when the period earned less than the currency's minor unit
+    transaction_id UUID REFERENCES transactions(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (account_id, period_start)
+);
+
+CREATE INDEX idx_interest_accruals_account_id_period_end ON interest_accruals(account_id, period_end DESC);
@@ -0,0 +1,57 @@
+-- Fees charged on transfers and withdrawals, credited to the house: a user that holds Dodo's own
+-- money. Nobody can sign in as it; `!` isn't a bcrypt hash, so no password matches it, and sign-in
+-- refuses the house before checking one.
+INSERT INTO users (id, email, password_hash, name, metadata)
+VALUES (
+    '00000000-0000-0000-0000-000000000001',
+    'house@dodo.internal',
+    '!',
+    'Dodo',
+    '{"house": true}'
+);
+
+CREATE TYPE fee_event AS ENUM ('transfer', 'withdrawal');
+CREATE TYPE fee_kind AS ENUM ('flat', 'percentage', 'tiered');
+
+CREATE TABLE fee_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    event fee_event NOT NULL,
+    -- Rules apply to amounts in this currency only
+    currency CHAR(3) NOT NULL,
+    kind fee_kind NOT NULL,
+    flat_amount DECIMAL(19,4) CHECK (flat_amount > 0),
+    percentage_bps INTEGER CHECK (percentage_bps > 0 AND percentage_bps <= 10000),
+    -- Amount bands for tiered fees: [{"up_to": "100", "flat_amount": "0.5", "percentage_bps": 0}, ...]
+    tiers JSONB NOT NULL DEFAULT '[]' CHECK (jsonb_typeof(tiers) = 'array'),
+    min_fee DECIMAL(19,4) CHECK (min_fee >= 0),
+    max_fee DECIMAL(19,4) CHECK (max_fee >= 0),
+    -- Rules are switched off rather than deleted, so past charges keep pointing at them
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID NOT NULL REFERENCES users(id),
+    updated_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (kind <> 'flat' OR flat_amount IS NOT NULL),
+    CHECK (kind <> 'percentage' OR percentage_bps IS NOT NULL),
+    CHECK (kind <> 'tiered' OR jsonb_array_length(tiers) > 0),
+    CHECK (min_fee IS NULL OR max_fee IS NULL OR min_fee <= max_fee)
+);
+
+CREATE INDEX idx_fee_rules_event_currency ON fee_rules(event, currency) WHERE active;
+
+-- One fee charged by one rule on one transaction: a debit from the payer and a credit to the house
+CREATE TABLE fee_charges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rule_id UUID NOT NULL REFERENCES fee_rules(id),
+    -- The transfer debit or withdrawal the fee was charged on
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    debit_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    credit_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    amount DECIMAL(19,4) NOT NULL CHECK (amount > 0),
+    currency CHAR(3) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (transaction_id, rule_id)
+);
+
+CREATE INDEX idx_fee_charges_rule_id ON fee_charges(rule_id);
@@ -0,0 +1,45 @@
+-- Where a user is in identity verification: they upload documents and submit them, and an admin
+-- verifies or rejects them. A rejected user can upload more and submit again.
+CREATE TYPE kyc_status AS ENUM ('unverified', 'pending', 'verified', 'rejected');
+
+CREATE TYPE kyc_document_type AS ENUM ('passport', 'drivers_license', 'national_id', 'proof_of_address');
+
+-- One row per user who has started verification; users without one are unverified
+CREATE TABLE kyc_verifications (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    status kyc_status NOT NULL DEFAULT 'unverified',
+    submitted_at TIMESTAMPTZ,
+    reviewed_by UUID REFERENCES users(id),
+    reviewed_at TIMESTAMPTZ,
+    rejection_reason TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((status = 'rejected') = (rejection_reason IS NOT NULL)),
+    CHECK (status = 'unverified' OR submitted_at IS NOT NULL)
+);
+
+CREATE INDEX idx_kyc_verifications_status ON kyc_verifications(status, submitted_at);
+
+-- Identity documents; the bytes live in object storage under `storage_key`, like attachments
+CREATE TABLE kyc_documents (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES kyc_verifications(user_id) ON DELETE CASCADE,
+    document_type kyc_document_type NOT NULL,
+    filename TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    sha256 CHAR(64) NOT NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_kyc_documents_user_id ON kyc_documents(user_id, created_at);
+
+-- Limits on live transactions by KYC status, set by admins. Statuses without a row and null limits
+-- don't apply; amounts are in the currency of the transaction being checked.
+CREATE TABLE kyc_limits (
+    status kyc_status PRIMARY KEY,
+    max_transaction_amount NUMERIC CHECK (max_transaction_amount > 0),
+    max_daily_debit_total NUMERIC CHECK (max_daily_debit_total > 0),
+    updated_by UUID NOT NULL REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,38 @@
+-- Create screening_operation enum, the flows that screen users
+CREATE TYPE screening_operation AS ENUM ('registration', 'transfer');
+
+-- Create screening_outcome enum. A hit matched a list; an error means the provider couldn't say,
+-- which is treated like a hit.
+CREATE TYPE screening_outcome AS ENUM ('clear', 'hit', 'error');
+
+-- Create screening_decision enum: a cleared result was a false positive, a confirmed one stands
+CREATE TYPE screening_decision AS ENUM ('cleared', 'confirmed');
+
+-- Create screening_results table, one per party screened. Hits and errors wait in the review queue
+-- until an admin clears or confirms them.
+CREATE TABLE screening_results (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    operation screening_operation NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    transfer_id UUID REFERENCES transfers(id) ON DELETE CASCADE,
+    provider VARCHAR(64) NOT NULL,
+    outcome screening_outcome NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_by UUID REFERENCES users(id),
+    reviewed_at TIMESTAMPTZ,
+    decision screening_decision,
+    review_note TEXT,
+    CONSTRAINT screening_results_transfer_check CHECK ((operation = 'transfer') = (transfer_id IS NOT NULL)),
+    CONSTRAINT screening_results_decision_check CHECK ((decision IS NULL) = (reviewed_at IS NULL))
+);
+
+-- Create partial index for the review queue
+CREATE INDEX idx_screening_results_unreviewed ON screening_results(created_at) WHERE outcome <> 'clear' AND reviewed_at IS NULL;
+CREATE INDEX idx_screening_results_user_id ON screening_results(user_id);
+
+-- Transfers flagged by screening join the held debit review queue, linked to the screening result
+ALTER TABLE held_debits ADD COLUMN screening_result_id UUID REFERENCES screening_results(id) ON DELETE CASCADE;
+ALTER TABLE held_debits DROP CONSTRAINT held_debits_reason_check;
+ALTER TABLE held_debits ADD CONSTRAINT held_debits_reason_check
+    CHECK (hold_id IS NOT NULL OR risk_event_id IS NOT NULL OR screening_result_id IS NOT NULL);
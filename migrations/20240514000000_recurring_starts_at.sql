@@ -0,0 +1,7 @@
+-- Monthly and yearly occurrences are computed from the schedule's first occurrence, so one clamped
+-- to a short month doesn't pull every later one earlier. Existing schedules are anchored at their
+-- next occurrence.
+ALTER TABLE recurring_transactions ADD COLUMN starts_at TIMESTAMPTZ;
+UPDATE recurring_transactions SET starts_at = next_run_at;
+ALTER TABLE recurring_transactions ALTER COLUMN starts_at SET NOT NULL;
+
@@ -0,0 +1,5 @@
+-- Amount limits were compared against each currency on its own, so spreading debits across wallets
+-- multiplied them. Limits now name the currency they're set in, and transactions and usage in other
+-- currencies are converted into it. Existing limits were set with USD in mind.
+ALTER TABLE velocity_limits ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD' CHECK (currency ~ '^[A-Z]{3}$');
+ALTER TABLE kyc_limits ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD' CHECK (currency ~ '^[A-Z]{3}$');
@@ -0,0 +1,3 @@
+-- Registrants flagged by screening wait in their own status rather than looking like accounts an
+-- admin deactivated
+ALTER TYPE user_status ADD VALUE 'pending_review';
@@ -0,0 +1,9 @@
+-- Move registrants still deactivated by an unreviewed registration screening into review. This is
+-- separate from adding the status, which can't be used in the migration that adds it.
+UPDATE users u SET status = 'pending_review'
+WHERE u.status = 'deactivated'
+    AND u.deleted_at IS NULL
+    AND EXISTS (
+        SELECT 1 FROM screening_results s
+        WHERE s.user_id = u.id AND s.operation = 'registration' AND s.outcome <> 'clear' AND s.reviewed_at IS NULL
+    );
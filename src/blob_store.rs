@@ -0,0 +1,230 @@
+use async_trait::async_trait;
+use reqwest::Url;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::error;
+
+use crate::aws::{uri_encode, AwsCredentials, Signer};
+use crate::config::Config;
+use crate::outbound::OutboundClient;
+
+#[derive(Debug)]
+pub enum BlobError {
+    // No storage is configured, so there is nowhere to keep files
+    NotConfigured,
+    // The store failed or refused the request, possibly only for now
+    Transport(String),
+}
+
+impl fmt::Display for BlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlobError::NotConfigured => write!(f, "no blob store is configured"),
+            BlobError::Transport(message) => write!(f, "blob store request failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for BlobError {}
+
+// Object storage for uploaded files. The database keeps what each object is; the store only holds
+// the bytes under a key.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, body: Vec<u8>) -> Result<(), BlobError>;
+    // Deleting a key that doesn't exist succeeds
+    async fn delete(&self, key: &str) -> Result<(), BlobError>;
+    // A URL anyone holding it can download the object from until `expires_in` has passed
+    fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, BlobError>;
+}
+
+// Used when no bucket is configured: nothing can be stored or fetched
+pub struct NoBlobStore;
+
+#[async_trait]
+impl BlobStore for NoBlobStore {
+    async fn put(&self, _key: &str, _content_type: &str, _body: Vec<u8>) -> Result<(), BlobError> {
+        Err(BlobError::NotConfigured)
+    }
+
+    async fn delete(&self, _key: &str) -> Result<(), BlobError> {
+        Err(BlobError::NotConfigured)
+    }
+
+    fn presigned_url(&self, _key: &str, _expires_in: Duration) -> Result<String, BlobError> {
+        Err(BlobError::NotConfigured)
+    }
+}
+
+// A bucket in Amazon S3, or in an S3-compatible store such as MinIO when `endpoint` is set.
+// Requests are signed with AWS Signature Version 4.
+pub struct S3BlobStore {
+    client: Arc<OutboundClient>,
+    credentials: AwsCredentials,
+    region: String,
+    bucket: String,
+    // Addressed path-style, `<endpoint>/<bucket>/<key>`; AWS buckets are addressed by subdomain
+    endpoint: Option<Url>,
+}
+
+impl S3BlobStore {
+    pub fn new(client: Arc<OutboundClient>, credentials: AwsCredentials, region: &str, bucket: &str, endpoint: Option<Url>) -> Self {
+        Self { client, credentials, region: region.to_string(), bucket: bucket.to_string(), endpoint }
+    }
+
+    // The base URL, host header and canonical path of an object
+    fn locate(&self, key: &str) -> (String, String, String) {
+        match &self.endpoint {
+            Some(endpoint) => {
+                let host = match endpoint.port() {
+                    Some(port) => format!("{}:{}", endpoint.host_str().unwrap_or_default(), port),
+                    None => endpoint.host_str().unwrap_or_default().to_string(),
+                };
+                let path = format!("/{}/{}", uri_encode(&self.bucket, true), uri_encode(key, false));
+                (format!("{}://{}", endpoint.scheme(), host), host, path)
+            }
+            None => {
+                let host = format!("{}.s3.{}.amazonaws.com", self.bucket, self.region);
+                (format!("https://{}", host), host, format!("/{}", uri_encode(key, false)))
+            }
+        }
+    }
+
+    fn signer(&self) -> Signer<'_> {
+        Signer { credentials: &self.credentials, region: &self.region, service: "s3" }
+    }
+
+    async fn send(&self, method: reqwest::Method, key: &str, content_type: Option<&str>, body: Vec<u8>) -> Result<(), BlobError> {
+        let (base, host, path) = self.locate(key);
+        let signed = self.signer().sign(method.as_str(), &host, &path, &body, OffsetDateTime::now_utc());
+        let mut request = self
+            .client
+            .client()
+            .request(method, format!("{}{}", base, path))
+            .header("x-amz-date", signed.amz_date)
+            .header("x-amz-content-sha256", signed.content_sha256)
+            .header(reqwest::header::AUTHORIZATION, signed.authorization);
+        if let Some(content_type) = content_type {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        if let Some(token) = &self.credentials.session_token {
+            request = request.header("x-amz-security-token", token);
+        }
+        let request = request.body(body).build().map_err(|e| BlobError::Transport(e.to_string()))?;
+
+        let response = self.client.execute(request).await.map_err(|e| BlobError::Transport(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let detail = response.text().await.unwrap_or_default();
+        Err(BlobError::Transport(format!("S3 returned {}: {}", status, detail)))
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, content_type: &str, body: Vec<u8>) -> Result<(), BlobError> {
+        self.send(reqwest::Method::PUT, key, Some(content_type), body).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), BlobError> {
+        self.send(reqwest::Method::DELETE, key, None, Vec::new()).await
+    }
+
+    fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, BlobError> {
+        let (base, host, path) = self.locate(key);
+        let query = self.signer().presign("GET", &host, &path, expires_in.as_secs(), OffsetDateTime::now_utc());
+        Ok(format!("{}{}?{}", base, path, query))
+    }
+}
+
+// Keeps objects in memory, for tests that need somewhere to upload to
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryBlobStore {
+    objects: std::sync::Mutex<std::collections::HashMap<String, (String, Vec<u8>)>>,
+}
+
+#[cfg(test)]
+impl MemoryBlobStore {
+    // The content type and bytes stored under `key`
+    pub fn get(&self, key: &str) -> Option<(String, Vec<u8>)> {
+        self.objects.lock().unwrap().get(key).cloned()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl BlobStore for MemoryBlobStore {
+    async fn put(&self, key: &str, content_type: &str, body: Vec<u8>) -> Result<(), BlobError> {
+        self.objects.lock().unwrap().insert(key.to_string(), (content_type.to_string(), body));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), BlobError> {
+        self.objects.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, BlobError> {
+        Ok(format!("memory:///{}?expires_in={}", key, expires_in.as_secs()))
+    }
+}
+
+// The bucket named in the configuration, or `NoBlobStore` when none is. A bucket that can't be
+// used is logged and replaced with `NoBlobStore` so the service still starts.
+pub fn from_config(config: &Config, client: Arc<OutboundClient>) -> Arc<dyn BlobStore> {
+    let (Some(bucket), Some(region)) = (config.s3_bucket.as_deref(), config.s3_region.as_deref()) else {
+        return Arc::new(NoBlobStore);
+    };
+    let endpoint = match config.s3_endpoint.as_deref().map(Url::parse).transpose() {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            error!("Invalid S3_ENDPOINT, not storing attachments: {}", e);
+            return Arc::new(NoBlobStore);
+        }
+    };
+    match AwsCredentials::from_env() {
+        Some(credentials) => Arc::new(S3BlobStore::new(client, credentials, region, bucket, endpoint)),
+        None => {
+            error!("S3_BUCKET is set but AWS_ACCESS_KEY_ID or AWS_SECRET_ACCESS_KEY is missing, not storing attachments");
+            Arc::new(NoBlobStore)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::HostPolicy;
+
+    fn store(endpoint: Option<&str>) -> S3BlobStore {
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+        };
+        let client = Arc::new(OutboundClient::new(HostPolicy::from_config(&Config::default())));
+        S3BlobStore::new(client, credentials, "eu-west-1", "receipts", endpoint.map(|url| Url::parse(url).unwrap()))
+    }
+
+    #[test]
+    fn test_objects_are_addressed_by_subdomain_on_aws_and_by_path_elsewhere() {
+        let (base, host, path) = store(None).locate("attachments/a b.pdf");
+        assert_eq!(base, "https://receipts.s3.eu-west-1.amazonaws.com");
+        assert_eq!(host, "receipts.s3.eu-west-1.amazonaws.com");
+        assert_eq!(path, "/attachments/a%20b.pdf");
+
+        let (base, host, path) = store(Some("http://localhost:9000")).locate("attachments/1");
+        assert_eq!(base, "http://localhost:9000");
+        assert_eq!(host, "localhost:9000");
+        assert_eq!(path, "/receipts/attachments/1");
+
+        let url = store(Some("https://minio.example.com")).presigned_url("attachments/1", Duration::from_secs(900)).unwrap();
+        assert!(url.starts_with("https://minio.example.com/receipts/attachments/1?X-Amz-Algorithm=AWS4-HMAC-SHA256&"));
+        assert!(url.contains("&X-Amz-Expires=900&"));
+    }
+}
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::AsyncCommands;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+
+// A slow cache must not be slower than the query it saves
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(250);
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Best-effort key-value store for derived data. The database stays the source of truth, so a cache
+// that fails only logs and acts as a miss; callers never see its errors.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> Option<String>;
+    async fn set(&self, key: &str, value: &str, ttl: Duration);
+    async fn delete(&self, key: &str);
+}
+
+// Used when no cache is configured: every read misses and writes are dropped
+pub struct NoCache;
+
+#[async_trait]
+impl Cache for NoCache {
+    async fn get(&self, _key: &str) -> Option<String> {
+        None
+    }
+
+    async fn set(&self, _key: &str, _value: &str, _ttl: Duration) {}
+
+    async fn delete(&self, _key: &str) {}
+}
+
+// Shared by every app instance, so an entry dropped by one is gone for all
+pub struct RedisCache {
+    connection: ConnectionManager,
+}
+
+impl RedisCache {
+    pub async fn connect(url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        let config = ConnectionManagerConfig::new()
+            .set_response_timeout(RESPONSE_TIMEOUT)
+            .set_connection_timeout(CONNECTION_TIMEOUT)
+            .set_number_of_retries(1);
+        let connection = ConnectionManager::new_with_config(client, config).await?;
+        Ok(Self { connection })
+    }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        self.connection
+            .clone()
+            .get::<_, Option<String>>(key)
+            .await
+            .map_err(|e| error!("Failed to read {} from the cache: {}", key, e))
+            .ok()
+            .flatten()
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) {
+        let result: redis::RedisResult<()> = self.connection.clone().set_ex(key, value, ttl.as_secs().max(1)).await;
+        if let Err(e) = result {
+            error!("Failed to write {} to the cache: {}", key, e);
+        }
+    }
+
+    async fn delete(&self, key: &str) {
+        let result: redis::RedisResult<()> = self.connection.clone().del(key).await;
+        if let Err(e) = result {
+            error!("Failed to delete {} from the cache: {}", key, e);
+        }
+    }
+}
+
+// Connects to the configured Redis, or falls back to no cache when there is none or it can't be
+// reached at startup
+pub async fn from_config(redis_url: Option<&str>) -> Arc<dyn Cache> {
+    let Some(url) = redis_url else {
+        return Arc::new(NoCache);
+    };
+
+    match RedisCache::connect(url).await {
+        Ok(cache) => {
+            tracing::info!("Connected to the Redis cache");
+            Arc::new(cache)
+        }
+        Err(e) => {
+            error!("Failed to connect to Redis, running without a cache: {}", e);
+            Arc::new(NoCache)
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::fs;
+use std::sync::RwLock;
+
+use crate::config::Config;
+
+// Bundled disposable-provider dataset, extended at runtime from DISPOSABLE_DOMAINS_FILE
+const BUILTIN_DISPOSABLE_DOMAINS: &str = include_str!("../data/disposable_email_domains.txt");
+
+#[derive(Debug, PartialEq)]
+pub enum DomainDecision {
+    Allowed,
+    Blocked,
+}
+
+// Decides which email domains may register. Allowlisted domains always pass, so a
+// deployment can unblock a provider the dataset flags; everything else is checked
+// against the blocklist and the disposable-domain dataset.
+#[derive(Debug, Default)]
+pub struct EmailDomainPolicy {
+    allowlist: HashSet<String>,
+    blocklist: HashSet<String>,
+}
+
+impl EmailDomainPolicy {
+    pub fn new<A, B>(allowlist: A, blocklist: B) -> Self
+    where
+        A: IntoIterator<Item = String>,
+        B: IntoIterator<Item = String>,
+    {
+        Self {
+            allowlist: allowlist.into_iter().map(|d| d.to_lowercase()).collect(),
+            blocklist: blocklist.into_iter().map(|d| d.to_lowercase()).collect(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut blocklist: Vec<String> = parse_domain_list(BUILTIN_DISPOSABLE_DOMAINS);
+
+        if let Some(path) = config.disposable_domains_file.as_deref().filter(|path| !path.is_empty()) {
+            match fs::read_to_string(path) {
+                Ok(contents) => blocklist.extend(parse_domain_list(&contents)),
+                Err(e) => tracing::error!("Failed to read disposable domains file {}: {}", path, e),
+            }
+        }
+
+        blocklist.extend(config.email_domain_blocklist.iter().cloned());
+
+        Self::new(config.email_domain_allowlist.iter().cloned(), blocklist)
+    }
+
+    pub fn check(&self, email: &str) -> DomainDecision {
+        let domain = match email.rsplit_once('@') {
+            Some((_, domain)) => domain.trim().trim_end_matches('.').to_lowercase(),
+            None => return DomainDecision::Blocked,
+        };
+
+        if self.matches(&self.allowlist, &domain) {
+            DomainDecision::Allowed
+        } else if self.matches(&self.blocklist, &domain) {
+            DomainDecision::Blocked
+        } else {
+            DomainDecision::Allowed
+        }
+    }
+
+    pub fn blocked_count(&self) -> usize {
+        self.blocklist.len()
+    }
+
+    // Matches the domain itself or any parent domain, e.g. `eu.mailinator.com`
+    fn matches(&self, list: &HashSet<String>, domain: &str) -> bool {
+        let mut candidate = domain;
+        loop {
+            if list.contains(candidate) {
+                return true;
+            }
+            match candidate.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => candidate = parent,
+                _ => return false,
+            }
+        }
+    }
+}
+
+// The policy registrations and email changes are checked against, replaced when it's reloaded
+pub struct EmailPolicy {
+    current: RwLock<EmailDomainPolicy>,
+}
+
+impl EmailPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self { current: RwLock::new(EmailDomainPolicy::from_config(config)) }
+    }
+
+    pub fn check(&self, email: &str) -> DomainDecision {
+        self.current.read().unwrap().check(email)
+    }
+
+    // Re-reads the disposable domains file so operators can update it without a restart
+    pub fn reload(&self, config: &Config) -> usize {
+        let updated = EmailDomainPolicy::from_config(config);
+        let count = updated.blocked_count();
+        *self.current.write().unwrap() = updated;
+        tracing::info!("Reloaded email domain policy with {} blocked domains", count);
+        count
+    }
+}
+
+fn parse_domain_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow: &[&str], block: &[&str]) -> EmailDomainPolicy {
+        EmailDomainPolicy::new(
+            allow.iter().map(|d| d.to_string()),
+            block.iter().map(|d| d.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_blocks_listed_domains_and_subdomains() {
+        let policy = policy(&[], &["mailinator.com"]);
+        assert_eq!(policy.check("a@mailinator.com"), DomainDecision::Blocked);
+        assert_eq!(policy.check("a@EU.Mailinator.com"), DomainDecision::Blocked);
+        assert_eq!(policy.check("a@example.com"), DomainDecision::Allowed);
+        assert_eq!(policy.check("a@notmailinator.com"), DomainDecision::Allowed);
+    }
+
+    #[test]
+    fn test_allowlist_overrides_blocklist() {
+        let policy = policy(&["trashmail.com"], &["trashmail.com"]);
+        assert_eq!(policy.check("a@trashmail.com"), DomainDecision::Allowed);
+    }
+
+    #[test]
+    fn test_builtin_dataset_is_loaded() {
+        let domains = parse_domain_list(BUILTIN_DISPOSABLE_DOMAINS);
+        assert!(domains.contains(&"mailinator.com".to_string()));
+        assert!(domains.iter().all(|d| !d.starts_with('#')));
+    }
+}
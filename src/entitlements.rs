@@ -0,0 +1,119 @@
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use tracing::error;
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::models::user::UserTier;
+
+// Usage limits that come with a tier
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Entitlements {
+    // Currencies the user may hold; posting in one more is refused
+    pub max_wallets: usize,
+    // Requests per minute across all of the user's API keys; sessions have their own limit
+    pub api_requests_per_minute: u32,
+    // Statement downloads and CSV imports per UTC day
+    pub daily_exports: i32,
+    pub max_webhook_endpoints: i64,
+}
+
+impl Entitlements {
+    pub fn for_tier(tier: UserTier) -> Self {
+        match tier {
+            UserTier::Free => Entitlements {
+                max_wallets: 3,
+                api_requests_per_minute: 60,
+                daily_exports: 20,
+                max_webhook_endpoints: 2,
+            },
+            UserTier::Plus => Entitlements {
+                max_wallets: 10,
+                api_requests_per_minute: 600,
+                daily_exports: 100,
+                max_webhook_endpoints: 10,
+            },
+            UserTier::Business => Entitlements {
+                max_wallets: 50,
+                api_requests_per_minute: 3000,
+                daily_exports: 1000,
+                max_webhook_endpoints: 50,
+            },
+        }
+    }
+}
+
+// None if the user doesn't exist
+pub async fn user_tier(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<UserTier>, sqlx::Error> {
+    sqlx::query_scalar!(r#"SELECT tier as "tier: UserTier" FROM users WHERE id = $1"#, user_id)
+        .fetch_optional(conn)
+        .await
+}
+
+// Fails with 404 if the user doesn't exist
+pub async fn entitlements_for(conn: &mut PgConnection, user_id: Uuid) -> Result<Entitlements, AppError> {
+    let tier = user_tier(conn, user_id).await.map_err(|e| {
+        error!("Failed to look up user tier: {}", e);
+        db_error(&e, "Failed to look up user tier")
+    })?;
+
+    tier.map(Entitlements::for_tier)
+        .ok_or(AppError::NotFound("User not found".to_string()))
+}
+
+// Every currency the user has a live or sandbox ledger entry in, whatever its status
+pub async fn wallet_currencies(conn: &mut PgConnection, user_id: Uuid, livemode: bool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT DISTINCT currency FROM transactions WHERE user_id = $1 AND livemode = $2",
+        user_id,
+        livemode
+    )
+    .fetch_all(conn)
+    .await
+}
+
+pub fn wallet_limit_reached(entitlements: &Entitlements) -> AppError {
+    AppError::PlanLimitReached(format!(
+        "Your plan allows at most {} currency wallets",
+        entitlements.max_wallets
+    ))
+}
+
+// Fails with 403 if posting in `currency` would open a wallet beyond the user's allowance, and with
+// 404 if the user doesn't exist
+pub async fn ensure_wallet_allowed(pool: &PgPool, user_id: Uuid, currency: &str, livemode: bool) -> Result<(), AppError> {
+    let mut conn = pool.acquire().await.map_err(|e| {
+        error!("Failed to acquire connection: {}", e);
+        db_error(&e, "Failed to check plan limits")
+    })?;
+
+    let entitlements = entitlements_for(&mut conn, user_id).await?;
+    let currencies = wallet_currencies(&mut conn, user_id, livemode).await.map_err(|e| {
+        error!("Failed to count wallets: {}", e);
+        db_error(&e, "Failed to check plan limits")
+    })?;
+
+    if !currencies.iter().any(|held| held == currency) && currencies.len() >= entitlements.max_wallets {
+        error!("User {} is at their limit of {} wallets", user_id, entitlements.max_wallets);
+        return Err(wallet_limit_reached(&entitlements));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_higher_tiers_never_allow_less() {
+        let tiers = [UserTier::Free, UserTier::Plus, UserTier::Business].map(Entitlements::for_tier);
+        for pair in tiers.windows(2) {
+            let (lower, higher) = (pair[0], pair[1]);
+            assert!(higher.max_wallets >= lower.max_wallets);
+            assert!(higher.api_requests_per_minute >= lower.api_requests_per_minute);
+            assert!(higher.daily_exports >= lower.daily_exports);
+            assert!(higher.max_webhook_endpoints >= lower.max_webhook_endpoints);
+        }
+    }
+}
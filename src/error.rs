@@ -0,0 +1,203 @@
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::feature_flags::{KillSwitch, FEATURE_DISABLED};
+use crate::middleware::request_id;
+use crate::models::velocity_limit::VelocityViolation;
+
+// Every error a handler or middleware can return. Messages are written for API clients, so causes
+// such as database errors are logged where they happen and never carried in here.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    BadRequest(String),
+    // The body broke validation rules; messages keyed by the field that broke them
+    Validation(BTreeMap<String, Vec<String>>),
+    Unauthorized(String),
+    // The API key is past its `expires_at`; the owner needs to create a new one
+    ApiKeyExpired,
+    Forbidden(String),
+    // The user's tier doesn't allow this; upgrading lifts the limit
+    PlanLimitReached(String),
+    // The session must re-authenticate through `POST /v1/auth/step-up` before retrying
+    StepUpRequired,
+    // The account was deactivated by an admin; its credentials no longer work
+    AccountDeactivated,
+    // Screening flagged the registrant; the account can't be used until an admin clears it
+    AccountPendingReview,
+    NotFound(String),
+    Conflict(String),
+    // The upload is larger than the endpoint accepts
+    PayloadTooLarge(String),
+    InsufficientFunds,
+    // The amount is one money can't move in, e.g. negative or finer than the currency's minor unit
+    InvalidAmount(String),
+    Unprocessable(String),
+    // The transaction would break one of the user's velocity limits, described in the body
+    VelocityLimitExceeded(Box<VelocityViolation>),
+    // The transaction is above what the user's KYC status allows; verifying lifts the limit
+    KycLimitExceeded(String),
+    // A fraud rule blocked the transaction; which one is kept from the client
+    TransactionDeclined,
+    // A per-user usage limit has been reached for now
+    LimitExceeded(String),
+    // Too many requests in a short time; `retry_after` is in seconds and sent as `Retry-After`
+    RateLimited { retry_after: u64 },
+    // An admin has switched the capability off
+    FeatureDisabled(KillSwitch),
+    // The database was too slow or busy to answer in time; safe to retry
+    Unavailable,
+    // The latest exchange rate is older than `FX_MAX_RATE_AGE_SECONDS`; retry once rates are fetched again
+    StaleExchangeRate(String),
+    // The service only serves reads, e.g. during a failover or maintenance window
+    ReadOnly,
+    Internal(String),
+}
+
+// Body of every error response
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    // Matches the `X-Request-Id` response header, for quoting to support
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    // What is wrong with each invalid field, for validation failures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<BTreeMap<String, Vec<String>>>,
+    // The velocity limit a transaction would break
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<VelocityViolation>,
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) | AppError::ApiKeyExpired => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_)
+            | AppError::PlanLimitReached(_)
+            | AppError::StepUpRequired
+            | AppError::AccountDeactivated
+            | AppError::AccountPendingReview => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::InsufficientFunds
+            | AppError::InvalidAmount(_)
+            | AppError::Unprocessable(_)
+            | AppError::VelocityLimitExceeded(_)
+            | AppError::KycLimitExceeded(_)
+            | AppError::TransactionDeclined => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::LimitExceeded(_) | AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::FeatureDisabled(_)
+            | AppError::Unavailable
+            | AppError::StaleExchangeRate(_)
+            | AppError::ReadOnly => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // Stable identifier clients can branch on; messages may be reworded at any time
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Validation(_) => "validation_failed",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::ApiKeyExpired => "api_key_expired",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::PlanLimitReached(_) => "plan_limit_reached",
+            AppError::StepUpRequired => "step_up_required",
+            AppError::AccountDeactivated => "account_deactivated",
+            AppError::AccountPendingReview => "account_pending_review",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::InsufficientFunds => "insufficient_funds",
+            AppError::InvalidAmount(_) => "invalid_amount",
+            AppError::Unprocessable(_) => "unprocessable",
+            AppError::VelocityLimitExceeded(_) => "velocity_limit_exceeded",
+            AppError::KycLimitExceeded(_) => "kyc_limit_exceeded",
+            AppError::TransactionDeclined => "transaction_declined",
+            AppError::LimitExceeded(_) => "limit_exceeded",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::FeatureDisabled(_) => "feature_disabled",
+            AppError::Unavailable => "service_unavailable",
+            AppError::StaleExchangeRate(_) => "stale_exchange_rate",
+            AppError::ReadOnly => "read_only",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AppError::BadRequest(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::PlanLimitReached(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict(message)
+            | AppError::PayloadTooLarge(message)
+            | AppError::InvalidAmount(message)
+            | AppError::Unprocessable(message)
+            | AppError::KycLimitExceeded(message)
+            | AppError::LimitExceeded(message)
+            | AppError::StaleExchangeRate(message)
+            | AppError::Internal(message) => message.clone(),
+            AppError::Validation(_) => "Request validation failed".to_string(),
+            AppError::ApiKeyExpired => "This API key has expired".to_string(),
+            AppError::StepUpRequired => "Step-up authentication required".to_string(),
+            AppError::AccountDeactivated => "This account has been deactivated".to_string(),
+            AppError::AccountPendingReview => "This account is waiting for review".to_string(),
+            AppError::InsufficientFunds => "Insufficient funds".to_string(),
+            AppError::VelocityLimitExceeded(violation) => violation.message(),
+            AppError::TransactionDeclined => "Transaction declined by risk checks".to_string(),
+            AppError::RateLimited { retry_after } => {
+                format!("Too many requests, retry in {} seconds", retry_after)
+            }
+            AppError::FeatureDisabled(switch) => format!("{}: {}", FEATURE_DISABLED, switch.flag_name()),
+            AppError::Unavailable => "Service temporarily unavailable, please retry".to_string(),
+            AppError::ReadOnly => "The service is read-only for maintenance; writes are disabled".to_string(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let errors = match &self {
+            AppError::Validation(errors) => Some(errors.clone()),
+            AppError::InvalidAmount(message) => Some(BTreeMap::from([("amount".to_string(), vec![message.clone()])])),
+            _ => None,
+        };
+        let limit = match &self {
+            AppError::VelocityLimitExceeded(violation) => Some(violation.as_ref().clone()),
+            _ => None,
+        };
+        let body = ErrorBody { code: self.code(), message: self.message(), request_id: request_id::current(), errors, limit };
+        let mut response = (self.status(), Json(body)).into_response();
+        if let AppError::RateLimited { retry_after } = self {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_response_carries_code_and_message() {
+        let response = AppError::FeatureDisabled(KillSwitch::Transfers).into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "feature_disabled");
+        assert_eq!(body["message"], "Feature temporarily disabled: transfers");
+    }
+}
@@ -0,0 +1,103 @@
+use serde_json::{json, Value};
+
+use crate::models::notification::EVENT_DIGEST;
+use crate::models::webhook::{WebhookPayloadVersion, EVENT_TRANSACTION_CREATED};
+
+// Account events are built once in an internal shape, `{ id, type, created_at, data }`, and adapted
+// here to each payload version receivers subscribed with. A payload change gets a new version with
+// its own function, and the older ones keep producing what their receivers were built against.
+pub fn adapt(version: WebhookPayloadVersion, event: &Value) -> Value {
+    match version {
+        WebhookPayloadVersion::V1 => v1(event),
+        WebhookPayloadVersion::V2 => v2(event),
+    }
+}
+
+// Digests carry whole events, which get the digest's version too
+fn adapt_digested(event: &mut Value, adapt_event: fn(&Value) -> Value) {
+    if event["type"] == EVENT_DIGEST {
+        if let Some(events) = event["data"]["events"].as_array_mut() {
+            for digested in events.iter_mut() {
+                *digested = adapt_event(digested);
+            }
+        }
+    }
+}
+
+fn v1(event: &Value) -> Value {
+    let mut adapted = json!({
+        "id": event["id"],
+        "type": event["type"],
+        "data": event["data"],
+    });
+    adapt_digested(&mut adapted, v1);
+    adapted
+}
+
+fn v2(event: &Value) -> Value {
+    let data = if event["type"] == EVENT_TRANSACTION_CREATED {
+        event["data"]["transaction"].clone()
+    } else {
+        event["data"].clone()
+    };
+    let mut adapted = json!({
+        "id": event["id"],
+        "type": event["type"],
+        "version": WebhookPayloadVersion::V2,
+        "created_at": event["created_at"],
+        "data": data,
+    });
+    adapt_digested(&mut adapted, v2);
+    adapted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::webhook::EVENT_BALANCE_UPDATED;
+
+    #[test]
+    fn test_events_are_adapted_to_each_version() {
+        let created = json!({
+            "id": "e1",
+            "type": EVENT_TRANSACTION_CREATED,
+            "created_at": 1710072000,
+            "data": { "transaction": { "id": "t1", "amount": "10.00" } },
+        });
+        let balance = json!({
+            "id": "e2",
+            "type": EVENT_BALANCE_UPDATED,
+            "created_at": 1710072001,
+            "data": { "currency": "USD", "balance": "10.00" },
+        });
+        let digest = json!({
+            "id": "e3",
+            "type": EVENT_DIGEST,
+            "created_at": 1710075600,
+            "data": { "event_type": EVENT_TRANSACTION_CREATED, "count": 1, "events": [created.clone()] },
+        });
+
+        // V1 is the original envelope, without the fields added since
+        assert_eq!(
+            adapt(WebhookPayloadVersion::V1, &created),
+            json!({ "id": "e1", "type": EVENT_TRANSACTION_CREATED, "data": { "transaction": { "id": "t1", "amount": "10.00" } } })
+        );
+        assert_eq!(adapt(WebhookPayloadVersion::V1, &digest)["data"]["events"][0], adapt(WebhookPayloadVersion::V1, &created));
+
+        assert_eq!(
+            adapt(WebhookPayloadVersion::V2, &created),
+            json!({
+                "id": "e1",
+                "type": EVENT_TRANSACTION_CREATED,
+                "version": "V2",
+                "created_at": 1710072000,
+                "data": { "id": "t1", "amount": "10.00" },
+            })
+        );
+        assert_eq!(adapt(WebhookPayloadVersion::V2, &balance)["data"], balance["data"]);
+        let digest_v2 = adapt(WebhookPayloadVersion::V2, &digest);
+        assert_eq!(digest_v2["version"], "V2");
+        assert_eq!(digest_v2["data"]["count"], 1);
+        assert_eq!(digest_v2["data"]["events"][0], adapt(WebhookPayloadVersion::V2, &created));
+    }
+}
@@ -0,0 +1,136 @@
+use serde_json::json;
+use sqlx::PgConnection;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::event_versions;
+use crate::handlers::{realtime, webhook};
+use crate::outbox;
+use crate::models::api_key::ApiKey;
+use crate::models::approval::TransactionApproval;
+use crate::models::budget::BudgetStatus;
+use crate::models::dispute::Dispute;
+use crate::models::payment_request::PaymentRequest;
+use crate::models::transaction::{Transaction, TransactionStatus};
+use crate::models::webhook::{
+    WebhookPayloadVersion, EVENT_API_KEY_EXPIRING, EVENT_APPROVAL_DECIDED, EVENT_APPROVAL_REQUESTED,
+    EVENT_BALANCE_UPDATED, EVENT_BUDGET_THRESHOLD_REACHED, EVENT_DISPUTE_OPENED, EVENT_DISPUTE_UPDATED,
+    EVENT_PAYMENT_REQUEST_CREATED, EVENT_PAYMENT_REQUEST_UPDATED, EVENT_TRANSACTION_CREATED,
+};
+
+// Account events fan out to webhook deliveries, realtime subscribers and the outbox for downstream
+// systems. All are written on the caller's connection, so nothing is sent unless the change the
+// event describes commits. Webhook
+// endpoints get events in their subscribed payload version, realtime subscribers always get V1.
+// Sandbox entries publish nothing, so test traffic never reaches a user's integrations.
+
+// Publishes `transaction.created` for a new ledger entry, plus `balance.updated` when it settled
+// straight away
+pub async fn publish_transaction_created(conn: &mut PgConnection, transaction: &Transaction) -> Result<(), sqlx::Error> {
+    if !transaction.livemode {
+        return Ok(());
+    }
+
+    publish(conn, transaction.user_id, EVENT_TRANSACTION_CREATED, json!({ "transaction": transaction })).await?;
+
+    if transaction.status == TransactionStatus::Settled {
+        publish_balance_updated(conn, transaction.user_id, &transaction.currency).await?;
+    }
+
+    Ok(())
+}
+
+// Publishes `balance.updated` with the user's current live settled and available balance in `currency`
+pub async fn publish_balance_updated(conn: &mut PgConnection, user_id: Uuid, currency: &str) -> Result<(), sqlx::Error> {
+    let balance = sqlx::query!(
+        "SELECT balance, held FROM account_balances WHERE user_id = $1 AND currency = $2 AND livemode",
+        user_id,
+        currency
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let data = json!({
+        "user_id": user_id,
+        "currency": currency,
+        "balance": balance.balance,
+        "available": &balance.balance - &balance.held,
+    });
+    publish(conn, user_id, EVENT_BALANCE_UPDATED, data).await
+}
+
+// Publishes `budget.threshold_reached` when live spending in a budget's current period reaches
+// `threshold` percent of its limit
+pub async fn publish_budget_threshold_reached(conn: &mut PgConnection, status: &BudgetStatus, threshold: i16) -> Result<(), sqlx::Error> {
+    let budget = &status.budget;
+    let data = json!({
+        "budget_id": budget.id,
+        "user_id": budget.user_id,
+        "category": budget.category,
+        "currency": budget.currency,
+        "period": budget.period,
+        "period_start": status.period_start.unix_timestamp(),
+        "period_end": status.period_end.unix_timestamp(),
+        "limit_amount": budget.limit_amount,
+        "spent": status.spent,
+        "threshold": threshold,
+    });
+    publish(conn, budget.user_id, EVENT_BUDGET_THRESHOLD_REACHED, data).await
+}
+
+// Publishes `approval.requested` to the transaction's owner and to each of their named approvers
+pub async fn publish_approval_requested(
+    conn: &mut PgConnection,
+    approval: &TransactionApproval,
+    approver_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    let data = json!(approval);
+    publish(conn, approval.user_id, EVENT_APPROVAL_REQUESTED, data.clone()).await?;
+    for approver_id in approver_ids {
+        publish(conn, *approver_id, EVENT_APPROVAL_REQUESTED, data.clone()).await?;
+    }
+    Ok(())
+}
+
+// Publishes `approval.decided` to the transaction's owner once the approval is approved or rejected
+pub async fn publish_approval_decided(conn: &mut PgConnection, approval: &TransactionApproval) -> Result<(), sqlx::Error> {
+    publish(conn, approval.user_id, EVENT_APPROVAL_DECIDED, json!(approval)).await
+}
+
+// Publishes `dispute.opened` for a new dispute, or `dispute.updated` when its status changes, to the
+// disputing user
+pub async fn publish_dispute(conn: &mut PgConnection, dispute: &Dispute, opened: bool) -> Result<(), sqlx::Error> {
+    if !dispute.livemode {
+        return Ok(());
+    }
+
+    let event_type = if opened { EVENT_DISPUTE_OPENED } else { EVENT_DISPUTE_UPDATED };
+    publish(conn, dispute.user_id, event_type, json!(dispute)).await
+}
+
+// Publishes `payment_request.created` for a new request, or `payment_request.updated` when its
+// status changes, to both the requester and the payer
+pub async fn publish_payment_request(conn: &mut PgConnection, request: &PaymentRequest, created: bool) -> Result<(), sqlx::Error> {
+    let event_type = if created { EVENT_PAYMENT_REQUEST_CREATED } else { EVENT_PAYMENT_REQUEST_UPDATED };
+    let data = json!(request);
+    publish(conn, request.requester_id, event_type, data.clone()).await?;
+    publish(conn, request.payer_id, event_type, data).await
+}
+
+// Publishes `api_key.expiring` to the key's owner ahead of its expiry
+pub async fn publish_api_key_expiring(conn: &mut PgConnection, api_key: &ApiKey) -> Result<(), sqlx::Error> {
+    publish(conn, api_key.user_id, EVENT_API_KEY_EXPIRING, json!(api_key)).await
+}
+
+async fn publish(conn: &mut PgConnection, user_id: Uuid, event_type: &str, data: serde_json::Value) -> Result<(), sqlx::Error> {
+    let payload = json!({
+        "id": Uuid::new_v4(),
+        "type": event_type,
+        "created_at": OffsetDateTime::now_utc().unix_timestamp(),
+        "data": data,
+    });
+
+    webhook::enqueue_deliveries(conn, user_id, event_type, &payload).await?;
+    outbox::enqueue(conn, Some(user_id), event_type, &payload).await?;
+    realtime::notify(conn, user_id, &event_versions::adapt(WebhookPayloadVersion::V1, &payload)).await
+}
@@ -0,0 +1,168 @@
+use bigdecimal::BigDecimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tracing::error;
+
+use crate::config::Config;
+use crate::models::transaction::normalize_currency;
+use crate::outbound::OutboundClient;
+
+// Placeholder in `FX_RATES_URL` replaced by the base currency being fetched
+pub const BASE_PLACEHOLDER: &str = "{base}";
+
+// The European Central Bank's daily reference rates, all against the euro
+const ECB_DAILY_URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+const ECB_BASE: &str = "EUR";
+
+const OPEN_EXCHANGE_RATES_URL: &str = "https://openexchangerates.org/api/latest.json";
+
+#[derive(Debug, Clone)]
+pub enum FxProvider {
+    // Any provider answering `{"rates": {...}}`, at a URL with `{base}` standing in for the base currency
+    Url(String),
+    // The ECB only publishes euro rates; other pairs are crossed through the euro
+    Ecb,
+    OpenExchangeRates { app_id: String },
+}
+
+// Where rates are fetched from. The fetcher only runs when a provider is configured.
+#[derive(Debug, Clone)]
+pub struct FxRateSource {
+    pub provider: FxProvider,
+    pub base_currencies: Vec<String>,
+}
+
+impl FxRateSource {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let provider = match config.fx_provider.as_deref().filter(|name| !name.is_empty()) {
+            Some("ecb") => FxProvider::Ecb,
+            Some("openexchangerates") => FxProvider::OpenExchangeRates { app_id: config.open_exchange_rates_app_id.clone()? },
+            Some(other) => {
+                error!("Unknown FX_PROVIDER {}; expected ecb or openexchangerates", other);
+                return None;
+            }
+            None => FxProvider::Url(config.fx_rates_url.clone().filter(|url| !url.is_empty())?),
+        };
+        let base_currencies = config.fx_base_currencies.iter().filter_map(|base| normalize_currency(base)).collect();
+
+        Some(Self { provider, base_currencies })
+    }
+
+    // The URL to fetch each base currency's rates from
+    fn requests(&self) -> Vec<(String, String)> {
+        match &self.provider {
+            FxProvider::Ecb => vec![(ECB_BASE.to_string(), ECB_DAILY_URL.to_string())],
+            FxProvider::Url(template) => self
+                .base_currencies
+                .iter()
+                .map(|base| (base.clone(), template.replace(BASE_PLACEHOLDER, base)))
+                .collect(),
+            FxProvider::OpenExchangeRates { app_id } => self
+                .base_currencies
+                .iter()
+                .map(|base| (base.clone(), format!("{}?app_id={}&base={}", OPEN_EXCHANGE_RATES_URL, app_id, base)))
+                .collect(),
+        }
+    }
+}
+
+// Provider response: how many units of each quote currency one unit of the base buys
+#[derive(Debug, Deserialize)]
+struct ProviderRates {
+    rates: HashMap<String, serde_json::Number>,
+}
+
+// Reads the `<Cube currency='USD' rate='1.0812'/>` entries of the ECB's daily feed
+fn parse_ecb(xml: &str) -> Vec<(String, String)> {
+    let attribute = |element: &str, name: &str| {
+        let start = element.find(&format!("{}='", name))? + name.len() + 2;
+        let end = element[start..].find('\'')?;
+        Some(element[start..start + end].to_string())
+    };
+    xml.split("<Cube")
+        .filter_map(|element| Some((attribute(element, "currency")?, attribute(element, "rate")?)))
+        .collect()
+}
+
+async fn fetch_base(client: &OutboundClient, provider: &FxProvider, url: &str) -> Result<(String, Vec<(String, String)>), String> {
+    let request = client.client().get(url).build().map_err(|e| format!("invalid URL: {}", e))?;
+    let host = request.url().host_str().unwrap_or_default().to_string();
+
+    let response = client.execute(request).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("provider returned {}", response.status()));
+    }
+    let rates = match provider {
+        FxProvider::Ecb => parse_ecb(&response.text().await.map_err(|e| e.to_string())?),
+        FxProvider::Url(_) | FxProvider::OpenExchangeRates { .. } => {
+            let rates = response.json::<ProviderRates>().await.map_err(|e| format!("unreadable rates: {}", e))?;
+            rates.rates.into_iter().map(|(quote, rate)| (quote, rate.to_string())).collect()
+        }
+    };
+    Ok((host, rates))
+}
+
+// Fetches the latest rates for every configured base currency and stores them. A provider failure
+// skips that base currency until the next run; only database errors abort the run.
+pub async fn fetch_rates(pool: &PgPool, client: &OutboundClient, source: &FxRateSource) -> Result<usize, sqlx::Error> {
+    let mut stored = 0;
+
+    for (base, url) in source.requests() {
+        let (host, rates) = match fetch_base(client, &source.provider, &url).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                error!("Failed to fetch FX rates for {}: {}", base, e);
+                continue;
+            }
+        };
+
+        let (quotes, values): (Vec<String>, Vec<BigDecimal>) = rates
+            .into_iter()
+            .filter_map(|(quote, rate)| {
+                let quote = normalize_currency(&quote).filter(|quote| *quote != base)?;
+                let rate = BigDecimal::from_str(&rate).ok().filter(|rate| *rate > 0)?;
+                Some((quote, rate))
+            })
+            .unzip();
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO fx_rates (base_currency, quote_currency, rate, source)
+            SELECT $1, quote_currency, rate, $4
+            FROM UNNEST($2::text[], $3::numeric[]) AS rates(quote_currency, rate)
+            "#,
+            base,
+            &quotes,
+            &values,
+            host
+        )
+        .execute(pool)
+        .await?;
+
+        stored += inserted.rows_affected() as usize;
+    }
+
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ecb_feed_is_read_as_euro_rates() {
+        let xml = r#"<gesmes:Envelope><Cube><Cube time='2024-05-03'>
+            <Cube currency='USD' rate='1.0760'/>
+            <Cube currency='JPY' rate='164.62'/>
+        </Cube></Cube></gesmes:Envelope>"#;
+
+        assert_eq!(
+            parse_ecb(xml),
+            [("USD".to_string(), "1.0760".to_string()), ("JPY".to_string(), "164.62".to_string())]
+        );
+        let source = FxRateSource { provider: FxProvider::Ecb, base_currencies: vec!["USD".to_string()] };
+        assert_eq!(source.requests(), [("EUR".to_string(), ECB_DAILY_URL.to_string())]);
+    }
+}
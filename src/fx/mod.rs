@@ -0,0 +1,296 @@
+pub mod fetcher;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use serde::Serialize;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use tracing::error;
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::models::money::{Currency, Money, MoneyError};
+
+// Rates are reloaded from the database at most this often; the fetcher runs far less often
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+// Derived rates are cut to this many decimal places
+const RATE_SCALE: i64 = 10;
+
+// A spread of 10000 basis points would take the whole amount
+const BASIS_POINTS: i64 = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub rate: BigDecimal,
+    pub fetched_at: OffsetDateTime,
+}
+
+// The latest stored rate of every currency pair
+#[derive(Debug, Default)]
+pub struct LatestRates {
+    quotes: HashMap<(Currency, Currency), Quote>,
+}
+
+impl LatestRates {
+    // Straight from the database, for when the cache's copy isn't wanted
+    pub async fn load(executor: impl PgExecutor<'_>) -> Result<LatestRates, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT ON (base_currency, quote_currency)
+                base_currency as "base_currency!", quote_currency as "quote_currency!", rate, fetched_at
+            FROM fx_rates
+            ORDER BY base_currency, quote_currency, fetched_at DESC
+            "#
+        )
+        .fetch_all(executor)
+        .await?;
+        let quotes = rows
+            .into_iter()
+            .filter_map(|row| {
+                let pair = (Currency::parse(&row.base_currency)?, Currency::parse(&row.quote_currency)?);
+                Some((pair, Quote { rate: row.rate, fetched_at: row.fetched_at }))
+            })
+            .collect();
+        Ok(LatestRates { quotes })
+    }
+
+    // How many units of `to` one unit of `from` buys, at mid-market. A stored quote for the pair
+    // is preferred, then the inverse of the opposite pair's, then a cross through a base both are
+    // quoted against, e.g. GBP to JPY through EUR for ECB rates, taking the freshest cross and the
+    // first base in code order among equally fresh ones. `fetched_at` is that of the oldest rate used.
+    pub fn mid_rate(&self, from: Currency, to: Currency) -> Option<Quote> {
+        if from == to {
+            return None;
+        }
+        if let Some(quote) = self.quotes.get(&(from, to)) {
+            return Some(quote.clone());
+        }
+        if let Some(quote) = self.quotes.get(&(to, from)) {
+            return Some(Quote {
+                rate: quote.rate.inverse().with_scale_round(RATE_SCALE, RoundingMode::HalfEven),
+                fetched_at: quote.fetched_at,
+            });
+        }
+        self.quotes
+            .iter()
+            .filter(|((_, quote), _)| *quote == from)
+            .filter_map(|((base, _), base_from)| {
+                let base_to = self.quotes.get(&(*base, to))?;
+                let quote = Quote {
+                    rate: (&base_to.rate / &base_from.rate).with_scale_round(RATE_SCALE, RoundingMode::HalfEven),
+                    fetched_at: base_from.fetched_at.min(base_to.fetched_at),
+                };
+                Some((*base, quote))
+            })
+            .max_by_key(|(base, quote)| (quote.fetched_at, Reverse(*base)))
+            .map(|(_, quote)| quote)
+    }
+
+    // `mid_rate` for converting money at: refused as missing when there's no rate, and as stale when
+    // it's older than `max_age`, e.g. because the fetcher has been failing
+    pub fn current_rate(&self, from: Currency, to: Currency, max_age: time::Duration) -> Result<Quote, AppError> {
+        let quote = self
+            .mid_rate(from, to)
+            .ok_or(AppError::Unprocessable(format!("No exchange rate from {} to {}", from, to)))?;
+        if quote.fetched_at < OffsetDateTime::now_utc() - max_age {
+            return Err(AppError::StaleExchangeRate(format!(
+                "The exchange rate from {} to {} is out of date, please retry later",
+                from, to
+            )));
+        }
+        Ok(quote)
+    }
+
+    // Every currency with a stored rate, as base or quote
+    pub fn currencies(&self) -> Vec<Currency> {
+        let mut currencies: Vec<Currency> = self.quotes.keys().flat_map(|(base, quote)| [*base, *quote]).collect();
+        currencies.sort();
+        currencies.dedup();
+        currencies
+    }
+}
+
+// Keeps the latest rates in memory so conversions don't query the rate history each time
+#[derive(Default)]
+pub struct RateCache {
+    latest: Mutex<Option<(Instant, Arc<LatestRates>)>>,
+}
+
+impl RateCache {
+    pub async fn latest(&self, pool: &PgPool) -> Result<Arc<LatestRates>, sqlx::Error> {
+        if let Some((loaded_at, rates)) = self.latest.lock().unwrap().as_ref() {
+            if loaded_at.elapsed() < CACHE_TTL {
+                return Ok(rates.clone());
+            }
+        }
+
+        let rates = Arc::new(LatestRates::load(pool).await?);
+        *self.latest.lock().unwrap() = Some((Instant::now(), rates.clone()));
+        Ok(rates)
+    }
+}
+
+// `amount` and the sum of `used`, stored amounts in any currency, converted into `to` at mid-market
+// for checking against a limit set in `to`. Money isn't moved at these values, so a stale rate will
+// do; rates are only loaded when something is in another currency.
+pub async fn limit_values(
+    conn: &mut PgConnection,
+    to: &str,
+    amount: &Money,
+    used: &[(&str, &BigDecimal)],
+) -> Result<(BigDecimal, BigDecimal), AppError> {
+    let stored = |code: &str| Currency::parse(code).ok_or(AppError::Internal(format!("Invalid stored currency {}", code)));
+    let to = stored(to)?;
+    let used = used
+        .iter()
+        .map(|(currency, total)| Ok((stored(currency)?, *total)))
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    let converting = amount.currency() != to || used.iter().any(|(currency, _)| *currency != to);
+    let rates = match converting {
+        true => LatestRates::load(conn).await.map_err(|e| {
+            error!("Failed to load exchange rates: {}", e);
+            db_error(&e, "Failed to load exchange rates")
+        })?,
+        false => LatestRates::default(),
+    };
+    let value = |currency: Currency, amount: &BigDecimal| -> Result<BigDecimal, AppError> {
+        if currency == to {
+            return Ok(amount.clone());
+        }
+        let quote = rates
+            .mid_rate(currency, to)
+            .ok_or(AppError::Unprocessable(format!("No exchange rate from {} to {}", currency, to)))?;
+        Ok((amount * quote.rate).with_scale_round(to.minor_units(), RoundingMode::HalfEven))
+    };
+
+    let total = used.iter().map(|(currency, total)| value(*currency, total)).sum::<Result<BigDecimal, AppError>>()?;
+    Ok((value(amount.currency(), &amount.to_decimal())?, total))
+}
+
+// The rate a customer gets: the mid-market rate less the spread, which Dodo keeps
+pub fn customer_rate(mid_rate: &BigDecimal, spread_bps: u32) -> BigDecimal {
+    let kept = BigDecimal::from(BASIS_POINTS - i64::from(spread_bps)) / BigDecimal::from(BASIS_POINTS);
+    (mid_rate * kept).with_scale_round(RATE_SCALE, RoundingMode::Down)
+}
+
+// An amount converted into another currency
+#[derive(Debug, Clone, Serialize)]
+pub struct Conversion {
+    pub mid_rate: BigDecimal,
+    // The rate applied, after the spread
+    pub rate: BigDecimal,
+    pub spread_bps: u32,
+    pub converted: Money,
+}
+
+// Converts `amount` at `mid_rate` less the spread, rounding down to `to`'s minor unit
+pub fn convert(amount: &Money, to: Currency, mid_rate: &BigDecimal, spread_bps: u32) -> Result<Conversion, MoneyError> {
+    let rate = customer_rate(mid_rate, spread_bps);
+    let converted = (amount.to_decimal() * &rate).with_scale_round(to.minor_units(), RoundingMode::Down);
+    Ok(Conversion {
+        mid_rate: mid_rate.clone(),
+        converted: Money::from_decimal(&converted, to)?,
+        rate,
+        spread_bps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn currency(code: &str) -> Currency {
+        Currency::parse(code).unwrap()
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_rates_are_found_directly_inverted_or_crossed() {
+        let now = OffsetDateTime::now_utc();
+        let rates = LatestRates {
+            quotes: HashMap::from([
+                ((currency("EUR"), currency("USD")), Quote { rate: decimal("1.25"), fetched_at: now }),
+                ((currency("EUR"), currency("JPY")), Quote { rate: decimal("160"), fetched_at: now - time::Duration::hours(1) }),
+            ]),
+        };
+
+        assert_eq!(rates.mid_rate(currency("EUR"), currency("USD")).unwrap().rate, decimal("1.25"));
+        assert_eq!(rates.mid_rate(currency("USD"), currency("EUR")).unwrap().rate, decimal("0.8"));
+        let crossed = rates.mid_rate(currency("USD"), currency("JPY")).unwrap();
+        assert_eq!(crossed.rate, decimal("128"));
+        assert_eq!(crossed.fetched_at, now - time::Duration::hours(1));
+        assert!(rates.mid_rate(currency("USD"), currency("GBP")).is_none());
+        assert!(rates.mid_rate(currency("USD"), currency("USD")).is_none());
+    }
+
+    #[test]
+    fn test_equally_fresh_crosses_go_through_the_first_base() {
+        let now = OffsetDateTime::now_utc();
+        let quote = |rate: &str| Quote { rate: decimal(rate), fetched_at: now };
+        let rates = LatestRates {
+            quotes: HashMap::from([
+                ((currency("EUR"), currency("GBP")), quote("0.8")),
+                ((currency("EUR"), currency("JPY")), quote("160")),
+                ((currency("USD"), currency("GBP")), quote("0.5")),
+                ((currency("USD"), currency("JPY")), quote("150")),
+            ]),
+        };
+
+        for _ in 0..10 {
+            assert_eq!(rates.mid_rate(currency("GBP"), currency("JPY")).unwrap().rate, decimal("200"));
+        }
+    }
+
+    #[test]
+    fn test_stale_rates_are_refused() {
+        let now = OffsetDateTime::now_utc();
+        let rates = LatestRates {
+            quotes: HashMap::from([
+                ((currency("EUR"), currency("USD")), Quote { rate: decimal("1.25"), fetched_at: now }),
+                ((currency("EUR"), currency("JPY")), Quote { rate: decimal("160"), fetched_at: now - time::Duration::days(5) }),
+            ]),
+        };
+        let max_age = time::Duration::days(4);
+
+        assert!(rates.current_rate(currency("EUR"), currency("USD"), max_age).is_ok());
+        assert!(matches!(
+            rates.current_rate(currency("EUR"), currency("JPY"), max_age),
+            Err(AppError::StaleExchangeRate(_))
+        ));
+        // A cross is as old as its oldest leg
+        assert!(matches!(
+            rates.current_rate(currency("USD"), currency("JPY"), max_age),
+            Err(AppError::StaleExchangeRate(_))
+        ));
+        assert!(matches!(
+            rates.current_rate(currency("USD"), currency("GBP"), max_age),
+            Err(AppError::Unprocessable(_))
+        ));
+    }
+
+    #[test]
+    fn test_conversions_take_the_spread_and_round_down() {
+        let amount = Money::from_decimal(&decimal("100"), currency("USD")).unwrap();
+
+        let conversion = convert(&amount, currency("EUR"), &decimal("0.92"), 0).unwrap();
+        assert_eq!(conversion.converted.to_decimal(), decimal("92"));
+
+        // 50 basis points off 0.92 is 0.9154
+        let conversion = convert(&amount, currency("EUR"), &decimal("0.92"), 50).unwrap();
+        assert_eq!(conversion.rate, decimal("0.9154"));
+        assert_eq!(conversion.converted.to_decimal(), decimal("91.54"));
+
+        // Yen have no minor unit, so the fraction is dropped
+        let conversion = convert(&amount, currency("JPY"), &decimal("155.559"), 0).unwrap();
+        assert_eq!(conversion.converted.to_decimal(), decimal("15555"));
+    }
+}
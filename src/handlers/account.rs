@@ -0,0 +1,246 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::db::db_error;
+use crate::entitlements::ensure_wallet_allowed;
+use crate::error::AppError;
+use crate::middleware::auth::Livemode;
+use crate::models::account::{Account, AccountBalanceDetails, CreateAccount, UpdateAccount};
+use crate::models::money::Currency;
+use crate::models::transaction::{TransactionCursor, TransactionPage, TransactionQuery};
+use crate::repositories::account as accounts;
+use crate::repositories::user as users;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::validation::ValidatedJson;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+async fn find_account(pool: &PgPool, user_id: Uuid, account_id: Uuid) -> Result<Account, AppError> {
+    accounts::find(pool, user_id, account_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch account: {}", e);
+            db_error(&e, "Failed to fetch account")
+        })?
+        .ok_or(AppError::NotFound("Account not found".to_string()))
+}
+
+// Opens another account next to the user's main one, e.g. savings
+pub async fn create_account(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Livemode(livemode): Livemode,
+    ValidatedJson(payload): ValidatedJson<CreateAccount>,
+) -> Result<Json<Account>, AppError> {
+    info!("Opening account for user {}: {:?}", user_id, payload);
+
+    let currency = Currency::parse(&payload.currency)
+        .ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    // Also checks that the user exists
+    ensure_wallet_allowed(&pool, user_id, currency.as_str(), livemode).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    let account = accounts::insert(&mut *tx, user_id, payload.name.trim(), payload.account_type, currency.as_str())
+        .await
+        .map_err(|e| {
+            error!("Failed to create account: {}", e);
+            db_error(&e, "Failed to create account")
+        })?;
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::AccountOpened, user_id, Some(user_id)).target(account.id).after(&account))
+        .await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!("Opened account {} for user {}", account.id, user_id);
+    Ok(Json(account))
+}
+
+pub async fn get_accounts(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<Account>>, AppError> {
+    info!("Fetching accounts for user {}", user_id);
+
+    let accounts = accounts::list(&pool, user_id).await.map_err(|e| {
+        error!("Failed to fetch accounts: {}", e);
+        db_error(&e, "Failed to fetch accounts")
+    })?;
+
+    Ok(Json(accounts))
+}
+
+pub async fn get_account(
+    State(pool): State<PgPool>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Account>, AppError> {
+    find_account(&pool, user_id, account_id).await.map(Json)
+}
+
+pub async fn update_account(
+    State(pool): State<PgPool>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<UpdateAccount>,
+) -> Result<Json<Account>, AppError> {
+    info!("Renaming account {} of user {}", account_id, user_id);
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    let before = accounts::find(&mut *tx, user_id, account_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch account: {}", e);
+            db_error(&e, "Failed to update account")
+        })?
+        .ok_or(AppError::NotFound("Account not found".to_string()))?;
+    let account = accounts::rename(&mut *tx, user_id, account_id, payload.name.trim())
+        .await
+        .map_err(|e| {
+            error!("Failed to rename account {}: {}", account_id, e);
+            db_error(&e, "Failed to update account")
+        })?
+        .ok_or(AppError::NotFound("Account not found".to_string()))?;
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::AccountRenamed, user_id, Some(user_id)).target(account.id).before(&before).after(&account))
+        .await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    Ok(Json(account))
+}
+
+// Closes an account the user no longer needs. Only an empty account with nothing still to settle
+// can be closed, so no money is stranded in it; its history is kept. Main accounts stay open.
+pub async fn close_account(
+    State(pool): State<PgPool>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Account>, AppError> {
+    info!("Closing account {} of user {}", account_id, user_id);
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    // Serializes against debits and transfers touching the account
+    users::lock(&mut *tx, user_id).await.map_err(|e| {
+        error!("Failed to lock user {}: {}", user_id, e);
+        db_error(&e, "Failed to close account")
+    })?;
+    let account = accounts::find(&mut *tx, user_id, account_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch account: {}", e);
+            db_error(&e, "Failed to close account")
+        })?
+        .ok_or(AppError::NotFound("Account not found".to_string()))?;
+
+    if account.is_primary {
+        return Err(AppError::Conflict("Main accounts cannot be closed".to_string()));
+    }
+    if account.closed_at.is_some() {
+        return Err(AppError::Conflict("Account is already closed".to_string()));
+    }
+    let open_entries = accounts::has_open_entries(&mut *tx, account_id).await.map_err(|e| {
+        error!("Failed to check open entries: {}", e);
+        db_error(&e, "Failed to close account")
+    })?;
+    if open_entries {
+        return Err(AppError::Conflict("Account has pending, scheduled or held transactions".to_string()));
+    }
+    for livemode in [true, false] {
+        let balance = accounts::balance(&mut *tx, &account, livemode).await.map_err(|e| {
+            error!("Failed to compute account balance: {}", e);
+            db_error(&e, "Failed to close account")
+        })?;
+        if balance.balance != 0 {
+            return Err(AppError::Conflict("Only accounts with a zero balance can be closed".to_string()));
+        }
+    }
+
+    let closed = accounts::close(&mut *tx, account_id).await.map_err(|e| {
+        error!("Failed to close account {}: {}", account_id, e);
+        db_error(&e, "Failed to close account")
+    })?;
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::AccountClosed, user_id, Some(user_id)).target(account_id).before(&account).after(&closed))
+        .await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!("Closed account {} of user {}", account_id, user_id);
+    Ok(Json(closed))
+}
+
+pub async fn get_account_balance(
+    State(pool): State<PgPool>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<AccountBalanceDetails>, AppError> {
+    let account = find_account(&pool, user_id, account_id).await?;
+    let balance = accounts::balance(&pool, &account, livemode).await.map_err(|e| {
+        error!("Failed to compute account balance: {}", e);
+        db_error(&e, "Failed to fetch balance")
+    })?;
+    Ok(Json(balance))
+}
+
+// The account's history, paginated like the user's whole history
+pub async fn get_account_transactions(
+    State(pool): State<PgPool>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+    Query(query): Query<TransactionQuery>,
+) -> Result<TransactionPage, AppError> {
+    info!("Fetching transactions of account {} for user {}: {:?}", account_id, user_id, query);
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let cursor = match query.cursor.as_deref() {
+        Some(cursor) => Some(
+            TransactionCursor::decode(cursor)
+                .ok_or(AppError::BadRequest("Invalid cursor".to_string()))?,
+        ),
+        None => None,
+    };
+
+    let account = find_account(&pool, user_id, account_id).await?;
+
+    // Fetch one extra row to learn whether another page follows
+    let mut transactions = accounts::transactions(&pool, account.id, livemode, cursor.as_ref(), limit + 1)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch transactions: {}", e);
+            db_error(&e, "Failed to fetch transactions")
+        })?;
+
+    let next_cursor = if transactions.len() as i64 > limit {
+        transactions.truncate(limit as usize);
+        transactions.last().map(|last| TransactionCursor {
+            created_at: last.created_at,
+            id: last.id,
+        }.encode())
+    } else {
+        None
+    };
+
+    Ok(TransactionPage { transactions, next_cursor, signed_amounts: query.signed_amounts })
+}
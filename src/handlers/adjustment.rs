@@ -0,0 +1,325 @@
+use axum::{
+    extract::{Extension, State, Path},
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::services::ledger::insert_transaction;
+use crate::middleware::auth::AuthContext;
+use crate::models::adjustment::{Adjustment, AdjustmentStatus, CreateAdjustment};
+use crate::models::money::{Currency, Money};
+use crate::models::transaction::TransactionStatus;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+
+pub async fn create_adjustment(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(user_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<CreateAdjustment>,
+) -> Result<Json<Adjustment>, AppError> {
+    info!("Admin {} creating adjustment for user {}: {:?}", auth.user_id, user_id, payload);
+
+    if payload.amount <= 0 {
+        return Err(AppError::BadRequest("Amount must be positive".to_string()));
+    }
+
+    if payload.note.trim().is_empty() {
+        return Err(AppError::BadRequest("A note is required".to_string()));
+    }
+
+    let currency = Currency::parse(&payload.currency)
+        .ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    // Corrections may exceed the transaction maximum, but must still be whole minor units
+    let amount = Money::from_decimal(&payload.amount, currency).map_err(|e| AppError::InvalidAmount(e.to_string()))?;
+
+    let user_exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1) as \"exists!\"",
+        user_id
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to check user existence: {}", e);
+        db_error(&e, "Failed to check user existence")
+    })?;
+    if !user_exists {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let adjustment = sqlx::query_as!(
+        Adjustment,
+        r#"
+        INSERT INTO adjustments (user_id, amount, currency, transaction_type, reason_code, note, status, requested_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, user_id, amount, currency, transaction_type as "transaction_type: _", reason_code as "reason_code: _",
+            note, status as "status: _", requested_by, decided_by, transaction_id, created_at, decided_at
+        "#,
+        user_id,
+        amount.to_decimal(),
+        currency.as_str(),
+        payload.transaction_type as _,
+        payload.reason_code as _,
+        payload.note,
+        AdjustmentStatus::PendingApproval as _,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to create adjustment: {}", e);
+        db_error(&e, "Failed to create adjustment")
+    })?;
+
+    // Adjustments above the threshold need a second admin's approval before they are posted
+    let adjustment = if adjustment.amount > config.adjustment_approval_threshold {
+        info!(target: "audit", "Adjustment {} by admin {} awaits approval", adjustment.id, auth.user_id);
+        adjustment
+    } else {
+        post_adjustment(&mut tx, &adjustment, None).await?
+    };
+    let record = AuditRecord::new(AuditAction::AdjustmentCreated, auth.user_id, Some(user_id))
+        .target(adjustment.id)
+        .after(&adjustment);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    Ok(Json(adjustment))
+}
+
+pub async fn approve_adjustment(
+    State(pool): State<PgPool>,
+    Path(adjustment_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Adjustment>, AppError> {
+    info!("Admin {} approving adjustment {}", auth.user_id, adjustment_id);
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let adjustment = lock_pending_adjustment(&mut tx, adjustment_id).await?;
+
+    if adjustment.requested_by == auth.user_id {
+        error!("Admin {} attempted to approve their own adjustment {}", auth.user_id, adjustment_id);
+        return Err(AppError::Forbidden("Adjustments must be approved by a different admin".to_string()));
+    }
+
+    let posted = post_adjustment(&mut tx, &adjustment, Some(auth.user_id)).await?;
+    let record = AuditRecord::new(AuditAction::AdjustmentApproved, auth.user_id, Some(posted.user_id))
+        .target(posted.id)
+        .before(&adjustment)
+        .after(&posted);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    Ok(Json(posted))
+}
+
+pub async fn reject_adjustment(
+    State(pool): State<PgPool>,
+    Path(adjustment_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Adjustment>, AppError> {
+    info!("Admin {} rejecting adjustment {}", auth.user_id, adjustment_id);
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let pending = lock_pending_adjustment(&mut tx, adjustment_id).await?;
+
+    let adjustment = sqlx::query_as!(
+        Adjustment,
+        r#"
+        UPDATE adjustments
+        SET status = 'rejected', decided_by = $2, decided_at = NOW()
+        WHERE id = $1
+        RETURNING id, user_id, amount, currency, transaction_type as "transaction_type: _", reason_code as "reason_code: _",
+            note, status as "status: _", requested_by, decided_by, transaction_id, created_at, decided_at
+        "#,
+        adjustment_id,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to reject adjustment: {}", e);
+        db_error(&e, "Failed to reject adjustment")
+    })?;
+    let record = AuditRecord::new(AuditAction::AdjustmentRejected, auth.user_id, Some(adjustment.user_id))
+        .target(adjustment.id)
+        .before(&pending)
+        .after(&adjustment);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "Adjustment {} rejected by admin {}", adjustment.id, auth.user_id);
+    Ok(Json(adjustment))
+}
+
+pub async fn get_user_adjustments(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<Adjustment>>, AppError> {
+    info!("Fetching adjustments for user {}", user_id);
+
+    let adjustments = sqlx::query_as!(
+        Adjustment,
+        r#"
+        SELECT id, user_id, amount, currency, transaction_type as "transaction_type: _", reason_code as "reason_code: _",
+            note, status as "status: _", requested_by, decided_by, transaction_id, created_at, decided_at
+        FROM adjustments
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch adjustments: {}", e);
+        db_error(&e, "Failed to fetch adjustments")
+    })?;
+
+    Ok(Json(adjustments))
+}
+
+pub async fn get_pending_adjustments(
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<Adjustment>>, AppError> {
+    info!("Fetching adjustments awaiting approval");
+
+    let adjustments = sqlx::query_as!(
+        Adjustment,
+        r#"
+        SELECT id, user_id, amount, currency, transaction_type as "transaction_type: _", reason_code as "reason_code: _",
+            note, status as "status: _", requested_by, decided_by, transaction_id, created_at, decided_at
+        FROM adjustments
+        WHERE status = 'pending_approval'
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch pending adjustments: {}", e);
+        db_error(&e, "Failed to fetch pending adjustments")
+    })?;
+
+    Ok(Json(adjustments))
+}
+
+async fn lock_pending_adjustment(
+    conn: &mut PgConnection,
+    adjustment_id: Uuid,
+) -> Result<Adjustment, AppError> {
+    let adjustment = sqlx::query_as!(
+        Adjustment,
+        r#"
+        SELECT id, user_id, amount, currency, transaction_type as "transaction_type: _", reason_code as "reason_code: _",
+            note, status as "status: _", requested_by, decided_by, transaction_id, created_at, decided_at
+        FROM adjustments
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        adjustment_id
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch adjustment: {}", e);
+        db_error(&e, "Failed to fetch adjustment")
+    })?
+    .ok_or(AppError::NotFound("Adjustment not found".to_string()))?;
+
+    if adjustment.status != AdjustmentStatus::PendingApproval {
+        return Err(AppError::Conflict("Adjustment is not awaiting approval".to_string()));
+    }
+
+    Ok(adjustment)
+}
+
+// Writes the ledger entry for an adjustment and marks it posted. Adjustments deliberately bypass
+// the overdraft policy, since corrections such as chargebacks may need to take a balance negative.
+async fn post_adjustment(
+    conn: &mut PgConnection,
+    adjustment: &Adjustment,
+    approved_by: Option<Uuid>,
+) -> Result<Adjustment, AppError> {
+    let amount = Money::from_stored(&adjustment.amount, &adjustment.currency)
+        .ok_or(AppError::InvalidAmount("Adjustment amount cannot be represented in its currency".to_string()))?;
+    let transaction = insert_transaction(
+        conn,
+        adjustment.user_id,
+        &amount,
+        adjustment.transaction_type,
+        Some(&format!("Manual adjustment ({:?}): {}", adjustment.reason_code, adjustment.note)),
+        None,
+        TransactionStatus::Settled,
+        true,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to post adjustment transaction: {}", e);
+        db_error(&e, "Failed to post adjustment")
+    })?;
+
+    let posted = sqlx::query_as!(
+        Adjustment,
+        r#"
+        UPDATE adjustments
+        SET status = 'posted', decided_by = $2, decided_at = NOW(), transaction_id = $3
+        WHERE id = $1
+        RETURNING id, user_id, amount, currency, transaction_type as "transaction_type: _", reason_code as "reason_code: _",
+            note, status as "status: _", requested_by, decided_by, transaction_id, created_at, decided_at
+        "#,
+        adjustment.id,
+        approved_by,
+        transaction.id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to mark adjustment posted: {}", e);
+        db_error(&e, "Failed to post adjustment")
+    })?;
+
+    info!(
+        target: "audit",
+        "Adjustment {} posted as transaction {} (requested by {}, approved by {:?})",
+        posted.id, transaction.id, posted.requested_by, approved_by
+    );
+    Ok(posted)
+}
@@ -0,0 +1,348 @@
+use axum::{
+    extract::{Extension, Path, State},
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::events::{publish_approval_decided, publish_balance_updated};
+use crate::middleware::auth::{is_admin, AuthContext};
+use crate::models::approval::{ApprovalDecision, ApprovalStatus, DecideApproval, TransactionApproval, TransactionApprover};
+use crate::models::transaction::{TransactionStatus, TransactionType};
+use crate::models::user::UserStatus;
+use crate::repositories::transaction as transactions;
+use crate::repositories::user as users;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::services::ledger::{charge_settled_debit, lock_account_balance, run_post_commit_hooks};
+
+// Pending approvals the caller may decide, oldest first: every one but their own for admins, and
+// those of users who named them as an approver for everyone else
+pub async fn get_pending_approvals(
+    State(pool): State<PgPool>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<TransactionApproval>>, AppError> {
+    info!("Fetching approvals pending for user {}", auth.user_id);
+
+    let admin = is_admin(&pool, auth.user_id).await?;
+    let approvals = sqlx::query_as!(
+        TransactionApproval,
+        r#"
+        SELECT a.id, a.transaction_id, t.user_id, t.account_id, t.amount, t.currency, t.transaction_type as "transaction_type: _",
+            t.description, t.category, t.status as "transaction_status: _", a.status as "status: _", a.created_at,
+            a.decided_by, a.decided_at, a.note
+        FROM transaction_approvals a
+        JOIN transactions t ON t.id = a.transaction_id
+        WHERE a.status = 'pending' AND a.user_id <> $1
+            AND ($2 OR EXISTS(SELECT 1 FROM transaction_approvers p WHERE p.user_id = a.user_id AND p.approver_id = $1))
+        ORDER BY a.created_at
+        "#,
+        auth.user_id,
+        admin
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch pending approvals: {}", e);
+        db_error(&e, "Failed to fetch pending approvals")
+    })?;
+
+    Ok(Json(approvals))
+}
+
+// Approves or rejects a transaction awaiting approval. Approving posts it, as pending if it was
+// requested so; the funds check a debit skipped while it waited happens here. Rejecting marks it
+// denied. Nobody can decide on their own transactions.
+pub async fn decide_approval(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(approval_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<DecideApproval>,
+) -> Result<Json<TransactionApproval>, AppError> {
+    info!("User {} deciding approval {}: {:?}", auth.user_id, approval_id, payload.decision);
+
+    let admin = is_admin(&pool, auth.user_id).await?;
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let (approval, pending) = lock_approval(&mut tx, approval_id).await?;
+    let named = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM transaction_approvers WHERE user_id = $1 AND approver_id = $2) as "exists!""#,
+        approval.user_id,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to check approvers: {}", e);
+        db_error(&e, "Failed to decide approval")
+    })?;
+    if approval.user_id == auth.user_id {
+        return Err(AppError::Forbidden("You can't decide on your own transactions".to_string()));
+    }
+    // Approvals the caller can't decide aren't revealed to them
+    if !admin && !named {
+        return Err(AppError::NotFound("Approval not found".to_string()));
+    }
+    if approval.status != ApprovalStatus::Pending {
+        return Err(AppError::Conflict("Approval has already been decided".to_string()));
+    }
+
+    let (status, transaction_status) = match payload.decision {
+        ApprovalDecision::Approve => {
+            if approval.transaction_type == TransactionType::Debit {
+                let balance = lock_account_balance(&mut tx, approval.user_id, approval.account_id, true).await
+                    .map_err(|e| {
+                        error!("Failed to compute balance: {}", e);
+                        db_error(&e, "Failed to compute balance")
+                    })?;
+                if &balance - &approval.amount < -&config.overdraft_limit {
+                    error!("Insufficient funds to approve transaction {}: balance {}, amount {}", approval.transaction_id, balance, approval.amount);
+                    return Err(AppError::InsufficientFunds);
+                }
+            }
+            let posted = if pending { TransactionStatus::Pending } else { TransactionStatus::Settled };
+            (ApprovalStatus::Approved, posted)
+        }
+        ApprovalDecision::Reject => (ApprovalStatus::Rejected, TransactionStatus::Denied),
+    };
+
+    let note = payload.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+    let transaction = transactions::transition(
+        &mut *tx,
+        approval.user_id,
+        approval.transaction_id,
+        true,
+        &[TransactionStatus::AwaitingApproval],
+        transaction_status,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to update transaction awaiting approval: {}", e);
+        db_error(&e, "Failed to decide approval")
+    })?
+    .ok_or(AppError::Conflict("Transaction is no longer awaiting approval".to_string()))?;
+    charge_settled_debit(&mut tx, &config, &transaction).await?;
+    let decided = sqlx::query_as!(
+        TransactionApproval,
+        r#"
+        WITH decided AS (
+            UPDATE transaction_approvals
+            SET status = $2, decided_by = $3, decided_at = NOW(), note = $4
+            WHERE id = $1
+            RETURNING id, transaction_id, status, created_at, decided_by, decided_at, note
+        )
+        SELECT d.id as "id!", d.transaction_id as "transaction_id!", t.user_id, t.account_id, t.amount, t.currency,
+            t.transaction_type as "transaction_type: _", t.description, t.category, t.status as "transaction_status: _",
+            d.status as "status!: _", d.created_at as "created_at!", d.decided_by, d.decided_at, d.note
+        FROM decided d
+        JOIN transactions t ON t.id = d.transaction_id
+        "#,
+        approval.id,
+        status as _,
+        auth.user_id,
+        note
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to record approval decision: {}", e);
+        db_error(&e, "Failed to decide approval")
+    })?;
+
+    if transaction_status == TransactionStatus::Settled {
+        publish_balance_updated(&mut tx, decided.user_id, &decided.currency).await
+            .map_err(|e| {
+                error!("Failed to queue balance events: {}", e);
+                db_error(&e, "Failed to decide approval")
+            })?;
+    }
+    publish_approval_decided(&mut tx, &decided).await
+        .map_err(|e| {
+            error!("Failed to queue approval events: {}", e);
+            db_error(&e, "Failed to decide approval")
+        })?;
+
+    let action = match payload.decision {
+        ApprovalDecision::Approve => AuditAction::TransactionApproved,
+        ApprovalDecision::Reject => AuditAction::TransactionRejected,
+    };
+    let record = AuditRecord::new(action, auth.user_id, Some(approval.user_id))
+        .target(approval.transaction_id)
+        .before(&approval)
+        .after(&decided);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "Approval {} {:?} by user {}", decided.id, decided.status, auth.user_id);
+    run_post_commit_hooks(&pool, &config, &transaction).await;
+    Ok(Json(decided))
+}
+
+// The approval as it stands, locked for the rest of the DB transaction, and whether its transaction
+// was requested as pending
+async fn lock_approval(conn: &mut PgConnection, approval_id: Uuid) -> Result<(TransactionApproval, bool), AppError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT a.id, a.transaction_id, t.user_id, t.account_id, t.amount, t.currency, t.transaction_type as "transaction_type: TransactionType",
+            t.description, t.category, t.status as "transaction_status: TransactionStatus", a.status as "status: ApprovalStatus",
+            a.created_at, a.decided_by, a.decided_at, a.note, a.pending
+        FROM transaction_approvals a
+        JOIN transactions t ON t.id = a.transaction_id
+        WHERE a.id = $1
+        FOR UPDATE OF a
+        "#,
+        approval_id
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch approval: {}", e);
+        db_error(&e, "Failed to fetch approval")
+    })?
+    .ok_or(AppError::NotFound("Approval not found".to_string()))?;
+
+    let approval = TransactionApproval {
+        id: row.id,
+        transaction_id: row.transaction_id,
+        user_id: row.user_id,
+        account_id: row.account_id,
+        amount: row.amount,
+        currency: row.currency,
+        transaction_type: row.transaction_type,
+        description: row.description,
+        category: row.category,
+        transaction_status: row.transaction_status,
+        status: row.status,
+        created_at: row.created_at,
+        decided_by: row.decided_by,
+        decided_at: row.decided_at,
+        note: row.note,
+    };
+    Ok((approval, row.pending))
+}
+
+pub async fn get_approvers(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<TransactionApprover>>, AppError> {
+    let approvers = sqlx::query_as!(
+        TransactionApprover,
+        "SELECT user_id, approver_id, created_at FROM transaction_approvers WHERE user_id = $1 ORDER BY created_at",
+        user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch approvers: {}", e);
+        db_error(&e, "Failed to fetch approvers")
+    })?;
+
+    Ok(Json(approvers))
+}
+
+// Lets another active user approve the caller's large transactions
+pub async fn add_approver(
+    State(pool): State<PgPool>,
+    Path((user_id, approver_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<TransactionApprover>, AppError> {
+    info!("User {} naming {} as an approver", user_id, approver_id);
+
+    if approver_id == user_id {
+        return Err(AppError::BadRequest("You can't approve your own transactions".to_string()));
+    }
+    let approver = users::find_by_id(&pool, approver_id).await
+        .map_err(|e| {
+            error!("Failed to look up approver: {}", e);
+            db_error(&e, "Failed to add approver")
+        })?
+        .ok_or(AppError::NotFound("Approver not found".to_string()))?;
+    if approver.status != UserStatus::Active {
+        return Err(AppError::Unprocessable("Approver account is deactivated".to_string()));
+    }
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+    let approver = sqlx::query_as!(
+        TransactionApprover,
+        r#"
+        INSERT INTO transaction_approvers (user_id, approver_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, approver_id) DO UPDATE SET user_id = EXCLUDED.user_id
+        RETURNING user_id, approver_id, created_at
+        "#,
+        user_id,
+        approver_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to add approver: {}", e);
+        db_error(&e, "Failed to add approver")
+    })?;
+    let record = AuditRecord::new(AuditAction::ApproverAdded, user_id, Some(user_id))
+        .target(approver_id)
+        .after(&approver);
+    audit::record(&mut *tx, record).await?;
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    Ok(Json(approver))
+}
+
+// Decisions the approver already made stand
+pub async fn remove_approver(
+    State(pool): State<PgPool>,
+    Path((user_id, approver_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<TransactionApprover>, AppError> {
+    info!("User {} removing approver {}", user_id, approver_id);
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+    let approver = sqlx::query_as!(
+        TransactionApprover,
+        "DELETE FROM transaction_approvers WHERE user_id = $1 AND approver_id = $2 RETURNING user_id, approver_id, created_at",
+        user_id,
+        approver_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to remove approver: {}", e);
+        db_error(&e, "Failed to remove approver")
+    })?
+    .ok_or(AppError::NotFound("Approver not found".to_string()))?;
+    let record = AuditRecord::new(AuditAction::ApproverRemoved, user_id, Some(user_id))
+        .target(approver_id)
+        .before(&approver);
+    audit::record(&mut *tx, record).await?;
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    Ok(Json(approver))
+}
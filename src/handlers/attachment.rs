@@ -0,0 +1,340 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    Json,
+};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::blob_store::{BlobError, BlobStore};
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::middleware::auth::Livemode;
+use crate::models::attachment::{
+    Attachment, AttachmentDownload, ALLOWED_CONTENT_TYPES, MAX_ATTACHMENTS_PER_TRANSACTION, MAX_ATTACHMENT_BYTES,
+};
+use crate::repositories::transaction as transactions;
+
+// Multipart field carrying the file
+const FILE_FIELD: &str = "file";
+
+// Longest filename kept; longer ones are cut short
+const MAX_FILENAME_CHARS: usize = 255;
+
+// How long a download URL works for
+const DOWNLOAD_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+// Stores a JPEG, PNG or PDF against one of the caller's transactions. The file is uploaded before
+// the row is written, and removed again if the row can't be.
+pub async fn upload_attachment(
+    State(pool): State<PgPool>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path((user_id, transaction_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+    mut multipart: Multipart,
+) -> Result<Json<Attachment>, AppError> {
+    info!("User {} uploading an attachment to transaction {}", user_id, transaction_id);
+
+    let mut file = None;
+    while let Some(mut field) = multipart.next_field().await.map_err(|e| {
+        error!("Failed to read multipart upload: {}", e);
+        AppError::BadRequest("Invalid multipart upload".to_string())
+    })? {
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+        let filename = field.file_name().map(sanitize_filename).unwrap_or_default();
+        let content_type = field.content_type().unwrap_or_default().to_ascii_lowercase();
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(|e| {
+            error!("Failed to read uploaded file: {}", e);
+            AppError::BadRequest("Invalid multipart upload".to_string())
+        })? {
+            if bytes.len() + chunk.len() > MAX_ATTACHMENT_BYTES {
+                return Err(AppError::PayloadTooLarge(format!(
+                    "Attachments must be at most {} MiB",
+                    MAX_ATTACHMENT_BYTES / (1024 * 1024)
+                )));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        file = Some((filename, content_type, bytes));
+        break;
+    }
+    let (filename, content_type, bytes) = file.ok_or(AppError::BadRequest(format!("Missing `{}` field", FILE_FIELD)))?;
+
+    if filename.is_empty() {
+        return Err(AppError::BadRequest("The file must have a name".to_string()));
+    }
+    if bytes.is_empty() {
+        return Err(AppError::BadRequest("The file is empty".to_string()));
+    }
+    if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) || sniff_content_type(&bytes) != Some(content_type.as_str()) {
+        return Err(AppError::Unprocessable("Attachments must be JPEG, PNG or PDF files".to_string()));
+    }
+
+    let id = Uuid::new_v4();
+    let storage_key = format!("attachments/{}/{}/{}", user_id, transaction_id, id);
+    let sha256 = hex::encode(Sha256::digest(&bytes));
+    let size_bytes = bytes.len() as i64;
+
+    blobs.put(&storage_key, &content_type, bytes).await.map_err(|e| blob_error(&e, "Failed to store attachment"))?;
+
+    let attachment = Attachment {
+        id,
+        transaction_id,
+        user_id,
+        filename,
+        content_type,
+        size_bytes,
+        sha256,
+        storage_key,
+        created_at: OffsetDateTime::now_utc(),
+    };
+    match insert_attachment(&pool, &attachment, livemode).await {
+        Ok(attachment) => {
+            info!("Stored attachment {} on transaction {}", attachment.id, transaction_id);
+            Ok(Json(attachment))
+        }
+        Err(e) => {
+            if let Err(cleanup) = blobs.delete(&attachment.storage_key).await {
+                error!("Failed to remove orphaned attachment {}: {}", attachment.storage_key, cleanup);
+            }
+            Err(e)
+        }
+    }
+}
+
+// Writes the row once the transaction is locked, so it can't be voided in between
+async fn insert_attachment(pool: &PgPool, attachment: &Attachment, livemode: bool) -> Result<Attachment, AppError> {
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let transaction = transactions::find_for_update(&mut *tx, attachment.user_id, attachment.transaction_id, livemode).await
+        .map_err(|e| {
+            error!("Failed to fetch transaction: {}", e);
+            db_error(&e, "Failed to fetch transaction")
+        })?
+        .ok_or(AppError::NotFound("Transaction not found".to_string()))?;
+    if transaction.status.is_void() {
+        return Err(AppError::Conflict("Cancelled, failed and denied transactions can't take attachments".to_string()));
+    }
+
+    let attached = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM attachments WHERE transaction_id = $1"#,
+        transaction.id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to count attachments: {}", e);
+        db_error(&e, "Failed to store attachment")
+    })?;
+    if attached >= MAX_ATTACHMENTS_PER_TRANSACTION {
+        return Err(AppError::Unprocessable(format!(
+            "A transaction can have at most {} attachments",
+            MAX_ATTACHMENTS_PER_TRANSACTION
+        )));
+    }
+
+    let inserted = sqlx::query_as!(
+        Attachment,
+        r#"
+        INSERT INTO attachments (id, transaction_id, user_id, filename, content_type, size_bytes, sha256, storage_key)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, transaction_id, user_id, filename, content_type, size_bytes, sha256, storage_key, created_at
+        "#,
+        attachment.id,
+        attachment.transaction_id,
+        attachment.user_id,
+        attachment.filename,
+        attachment.content_type,
+        attachment.size_bytes,
+        attachment.sha256,
+        attachment.storage_key
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to store attachment: {}", e);
+        db_error(&e, "Failed to store attachment")
+    })?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    Ok(inserted)
+}
+
+// A transaction's attachments, oldest first
+pub async fn get_attachments(
+    State(pool): State<PgPool>,
+    Path((user_id, transaction_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<Vec<Attachment>>, AppError> {
+    let exists = transactions::exists(&pool, user_id, transaction_id, livemode).await
+        .map_err(|e| {
+            error!("Failed to check transaction existence: {}", e);
+            db_error(&e, "Failed to fetch attachments")
+        })?;
+    if !exists {
+        return Err(AppError::NotFound("Transaction not found".to_string()));
+    }
+
+    let attachments = sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT id, transaction_id, user_id, filename, content_type, size_bytes, sha256, storage_key, created_at
+        FROM attachments
+        WHERE transaction_id = $1 AND user_id = $2
+        ORDER BY created_at
+        "#,
+        transaction_id,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch attachments: {}", e);
+        db_error(&e, "Failed to fetch attachments")
+    })?;
+
+    Ok(Json(attachments))
+}
+
+// One attachment with a pre-signed URL to download it from
+pub async fn get_attachment(
+    State(pool): State<PgPool>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path((user_id, transaction_id, attachment_id)): Path<(Uuid, Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<AttachmentDownload>, AppError> {
+    let attachment = sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT a.id, a.transaction_id, a.user_id, a.filename, a.content_type, a.size_bytes, a.sha256, a.storage_key,
+            a.created_at
+        FROM attachments a
+        JOIN transactions t ON t.id = a.transaction_id
+        WHERE a.id = $1 AND a.transaction_id = $2 AND a.user_id = $3 AND t.livemode = $4
+        "#,
+        attachment_id,
+        transaction_id,
+        user_id,
+        livemode
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch attachment: {}", e);
+        db_error(&e, "Failed to fetch attachment")
+    })?
+    .ok_or(AppError::NotFound("Attachment not found".to_string()))?;
+
+    let download_url = blobs
+        .presigned_url(&attachment.storage_key, DOWNLOAD_URL_TTL)
+        .map_err(|e| blob_error(&e, "Failed to sign download URL"))?;
+
+    Ok(Json(AttachmentDownload {
+        attachment,
+        download_url,
+        download_url_expires_at: OffsetDateTime::now_utc() + DOWNLOAD_URL_TTL,
+    }))
+}
+
+// Deletes the files of attachments on voided transactions from the blob store, then their rows,
+// one at a time. A file that can't be deleted ends the pass; it is retried on the next one.
+pub async fn purge_void_attachments(pool: &PgPool, blobs: &dyn BlobStore) -> Result<usize, sqlx::Error> {
+    let mut purged = 0;
+
+    loop {
+        let mut tx = pool.begin().await?;
+
+        let Some(attachment) = sqlx::query!(
+            r#"
+            SELECT a.id, a.storage_key
+            FROM attachments a
+            JOIN transactions t ON t.id = a.transaction_id
+            WHERE t.status IN ('cancelled', 'failed', 'denied')
+            LIMIT 1
+            FOR UPDATE OF a SKIP LOCKED
+            "#
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            break;
+        };
+
+        if let Err(e) = blobs.delete(&attachment.storage_key).await {
+            error!("Failed to delete attachment {} from the blob store: {}", attachment.id, e);
+            break;
+        }
+        sqlx::query!("DELETE FROM attachments WHERE id = $1", attachment.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        purged += 1;
+    }
+
+    Ok(purged)
+}
+
+// The type a file's leading bytes identify it as, among the accepted ones
+pub fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else {
+        None
+    }
+}
+
+// Keeps the last path segment with control characters dropped, so the name is safe to show
+pub fn sanitize_filename(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    name.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_FILENAME_CHARS)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// Storage failures and missing storage both leave the endpoint unusable until an operator steps in
+pub fn blob_error(e: &BlobError, message: &str) -> AppError {
+    error!("{}: {}", message, e);
+    AppError::Unavailable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_are_identified_by_their_leading_bytes() {
+        assert_eq!(sniff_content_type(b"\xFF\xD8\xFF\xE0\x00\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniff_content_type(b"\x89PNG\r\n\x1a\n\x00\x00"), Some("image/png"));
+        assert_eq!(sniff_content_type(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff_content_type(b"<html>"), None);
+
+        assert_eq!(sanitize_filename("C:\\Users\\ada\\receipt.pdf"), "receipt.pdf");
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename(" lunch\n.jpg "), "lunch.jpg");
+    }
+}
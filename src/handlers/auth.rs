@@ -1,197 +1,126 @@
-use axum::extract::State;
-use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap};
 use axum::Json;
-use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{encode, EncodingKey, Header};
-use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use uuid::Uuid;
 use time::OffsetDateTime;
-use tracing::error;
-use std::env;
-
-use crate::models::user::{User, CreateUser, LoginUser, AuthResponse, RegisterResponse};
+use std::sync::Arc;
+use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String, // user id
-    exp: i64,    // expiration time
-}
+use crate::db::db_error;
+use crate::email_policy::EmailPolicy;
+use crate::error::AppError;
+use crate::middleware::auth::{AuthUser, Credential};
+use crate::models::user::{ChangePassword, CreateUser, CurrentUser, LoginUser, AuthResponse, RegisterResponse, StepUpRequest, User, UserStatus};
+use crate::repositories::user as users;
+use crate::screening::Screening;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::services::auth::{self, JwtKeys};
+use crate::validation::ValidatedJson;
 
 pub async fn register_user(
     State(pool): State<PgPool>,
-    Json(payload): Json<CreateUser>,
-) -> Result<Json<RegisterResponse>, (StatusCode, String)> {
+    State(email_policy): State<Arc<EmailPolicy>>,
+    State(screening): State<Arc<dyn Screening>>,
+    ValidatedJson(payload): ValidatedJson<CreateUser>,
+) -> Result<Json<RegisterResponse>, AppError> {
     tracing::info!("Starting registration for user: {}", payload.email);
-    
-    // Check if user already exists
-    tracing::info!("Checking if user already exists");
-    let existing_user = sqlx::query!(
-        "SELECT id FROM users WHERE email = $1",
-        payload.email
-    )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("Database error checking existing user: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
-    })?;
-
-    if existing_user.is_some() {
-        tracing::error!("User already exists: {}", payload.email);
-        return Err((StatusCode::CONFLICT, "User already exists".to_string()));
-    }
-
-    // Validate email format
-    if !payload.email.contains('@') {
-        error!("Invalid email format: {}", payload.email);
-        return Err((StatusCode::BAD_REQUEST, "Invalid email format".to_string()));
-    }
-
-    // Validate password length
-    if payload.password.len() < 8 {
-        error!("Password too short");
-        return Err((StatusCode::BAD_REQUEST, "Password must be at least 8 characters long".to_string()));
-    }
-
-    // Hash password
-    tracing::info!("Hashing password");
-    let password_hash = match hash(payload.password.as_bytes(), DEFAULT_COST) {
-        Ok(hash) => {
-            tracing::info!("Password hashed successfully");
-            hash
-        },
-        Err(e) => {
-            tracing::error!("Failed to hash password: {}", e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to hash password: {}", e)));
-        }
-    };
 
-    // Create user
-    tracing::info!("Creating new user in database");
-    let user = match sqlx::query_as!(
-        User,
-        r#"
-        INSERT INTO users (email, password_hash, name)
-        VALUES ($1, $2, $3)
-        RETURNING id, email, password_hash, name, created_at, updated_at
-        "#,
-        payload.email,
-        password_hash,
-        payload.name
-    )
-    .fetch_one(&pool)
-    .await {
-        Ok(user) => {
-            tracing::info!("User created successfully: {}", user.email);
-            user
-        },
-        Err(e) => {
-            tracing::error!("Failed to create user: {:?}", e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create user: {}", e)));
-        }
-    };
+    let user = auth::register(&pool, &email_policy, screening.as_ref(), payload).await?;
 
     tracing::info!("Registration completed successfully for user: {}", user.email);
+    // Screening held the account; it can sign in once an admin clears it
+    let message = match user.status {
+        UserStatus::PendingReview => "Registration is pending review",
+        _ => "User registered successfully",
+    };
     Ok(Json(RegisterResponse {
-        message: "User registered successfully".to_string(),
+        message: message.to_string(),
         user,
     }))
 }
 
 pub async fn authenticate_user(
     State(pool): State<PgPool>,
-    Json(payload): Json<LoginUser>,
-) -> Result<Json<AuthResponse>, (StatusCode, String)> {
+    State(jwt_keys): State<Arc<JwtKeys>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<LoginUser>,
+) -> Result<Json<AuthResponse>, AppError> {
     tracing::info!("Starting authentication for user: {}", payload.email);
-    
-    // Find user
-    tracing::info!("Querying database for user");
-    let user = match sqlx::query_as!(
-        User,
-        r#"
-        SELECT id, email, password_hash, name, created_at, updated_at
-        FROM users
-        WHERE email = $1
-        "#,
-        payload.email
-    )
-    .fetch_optional(&pool)
-    .await {
-        Ok(Some(user)) => {
-            tracing::info!("User found in database");
-            user
-        },
-        Ok(None) => {
-            tracing::error!("User not found: {}", payload.email);
-            return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-        },
-        Err(e) => {
-            tracing::error!("Database error during user lookup: {:?}", e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)));
-        }
-    };
 
-    // Verify password
-    tracing::info!("Verifying password");
-    match verify(&payload.password, &user.password_hash) {
-        Ok(true) => {
-            tracing::info!("Password verified successfully");
-        },
-        Ok(false) => {
-            tracing::error!("Invalid password for user: {}", payload.email);
-            return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
-        },
-        Err(e) => {
-            tracing::error!("Error verifying password: {}", e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to verify password: {}", e)));
-        }
-    }
-
-    // Generate JWT
-    tracing::info!("Generating JWT token");
-    let token = match generate_token(&user.id) {
-        Ok(token) => {
-            tracing::info!("JWT token generated successfully");
-            token
-        },
-        Err(e) => {
-            tracing::error!("Failed to generate JWT token: {:?}", e);
-            return Err(e);
-        }
-    };
+    let user_agent = headers.get(header::USER_AGENT).and_then(|value| value.to_str().ok());
+    let user = auth::authenticate(&pool, &payload.email, &payload.password, user_agent).await?;
+    let token = jwt_keys.generate_token(&user.id, OffsetDateTime::now_utc().unix_timestamp(), user.session_version)?;
 
     tracing::info!("Successfully authenticated user: {}", user.email);
     Ok(Json(AuthResponse { token, user }))
 }
 
-fn generate_token(user_id: &Uuid) -> Result<String, (StatusCode, String)> {
-    let expiration = OffsetDateTime::now_utc().unix_timestamp() + 24 * 3600;
-
-    let claims = Claims {
-        sub: user_id.to_string(),
-        exp: expiration,
+// Returns the caller's own user record, and what an API key caller may do
+pub async fn get_current_user(AuthUser { user, context }: AuthUser) -> Json<CurrentUser> {
+    let scopes = match context.credential {
+        Credential::Session { .. } => None,
+        Credential::ApiKey { scopes, .. } => Some(scopes),
     };
+    Json(CurrentUser { user, scopes })
+}
+
+// Re-checks the password of an existing session and issues a token with a fresh `auth_time`,
+// unlocking operations guarded by step-up authentication
+pub async fn step_up(
+    State(jwt_keys): State<Arc<JwtKeys>>,
+    AuthUser { user, .. }: AuthUser,
+    Json(payload): Json<StepUpRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    tracing::info!("Step-up authentication for user {}", user.id);
+
+    auth::verify_password(&user, &payload.password)?;
 
-    let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| {
-        tracing::error!("JWT_SECRET environment variable not set");
-        "your-secret-key".to_string()
-    });
-
-    tracing::info!("Using JWT secret key length: {}", jwt_secret.len());
-
-    match encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_bytes())
-    ) {
-        Ok(token) => {
-            tracing::info!("Token generated successfully");
-            Ok(token)
-        },
-        Err(e) => {
-            tracing::error!("Failed to generate token: {:?}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to generate token: {}", e)))
-        }
-    }
-} 
\ No newline at end of file
+    let token = jwt_keys.generate_token(&user.id, OffsetDateTime::now_utc().unix_timestamp(), user.session_version)?;
+    tracing::info!(target: "audit", "User {} completed step-up authentication", user.id);
+    Ok(Json(AuthResponse { token, user }))
+}
+
+// Changes the caller's password. Every existing session is signed out, so the response carries a
+// fresh token for the caller to continue with; API keys keep working.
+pub async fn change_password(
+    State(pool): State<PgPool>,
+    State(jwt_keys): State<Arc<JwtKeys>>,
+    AuthUser { user, .. }: AuthUser,
+    ValidatedJson(payload): ValidatedJson<ChangePassword>,
+) -> Result<Json<AuthResponse>, AppError> {
+    tracing::info!("Password change for user {}", user.id);
+
+    let user = auth::change_password(&pool, &user, &payload.current_password, &payload.new_password).await?;
+    let token = jwt_keys.generate_token(&user.id, OffsetDateTime::now_utc().unix_timestamp(), user.session_version)?;
+
+    tracing::info!(target: "audit", "User {} changed their password", user.id);
+    Ok(Json(AuthResponse { token, user }))
+}
+
+// Deletes the caller's account by deactivating it: sessions and API keys stop working, while the
+// user row and ledger are kept for the records
+pub async fn delete_account(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<User>, AppError> {
+    let mut tx = pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+    let user = users::soft_delete(&mut *tx, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete user {}: {}", user_id, e);
+            db_error(&e, "Failed to delete account")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::AccountDeleted, user_id, Some(user_id)).target(user_id).after(&user))
+        .await?;
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    tracing::info!(target: "audit", "User {} deleted their account", user.id);
+    Ok(Json(user))
+}
@@ -0,0 +1,203 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use bigdecimal::BigDecimal;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::middleware::auth::Livemode;
+use crate::models::budget::{Budget, BudgetReport, CreateBudget, UpdateBudget};
+use crate::models::money::{Currency, Money};
+use crate::models::transaction::{normalize_category, MAX_CATEGORY_LENGTH};
+use crate::services::budget;
+
+// Limits are held to the currency's minor unit like any amount
+fn check_limit(amount: &BigDecimal, currency: Currency) -> Result<Money, AppError> {
+    if *amount <= 0 {
+        return Err(AppError::InvalidAmount("Limit must be greater than zero".to_string()));
+    }
+    Money::from_decimal(amount, currency).map_err(|e| AppError::InvalidAmount(e.to_string()))
+}
+
+pub async fn create_budget(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<CreateBudget>,
+) -> Result<Json<Budget>, AppError> {
+    info!("Creating budget for user {}: {:?}", user_id, payload);
+
+    let category = normalize_category(&payload.category).ok_or(AppError::BadRequest(format!(
+        "Category must be between 1 and {} characters",
+        MAX_CATEGORY_LENGTH
+    )))?;
+    let currency = Currency::parse(&payload.currency)
+        .ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    let limit = check_limit(&payload.limit_amount, currency)?;
+
+    let budget = sqlx::query_as!(
+        Budget,
+        r#"
+        INSERT INTO budgets (user_id, category, currency, period, limit_amount)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, category, currency, period) DO NOTHING
+        RETURNING id, user_id, category, currency, period as "period: _", limit_amount, created_at, updated_at
+        "#,
+        user_id,
+        category,
+        currency.as_str(),
+        payload.period as _,
+        limit.to_decimal()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to create budget: {}", e);
+        db_error(&e, "Failed to create budget")
+    })?
+    .ok_or(AppError::Conflict("A budget for this category, currency and period already exists".to_string()))?;
+
+    info!("Created budget {} for user {}", budget.id, user_id);
+    Ok(Json(budget))
+}
+
+pub async fn get_budgets(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<Budget>>, AppError> {
+    info!("Fetching budgets for user {}", user_id);
+
+    let budgets = sqlx::query_as!(
+        Budget,
+        r#"
+        SELECT id, user_id, category, currency, period as "period: _", limit_amount, created_at, updated_at
+        FROM budgets
+        WHERE user_id = $1
+        ORDER BY category, currency, period
+        "#,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch budgets: {}", e);
+        db_error(&e, "Failed to fetch budgets")
+    })?;
+
+    Ok(Json(budgets))
+}
+
+pub async fn get_budget(
+    State(pool): State<PgPool>,
+    Path((user_id, budget_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Budget>, AppError> {
+    sqlx::query_as!(
+        Budget,
+        r#"
+        SELECT id, user_id, category, currency, period as "period: _", limit_amount, created_at, updated_at
+        FROM budgets
+        WHERE id = $1 AND user_id = $2
+        "#,
+        budget_id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch budget: {}", e);
+        db_error(&e, "Failed to fetch budget")
+    })?
+    .ok_or(AppError::NotFound("Budget not found".to_string()))
+    .map(Json)
+}
+
+// Changes the limit. Alerts already sent this period are forgotten, so thresholds of the new
+// limit are reported as spending reaches them.
+pub async fn update_budget(
+    State(pool): State<PgPool>,
+    Path((user_id, budget_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateBudget>,
+) -> Result<Json<Budget>, AppError> {
+    info!("Updating budget {} for user {}: {:?}", budget_id, user_id, payload);
+
+    let Json(existing) = get_budget(State(pool.clone()), Path((user_id, budget_id))).await?;
+    let currency = Currency::parse(&existing.currency)
+        .ok_or(AppError::Internal("Budget has an invalid currency".to_string()))?;
+    let limit = check_limit(&payload.limit_amount, currency)?;
+
+    let budget = sqlx::query_as!(
+        Budget,
+        r#"
+        UPDATE budgets
+        SET limit_amount = $3, alerted_period_start = NULL, alerted_threshold = NULL, updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, category, currency, period as "period: _", limit_amount, created_at, updated_at
+        "#,
+        budget_id,
+        user_id,
+        limit.to_decimal()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to update budget {}: {}", budget_id, e);
+        db_error(&e, "Failed to update budget")
+    })?
+    .ok_or(AppError::NotFound("Budget not found".to_string()))?;
+
+    Ok(Json(budget))
+}
+
+pub async fn delete_budget(
+    State(pool): State<PgPool>,
+    Path((user_id, budget_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Budget>, AppError> {
+    info!("Deleting budget {} for user {}", budget_id, user_id);
+
+    let budget = sqlx::query_as!(
+        Budget,
+        r#"
+        DELETE FROM budgets
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, category, currency, period as "period: _", limit_amount, created_at, updated_at
+        "#,
+        budget_id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to delete budget: {}", e);
+        db_error(&e, "Failed to delete budget")
+    })?
+    .ok_or(AppError::NotFound("Budget not found".to_string()))?;
+
+    info!("Deleted budget {}", budget.id);
+    Ok(Json(budget))
+}
+
+// Each budget against what has been spent in its current period, for the analytics dashboard
+pub async fn get_budget_report(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<BudgetReport>, AppError> {
+    info!("Fetching budget report for user {}", user_id);
+
+    let Json(budgets) = get_budgets(State(pool.clone()), Path(user_id)).await?;
+    let now = OffsetDateTime::now_utc();
+    let mut statuses = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        let status = budget::status(&pool, budget, livemode, now).await.map_err(|e| {
+            error!("Failed to compute budget spending: {}", e);
+            db_error(&e, "Failed to compute budget report")
+        })?;
+        statuses.push(status);
+    }
+
+    Ok(Json(BudgetReport { user_id, budgets: statuses }))
+}
@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::entitlements::ensure_wallet_allowed;
+use crate::error::AppError;
+use crate::models::deposit::{CreateDeposit, Deposit, DepositResponse};
+use crate::models::money::Currency;
+use crate::payments::{PaymentError, PaymentGateway};
+use crate::services::ledger::check_amount;
+
+// Starts a card top-up. The balance is credited once Stripe reports the payment succeeded.
+pub async fn create_deposit(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(payments): State<Arc<dyn PaymentGateway>>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<CreateDeposit>,
+) -> Result<Json<DepositResponse>, AppError> {
+    info!("Creating deposit for user {}: {:?}", user_id, payload);
+
+    let currency = Currency::parse(&payload.currency)
+        .ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    let amount = check_amount(&config, &payload.amount, currency).map_err(AppError::InvalidAmount)?;
+    // Also checks that the user exists
+    ensure_wallet_allowed(&pool, user_id, currency.as_str(), true).await?;
+
+    let deposit = sqlx::query_as!(
+        Deposit,
+        r#"
+        INSERT INTO deposits (user_id, amount, currency)
+        VALUES ($1, $2, $3)
+        RETURNING id, user_id, amount, currency, status as "status: _", payment_intent_id, transaction_id, failure_reason,
+            created_at, updated_at
+        "#,
+        user_id,
+        amount.to_decimal(),
+        currency.as_str()
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to create deposit: {}", e);
+        db_error(&e, "Failed to create deposit")
+    })?;
+
+    let intent = match payments.create_payment_intent(deposit.id, user_id, &amount).await {
+        Ok(intent) => intent,
+        Err(e) => {
+            error!("Failed to create payment intent for deposit {}: {}", deposit.id, e);
+            let reason = match e {
+                PaymentError::NotConfigured => "Deposits aren't available",
+                PaymentError::Provider(_) => "The payment provider couldn't be reached",
+            };
+            sqlx::query!(
+                "UPDATE deposits SET status = 'failed', failure_reason = $2, updated_at = NOW() WHERE id = $1",
+                deposit.id,
+                reason
+            )
+            .execute(&pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to update deposit {}: {}", deposit.id, e);
+                db_error(&e, "Failed to create deposit")
+            })?;
+            return Err(AppError::Unavailable);
+        }
+    };
+
+    let deposit = sqlx::query_as!(
+        Deposit,
+        r#"
+        UPDATE deposits
+        SET payment_intent_id = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, user_id, amount, currency, status as "status: _", payment_intent_id, transaction_id, failure_reason,
+            created_at, updated_at
+        "#,
+        deposit.id,
+        intent.id
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to update deposit: {}", e);
+        db_error(&e, "Failed to create deposit")
+    })?;
+
+    info!("Created deposit {} with PaymentIntent {}", deposit.id, intent.id);
+    Ok(Json(DepositResponse { deposit, client_secret: intent.client_secret }))
+}
+
+// The user's deposits, newest first
+pub async fn get_deposits(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<Deposit>>, AppError> {
+    let deposits = sqlx::query_as!(
+        Deposit,
+        r#"
+        SELECT id, user_id, amount, currency, status as "status: _", payment_intent_id, transaction_id, failure_reason,
+            created_at, updated_at
+        FROM deposits
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        LIMIT 500
+        "#,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch deposits: {}", e);
+        db_error(&e, "Failed to fetch deposits")
+    })?;
+
+    Ok(Json(deposits))
+}
@@ -0,0 +1,319 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::events::publish_dispute;
+use crate::middleware::auth::{AuthContext, Livemode};
+use crate::models::dispute::{Dispute, DisputeOutcome, DisputeQuery, DisputeStatus, OpenDispute, ResolveDispute};
+use crate::models::transaction::{TransactionStatus, TransactionType};
+use crate::repositories::transaction as transactions;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::services::ledger::post_reversal;
+use crate::validation::ValidatedJson;
+
+// Disputes a settled debit of the caller's. Each transaction can be disputed once.
+pub async fn open_dispute(
+    State(pool): State<PgPool>,
+    Path((user_id, transaction_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+    ValidatedJson(payload): ValidatedJson<OpenDispute>,
+) -> Result<Json<Dispute>, AppError> {
+    info!("User {} disputing transaction {}", user_id, transaction_id);
+
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let transaction = transactions::find_for_update(&mut *tx, user_id, transaction_id, livemode).await
+        .map_err(|e| {
+            error!("Failed to fetch transaction: {}", e);
+            db_error(&e, "Failed to fetch transaction")
+        })?
+        .ok_or(AppError::NotFound("Transaction not found".to_string()))?;
+    if transaction.transaction_type != TransactionType::Debit {
+        return Err(AppError::Unprocessable("Only debits can be disputed".to_string()));
+    }
+    if transaction.transfer_id.is_some() || transaction.reverses.is_some() {
+        return Err(AppError::Unprocessable("Transfers and reversals can't be disputed".to_string()));
+    }
+    if transaction.status != TransactionStatus::Settled {
+        return Err(AppError::Conflict("Only settled transactions can be disputed".to_string()));
+    }
+
+    let dispute = sqlx::query_as!(
+        Dispute,
+        r#"
+        INSERT INTO disputes (transaction_id, user_id, livemode, amount, currency, reason)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (transaction_id) DO NOTHING
+        RETURNING id, transaction_id, user_id, livemode, amount, currency, reason, status as "status: _", reviewed_by,
+            resolved_by, resolved_at, resolution_note, refund_transaction_id, created_at, updated_at
+        "#,
+        transaction.id,
+        user_id,
+        livemode,
+        transaction.amount,
+        transaction.currency,
+        reason
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to open dispute: {}", e);
+        db_error(&e, "Failed to open dispute")
+    })?
+    .ok_or(AppError::Conflict("Transaction has already been disputed".to_string()))?;
+
+    publish_dispute(&mut tx, &dispute, true).await
+        .map_err(|e| {
+            error!("Failed to queue dispute events: {}", e);
+            db_error(&e, "Failed to open dispute")
+        })?;
+    let record = AuditRecord::new(AuditAction::DisputeOpened, user_id, Some(user_id))
+        .target(dispute.id)
+        .after(&dispute);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "Dispute {} opened on transaction {}", dispute.id, transaction.id);
+    Ok(Json(dispute))
+}
+
+// The caller's disputes in the mode of their credential, newest first
+pub async fn get_disputes(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<Vec<Dispute>>, AppError> {
+    let disputes = sqlx::query_as!(
+        Dispute,
+        r#"
+        SELECT id, transaction_id, user_id, livemode, amount, currency, reason, status as "status: _", reviewed_by,
+            resolved_by, resolved_at, resolution_note, refund_transaction_id, created_at, updated_at
+        FROM disputes
+        WHERE user_id = $1 AND livemode = $2
+        ORDER BY created_at DESC
+        "#,
+        user_id,
+        livemode
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch disputes: {}", e);
+        db_error(&e, "Failed to fetch disputes")
+    })?;
+
+    Ok(Json(disputes))
+}
+
+// Disputes in one status, oldest first; by default those still waiting for a decision
+pub async fn get_admin_disputes(
+    State(pool): State<PgPool>,
+    Query(query): Query<DisputeQuery>,
+) -> Result<Json<Vec<Dispute>>, AppError> {
+    info!("Fetching disputes: {:?}", query);
+
+    let disputes = sqlx::query_as!(
+        Dispute,
+        r#"
+        SELECT id, transaction_id, user_id, livemode, amount, currency, reason, status as "status: _", reviewed_by,
+            resolved_by, resolved_at, resolution_note, refund_transaction_id, created_at, updated_at
+        FROM disputes
+        WHERE CASE WHEN $1::dispute_status IS NULL THEN status IN ('open', 'under_review') ELSE status = $1 END
+        ORDER BY created_at
+        LIMIT 500
+        "#,
+        query.status as _
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch disputes: {}", e);
+        db_error(&e, "Failed to fetch disputes")
+    })?;
+
+    Ok(Json(disputes))
+}
+
+// Takes an open dispute under review
+pub async fn start_dispute_review(
+    State(pool): State<PgPool>,
+    Path(dispute_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Dispute>, AppError> {
+    info!("Admin {} reviewing dispute {}", auth.user_id, dispute_id);
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let dispute = lock_dispute(&mut tx, dispute_id).await?;
+    if dispute.status != DisputeStatus::Open {
+        return Err(AppError::Conflict("Only open disputes can be taken under review".to_string()));
+    }
+
+    let reviewed = sqlx::query_as!(
+        Dispute,
+        r#"
+        UPDATE disputes
+        SET status = 'under_review', reviewed_by = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, transaction_id, user_id, livemode, amount, currency, reason, status as "status: _", reviewed_by,
+            resolved_by, resolved_at, resolution_note, refund_transaction_id, created_at, updated_at
+        "#,
+        dispute.id,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to update dispute: {}", e);
+        db_error(&e, "Failed to update dispute")
+    })?;
+
+    publish_dispute(&mut tx, &reviewed, false).await
+        .map_err(|e| {
+            error!("Failed to queue dispute events: {}", e);
+            db_error(&e, "Failed to update dispute")
+        })?;
+    let record = AuditRecord::new(AuditAction::DisputeReviewStarted, auth.user_id, Some(dispute.user_id))
+        .target(dispute.id)
+        .before(&dispute)
+        .after(&reviewed);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "Dispute {} taken under review by admin {}", reviewed.id, auth.user_id);
+    Ok(Json(reviewed))
+}
+
+// Decides an open or under review dispute. Upholding it posts a reversal crediting the disputed
+// amount back and marks the dispute refunded; rejecting it marks it resolved.
+pub async fn resolve_dispute(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(dispute_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<ResolveDispute>,
+) -> Result<Json<Dispute>, AppError> {
+    info!("Admin {} resolving dispute {}: {:?}", auth.user_id, dispute_id, payload.outcome);
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let dispute = lock_dispute(&mut tx, dispute_id).await?;
+    if dispute.status.is_decided() {
+        return Err(AppError::Conflict("Dispute has already been resolved".to_string()));
+    }
+
+    let (status, refund_transaction_id) = match payload.outcome {
+        DisputeOutcome::Upheld => {
+            let original = transactions::find_for_update(&mut *tx, dispute.user_id, dispute.transaction_id, dispute.livemode).await
+                .map_err(|e| {
+                    error!("Failed to fetch transaction: {}", e);
+                    db_error(&e, "Failed to fetch transaction")
+                })?
+                .ok_or(AppError::NotFound("Transaction not found".to_string()))?;
+            let description = format!("Refund of disputed transaction {}", original.id);
+            let refund = post_reversal(&mut tx, &config, original, &description).await?;
+            (DisputeStatus::Refunded, Some(refund.reversal.id))
+        }
+        DisputeOutcome::Rejected => (DisputeStatus::Resolved, None),
+    };
+
+    let note = payload.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+    let resolved = sqlx::query_as!(
+        Dispute,
+        r#"
+        UPDATE disputes
+        SET status = $2, resolved_by = $3, resolved_at = NOW(), resolution_note = $4, refund_transaction_id = $5,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, transaction_id, user_id, livemode, amount, currency, reason, status as "status: _", reviewed_by,
+            resolved_by, resolved_at, resolution_note, refund_transaction_id, created_at, updated_at
+        "#,
+        dispute.id,
+        status as _,
+        auth.user_id,
+        note,
+        refund_transaction_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to resolve dispute: {}", e);
+        db_error(&e, "Failed to resolve dispute")
+    })?;
+
+    publish_dispute(&mut tx, &resolved, false).await
+        .map_err(|e| {
+            error!("Failed to queue dispute events: {}", e);
+            db_error(&e, "Failed to resolve dispute")
+        })?;
+    let record = AuditRecord::new(AuditAction::DisputeResolved, auth.user_id, Some(dispute.user_id))
+        .target(dispute.id)
+        .before(&dispute)
+        .after(&resolved);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "Dispute {} {:?} by admin {}", resolved.id, resolved.status, auth.user_id);
+    Ok(Json(resolved))
+}
+
+async fn lock_dispute(conn: &mut PgConnection, dispute_id: Uuid) -> Result<Dispute, AppError> {
+    sqlx::query_as!(
+        Dispute,
+        r#"
+        SELECT id, transaction_id, user_id, livemode, amount, currency, reason, status as "status: _", reviewed_by,
+            resolved_by, resolved_at, resolution_note, refund_transaction_id, created_at, updated_at
+        FROM disputes
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        dispute_id
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch dispute: {}", e);
+        db_error(&e, "Failed to fetch dispute")
+    })?
+    .ok_or(AppError::NotFound("Dispute not found".to_string()))
+}
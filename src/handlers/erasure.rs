@@ -0,0 +1,285 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::blob_store::{BlobError, BlobStore};
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::models::erasure::{ConfirmErasure, ErasureRequest, IssuedErasureRequest};
+
+// How long a confirmation token can be used for
+const CONFIRMATION_TOKEN_LIFETIME_MINUTES: i64 = 30;
+
+fn hash_confirmation_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// Starts an erasure request, replacing any earlier request that was never confirmed, and returns
+// the token that confirms it
+pub async fn request_erasure(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<IssuedErasureRequest>, AppError> {
+    info!("Erasure requested for user {}", user_id);
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let scheduled = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM erasure_requests WHERE user_id = $1 AND status = 'confirmed') as \"exists!\"",
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to check erasure requests: {}", e);
+        db_error(&e, "Failed to request erasure")
+    })?;
+    if scheduled {
+        return Err(AppError::Conflict("An erasure is already scheduled for this account".to_string()));
+    }
+
+    sqlx::query!(
+        "UPDATE erasure_requests SET status = 'cancelled', cancelled_at = NOW() WHERE user_id = $1 AND status = 'pending'",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to replace pending erasure request: {}", e);
+        db_error(&e, "Failed to request erasure")
+    })?;
+
+    let confirmation_token = format!("erase_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let request = sqlx::query_as!(
+        ErasureRequest,
+        r#"
+        INSERT INTO erasure_requests (user_id, confirmation_token_hash, token_expires_at)
+        VALUES ($1, $2, $3)
+        RETURNING id, user_id, status as "status: _", token_expires_at, requested_at,
+            confirmed_at, scheduled_for, completed_at, cancelled_at
+        "#,
+        user_id,
+        hash_confirmation_token(&confirmation_token),
+        OffsetDateTime::now_utc() + Duration::minutes(CONFIRMATION_TOKEN_LIFETIME_MINUTES)
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to create erasure request: {}", e);
+        db_error(&e, "Failed to request erasure")
+    })?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "User {} requested erasure {}", user_id, request.id);
+    Ok(Json(IssuedErasureRequest { confirmation_token, request }))
+}
+
+// Confirms the pending request, scheduling the erasure once the grace period has passed
+pub async fn confirm_erasure(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<ConfirmErasure>,
+) -> Result<Json<ErasureRequest>, AppError> {
+    let request = sqlx::query_as!(
+        ErasureRequest,
+        r#"
+        UPDATE erasure_requests
+        SET status = 'confirmed', confirmed_at = NOW(), scheduled_for = NOW() + $3
+        WHERE user_id = $1 AND status = 'pending' AND confirmation_token_hash = $2 AND token_expires_at > NOW()
+        RETURNING id, user_id, status as "status: _", token_expires_at, requested_at,
+            confirmed_at, scheduled_for, completed_at, cancelled_at
+        "#,
+        user_id,
+        hash_confirmation_token(&payload.confirmation_token),
+        Duration::days(config.erasure_grace_period_days) as _
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to confirm erasure request: {}", e);
+        db_error(&e, "Failed to confirm erasure")
+    })?
+    .ok_or(AppError::BadRequest("Invalid or expired confirmation token".to_string()))?;
+
+    info!(
+        target: "audit",
+        "User {} confirmed erasure {}, scheduled for {:?}",
+        user_id,
+        request.id,
+        request.scheduled_for
+    );
+    Ok(Json(request))
+}
+
+// Cancels the user's open request, confirmed or not, as long as it hasn't been carried out
+pub async fn cancel_erasure(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ErasureRequest>, AppError> {
+    let request = sqlx::query_as!(
+        ErasureRequest,
+        r#"
+        UPDATE erasure_requests
+        SET status = 'cancelled', cancelled_at = NOW()
+        WHERE user_id = $1 AND status IN ('pending', 'confirmed')
+        RETURNING id, user_id, status as "status: _", token_expires_at, requested_at,
+            confirmed_at, scheduled_for, completed_at, cancelled_at
+        "#,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to cancel erasure request: {}", e);
+        db_error(&e, "Failed to cancel erasure")
+    })?
+    .ok_or(AppError::NotFound("No erasure request to cancel".to_string()))?;
+
+    info!(target: "audit", "User {} cancelled erasure {}", user_id, request.id);
+    Ok(Json(request))
+}
+
+// Carries out every confirmed erasure whose grace period has passed, one DB transaction each. Rows
+// are claimed with SKIP LOCKED so several instances can run this without erasing a user twice. The
+// user's uploaded files are deleted from the blob store first; a file that can't be deleted ends the
+// pass, and the erasure is retried on the next one.
+pub async fn erase_due_accounts(pool: &PgPool, blobs: &dyn BlobStore) -> Result<usize, sqlx::Error> {
+    let mut erased = 0;
+
+    loop {
+        let mut tx = pool.begin().await?;
+
+        let Some(request) = sqlx::query!(
+            r#"
+            SELECT id, user_id
+            FROM erasure_requests
+            WHERE status = 'confirmed' AND scheduled_for <= NOW()
+            ORDER BY scheduled_for
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            break;
+        };
+
+        let storage_keys = sqlx::query_scalar!(
+            r#"
+            SELECT storage_key as "storage_key!" FROM (
+                SELECT storage_key FROM attachments WHERE user_id = $1
+                UNION ALL
+                SELECT storage_key FROM kyc_documents WHERE user_id = $1
+            ) files
+            "#,
+            request.user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if let Err(e) = delete_files(blobs, &storage_keys).await {
+            error!("Failed to delete the files of user {} from the blob store: {}", request.user_id, e);
+            break;
+        }
+
+        anonymize_user(&mut tx, request.user_id).await?;
+        sqlx::query!(
+            "UPDATE erasure_requests SET status = 'completed', completed_at = NOW() WHERE id = $1",
+            request.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        info!(target: "audit", "Erased personal data of user {} for erasure {}", request.user_id, request.id);
+        erased += 1;
+    }
+
+    Ok(erased)
+}
+
+async fn delete_files(blobs: &dyn BlobStore, storage_keys: &[String]) -> Result<(), BlobError> {
+    for key in storage_keys {
+        blobs.delete(key).await?;
+    }
+    Ok(())
+}
+
+// Replaces the user's personal details with placeholders and closes the account. Ledger entries are
+// kept for audit; stored statements, which print the name and email, are dropped, as are the record
+// snapshots in audit log entries about the user, their attachments and KYC documents, saved payees
+// and linked bank logins with their access tokens. Screening results keep their outcome but not
+// the reasons, which quote the user's details.
+async fn anonymize_user(conn: &mut PgConnection, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = 'erased-' || id || '@erased.invalid',
+            name = 'Erased User',
+            metadata = '{}',
+            password_hash = '',
+            status = 'deactivated',
+            deactivated_at = COALESCE(deactivated_at, NOW()),
+            deleted_at = COALESCE(deleted_at, NOW()),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!("UPDATE api_keys SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM statements WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM email_changes WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM attachments WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM kyc_documents WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    // Linked accounts and the transactions pulled from them go with their item
+    sqlx::query!("DELETE FROM linked_items WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM payees WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "UPDATE screening_results SET reason = NULL, review_note = NULL WHERE user_id = $1 AND (reason IS NOT NULL OR review_note IS NOT NULL)",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "UPDATE audit_log SET before = NULL, after = NULL WHERE user_id = $1 AND (before IS NOT NULL OR after IS NOT NULL)",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
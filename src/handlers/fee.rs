@@ -0,0 +1,334 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use bigdecimal::BigDecimal;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::middleware::auth::AuthContext;
+use crate::models::fee::{FeeKind, FeeRule, FeeRuleInput, FeeRuleQuery};
+use crate::models::money::Currency;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::services::ledger::check_amount;
+use crate::validation::ValidatedJson;
+
+const MAX_BPS: i32 = 10_000;
+
+// A rule's settings once checked, with those its kind doesn't use cleared
+struct CheckedRule {
+    currency: Currency,
+    flat_amount: Option<BigDecimal>,
+    percentage_bps: Option<i32>,
+    tiers: Value,
+}
+
+fn check_bps(bps: i32) -> Result<(), AppError> {
+    if (0..=MAX_BPS).contains(&bps) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!("Percentages must be between 0 and {} basis points", MAX_BPS)))
+    }
+}
+
+fn check_rule(config: &Config, input: &FeeRuleInput) -> Result<CheckedRule, AppError> {
+    let currency = Currency::parse(&input.currency).ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    let zero = BigDecimal::from(0);
+
+    let mut checked = CheckedRule { currency, flat_amount: None, percentage_bps: None, tiers: Value::Array(Vec::new()) };
+    match input.kind {
+        FeeKind::Flat => {
+            let flat_amount = input.flat_amount.as_ref().ok_or(AppError::BadRequest("Flat fees need a flat_amount".to_string()))?;
+            check_amount(config, flat_amount, currency).map_err(AppError::InvalidAmount)?;
+            checked.flat_amount = Some(flat_amount.clone());
+        }
+        FeeKind::Percentage => {
+            let bps = input.percentage_bps.ok_or(AppError::BadRequest("Percentage fees need percentage_bps".to_string()))?;
+            check_bps(bps)?;
+            if bps == 0 {
+                return Err(AppError::BadRequest("percentage_bps must be positive".to_string()));
+            }
+            checked.percentage_bps = Some(bps);
+        }
+        FeeKind::Tiered => {
+            if input.tiers.is_empty() {
+                return Err(AppError::BadRequest("Tiered fees need at least one tier".to_string()));
+            }
+            let mut previous: Option<&BigDecimal> = None;
+            for (index, tier) in input.tiers.iter().enumerate() {
+                check_bps(tier.percentage_bps)?;
+                if tier.flat_amount < zero {
+                    return Err(AppError::BadRequest("Tier flat amounts cannot be negative".to_string()));
+                }
+                match (&tier.up_to, previous) {
+                    (None, _) if index + 1 < input.tiers.len() => {
+                        return Err(AppError::BadRequest("Only the last tier can leave up_to open".to_string()))
+                    }
+                    (Some(up_to), Some(previous)) if up_to <= previous => {
+                        return Err(AppError::BadRequest("Tiers must be in increasing order of up_to".to_string()))
+                    }
+                    _ => {}
+                }
+                previous = tier.up_to.as_ref();
+            }
+            checked.tiers = serde_json::to_value(&input.tiers).map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+    }
+
+    match (&input.min_fee, &input.max_fee) {
+        (Some(min_fee), _) if *min_fee < zero => Err(AppError::BadRequest("min_fee cannot be negative".to_string())),
+        (_, Some(max_fee)) if *max_fee < zero => Err(AppError::BadRequest("max_fee cannot be negative".to_string())),
+        (Some(min_fee), Some(max_fee)) if min_fee > max_fee => {
+            Err(AppError::BadRequest("min_fee cannot be above max_fee".to_string()))
+        }
+        _ => Ok(checked),
+    }
+}
+
+async fn find_rule(pool: &PgPool, rule_id: Uuid) -> Result<FeeRule, AppError> {
+    sqlx::query_as!(
+        FeeRule,
+        r#"
+        SELECT id, name, event as "event: _", currency, kind as "kind: _", flat_amount, percentage_bps, tiers,
+            min_fee, max_fee, active, created_by, updated_by, created_at, updated_at
+        FROM fee_rules
+        WHERE id = $1
+        "#,
+        rule_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch fee rule: {}", e);
+        db_error(&e, "Failed to fetch fee rule")
+    })?
+    .ok_or(AppError::NotFound("Fee rule not found".to_string()))
+}
+
+// Active rules, or every rule with `include_inactive`, oldest first
+pub async fn get_fee_rules(
+    State(pool): State<PgPool>,
+    Query(query): Query<FeeRuleQuery>,
+) -> Result<Json<Vec<FeeRule>>, AppError> {
+    let rules = sqlx::query_as!(
+        FeeRule,
+        r#"
+        SELECT id, name, event as "event: _", currency, kind as "kind: _", flat_amount, percentage_bps, tiers,
+            min_fee, max_fee, active, created_by, updated_by, created_at, updated_at
+        FROM fee_rules
+        WHERE active OR $1
+        ORDER BY created_at, id
+        "#,
+        query.include_inactive
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch fee rules: {}", e);
+        db_error(&e, "Failed to fetch fee rules")
+    })?;
+
+    Ok(Json(rules))
+}
+
+pub async fn create_fee_rule(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<FeeRuleInput>,
+) -> Result<Json<FeeRule>, AppError> {
+    info!("Admin {} creating fee rule: {:?}", auth.user_id, payload);
+    let checked = check_rule(&config, &payload)?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+    let rule = sqlx::query_as!(
+        FeeRule,
+        r#"
+        INSERT INTO fee_rules
+            (name, event, currency, kind, flat_amount, percentage_bps, tiers, min_fee, max_fee, active, created_by, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11)
+        RETURNING id, name, event as "event: _", currency, kind as "kind: _", flat_amount, percentage_bps, tiers,
+            min_fee, max_fee, active, created_by, updated_by, created_at, updated_at
+        "#,
+        payload.name,
+        payload.event as _,
+        checked.currency.as_str(),
+        payload.kind as _,
+        checked.flat_amount,
+        checked.percentage_bps,
+        checked.tiers,
+        payload.min_fee,
+        payload.max_fee,
+        payload.active,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to create fee rule: {}", e);
+        db_error(&e, "Failed to create fee rule")
+    })?;
+
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::FeeRuleCreated, auth.user_id, None).target(rule.id).after(&rule))
+        .await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!(target: "audit", "Fee rule {} created by admin {}", rule.id, auth.user_id);
+    Ok(Json(rule))
+}
+
+// Replaces every setting of a rule; charges already made keep their amounts
+pub async fn update_fee_rule(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(rule_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<FeeRuleInput>,
+) -> Result<Json<FeeRule>, AppError> {
+    info!("Admin {} updating fee rule {}: {:?}", auth.user_id, rule_id, payload);
+    let checked = check_rule(&config, &payload)?;
+    let previous = find_rule(&pool, rule_id).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+    let rule = sqlx::query_as!(
+        FeeRule,
+        r#"
+        UPDATE fee_rules
+        SET name = $2, event = $3, currency = $4, kind = $5, flat_amount = $6, percentage_bps = $7, tiers = $8,
+            min_fee = $9, max_fee = $10, active = $11, updated_by = $12, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, name, event as "event: _", currency, kind as "kind: _", flat_amount, percentage_bps, tiers,
+            min_fee, max_fee, active, created_by, updated_by, created_at, updated_at
+        "#,
+        rule_id,
+        payload.name,
+        payload.event as _,
+        checked.currency.as_str(),
+        payload.kind as _,
+        checked.flat_amount,
+        checked.percentage_bps,
+        checked.tiers,
+        payload.min_fee,
+        payload.max_fee,
+        payload.active,
+        auth.user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to update fee rule: {}", e);
+        db_error(&e, "Failed to update fee rule")
+    })?
+    .ok_or(AppError::NotFound("Fee rule not found".to_string()))?;
+
+    audit::record(
+        &mut *tx,
+        AuditRecord::new(AuditAction::FeeRuleUpdated, auth.user_id, None).target(rule.id).before(&previous).after(&rule),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!(target: "audit", "Fee rule {} updated by admin {}", rule.id, auth.user_id);
+    Ok(Json(rule))
+}
+
+// Switches a rule off. Rules are kept so past charges still point at the rule that made them.
+pub async fn deactivate_fee_rule(
+    State(pool): State<PgPool>,
+    Path(rule_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<FeeRule>, AppError> {
+    let previous = find_rule(&pool, rule_id).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+    let rule = sqlx::query_as!(
+        FeeRule,
+        r#"
+        UPDATE fee_rules SET active = false, updated_by = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, name, event as "event: _", currency, kind as "kind: _", flat_amount, percentage_bps, tiers,
+            min_fee, max_fee, active, created_by, updated_by, created_at, updated_at
+        "#,
+        rule_id,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to deactivate fee rule: {}", e);
+        db_error(&e, "Failed to deactivate fee rule")
+    })?;
+
+    audit::record(
+        &mut *tx,
+        AuditRecord::new(AuditAction::FeeRuleDeactivated, auth.user_id, None).target(rule.id).before(&previous).after(&rule),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!(target: "audit", "Fee rule {} deactivated by admin {}", rule.id, auth.user_id);
+    Ok(Json(rule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fee::{FeeEvent, FeeTier};
+    use std::str::FromStr;
+
+    fn tiered(tiers: Vec<FeeTier>) -> FeeRuleInput {
+        FeeRuleInput {
+            name: "Transfers".to_string(),
+            event: FeeEvent::Transfer,
+            currency: "usd".to_string(),
+            kind: FeeKind::Tiered,
+            flat_amount: Some(BigDecimal::from(1)),
+            percentage_bps: None,
+            tiers,
+            min_fee: None,
+            max_fee: None,
+            active: true,
+        }
+    }
+
+    fn tier(up_to: Option<&str>) -> FeeTier {
+        FeeTier { up_to: up_to.map(|up_to| BigDecimal::from_str(up_to).unwrap()), flat_amount: BigDecimal::from(1), percentage_bps: 0 }
+    }
+
+    #[test]
+    fn test_tiers_must_increase_and_only_the_last_may_be_open() {
+        let config = Config::default();
+        let checked = check_rule(&config, &tiered(vec![tier(Some("100")), tier(None)])).unwrap();
+        assert_eq!(checked.currency.as_str(), "USD");
+        // Settings the kind doesn't use are dropped
+        assert!(checked.flat_amount.is_none());
+
+        assert!(check_rule(&config, &tiered(vec![])).is_err());
+        assert!(check_rule(&config, &tiered(vec![tier(None), tier(Some("100"))])).is_err());
+        assert!(check_rule(&config, &tiered(vec![tier(Some("100")), tier(Some("100"))])).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use axum::{
+    extract::{Extension, State},
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{info, error};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::feature_flags::{ensure_enabled, KillSwitch};
+use crate::fx::{convert, Conversion, RateCache};
+use crate::handlers::transfer::{post_exchange, post_transfer};
+use crate::middleware::auth::AuthContext;
+use crate::models::fx_quote::{CreateFxQuote, CreateFxTransfer, FxQuote, FxTransferResponse, QUOTE_TTL};
+use crate::models::money::{Currency, Money};
+use crate::screening::{self, Screening};
+use crate::services::ledger::check_amount;
+
+// Locks the latest rate less the spread for converting an amount, for `QUOTE_TTL`
+pub async fn create_quote(
+    State(pool): State<PgPool>,
+    State(rates): State<Arc<RateCache>>,
+    State(config): State<Arc<Config>>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<CreateFxQuote>,
+) -> Result<Json<FxQuote>, AppError> {
+    info!("Quoting conversion for user {}: {:?}", auth.user_id, payload);
+
+    let from = Currency::parse(&payload.from_currency)
+        .ok_or(AppError::BadRequest("Invalid from_currency code".to_string()))?;
+    let to = Currency::parse(&payload.to_currency)
+        .ok_or(AppError::BadRequest("Invalid to_currency code".to_string()))?;
+    if from == to {
+        return Err(AppError::BadRequest("Cannot quote a currency against itself".to_string()));
+    }
+    let amount = check_amount(&config, &payload.amount, from).map_err(AppError::InvalidAmount)?;
+
+    let latest = rates.latest(&pool).await.map_err(|e| {
+        error!("Failed to load FX rates: {}", e);
+        db_error(&e, "Failed to create quote")
+    })?;
+    let mid = latest.current_rate(from, to, config.fx_max_rate_age())?;
+    let conversion = convert(&amount, to, &mid.rate, config.fx_spread_bps)
+        .map_err(|e| AppError::InvalidAmount(e.to_string()))?;
+    if conversion.converted.amount_minor() <= 0 {
+        return Err(AppError::InvalidAmount(format!("Amount is too small to convert to {}", to)));
+    }
+
+    let quote = sqlx::query_as!(
+        FxQuote,
+        r#"
+        INSERT INTO fx_quotes
+            (user_id, from_currency, to_currency, amount, converted_amount, mid_rate, rate, spread_bps, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, user_id, from_currency, to_currency, amount, converted_amount, mid_rate, rate, spread_bps,
+            expires_at, used_at, transfer_id, created_at
+        "#,
+        auth.user_id,
+        from.as_str(),
+        to.as_str(),
+        amount.to_decimal(),
+        conversion.converted.to_decimal(),
+        conversion.mid_rate,
+        conversion.rate,
+        conversion.spread_bps as i32,
+        OffsetDateTime::now_utc() + QUOTE_TTL
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to create quote: {}", e);
+        db_error(&e, "Failed to create quote")
+    })?;
+
+    info!("Quoted {} {} to {} at {} for user {}", quote.amount, from, to, quote.rate, auth.user_id);
+    Ok(Json(quote))
+}
+
+// Transfers a quote's amount at its locked rate, debiting the caller in the quote's currency and
+// crediting the recipient the converted amount in the other, as a single DB transaction. A quote
+// can be used once, before it expires.
+pub async fn create_fx_transfer(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(screening): State<Arc<dyn Screening>>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<CreateFxTransfer>,
+) -> Result<Json<FxTransferResponse>, AppError> {
+    info!("Creating FX transfer for user {}: {:?}", auth.user_id, payload);
+
+    ensure_enabled(&pool, KillSwitch::Transfers).await?;
+
+    // Without another user to pay, the caller exchanges between their own wallets
+    let recipient = match payload.to_user_id.filter(|to_user_id| *to_user_id != auth.user_id) {
+        Some(to_user_id) => {
+            let parties = screening::screen_transfer_parties(&pool, screening.as_ref(), &[auth.user_id, to_user_id]).await
+                .map_err(|e| {
+                    error!("Failed to screen transfer parties: {}", e);
+                    db_error(&e, "Failed to create transfer")
+                })?;
+            Some((to_user_id, parties))
+        }
+        None => None,
+    };
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let quote = lock_usable(&mut tx, payload.quote_id, auth.user_id).await?;
+    if quote.amount > config.step_up_transfer_threshold && !auth.has_recent_auth(&config) {
+        error!("FX transfer of {} by user {} needs step-up authentication", quote.amount, auth.user_id);
+        return Err(AppError::StepUpRequired);
+    }
+
+    let (from, to) = match (Currency::parse(&quote.from_currency), Currency::parse(&quote.to_currency)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Err(AppError::Internal("Quote has an invalid currency".to_string())),
+    };
+    let amount = check_amount(&config, &quote.amount, from).map_err(AppError::InvalidAmount)?;
+    let conversion = Conversion {
+        mid_rate: quote.mid_rate.clone(),
+        rate: quote.rate.clone(),
+        spread_bps: quote.spread_bps as u32,
+        converted: Money::from_decimal(&quote.converted_amount, to)
+            .map_err(|e| AppError::Internal(format!("Quote has an invalid converted amount: {}", e)))?,
+    };
+
+    let transfer = match &recipient {
+        Some((to_user_id, parties)) => {
+            post_transfer(
+                &mut tx,
+                &config,
+                auth.user_id,
+                *to_user_id,
+                &amount,
+                Some(&conversion),
+                payload.description.as_deref(),
+                None,
+                parties,
+            )
+            .await?
+        }
+        None => post_exchange(&mut tx, &config, auth.user_id, &amount, &conversion, payload.description.as_deref()).await?,
+    };
+
+    let quote = sqlx::query_as!(
+        FxQuote,
+        r#"
+        UPDATE fx_quotes SET used_at = NOW(), transfer_id = $2
+        WHERE id = $1
+        RETURNING id, user_id, from_currency, to_currency, amount, converted_amount, mid_rate, rate, spread_bps,
+            expires_at, used_at, transfer_id, created_at
+        "#,
+        quote.id,
+        transfer.transfer.id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to mark quote {} used: {}", quote.id, e);
+        db_error(&e, "Failed to create transfer")
+    })?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!("Quote {} used for transfer {}", quote.id, transfer.transfer.id);
+    Ok(Json(FxTransferResponse { quote, transfer }))
+}
+
+// Locks one of the user's quotes, refusing one that was already used or has expired
+async fn lock_usable(conn: &mut PgConnection, quote_id: Uuid, user_id: Uuid) -> Result<FxQuote, AppError> {
+    let quote = sqlx::query_as!(
+        FxQuote,
+        r#"
+        SELECT id, user_id, from_currency, to_currency, amount, converted_amount, mid_rate, rate, spread_bps,
+            expires_at, used_at, transfer_id, created_at
+        FROM fx_quotes
+        WHERE id = $1 AND user_id = $2
+        FOR UPDATE
+        "#,
+        quote_id,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch quote: {}", e);
+        db_error(&e, "Failed to fetch quote")
+    })?
+    .ok_or(AppError::NotFound("Quote not found".to_string()))?;
+
+    if quote.used_at.is_some() {
+        return Err(AppError::Conflict("Quote has already been used".to_string()));
+    }
+    if quote.expires_at <= OffsetDateTime::now_utc() {
+        return Err(AppError::Unprocessable("Quote has expired; request a new one".to_string()));
+    }
+    Ok(quote)
+}
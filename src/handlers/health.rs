@@ -0,0 +1,91 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::db::pending_migrations;
+use crate::error::AppError;
+use crate::shutdown::WorkerStatus;
+
+// Health check handler
+pub async fn health_check(
+    State(pool): State<PgPool>
+) -> Result<String, AppError> {
+    match sqlx::query("SELECT 1").execute(&pool).await {
+        Ok(_) => Ok("Database connection OK".to_string()),
+        Err(e) => {
+            tracing::error!("Database health check failed: {}", e);
+            Err(AppError::Internal("Database connection error".to_string()))
+        },
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComponentStatus {
+    pub status: &'static str,
+    // What is wrong, when the component is down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ComponentStatus {
+    fn up() -> Self {
+        Self { status: "up", detail: None }
+    }
+
+    fn down(detail: String) -> Self {
+        Self { status: "down", detail: Some(detail) }
+    }
+
+    fn is_up(&self) -> bool {
+        self.detail.is_none()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessComponents {
+    pub database: ComponentStatus,
+    pub migrations: ComponentStatus,
+    pub workers: ComponentStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub status: &'static str,
+    pub components: ReadinessComponents,
+}
+
+// Liveness: answers as long as the process can serve requests at all, without touching the
+// database, so a database outage doesn't get the process restarted
+pub async fn livez() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+// Readiness: the database answers, every migration is applied and no background worker has
+// exited. Answers 503 with the same body when any of them isn't the case.
+pub async fn readyz(State(pool): State<PgPool>, State(workers): State<WorkerStatus>) -> (StatusCode, Json<Readiness>) {
+    let (database, migrations) = match pending_migrations(&pool).await {
+        Ok(pending) if pending.is_empty() => (ComponentStatus::up(), ComponentStatus::up()),
+        Ok(pending) => (
+            ComponentStatus::up(),
+            ComponentStatus::down(format!("{} migrations not applied, starting with {}", pending.len(), pending[0])),
+        ),
+        Err(e) => {
+            tracing::error!("Readiness check could not query the database: {}", e);
+            let unknown = ComponentStatus::down("database unavailable".to_string());
+            (ComponentStatus::down("query failed".to_string()), unknown)
+        }
+    };
+
+    let stopped = workers.stopped();
+    let workers = if stopped.is_empty() {
+        ComponentStatus::up()
+    } else {
+        tracing::error!("Background workers have stopped: {}", stopped.join(", "));
+        ComponentStatus::down(format!("stopped: {}", stopped.join(", ")))
+    };
+
+    let components = ReadinessComponents { database, migrations, workers };
+    let ready = components.database.is_up() && components.migrations.is_up() && components.workers.is_up();
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(Readiness { status: if ready { "ready" } else { "not_ready" }, components }))
+}
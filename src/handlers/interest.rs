@@ -0,0 +1,377 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use bigdecimal::{BigDecimal, RoundingMode};
+use sqlx::{PgConnection, PgPool};
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::events::publish_transaction_created;
+use crate::middleware::auth::AuthContext;
+use crate::models::account::AccountType;
+use crate::models::interest::{CompoundingPeriod, InterestAccrual, InterestAccrualQuery, InterestRate, UpdateInterestRate, MAX_APY};
+use crate::models::money::{Currency, Money};
+use crate::models::transaction::{TransactionStatus, TransactionType};
+use crate::repositories::account as accounts;
+use crate::repositories::transaction::{self as transactions, NewTransaction};
+use crate::services::audit::{self, AuditAction, AuditRecord};
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+// Decimal places of the APY, as stored
+const APY_SCALE: i64 = 4;
+
+// Periodic rates are cut to this many decimal places
+const RATE_SCALE: i64 = 12;
+
+// Decimal places periodic rates are worked out to before being cut
+const WORKING_SCALE: i64 = 30;
+
+// Category interest credits are filed under
+pub const INTEREST_CATEGORY: &str = "interest";
+
+pub async fn get_interest_rates(
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<InterestRate>>, AppError> {
+    let rates = sqlx::query_as!(
+        InterestRate,
+        r#"
+        SELECT account_type as "account_type: _", apy, compounding as "compounding: _", effective_from, updated_by, updated_at
+        FROM interest_rates
+        ORDER BY account_type
+        "#
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch interest rates: {}", e);
+        db_error(&e, "Failed to fetch interest rates")
+    })?;
+
+    Ok(Json(rates))
+}
+
+// Sets the interest paid on accounts of one type from the next period accrued on. Raising a rate
+// of zero starts interest from today rather than paying for the time it was off.
+pub async fn update_interest_rate(
+    State(pool): State<PgPool>,
+    Path(account_type): Path<AccountType>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<UpdateInterestRate>,
+) -> Result<Json<InterestRate>, AppError> {
+    if payload.apy < 0 || payload.apy > MAX_APY {
+        return Err(AppError::BadRequest(format!("APY must be between 0 and {} percent", MAX_APY)));
+    }
+    if payload.apy.fractional_digit_count() > APY_SCALE {
+        return Err(AppError::BadRequest(format!("APY can have at most {} decimal places", APY_SCALE)));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    let previous = sqlx::query_as!(
+        InterestRate,
+        r#"
+        SELECT account_type as "account_type: _", apy, compounding as "compounding: _", effective_from, updated_by, updated_at
+        FROM interest_rates
+        WHERE account_type = $1
+        FOR UPDATE
+        "#,
+        account_type as _
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch interest rate: {}", e);
+        db_error(&e, "Failed to update interest rate")
+    })?;
+
+    let rate = sqlx::query_as!(
+        InterestRate,
+        r#"
+        INSERT INTO interest_rates (account_type, apy, compounding, effective_from, updated_by)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (account_type) DO UPDATE
+        SET apy = EXCLUDED.apy,
+            compounding = EXCLUDED.compounding,
+            effective_from = CASE WHEN interest_rates.apy = 0 THEN EXCLUDED.effective_from ELSE interest_rates.effective_from END,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING account_type as "account_type: _", apy, compounding as "compounding: _", effective_from, updated_by, updated_at
+        "#,
+        account_type as _,
+        payload.apy,
+        payload.compounding as _,
+        OffsetDateTime::now_utc().date(),
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to update interest rate: {}", e);
+        db_error(&e, "Failed to update interest rate")
+    })?;
+
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::InterestRateUpdated, auth.user_id, None).before(&previous).after(&rate))
+        .await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!(target: "audit", "Interest on {:?} accounts set to {}% compounded {:?} by admin {}", account_type, rate.apy, rate.compounding, auth.user_id);
+    Ok(Json(rate))
+}
+
+// The interest worked out for one of the user's accounts, newest period first
+pub async fn get_interest_accruals(
+    State(pool): State<PgPool>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<InterestAccrualQuery>,
+) -> Result<Json<Vec<InterestAccrual>>, AppError> {
+    info!("Fetching interest accruals of account {} for user {}", account_id, user_id);
+
+    accounts::find(&pool, user_id, account_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch account: {}", e);
+            db_error(&e, "Failed to fetch account")
+        })?
+        .ok_or(AppError::NotFound("Account not found".to_string()))?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let accruals = sqlx::query_as!(
+        InterestAccrual,
+        r#"
+        SELECT id, account_id, user_id, period_start, period_end, average_balance, apy, compounding as "compounding: _",
+            amount, currency, transaction_id, created_at
+        FROM interest_accruals
+        WHERE account_id = $1
+        ORDER BY period_end DESC
+        LIMIT $2
+        "#,
+        account_id,
+        limit
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch interest accruals: {}", e);
+        db_error(&e, "Failed to fetch interest accruals")
+    })?;
+
+    Ok(Json(accruals))
+}
+
+// The rate per compounding period that compounds to `apy` percent over a year, i.e. the
+// `per_year`th root of 1 + APY less one. The root is found by Newton's method, starting from the
+// simple rate, which is never below it, and stepping down until a step no longer lowers it.
+pub fn periodic_rate(apy: &BigDecimal, compounding: CompoundingPeriod) -> BigDecimal {
+    let periods = compounding.per_year() as u32;
+    let yearly = BigDecimal::from(1) + apy / BigDecimal::from(100);
+    let mut root = BigDecimal::from(1) + apy / BigDecimal::from(100 * periods);
+    loop {
+        let next = ((BigDecimal::from(periods - 1) * &root + &yearly / power(&root, periods - 1)) / BigDecimal::from(periods))
+            .with_scale_round(WORKING_SCALE, RoundingMode::HalfEven);
+        if next >= root {
+            break;
+        }
+        root = next;
+    }
+    (root - BigDecimal::from(1)).with_scale_round(RATE_SCALE, RoundingMode::Down)
+}
+
+// `base` to the power of `exponent`, by squaring, kept to the working scale along the way
+fn power(base: &BigDecimal, mut exponent: u32) -> BigDecimal {
+    let mut result = BigDecimal::from(1);
+    let mut square = base.clone();
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = (&result * &square).with_scale_round(WORKING_SCALE, RoundingMode::HalfEven);
+        }
+        square = square.square().with_scale_round(WORKING_SCALE, RoundingMode::HalfEven);
+        exponent /= 2;
+    }
+    result
+}
+
+// Works out and pays the interest of every period that has ended by `today` (UTC) on open accounts
+// whose type earns interest, oldest period first. Each period is recorded with its credit in one DB
+// transaction, so a period is never paid twice however often this runs or wherever it stops.
+pub async fn accrue_due_interest(pool: &PgPool, today: Date) -> Result<usize, sqlx::Error> {
+    let mut accrued = 0;
+    // Accounts that can't be accrued on, left out of the rest of the run
+    let mut skipped: Vec<Uuid> = Vec::new();
+
+    loop {
+        let mut tx = pool.begin().await?;
+
+        // An account's next period starts where its last one ended, or on the day it opened or
+        // its type started earning interest
+        let Some(due) = sqlx::query!(
+            r#"
+            SELECT a.id, a.user_id, a.currency, r.apy, r.compounding as "compounding: CompoundingPeriod",
+                p.period_start as "period_start!"
+            FROM accounts a
+            JOIN interest_rates r ON r.account_type = a.account_type
+            CROSS JOIN LATERAL (
+                SELECT GREATEST(
+                    (SELECT MAX(period_end) FROM interest_accruals i WHERE i.account_id = a.id),
+                    r.effective_from,
+                    (a.created_at AT TIME ZONE 'UTC')::date
+                ) AS period_start
+            ) p
+            WHERE a.closed_at IS NULL AND r.apy > 0 AND a.id <> ALL($2)
+                AND CASE r.compounding
+                    WHEN 'daily' THEN p.period_start + 1
+                    ELSE (date_trunc('month', p.period_start) + INTERVAL '1 month')::date
+                END <= $1
+            ORDER BY p.period_start, a.id
+            LIMIT 1
+            FOR UPDATE OF a SKIP LOCKED
+            "#,
+            today,
+            &skipped
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            break;
+        };
+
+        let Some(currency) = Currency::parse(&due.currency) else {
+            error!("Skipping interest on account {} with invalid currency {}", due.id, due.currency);
+            skipped.push(due.id);
+            continue;
+        };
+        let period_end = due.compounding.end_of(due.period_start);
+        accrue(&mut tx, due.id, due.user_id, currency, &due.apy, due.compounding, due.period_start, period_end).await?;
+
+        tx.commit().await?;
+        accrued += 1;
+    }
+
+    Ok(accrued)
+}
+
+// Pays interest on the account's average end-of-day balance over the period, at the periodic rate
+// prorated for a period the account only held part of, and records the accrual
+#[allow(clippy::too_many_arguments)]
+async fn accrue(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    user_id: Uuid,
+    currency: Currency,
+    apy: &BigDecimal,
+    compounding: CompoundingPeriod,
+    period_start: Date,
+    period_end: Date,
+) -> Result<(), sqlx::Error> {
+    // Sum of the live settled balance at the end of each day, negative balances earning nothing
+    let balance_days = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(GREATEST(b.balance, 0)), 0) as "balance_days!"
+        FROM generate_series($2::date, $3::date - 1, INTERVAL '1 day') AS day
+        CROSS JOIN LATERAL (
+            SELECT COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END), 0) AS balance
+            FROM transactions
+            WHERE account_id = $1 AND livemode AND status IN ('settled', 'reversed')
+                AND created_at < (day + INTERVAL '1 day') AT TIME ZONE 'UTC'
+        ) b
+        "#,
+        account_id,
+        period_start,
+        period_end
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let days = (period_end - period_start).whole_days();
+    let full_period_days = (compounding.end_of(period_start) - compounding.start_of(period_start)).whole_days();
+    let average_balance = (&balance_days / BigDecimal::from(days)).with_scale_round(4, RoundingMode::Down);
+    let amount = (&balance_days * periodic_rate(apy, compounding) / BigDecimal::from(full_period_days))
+        .with_scale_round(currency.minor_units(), RoundingMode::Down);
+
+    let credit = Money::from_decimal(&amount, currency).ok().filter(|money| money.amount_minor() > 0);
+    let transaction_id = if let Some(money) = credit {
+        let entry = NewTransaction {
+            user_id,
+            account_id: Some(account_id),
+            amount: &money,
+            transaction_type: TransactionType::Credit,
+            description: Some("Interest"),
+            transfer_id: None,
+            status: TransactionStatus::Settled,
+            reverses: None,
+            execute_at: None,
+            livemode: true,
+            category: Some(INTEREST_CATEGORY),
+            payee_id: None,
+            fx_rate: None,
+        };
+        let transaction = transactions::insert(&mut *conn, &entry).await?;
+        publish_transaction_created(&mut *conn, &transaction).await?;
+        Some(transaction.id)
+    } else {
+        None
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO interest_accruals
+            (account_id, user_id, period_start, period_end, average_balance, apy, compounding, amount, currency, transaction_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+        account_id,
+        user_id,
+        period_start,
+        period_end,
+        average_balance,
+        apy,
+        compounding as _,
+        amount,
+        currency.as_str(),
+        transaction_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    info!("Accrued {} {} interest on account {} for {} to {}", amount, currency, account_id, period_start, period_end);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::ToPrimitive;
+    use std::str::FromStr;
+    use time::macros::date;
+
+    #[test]
+    fn test_periodic_rates_compound_to_the_apy() {
+        let apy = BigDecimal::from_str("5").unwrap();
+        for compounding in [CompoundingPeriod::Daily, CompoundingPeriod::Monthly] {
+            let rate = periodic_rate(&apy, compounding).to_f64().unwrap();
+            let yearly = (1.0 + rate).powi(compounding.per_year());
+            assert!((yearly - 1.05).abs() < 1e-9, "{:?} compounds to {}", compounding, yearly);
+        }
+        assert_eq!(periodic_rate(&apy, CompoundingPeriod::Daily), BigDecimal::from_str("0.000133680617").unwrap());
+        assert_eq!(periodic_rate(&apy, CompoundingPeriod::Monthly), BigDecimal::from_str("0.004074123783").unwrap());
+        assert_eq!(periodic_rate(&BigDecimal::from(0), CompoundingPeriod::Daily), BigDecimal::from(0));
+    }
+
+    #[test]
+    fn test_periods_end_at_the_next_day_or_month() {
+        assert_eq!(CompoundingPeriod::Daily.end_of(date!(2024 - 02 - 28)), date!(2024 - 02 - 29));
+        assert_eq!(CompoundingPeriod::Monthly.start_of(date!(2024 - 02 - 15)), date!(2024 - 02 - 01));
+        assert_eq!(CompoundingPeriod::Monthly.end_of(date!(2024 - 02 - 15)), date!(2024 - 03 - 01));
+        assert_eq!(CompoundingPeriod::Monthly.end_of(date!(2024 - 12 - 31)), date!(2025 - 01 - 01));
+    }
+}
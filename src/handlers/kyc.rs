@@ -0,0 +1,540 @@
+use axum::{
+    extract::{Extension, Multipart, Path, Query, State},
+    Json,
+};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::blob_store::BlobStore;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::handlers::attachment::{blob_error, sanitize_filename, sniff_content_type};
+use crate::handlers::velocity_limit::check_positive_amount;
+use crate::kyc;
+use crate::middleware::auth::AuthContext;
+use crate::models::attachment::ALLOWED_CONTENT_TYPES;
+use crate::models::kyc::{
+    KycDecision, KycDocument, KycDocumentDownload, KycLimits, KycQuery, KycReviewView, KycStatus, KycSummary,
+    KycVerification, ReviewKyc, UpdateKycLimits, UploadKycDocument, MAX_KYC_DOCUMENTS, MAX_KYC_DOCUMENT_BYTES,
+};
+use crate::models::money::Currency;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::validation::ValidatedJson;
+
+// Multipart field carrying the file
+const FILE_FIELD: &str = "file";
+
+// How long a reviewing admin's download URL works for
+const DOWNLOAD_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+async fn find_verification(conn: &mut PgConnection, user_id: Uuid) -> Result<KycVerification, AppError> {
+    kyc::verification(conn, user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch KYC verification: {}", e);
+            db_error(&e, "Failed to fetch KYC verification")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))
+}
+
+// Starts the user's verification if they haven't yet, and locks it for the rest of the DB transaction
+async fn lock_verification(conn: &mut PgConnection, user_id: Uuid) -> Result<KycVerification, sqlx::Error> {
+    sqlx::query!("INSERT INTO kyc_verifications (user_id) VALUES ($1) ON CONFLICT (user_id) DO NOTHING", user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query_as!(
+        KycVerification,
+        r#"
+        SELECT user_id, status as "status: _", submitted_at, reviewed_by, reviewed_at, rejection_reason, updated_at
+        FROM kyc_verifications
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_one(conn)
+    .await
+}
+
+async fn documents_of(pool: &PgPool, user_id: Uuid) -> Result<Vec<KycDocument>, AppError> {
+    sqlx::query_as!(
+        KycDocument,
+        r#"
+        SELECT id, user_id, document_type as "document_type: _", filename, content_type, size_bytes, sha256, storage_key,
+            created_at
+        FROM kyc_documents
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch KYC documents: {}", e);
+        db_error(&e, "Failed to fetch KYC documents")
+    })
+}
+
+// The user's verification status and the transaction limits it brings
+pub async fn get_kyc(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<KycSummary>, AppError> {
+    let mut conn = pool.acquire().await.map_err(|e| {
+        error!("Failed to acquire connection: {}", e);
+        db_error(&e, "Failed to fetch KYC verification")
+    })?;
+
+    let verification = find_verification(&mut conn, user_id).await?;
+    let limits = kyc::limits_for(&mut conn, verification.status).await.map_err(|e| {
+        error!("Failed to fetch KYC limits: {}", e);
+        db_error(&e, "Failed to fetch KYC verification")
+    })?;
+
+    Ok(Json(KycSummary { verification, limits }))
+}
+
+// The documents the user has uploaded, oldest first
+pub async fn get_kyc_documents(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<KycDocument>>, AppError> {
+    Ok(Json(documents_of(&pool, user_id).await?))
+}
+
+// Stores a JPEG, PNG or PDF identity document. Like attachments, the file is uploaded before the row
+// is written, and removed again if the row can't be.
+pub async fn upload_kyc_document(
+    State(pool): State<PgPool>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<UploadKycDocument>,
+    mut multipart: Multipart,
+) -> Result<Json<KycDocument>, AppError> {
+    info!("User {} uploading a {:?} KYC document", user_id, query.document_type);
+
+    let mut file = None;
+    while let Some(mut field) = multipart.next_field().await.map_err(|e| {
+        error!("Failed to read multipart upload: {}", e);
+        AppError::BadRequest("Invalid multipart upload".to_string())
+    })? {
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+        let filename = field.file_name().map(sanitize_filename).unwrap_or_default();
+        let content_type = field.content_type().unwrap_or_default().to_ascii_lowercase();
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(|e| {
+            error!("Failed to read uploaded file: {}", e);
+            AppError::BadRequest("Invalid multipart upload".to_string())
+        })? {
+            if bytes.len() + chunk.len() > MAX_KYC_DOCUMENT_BYTES {
+                return Err(AppError::PayloadTooLarge(format!(
+                    "Documents must be at most {} MiB",
+                    MAX_KYC_DOCUMENT_BYTES / (1024 * 1024)
+                )));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        file = Some((filename, content_type, bytes));
+        break;
+    }
+    let (filename, content_type, bytes) = file.ok_or(AppError::BadRequest(format!("Missing `{}` field", FILE_FIELD)))?;
+
+    if filename.is_empty() {
+        return Err(AppError::BadRequest("The file must have a name".to_string()));
+    }
+    if bytes.is_empty() {
+        return Err(AppError::BadRequest("The file is empty".to_string()));
+    }
+    if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) || sniff_content_type(&bytes) != Some(content_type.as_str()) {
+        return Err(AppError::Unprocessable("Documents must be JPEG, PNG or PDF files".to_string()));
+    }
+
+    let id = Uuid::new_v4();
+    let document = KycDocument {
+        id,
+        user_id,
+        document_type: query.document_type,
+        filename,
+        sha256: hex::encode(Sha256::digest(&bytes)),
+        size_bytes: bytes.len() as i64,
+        storage_key: format!("kyc/{}/{}", user_id, id),
+        content_type,
+        created_at: OffsetDateTime::now_utc(),
+    };
+
+    blobs.put(&document.storage_key, &document.content_type, bytes).await
+        .map_err(|e| blob_error(&e, "Failed to store document"))?;
+
+    match insert_document(&pool, &document).await {
+        Ok(document) => {
+            info!("Stored KYC document {} for user {}", document.id, user_id);
+            Ok(Json(document))
+        }
+        Err(e) => {
+            if let Err(cleanup) = blobs.delete(&document.storage_key).await {
+                error!("Failed to remove orphaned KYC document {}: {}", document.storage_key, cleanup);
+            }
+            Err(e)
+        }
+    }
+}
+
+// Writes the row once the verification is locked, so it can't be submitted in between
+async fn insert_document(pool: &PgPool, document: &KycDocument) -> Result<KycDocument, AppError> {
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let verification = lock_verification(&mut tx, document.user_id).await.map_err(|e| {
+        error!("Failed to lock KYC verification: {}", e);
+        db_error(&e, "Failed to store document")
+    })?;
+    if !verification.status.can_submit() {
+        return Err(AppError::Conflict("Documents can't be added while a review is pending or once verified".to_string()));
+    }
+
+    let uploaded = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM kyc_documents WHERE user_id = $1"#,
+        document.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to count KYC documents: {}", e);
+        db_error(&e, "Failed to store document")
+    })?;
+    if uploaded >= MAX_KYC_DOCUMENTS {
+        return Err(AppError::Unprocessable(format!("At most {} documents can be uploaded", MAX_KYC_DOCUMENTS)));
+    }
+
+    let inserted = sqlx::query_as!(
+        KycDocument,
+        r#"
+        INSERT INTO kyc_documents (id, user_id, document_type, filename, content_type, size_bytes, sha256, storage_key)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, user_id, document_type as "document_type: _", filename, content_type, size_bytes, sha256,
+            storage_key, created_at
+        "#,
+        document.id,
+        document.user_id,
+        document.document_type as _,
+        document.filename,
+        document.content_type,
+        document.size_bytes,
+        document.sha256,
+        document.storage_key
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to store KYC document: {}", e);
+        db_error(&e, "Failed to store document")
+    })?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    Ok(inserted)
+}
+
+// Sends the uploaded documents for review. A submission needs an identity document uploaded since
+// the last review, so a rejected user can't resubmit what was already turned down.
+pub async fn submit_kyc(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<KycVerification>, AppError> {
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let previous = lock_verification(&mut tx, user_id).await.map_err(|e| {
+        error!("Failed to lock KYC verification: {}", e);
+        db_error(&e, "Failed to submit KYC documents")
+    })?;
+    if !previous.status.can_submit() {
+        return Err(AppError::Conflict("Verification is already pending review or complete".to_string()));
+    }
+
+    let new_documents = sqlx::query_scalar!(
+        r#"
+        SELECT document_type as "document_type: crate::models::kyc::KycDocumentType"
+        FROM kyc_documents
+        WHERE user_id = $1 AND created_at > COALESCE($2, '-infinity'::timestamptz)
+        "#,
+        user_id,
+        previous.reviewed_at
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch KYC documents: {}", e);
+        db_error(&e, "Failed to submit KYC documents")
+    })?;
+    if !new_documents.into_iter().any(|document_type| document_type.proves_identity()) {
+        return Err(AppError::Unprocessable(
+            "Upload a passport, driver's license or national ID before submitting".to_string(),
+        ));
+    }
+
+    let verification = sqlx::query_as!(
+        KycVerification,
+        r#"
+        UPDATE kyc_verifications
+        SET status = 'pending', submitted_at = NOW(), rejection_reason = NULL, updated_at = NOW()
+        WHERE user_id = $1
+        RETURNING user_id, status as "status: _", submitted_at, reviewed_by, reviewed_at, rejection_reason, updated_at
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to submit KYC documents: {}", e);
+        db_error(&e, "Failed to submit KYC documents")
+    })?;
+
+    audit::record(
+        &mut *tx,
+        AuditRecord::new(AuditAction::KycSubmitted, user_id, Some(user_id)).before(&previous).after(&verification),
+    )
+    .await?;
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!("User {} submitted KYC documents for review", user_id);
+    Ok(Json(verification))
+}
+
+// Verifications in one status, pending ones by default, longest waiting first
+pub async fn get_kyc_verifications(
+    State(pool): State<PgPool>,
+    Query(query): Query<KycQuery>,
+) -> Result<Json<Vec<KycVerification>>, AppError> {
+    let status = query.status.unwrap_or(KycStatus::Pending);
+    let verifications = sqlx::query_as!(
+        KycVerification,
+        r#"
+        SELECT user_id, status as "status: _", submitted_at, reviewed_by, reviewed_at, rejection_reason, updated_at
+        FROM kyc_verifications
+        WHERE status = $1
+        ORDER BY submitted_at NULLS LAST, user_id
+        "#,
+        status as _
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch KYC verifications: {}", e);
+        db_error(&e, "Failed to fetch KYC verifications")
+    })?;
+
+    Ok(Json(verifications))
+}
+
+// One user's verification with pre-signed URLs to download each of their documents from
+pub async fn get_kyc_review(
+    State(pool): State<PgPool>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<KycReviewView>, AppError> {
+    let mut conn = pool.acquire().await.map_err(|e| {
+        error!("Failed to acquire connection: {}", e);
+        db_error(&e, "Failed to fetch KYC verification")
+    })?;
+    let verification = find_verification(&mut conn, user_id).await?;
+    drop(conn);
+
+    let expires_at = OffsetDateTime::now_utc() + DOWNLOAD_URL_TTL;
+    let documents = documents_of(&pool, user_id)
+        .await?
+        .into_iter()
+        .map(|document| {
+            let download_url = blobs
+                .presigned_url(&document.storage_key, DOWNLOAD_URL_TTL)
+                .map_err(|e| blob_error(&e, "Failed to sign download URL"))?;
+            Ok(KycDocumentDownload { document, download_url, download_url_expires_at: expires_at })
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    Ok(Json(KycReviewView { verification, documents }))
+}
+
+// Verifies or rejects a pending submission; a rejection needs a reason, which the user is shown
+pub async fn review_kyc(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<ReviewKyc>,
+) -> Result<Json<KycVerification>, AppError> {
+    info!("Admin {} reviewing KYC of user {}: {:?}", auth.user_id, user_id, payload);
+
+    let (status, reason) = match (payload.decision, payload.reason) {
+        (KycDecision::Verified, _) => (KycStatus::Verified, None),
+        (KycDecision::Rejected, Some(reason)) => (KycStatus::Rejected, Some(reason)),
+        (KycDecision::Rejected, None) => return Err(AppError::BadRequest("A rejection needs a reason".to_string())),
+    };
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let previous = sqlx::query_as!(
+        KycVerification,
+        r#"
+        SELECT user_id, status as "status: _", submitted_at, reviewed_by, reviewed_at, rejection_reason, updated_at
+        FROM kyc_verifications
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch KYC verification: {}", e);
+        db_error(&e, "Failed to review KYC verification")
+    })?
+    .ok_or(AppError::NotFound("Verification not found".to_string()))?;
+    if previous.status != KycStatus::Pending {
+        return Err(AppError::Conflict("Only pending verifications can be reviewed".to_string()));
+    }
+
+    let verification = sqlx::query_as!(
+        KycVerification,
+        r#"
+        UPDATE kyc_verifications
+        SET status = $2, rejection_reason = $3, reviewed_by = $4, reviewed_at = NOW(), updated_at = NOW()
+        WHERE user_id = $1
+        RETURNING user_id, status as "status: _", submitted_at, reviewed_by, reviewed_at, rejection_reason, updated_at
+        "#,
+        user_id,
+        status as _,
+        reason,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to review KYC verification: {}", e);
+        db_error(&e, "Failed to review KYC verification")
+    })?;
+
+    audit::record(
+        &mut *tx,
+        AuditRecord::new(AuditAction::KycReviewed, auth.user_id, Some(user_id)).before(&previous).after(&verification),
+    )
+    .await?;
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "KYC of user {} marked {:?} by admin {}", user_id, status, auth.user_id);
+    Ok(Json(verification))
+}
+
+// The limits set for each status; statuses without limits are left out
+pub async fn get_kyc_limits(State(pool): State<PgPool>) -> Result<Json<Vec<KycLimits>>, AppError> {
+    let limits = sqlx::query_as!(
+        KycLimits,
+        r#"
+        SELECT status as "status: _", currency, max_transaction_amount, max_daily_debit_total, updated_by, updated_at
+        FROM kyc_limits
+        ORDER BY status
+        "#
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch KYC limits: {}", e);
+        db_error(&e, "Failed to fetch KYC limits")
+    })?;
+
+    Ok(Json(limits))
+}
+
+// Replaces the limits on users with one status; they apply to transactions created afterwards
+pub async fn update_kyc_limits(
+    State(pool): State<PgPool>,
+    Path(status): Path<KycStatus>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<UpdateKycLimits>,
+) -> Result<Json<KycLimits>, AppError> {
+    info!("Admin {} updating limits on {:?} users: {:?}", auth.user_id, status, payload);
+
+    let currency = Currency::parse(&payload.currency)
+        .ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    check_positive_amount("max_transaction_amount", payload.max_transaction_amount.as_ref())?;
+    check_positive_amount("max_daily_debit_total", payload.max_daily_debit_total.as_ref())?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+    let before = kyc::limits_for(&mut tx, status).await.map_err(|e| {
+        error!("Failed to fetch KYC limits: {}", e);
+        db_error(&e, "Failed to update KYC limits")
+    })?;
+
+    let limits = sqlx::query_as!(
+        KycLimits,
+        r#"
+        INSERT INTO kyc_limits (status, currency, max_transaction_amount, max_daily_debit_total, updated_by)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (status) DO UPDATE
+        SET currency = EXCLUDED.currency,
+            max_transaction_amount = EXCLUDED.max_transaction_amount,
+            max_daily_debit_total = EXCLUDED.max_daily_debit_total,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING status as "status: _", currency, max_transaction_amount, max_daily_debit_total, updated_by, updated_at
+        "#,
+        status as _,
+        currency.as_str(),
+        payload.max_transaction_amount,
+        payload.max_daily_debit_total,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to update KYC limits: {}", e);
+        db_error(&e, "Failed to update KYC limits")
+    })?;
+
+    let mut record = AuditRecord::new(AuditAction::KycLimitsUpdated, auth.user_id, None).after(&limits);
+    if let Some(before) = &before {
+        record = record.before(before);
+    }
+    audit::record(&mut *tx, record).await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!(target: "audit", "Limits on {:?} users updated by admin {}", status, auth.user_id);
+    Ok(Json(limits))
+}
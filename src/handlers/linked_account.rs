@@ -0,0 +1,427 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::linked_accounts::sealing::{open_access_token, seal_access_token};
+use crate::linked_accounts::{BankDataProvider, ExternalAccount, ExternalTransaction, LinkError};
+use crate::models::linked_account::{
+    LinkAccounts, LinkTokenResponse, LinkedAccount, LinkedAccountTransaction, RefreshResponse,
+};
+
+fn link_error(e: LinkError) -> AppError {
+    match e {
+        LinkError::InvalidToken => AppError::BadRequest("Invalid or expired public token".to_string()),
+        LinkError::NotConfigured | LinkError::Provider(_) => {
+            error!("Bank data provider request failed: {}", e);
+            AppError::Unavailable
+        }
+        LinkError::Sealing(_) => {
+            error!("Failed to seal linked item access token: {}", e);
+            AppError::Internal("Failed to read linked account".to_string())
+        }
+    }
+}
+
+// Starts linking: the client opens Plaid Link with this token and gets a public token back
+pub async fn create_link_token(
+    State(provider): State<Arc<dyn BankDataProvider>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<LinkTokenResponse>, AppError> {
+    let token = provider.create_link_token(user_id).await.map_err(link_error)?;
+    Ok(Json(LinkTokenResponse { link_token: token.link_token, expiration: token.expiration }))
+}
+
+// Finishes linking: trades the public token for access to the bank login and saves its accounts
+// with their current balances. Transactions are pulled by refreshing an account.
+pub async fn link_accounts(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(provider): State<Arc<dyn BankDataProvider>>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<LinkAccounts>,
+) -> Result<Json<Vec<LinkedAccount>>, AppError> {
+    info!("Linking bank accounts for user {}", user_id);
+
+    let item = provider.exchange_public_token(&payload.public_token).await.map_err(link_error)?;
+    let accounts = provider.get_accounts(&item.access_token).await.map_err(link_error)?;
+    let sealed_token = seal_access_token(&config, &item.access_token).map_err(link_error)?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to begin transaction: {}", e);
+        db_error(&e, "Failed to link accounts")
+    })?;
+
+    // Plaid issues a new item for every link, so a repeat is only a retry of this same request
+    let item_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO linked_items (user_id, provider_item_id, access_token)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (provider_item_id) DO UPDATE
+        SET access_token = EXCLUDED.access_token, updated_at = NOW()
+        WHERE linked_items.user_id = EXCLUDED.user_id
+        RETURNING id
+        "#,
+        user_id,
+        item.item_id,
+        sealed_token
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to save linked item: {}", e);
+        db_error(&e, "Failed to link accounts")
+    })?
+    .ok_or(AppError::Conflict("This bank login is linked to another user".to_string()))?;
+
+    let linked = save_accounts(&mut tx, user_id, item_id, &accounts).await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to link accounts")
+    })?;
+
+    info!("Linked {} bank accounts for user {}", linked.len(), user_id);
+    Ok(Json(linked))
+}
+
+// Saves an item's accounts, updating the balances of ones already saved
+async fn save_accounts(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    item_id: Uuid,
+    accounts: &[ExternalAccount],
+) -> Result<Vec<LinkedAccount>, AppError> {
+    let mut saved = Vec::with_capacity(accounts.len());
+    for account in accounts {
+        let linked = sqlx::query_as!(
+            LinkedAccount,
+            r#"
+            INSERT INTO linked_accounts (user_id, item_id, provider_account_id, name, mask, account_type, subtype, currency,
+                available_balance, current_balance)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (provider_account_id) DO UPDATE
+            SET name = EXCLUDED.name, mask = EXCLUDED.mask, currency = EXCLUDED.currency,
+                available_balance = EXCLUDED.available_balance, current_balance = EXCLUDED.current_balance,
+                balances_updated_at = NOW()
+            WHERE linked_accounts.item_id = EXCLUDED.item_id
+            RETURNING id, user_id, item_id, name, mask, account_type, subtype, currency, available_balance, current_balance,
+                balances_updated_at, created_at
+            "#,
+            user_id,
+            item_id,
+            account.id,
+            account.name,
+            account.mask,
+            account.account_type,
+            account.subtype,
+            account.currency,
+            account.available_balance,
+            account.current_balance
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to save linked account: {}", e);
+            db_error(&e, "Failed to save linked accounts")
+        })?;
+        match linked {
+            Some(linked) => saved.push(linked),
+            None => error!("Linked account {} already belongs to another item", account.id),
+        }
+    }
+    Ok(saved)
+}
+
+// The user's linked accounts, grouped by bank login
+pub async fn get_linked_accounts(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<LinkedAccount>>, AppError> {
+    let accounts = sqlx::query_as!(
+        LinkedAccount,
+        r#"
+        SELECT id, user_id, item_id, name, mask, account_type, subtype, currency, available_balance, current_balance,
+            balances_updated_at, created_at
+        FROM linked_accounts
+        WHERE user_id = $1
+        ORDER BY item_id, created_at, name
+        "#,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch linked accounts: {}", e);
+        db_error(&e, "Failed to fetch linked accounts")
+    })?;
+
+    Ok(Json(accounts))
+}
+
+// Pulls fresh balances for every account of the bank login and the transactions changed since the
+// last refresh. Each page of changes is saved with the sync cursor it leads to, so a refresh that
+// fails part way resumes where it stopped.
+pub async fn refresh_linked_account(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(provider): State<Arc<dyn BankDataProvider>>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<RefreshResponse>, AppError> {
+    info!("Refreshing linked account {} for user {}", account_id, user_id);
+
+    let item = sqlx::query!(
+        r#"
+        SELECT i.id, i.access_token, i.transactions_cursor
+        FROM linked_accounts a
+        JOIN linked_items i ON i.id = a.item_id
+        WHERE a.id = $1 AND a.user_id = $2
+        "#,
+        account_id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch linked item: {}", e);
+        db_error(&e, "Failed to refresh linked account")
+    })?
+    .ok_or(AppError::NotFound("Linked account not found".to_string()))?;
+    let access_token = open_access_token(&config, &item.access_token).map_err(link_error)?;
+
+    let accounts = provider.get_accounts(&access_token).await.map_err(link_error)?;
+    let mut conn = pool.acquire().await.map_err(|e| {
+        error!("Failed to acquire connection: {}", e);
+        db_error(&e, "Failed to refresh linked account")
+    })?;
+    let saved = save_accounts(&mut conn, user_id, item.id, &accounts).await?;
+    drop(conn);
+
+    let (mut added, mut modified, mut removed) = (0, 0, 0);
+    let mut cursor = item.transactions_cursor;
+    loop {
+        let changes = provider.sync_transactions(&access_token, cursor.as_deref()).await.map_err(link_error)?;
+
+        let mut tx = pool.begin().await.map_err(|e| {
+            error!("Failed to begin transaction: {}", e);
+            db_error(&e, "Failed to refresh linked account")
+        })?;
+        // A concurrent refresh that already moved the cursor on has saved these same changes
+        let advanced = sqlx::query!(
+            r#"
+            UPDATE linked_items
+            SET transactions_cursor = $2, updated_at = NOW()
+            WHERE id = $1 AND transactions_cursor IS NOT DISTINCT FROM $3
+            "#,
+            item.id,
+            changes.next_cursor,
+            cursor
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Failed to advance transactions cursor: {}", e);
+            db_error(&e, "Failed to refresh linked account")
+        })?
+        .rows_affected();
+        if advanced == 0 {
+            info!("Linked item {} was refreshed concurrently", item.id);
+            break;
+        }
+
+        for transaction in changes.added.iter().chain(&changes.modified) {
+            save_transaction(&mut tx, item.id, transaction).await?;
+        }
+        sqlx::query!(
+            r#"
+            DELETE FROM linked_account_transactions
+            WHERE provider_transaction_id = ANY($2)
+                AND linked_account_id IN (SELECT id FROM linked_accounts WHERE item_id = $1)
+            "#,
+            item.id,
+            &changes.removed
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Failed to remove linked account transactions: {}", e);
+            db_error(&e, "Failed to refresh linked account")
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to refresh linked account")
+        })?;
+
+        added += changes.added.len();
+        modified += changes.modified.len();
+        removed += changes.removed.len();
+        cursor = Some(changes.next_cursor);
+        if !changes.has_more {
+            break;
+        }
+    }
+
+    let account = saved
+        .into_iter()
+        .find(|account| account.id == account_id)
+        .ok_or(AppError::NotFound("The bank no longer reports this account".to_string()))?;
+
+    info!("Refreshed linked account {}: {} added, {} modified, {} removed", account_id, added, modified, removed);
+    Ok(Json(RefreshResponse {
+        account,
+        transactions_added: added,
+        transactions_modified: modified,
+        transactions_removed: removed,
+    }))
+}
+
+// Saves a pulled transaction, skipping ones on accounts of the item that weren't saved
+async fn save_transaction(conn: &mut PgConnection, item_id: Uuid, transaction: &ExternalTransaction) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO linked_account_transactions (linked_account_id, provider_transaction_id, amount, currency, description,
+            merchant_name, posted_on, pending)
+        SELECT id, $3, $4, $5, $6, $7, $8, $9
+        FROM linked_accounts
+        WHERE item_id = $1 AND provider_account_id = $2
+        ON CONFLICT (provider_transaction_id) DO UPDATE
+        SET amount = EXCLUDED.amount, currency = EXCLUDED.currency, description = EXCLUDED.description,
+            merchant_name = EXCLUDED.merchant_name, posted_on = EXCLUDED.posted_on, pending = EXCLUDED.pending,
+            updated_at = NOW()
+        "#,
+        item_id,
+        transaction.account_id,
+        transaction.id,
+        transaction.amount,
+        transaction.currency,
+        transaction.description,
+        transaction.merchant_name,
+        transaction.posted_on,
+        transaction.pending
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to save linked account transaction: {}", e);
+        db_error(&e, "Failed to refresh linked account")
+    })?;
+    Ok(())
+}
+
+// Transactions pulled from a linked account, newest first
+pub async fn get_linked_account_transactions(
+    State(pool): State<PgPool>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<LinkedAccountTransaction>>, AppError> {
+    let owned = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM linked_accounts WHERE id = $1 AND user_id = $2) as "exists!""#,
+        account_id,
+        user_id
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch linked account: {}", e);
+        db_error(&e, "Failed to fetch linked account transactions")
+    })?;
+    if !owned {
+        return Err(AppError::NotFound("Linked account not found".to_string()));
+    }
+
+    let transactions = sqlx::query_as!(
+        LinkedAccountTransaction,
+        r#"
+        SELECT id, linked_account_id, amount, currency, description, merchant_name, posted_on, pending, created_at, updated_at
+        FROM linked_account_transactions
+        WHERE linked_account_id = $1
+        ORDER BY posted_on DESC, created_at DESC
+        LIMIT 500
+        "#,
+        account_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch linked account transactions: {}", e);
+        db_error(&e, "Failed to fetch linked account transactions")
+    })?;
+
+    Ok(Json(transactions))
+}
+
+// Unlinks an account and its pulled transactions. Unlinking the last account of a bank login also
+// revokes Plaid's access to it.
+pub async fn delete_linked_account(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(provider): State<Arc<dyn BankDataProvider>>,
+    Path((user_id, account_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<LinkedAccount>, AppError> {
+    info!("Unlinking account {} for user {}", account_id, user_id);
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to begin transaction: {}", e);
+        db_error(&e, "Failed to unlink account")
+    })?;
+
+    let account = sqlx::query_as!(
+        LinkedAccount,
+        r#"
+        DELETE FROM linked_accounts
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, item_id, name, mask, account_type, subtype, currency, available_balance, current_balance,
+            balances_updated_at, created_at
+        "#,
+        account_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to delete linked account: {}", e);
+        db_error(&e, "Failed to unlink account")
+    })?
+    .ok_or(AppError::NotFound("Linked account not found".to_string()))?;
+
+    let access_token = sqlx::query_scalar!(
+        r#"
+        DELETE FROM linked_items i
+        WHERE i.id = $1 AND NOT EXISTS (SELECT 1 FROM linked_accounts a WHERE a.item_id = i.id)
+        RETURNING access_token
+        "#,
+        account.item_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to delete linked item: {}", e);
+        db_error(&e, "Failed to unlink account")
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to unlink account")
+    })?;
+
+    // The item is already gone here, so a failed revocation is only logged
+    if let Some(access_token) = access_token {
+        let revoked = match open_access_token(&config, &access_token) {
+            Ok(access_token) => provider.remove_item(&access_token).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = revoked {
+            error!("Failed to revoke access to linked item {}: {}", account.item_id, e);
+        }
+    }
+
+    info!("Unlinked account {}", account.id);
+    Ok(Json(account))
+}
@@ -0,0 +1,200 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::models::payee::{normalize_account_identifier, CreatePayee, Payee, UpdatePayee, MAX_ACCOUNT_IDENTIFIER_LENGTH, MIN_ACCOUNT_IDENTIFIER_LENGTH};
+use crate::models::user::UserStatus;
+use crate::repositories::user as users;
+use crate::services::payee::find_payee;
+use crate::validation::ValidatedJson;
+
+fn payee_name(name: &str) -> Result<&str, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("A name is required".to_string()));
+    }
+    Ok(name)
+}
+
+// Saves a recipient. Saving one the user already has, by user id or by account identifier, is
+// refused with the existing payee's id so clients can use that instead.
+pub async fn create_payee(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreatePayee>,
+) -> Result<Json<Payee>, AppError> {
+    info!("Creating payee for user {}: {:?}", user_id, payload);
+
+    let name = payee_name(&payload.name)?;
+    let account_identifier = match (payload.recipient_user_id, payload.account_identifier.as_deref()) {
+        (Some(recipient_user_id), None) => {
+            if recipient_user_id == user_id {
+                return Err(AppError::BadRequest("Cannot save yourself as a payee".to_string()));
+            }
+            let recipient = users::find_by_id(&pool, recipient_user_id).await
+                .map_err(|e| {
+                    error!("Failed to fetch recipient: {}", e);
+                    db_error(&e, "Failed to create payee")
+                })?
+                .ok_or(AppError::NotFound("Recipient not found".to_string()))?;
+            if recipient.status != UserStatus::Active {
+                return Err(AppError::Unprocessable("Recipient account is deactivated".to_string()));
+            }
+            None
+        }
+        (None, Some(identifier)) => Some(normalize_account_identifier(identifier).ok_or(AppError::BadRequest(format!(
+            "Account identifier must be {} to {} letters and digits",
+            MIN_ACCOUNT_IDENTIFIER_LENGTH, MAX_ACCOUNT_IDENTIFIER_LENGTH
+        )))?),
+        _ => {
+            return Err(AppError::BadRequest(
+                "Give exactly one of recipient_user_id and account_identifier".to_string(),
+            ))
+        }
+    };
+
+    let created = sqlx::query_as!(
+        Payee,
+        r#"
+        INSERT INTO payees (user_id, name, recipient_user_id, account_identifier)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        RETURNING id, user_id, name, recipient_user_id, account_identifier, last_used_at, created_at, updated_at
+        "#,
+        user_id,
+        name,
+        payload.recipient_user_id,
+        account_identifier
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to create payee: {}", e);
+        db_error(&e, "Failed to create payee")
+    })?;
+
+    let Some(payee) = created else {
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM payees
+            WHERE user_id = $1 AND (recipient_user_id = $2 OR account_identifier = $3)
+            "#,
+            user_id,
+            payload.recipient_user_id,
+            account_identifier
+        )
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch duplicate payee: {}", e);
+            db_error(&e, "Failed to create payee")
+        })?;
+        return Err(match existing {
+            Some(id) => AppError::Conflict(format!("This recipient is already saved as payee {}", id)),
+            None => AppError::Conflict("This recipient is already saved as a payee".to_string()),
+        });
+    };
+
+    info!("Created payee {} for user {}", payee.id, user_id);
+    Ok(Json(payee))
+}
+
+// The user's payees, most recently paid first, then those never paid by name
+pub async fn get_payees(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<Payee>>, AppError> {
+    info!("Fetching payees for user {}", user_id);
+
+    let payees = sqlx::query_as!(
+        Payee,
+        r#"
+        SELECT id, user_id, name, recipient_user_id, account_identifier, last_used_at, created_at, updated_at
+        FROM payees
+        WHERE user_id = $1
+        ORDER BY last_used_at DESC NULLS LAST, name, created_at
+        "#,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch payees: {}", e);
+        db_error(&e, "Failed to fetch payees")
+    })?;
+
+    Ok(Json(payees))
+}
+
+pub async fn get_payee(
+    State(pool): State<PgPool>,
+    Path((user_id, payee_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Payee>, AppError> {
+    find_payee(&pool, user_id, payee_id).await.map(Json)
+}
+
+pub async fn update_payee(
+    State(pool): State<PgPool>,
+    Path((user_id, payee_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<UpdatePayee>,
+) -> Result<Json<Payee>, AppError> {
+    info!("Updating payee {} for user {}: {:?}", payee_id, user_id, payload);
+
+    let name = payee_name(&payload.name)?;
+    let payee = sqlx::query_as!(
+        Payee,
+        r#"
+        UPDATE payees
+        SET name = $3, updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, name, recipient_user_id, account_identifier, last_used_at, created_at, updated_at
+        "#,
+        payee_id,
+        user_id,
+        name
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to update payee {}: {}", payee_id, e);
+        db_error(&e, "Failed to update payee")
+    })?
+    .ok_or(AppError::NotFound("Payee not found".to_string()))?;
+
+    Ok(Json(payee))
+}
+
+// Transactions and transfers made to the payee are kept and no longer reference it
+pub async fn delete_payee(
+    State(pool): State<PgPool>,
+    Path((user_id, payee_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Payee>, AppError> {
+    info!("Deleting payee {} for user {}", payee_id, user_id);
+
+    let payee = sqlx::query_as!(
+        Payee,
+        r#"
+        DELETE FROM payees
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, name, recipient_user_id, account_identifier, last_used_at, created_at, updated_at
+        "#,
+        payee_id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to delete payee: {}", e);
+        db_error(&e, "Failed to delete payee")
+    })?
+    .ok_or(AppError::NotFound("Payee not found".to_string()))?;
+
+    info!("Deleted payee {}", payee.id);
+    Ok(Json(payee))
+}
@@ -0,0 +1,393 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::events::publish_payment_request;
+use crate::feature_flags::{ensure_enabled, KillSwitch};
+use crate::handlers::transfer::post_transfer;
+use crate::middleware::auth::AuthContext;
+use crate::models::money::Currency;
+use crate::models::payment_request::{
+    CreatePaymentRequest, PaymentRequest, PaymentRequestAcceptance, PaymentRequestQuery, PaymentRequestStatus,
+    DEFAULT_EXPIRY, MAX_EXPIRY,
+};
+use crate::models::user::UserStatus;
+use crate::repositories::user as users;
+use crate::screening::{self, Screening};
+use crate::services::ledger::check_amount;
+use crate::validation::ValidatedJson;
+
+// Asks another user for money on behalf of the authenticated user
+pub async fn create_payment_request(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<CreatePaymentRequest>,
+) -> Result<Json<PaymentRequest>, AppError> {
+    let requester_id = auth.user_id;
+    info!("User {} requesting payment: {:?}", requester_id, payload);
+
+    if payload.payer_id == requester_id {
+        return Err(AppError::BadRequest("Cannot request money from yourself".to_string()));
+    }
+    let currency = Currency::parse(&payload.currency)
+        .ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    let amount = check_amount(&config, &payload.amount, currency).map_err(AppError::InvalidAmount)?;
+
+    let now = OffsetDateTime::now_utc();
+    let expires_at = payload.expires_at.unwrap_or(now + DEFAULT_EXPIRY);
+    if expires_at <= now {
+        return Err(AppError::BadRequest("Expiry must be in the future".to_string()));
+    }
+    if expires_at > now + MAX_EXPIRY {
+        return Err(AppError::BadRequest(format!("Requests can stay open for at most {} days", MAX_EXPIRY.whole_days())));
+    }
+
+    let payer = users::find_by_id(&pool, payload.payer_id).await
+        .map_err(|e| {
+            error!("Failed to fetch payer: {}", e);
+            db_error(&e, "Failed to create payment request")
+        })?
+        .ok_or(AppError::NotFound("Payer not found".to_string()))?;
+    if payer.status != UserStatus::Active {
+        return Err(AppError::Unprocessable("Payer account is deactivated".to_string()));
+    }
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let request = sqlx::query_as!(
+        PaymentRequest,
+        r#"
+        INSERT INTO payment_requests (requester_id, payer_id, amount, currency, description, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, requester_id, payer_id, amount, currency, description, status as "status: _", expires_at,
+            transfer_id, decided_at, created_at, updated_at
+        "#,
+        requester_id,
+        payer.id,
+        amount.to_decimal(),
+        currency.as_str(),
+        payload.description,
+        expires_at
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to create payment request: {}", e);
+        db_error(&e, "Failed to create payment request")
+    })?;
+
+    publish_payment_request(&mut tx, &request, true).await
+        .map_err(|e| {
+            error!("Failed to queue payment request events: {}", e);
+            db_error(&e, "Failed to create payment request")
+        })?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!("Created payment request {} from user {} to user {}", request.id, requester_id, payer.id);
+    Ok(Json(request))
+}
+
+// Requests the caller has sent or received, newest first, optionally in one status
+pub async fn get_payment_requests(
+    State(pool): State<PgPool>,
+    Extension(auth): Extension<AuthContext>,
+    Query(query): Query<PaymentRequestQuery>,
+) -> Result<Json<Vec<PaymentRequest>>, AppError> {
+    let requests = sqlx::query_as!(
+        PaymentRequest,
+        r#"
+        SELECT id, requester_id, payer_id, amount, currency, description, status as "status: _", expires_at,
+            transfer_id, decided_at, created_at, updated_at
+        FROM payment_requests
+        WHERE (requester_id = $1 OR payer_id = $1) AND ($2::payment_request_status IS NULL OR status = $2)
+        ORDER BY created_at DESC
+        LIMIT 500
+        "#,
+        auth.user_id,
+        query.status as _
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch payment requests: {}", e);
+        db_error(&e, "Failed to fetch payment requests")
+    })?;
+
+    Ok(Json(requests))
+}
+
+// A request the caller sent or received; anyone else's is reported as not found
+pub async fn get_payment_request(
+    State(pool): State<PgPool>,
+    Path(request_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<PaymentRequest>, AppError> {
+    sqlx::query_as!(
+        PaymentRequest,
+        r#"
+        SELECT id, requester_id, payer_id, amount, currency, description, status as "status: _", expires_at,
+            transfer_id, decided_at, created_at, updated_at
+        FROM payment_requests
+        WHERE id = $1 AND (requester_id = $2 OR payer_id = $2)
+        "#,
+        request_id,
+        auth.user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch payment request: {}", e);
+        db_error(&e, "Failed to fetch payment request")
+    })?
+    .ok_or(AppError::NotFound("Payment request not found".to_string()))
+    .map(Json)
+}
+
+// Pays a pending request addressed to the caller, transferring the amount to the requester as if
+// the payer had sent it themselves
+pub async fn accept_payment_request(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(screening): State<Arc<dyn Screening>>,
+    Path(request_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<PaymentRequestAcceptance>, AppError> {
+    info!("User {} accepting payment request {}", auth.user_id, request_id);
+
+    ensure_enabled(&pool, KillSwitch::Transfers).await?;
+
+    // The parties are screened before the request is locked; a request that isn't the caller's to pay
+    // is refused once it is
+    let requester_id = sqlx::query_scalar!(
+        "SELECT requester_id FROM payment_requests WHERE id = $1 AND payer_id = $2",
+        request_id,
+        auth.user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch payment request: {}", e);
+        db_error(&e, "Failed to fetch payment request")
+    })?;
+    let parties: Vec<Uuid> = std::iter::once(auth.user_id).chain(requester_id).collect();
+    let parties = screening::screen_transfer_parties(&pool, screening.as_ref(), &parties).await
+        .map_err(|e| {
+            error!("Failed to screen transfer parties: {}", e);
+            db_error(&e, "Failed to create transfer")
+        })?;
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let request = lock_pending(&mut tx, request_id, auth.user_id, Party::Payer).await?;
+    if request.amount > config.step_up_transfer_threshold && !auth.has_recent_auth(&config) {
+        error!("Payment request {} of {} needs step-up authentication", request.id, request.amount);
+        return Err(AppError::StepUpRequired);
+    }
+    let currency = Currency::parse(&request.currency)
+        .ok_or(AppError::Internal("Payment request has an invalid currency".to_string()))?;
+    let amount = check_amount(&config, &request.amount, currency).map_err(AppError::InvalidAmount)?;
+
+    let transfer = post_transfer(
+        &mut tx,
+        &config,
+        request.payer_id,
+        request.requester_id,
+        &amount,
+        None,
+        request.description.as_deref(),
+        None,
+        &parties,
+    )
+    .await?;
+    let accepted = decide(&mut tx, request.id, PaymentRequestStatus::Accepted, Some(transfer.transfer.id)).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!("Payment request {} paid by transfer {}", accepted.id, transfer.transfer.id);
+    Ok(Json(PaymentRequestAcceptance { payment_request: accepted, transfer }))
+}
+
+// Refuses a pending request addressed to the caller
+pub async fn decline_payment_request(
+    State(pool): State<PgPool>,
+    Path(request_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<PaymentRequest>, AppError> {
+    info!("User {} declining payment request {}", auth.user_id, request_id);
+    close(&pool, request_id, auth.user_id, Party::Payer, PaymentRequestStatus::Declined).await.map(Json)
+}
+
+// Withdraws a pending request the caller sent
+pub async fn cancel_payment_request(
+    State(pool): State<PgPool>,
+    Path(request_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<PaymentRequest>, AppError> {
+    info!("User {} cancelling payment request {}", auth.user_id, request_id);
+    close(&pool, request_id, auth.user_id, Party::Requester, PaymentRequestStatus::Cancelled).await.map(Json)
+}
+
+// Marks pending requests past their expiry as expired, notifying both parties. Returns how many
+// expired.
+pub async fn expire_due_requests(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let expired = sqlx::query_as!(
+        PaymentRequest,
+        r#"
+        UPDATE payment_requests
+        SET status = 'expired', decided_at = NOW(), updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM payment_requests
+            WHERE status = 'pending' AND expires_at <= NOW()
+            ORDER BY expires_at
+            LIMIT 500
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, requester_id, payer_id, amount, currency, description, status as "status: _", expires_at,
+            transfer_id, decided_at, created_at, updated_at
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for request in &expired {
+        publish_payment_request(&mut tx, request, false).await?;
+    }
+    tx.commit().await?;
+    Ok(expired.len())
+}
+
+// Which side of a request the caller must be on to act on it
+#[derive(Clone, Copy)]
+enum Party {
+    Requester,
+    Payer,
+}
+
+async fn close(
+    pool: &PgPool,
+    request_id: Uuid,
+    user_id: Uuid,
+    party: Party,
+    status: PaymentRequestStatus,
+) -> Result<PaymentRequest, AppError> {
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let request = lock_pending(&mut tx, request_id, user_id, party).await?;
+    let closed = decide(&mut tx, request.id, status, None).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!("Payment request {} {:?}", closed.id, closed.status);
+    Ok(closed)
+}
+
+// Locks a request the caller is on `party`'s side of, refusing one that is no longer pending or
+// has expired without the sweep having caught up yet
+async fn lock_pending(conn: &mut PgConnection, request_id: Uuid, user_id: Uuid, party: Party) -> Result<PaymentRequest, AppError> {
+    let request = sqlx::query_as!(
+        PaymentRequest,
+        r#"
+        SELECT id, requester_id, payer_id, amount, currency, description, status as "status: _", expires_at,
+            transfer_id, decided_at, created_at, updated_at
+        FROM payment_requests
+        WHERE id = $1 AND (requester_id = $2 OR payer_id = $2)
+        FOR UPDATE
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch payment request: {}", e);
+        db_error(&e, "Failed to fetch payment request")
+    })?
+    .ok_or(AppError::NotFound("Payment request not found".to_string()))?;
+
+    match party {
+        Party::Payer if request.payer_id != user_id => {
+            return Err(AppError::Forbidden("Only the payer can accept or decline a payment request".to_string()))
+        }
+        Party::Requester if request.requester_id != user_id => {
+            return Err(AppError::Forbidden("Only the requester can cancel a payment request".to_string()))
+        }
+        _ => {}
+    }
+    if request.status != PaymentRequestStatus::Pending {
+        return Err(AppError::Conflict("Payment request is no longer pending".to_string()));
+    }
+    if request.expires_at <= OffsetDateTime::now_utc() {
+        return Err(AppError::Conflict("Payment request has expired".to_string()));
+    }
+    Ok(request)
+}
+
+async fn decide(
+    conn: &mut PgConnection,
+    request_id: Uuid,
+    status: PaymentRequestStatus,
+    transfer_id: Option<Uuid>,
+) -> Result<PaymentRequest, AppError> {
+    let decided = sqlx::query_as!(
+        PaymentRequest,
+        r#"
+        UPDATE payment_requests
+        SET status = $2, transfer_id = $3, decided_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, requester_id, payer_id, amount, currency, description, status as "status: _", expires_at,
+            transfer_id, decided_at, created_at, updated_at
+        "#,
+        request_id,
+        status as _,
+        transfer_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to update payment request: {}", e);
+        db_error(&e, "Failed to update payment request")
+    })?;
+
+    publish_payment_request(conn, &decided, false).await
+        .map_err(|e| {
+            error!("Failed to queue payment request events: {}", e);
+            db_error(&e, "Failed to update payment request")
+        })?;
+    Ok(decided)
+}
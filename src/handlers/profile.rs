@@ -0,0 +1,229 @@
+use axum::extract::State;
+use axum::Json;
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool};
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::email_policy::{DomainDecision, EmailPolicy};
+use crate::error::AppError;
+use crate::jobs::{self, Job};
+use crate::mailer::EmailTemplate;
+use crate::middleware::auth::AuthUser;
+use crate::models::user::{Profile, UpdateProfile, User, VerifyEmail};
+use crate::repositories::user as users;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::services::auth::ensure_active;
+
+// How long the link confirming a new email address can be used for
+const VERIFICATION_TOKEN_LIFETIME_HOURS: i64 = 24;
+
+// Longest name accepted, matching the column
+const MAX_NAME_LENGTH: usize = 255;
+
+fn hash_verification_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+async fn pending_email(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<Option<String>, AppError> {
+    sqlx::query_scalar!(
+        "SELECT new_email FROM email_changes WHERE user_id = $1 AND expires_at > NOW()",
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(|e| {
+        error!("Failed to load pending email change: {}", e);
+        db_error(&e, "Failed to load profile")
+    })
+}
+
+// Returns the caller's profile
+pub async fn get_profile(
+    State(pool): State<PgPool>,
+    AuthUser { user, .. }: AuthUser,
+) -> Result<Json<Profile>, AppError> {
+    let pending_email = pending_email(&pool, user.id).await?;
+    Ok(Json(Profile { user, pending_email }))
+}
+
+// Renames the caller straight away. A new email address only replaces the current one once the
+// user follows the link sent to it, so a typo can't lock them out; changing it needs a recent
+// step-up, like the other operations that could hand the account to someone else.
+pub async fn update_profile(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(email_policy): State<Arc<EmailPolicy>>,
+    AuthUser { user, context }: AuthUser,
+    Json(payload): Json<UpdateProfile>,
+) -> Result<Json<Profile>, AppError> {
+    if payload.name.is_none() && payload.email.is_none() {
+        return Err(AppError::BadRequest("Nothing to update".to_string()));
+    }
+
+    let name = payload.name.as_deref().map(str::trim);
+    if let Some(name) = name {
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(AppError::BadRequest(format!("Name must be between 1 and {} characters", MAX_NAME_LENGTH)));
+        }
+    }
+
+    // Asking for the current address back drops any pending change
+    let new_email = payload.email.as_deref().map(str::trim).filter(|email| *email != user.email);
+    if let Some(email) = new_email {
+        if !context.has_recent_auth(&config) {
+            error!("User {} needs step-up authentication to change their email", user.id);
+            return Err(AppError::StepUpRequired);
+        }
+        if !email.contains('@') {
+            error!("Invalid email format: {}", email);
+            return Err(AppError::BadRequest("Invalid email format".to_string()));
+        }
+        if email_policy.check(email) == DomainDecision::Blocked {
+            error!("Blocked email domain: {}", email);
+            return Err(AppError::Unprocessable("Email domain is not allowed".to_string()));
+        }
+        let taken = users::email_taken(&pool, email).await.map_err(|e| {
+            error!("Failed to check existing user: {}", e);
+            db_error(&e, "Failed to check existing user")
+        })?;
+        if taken {
+            return Err(AppError::Conflict("Email is already in use".to_string()));
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    let user = match name {
+        Some(name) if name != user.name => {
+            let updated = users::update_profile(&mut *tx, user.id, Some(name), None)
+                .await
+                .map_err(|e| {
+                    error!("Failed to update user {}: {}", user.id, e);
+                    db_error(&e, "Failed to update profile")
+                })?
+                .ok_or(AppError::NotFound("User not found".to_string()))?;
+            audit::record(&mut *tx, AuditRecord::new(AuditAction::ProfileUpdated, user.id, Some(user.id)).target(user.id).before(&user).after(&updated))
+                .await?;
+            updated
+        }
+        _ => user,
+    };
+
+    if payload.email.is_some() {
+        sqlx::query!("DELETE FROM email_changes WHERE user_id = $1", user.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                error!("Failed to replace pending email change: {}", e);
+                db_error(&e, "Failed to update profile")
+            })?;
+    }
+    if let Some(email) = new_email {
+        let token = format!("verify_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        sqlx::query!(
+            "INSERT INTO email_changes (user_id, new_email, token_hash, expires_at) VALUES ($1, $2, $3, $4)",
+            user.id,
+            email,
+            hash_verification_token(&token),
+            OffsetDateTime::now_utc() + Duration::hours(VERIFICATION_TOKEN_LIFETIME_HOURS)
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Failed to store email change: {}", e);
+            db_error(&e, "Failed to update profile")
+        })?;
+
+        let link = config.email_verification_url.replace("{token}", &token);
+        jobs::enqueue(&mut *tx, &config, &Job::SendEmail { user_id: user.id, email: EmailTemplate::Verification { link } })
+            .await
+            .map_err(|e| {
+                error!("Failed to queue verification email: {}", e);
+                db_error(&e, "Failed to update profile")
+            })?;
+        info!("User {} asked to change their email", user.id);
+    }
+
+    let pending_email = pending_email(&mut *tx, user.id).await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    Ok(Json(Profile { user, pending_email }))
+}
+
+// Confirms a new email address with the token from the link sent to it, making it the user's
+// address. Needs no session, since the link may be opened on another device.
+pub async fn verify_email(
+    State(pool): State<PgPool>,
+    Json(payload): Json<VerifyEmail>,
+) -> Result<Json<User>, AppError> {
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    let change = sqlx::query!(
+        "DELETE FROM email_changes WHERE token_hash = $1 AND expires_at > NOW() RETURNING user_id, new_email",
+        hash_verification_token(&payload.token)
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to look up email change: {}", e);
+        db_error(&e, "Failed to verify email")
+    })?
+    .ok_or(AppError::BadRequest("Invalid or expired verification token".to_string()))?;
+
+    let status = users::lock(&mut *tx, change.user_id).await.map_err(|e| {
+        error!("Failed to lock user {}: {}", change.user_id, e);
+        db_error(&e, "Failed to verify email")
+    })?;
+    match status {
+        None => return Err(AppError::NotFound("User not found".to_string())),
+        Some(status) => ensure_active(status)?,
+    }
+
+    // Someone may have registered the address since the change was asked for
+    let taken = users::email_taken(&mut *tx, &change.new_email).await.map_err(|e| {
+        error!("Failed to check existing user: {}", e);
+        db_error(&e, "Failed to verify email")
+    })?;
+    if taken {
+        return Err(AppError::Conflict("Email is already in use".to_string()));
+    }
+
+    let before = users::find_by_id(&mut *tx, change.user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load user {}: {}", change.user_id, e);
+            db_error(&e, "Failed to verify email")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+    let user = users::update_profile(&mut *tx, change.user_id, None, Some(&change.new_email))
+        .await
+        .map_err(|e| {
+            error!("Failed to change email for user {}: {}", change.user_id, e);
+            db_error(&e, "Failed to verify email")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::EmailChanged, user.id, Some(user.id)).target(user.id).before(&before).after(&user))
+        .await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    tracing::info!(target: "audit", "User {} changed their email", user.id);
+    Ok(Json(user))
+}
@@ -0,0 +1,112 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::middleware::auth::AuthContext;
+use crate::models::risk::{ReviewRiskEvent, RiskEvent, RiskEventQuery};
+use crate::services::audit::{self, AuditAction, AuditRecord};
+
+// The review queue, oldest first, or with `reviewed=true` the events already reviewed, newest first
+pub async fn get_risk_events(
+    State(pool): State<PgPool>,
+    Query(query): Query<RiskEventQuery>,
+) -> Result<Json<Vec<RiskEvent>>, AppError> {
+    info!("Fetching risk events: {:?}", query);
+
+    let events = sqlx::query_as!(
+        RiskEvent,
+        r#"
+        SELECT id, user_id, transaction_id, rule, action as "action: _", reason, amount, currency,
+            transaction_type as "transaction_type: _", created_at, reviewed_by, reviewed_at, review_note
+        FROM risk_events
+        WHERE (reviewed_at IS NOT NULL) = $1 AND ($2::uuid IS NULL OR user_id = $2)
+        ORDER BY
+            CASE WHEN $1 THEN created_at END DESC,
+            CASE WHEN NOT $1 THEN created_at END ASC
+        LIMIT 500
+        "#,
+        query.reviewed,
+        query.user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch risk events: {}", e);
+        db_error(&e, "Failed to fetch risk events")
+    })?;
+
+    Ok(Json(events))
+}
+
+// Marks a risk event as looked at. Held debits are still released or denied through the held debit
+// queue; reviewing their event doesn't decide them.
+pub async fn review_risk_event(
+    State(pool): State<PgPool>,
+    Path(risk_event_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<ReviewRiskEvent>,
+) -> Result<Json<RiskEvent>, AppError> {
+    info!("Admin {} reviewing risk event {}", auth.user_id, risk_event_id);
+
+    let note = payload.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let reviewed_at = sqlx::query_scalar!(
+        "SELECT reviewed_at FROM risk_events WHERE id = $1 FOR UPDATE",
+        risk_event_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch risk event: {}", e);
+        db_error(&e, "Failed to fetch risk event")
+    })?
+    .ok_or(AppError::NotFound("Risk event not found".to_string()))?;
+    if reviewed_at.is_some() {
+        return Err(AppError::Conflict("Risk event has already been reviewed".to_string()));
+    }
+
+    let event = sqlx::query_as!(
+        RiskEvent,
+        r#"
+        UPDATE risk_events
+        SET reviewed_by = $2, reviewed_at = NOW(), review_note = $3
+        WHERE id = $1
+        RETURNING id, user_id, transaction_id, rule, action as "action: _", reason, amount, currency,
+            transaction_type as "transaction_type: _", created_at, reviewed_by, reviewed_at, review_note
+        "#,
+        risk_event_id,
+        auth.user_id,
+        note
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to review risk event: {}", e);
+        db_error(&e, "Failed to review risk event")
+    })?;
+    let record = AuditRecord::new(AuditAction::RiskEventReviewed, auth.user_id, Some(event.user_id))
+        .target(event.id)
+        .after(&event);
+    audit::record(&mut *tx, record).await?;
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "Risk event {} reviewed by admin {}", event.id, auth.user_id);
+    Ok(Json(event))
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::models::round_up::{RoundUpRule, UpdateRoundUpRule};
+use crate::repositories::account as accounts;
+
+pub async fn get_round_up(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<RoundUpRule>, AppError> {
+    sqlx::query_as!(
+        RoundUpRule,
+        "SELECT user_id, account_id, enabled, created_at, updated_at FROM round_up_rules WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch round-up rule: {}", e);
+        db_error(&e, "Failed to fetch round-up rule")
+    })?
+    .ok_or(AppError::NotFound("Round-ups are not set up".to_string()))
+    .map(Json)
+}
+
+// Turns round-ups on or off and picks the account they are saved in
+pub async fn update_round_up(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<UpdateRoundUpRule>,
+) -> Result<Json<RoundUpRule>, AppError> {
+    info!("Updating round-ups for user {}: {:?}", user_id, payload);
+
+    let account = accounts::find(&pool, user_id, payload.account_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch account: {}", e);
+            db_error(&e, "Failed to fetch account")
+        })?
+        .ok_or(AppError::NotFound("Account not found".to_string()))?;
+    if account.is_primary {
+        return Err(AppError::BadRequest("Round-ups need an account other than the main one".to_string()));
+    }
+    if account.closed_at.is_some() {
+        return Err(AppError::Conflict("Account is closed".to_string()));
+    }
+
+    let rule = sqlx::query_as!(
+        RoundUpRule,
+        r#"
+        INSERT INTO round_up_rules (user_id, account_id, enabled)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id) DO UPDATE SET account_id = $2, enabled = $3, updated_at = NOW()
+        RETURNING user_id, account_id, enabled, created_at, updated_at
+        "#,
+        user_id,
+        account.id,
+        payload.enabled
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to update round-up rule: {}", e);
+        db_error(&e, "Failed to update round-up rule")
+    })?;
+
+    info!("Round-ups for user {} are {}", user_id, if rule.enabled { "on" } else { "off" });
+    Ok(Json(rule))
+}
@@ -0,0 +1,568 @@
+use axum::{
+    extract::{State, Path},
+    Json,
+};
+use bigdecimal::BigDecimal;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::handlers::transfer::post_account_transfer;
+use crate::models::account::Account;
+use crate::models::money::{Currency, Money};
+use crate::models::recurring::RecurrenceFrequency;
+use crate::models::savings_goal::{
+    AutoTransferRule, CreateSavingsGoal, GoalProgress, SavingsGoal, SavingsGoalDetails, UpdateSavingsGoal,
+};
+use crate::models::user::UserStatus;
+use crate::repositories::account as accounts;
+use crate::repositories::user as users;
+use crate::services::ledger::{check_amount, lock_account_balance};
+use crate::validation::ValidatedJson;
+
+// An auto-transfer rule that has passed every check, ready to store
+struct CheckedRule {
+    from_account_id: Uuid,
+    amount: Money,
+    frequency: RecurrenceFrequency,
+    starts_at: OffsetDateTime,
+}
+
+// What one due auto-transfer did
+enum AutoTransferOutcome {
+    Transferred { reached: bool },
+    // The goal was already reached, so nothing was moved
+    Reached,
+    Skipped(String),
+}
+
+// The user's open account a goal saves in or an auto-transfer draws from
+async fn open_account(conn: &mut PgConnection, user_id: Uuid, account_id: Uuid) -> Result<Account, AppError> {
+    let account = accounts::find(conn, user_id, account_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch account: {}", e);
+            db_error(&e, "Failed to fetch account")
+        })?
+        .ok_or(AppError::NotFound("Account not found".to_string()))?;
+    if account.closed_at.is_some() {
+        return Err(AppError::Conflict("Account is closed".to_string()));
+    }
+    Ok(account)
+}
+
+fn account_currency(account: &Account) -> Result<Currency, AppError> {
+    Currency::parse(&account.currency).ok_or(AppError::Internal("Account has an invalid currency".to_string()))
+}
+
+// Targets are held to the currency's minor unit like any amount, but may exceed the per-transaction maximum
+fn check_target(amount: &BigDecimal, currency: Currency) -> Result<Money, AppError> {
+    if *amount <= 0 {
+        return Err(AppError::InvalidAmount("Target amount must be greater than zero".to_string()));
+    }
+    Money::from_decimal(amount, currency).map_err(|e| AppError::InvalidAmount(e.to_string()))
+}
+
+fn check_deadline(deadline: Option<OffsetDateTime>) -> Result<(), AppError> {
+    if deadline.is_some_and(|deadline| deadline <= OffsetDateTime::now_utc()) {
+        return Err(AppError::BadRequest("Deadline must be in the future".to_string()));
+    }
+    Ok(())
+}
+
+async fn check_rule(
+    conn: &mut PgConnection,
+    config: &Config,
+    user_id: Uuid,
+    goal_account: &Account,
+    rule: &AutoTransferRule,
+) -> Result<CheckedRule, AppError> {
+    if rule.from_account_id == goal_account.id {
+        return Err(AppError::BadRequest("Auto-transfers must come from another account".to_string()));
+    }
+    let from = open_account(conn, user_id, rule.from_account_id).await?;
+    if from.currency != goal_account.currency {
+        return Err(AppError::BadRequest(format!("Account holds {}, not {}", from.currency, goal_account.currency)));
+    }
+    let amount = check_amount(config, &rule.amount, account_currency(goal_account)?).map_err(AppError::InvalidAmount)?;
+
+    let now = OffsetDateTime::now_utc();
+    let starts_at = rule.starts_at.unwrap_or(now);
+    if starts_at < now - time::Duration::minutes(1) {
+        return Err(AppError::BadRequest("Start time must not be in the past".to_string()));
+    }
+
+    Ok(CheckedRule { from_account_id: from.id, amount, frequency: rule.frequency, starts_at })
+}
+
+// The goal together with its progress, read from the live ledger
+async fn with_progress(conn: &mut PgConnection, goal: SavingsGoal) -> Result<SavingsGoalDetails, AppError> {
+    let saved = accounts::settled_balance(conn, goal.account_id, true).await.map_err(|e| {
+        error!("Failed to compute savings goal progress: {}", e);
+        db_error(&e, "Failed to fetch savings goal")
+    })?;
+    let progress = GoalProgress::new(saved, &goal.target_amount);
+    Ok(SavingsGoalDetails { goal, progress })
+}
+
+async fn lock_goal(conn: &mut PgConnection, user_id: Uuid, goal_id: Uuid) -> Result<SavingsGoal, AppError> {
+    sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        SELECT id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+            auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+            last_auto_transfer_error, created_at, updated_at
+        FROM savings_goals
+        WHERE id = $1 AND user_id = $2
+        FOR UPDATE
+        "#,
+        goal_id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch savings goal: {}", e);
+        db_error(&e, "Failed to fetch savings goal")
+    })?
+    .ok_or(AppError::NotFound("Savings goal not found".to_string()))
+}
+
+// Sets a goal's auto-transfer rule, or removes it when `rule` is None
+async fn store_rule(conn: &mut PgConnection, goal_id: Uuid, rule: Option<&CheckedRule>) -> Result<SavingsGoal, AppError> {
+    sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        UPDATE savings_goals
+        SET auto_transfer_from_account_id = $2, auto_transfer_amount = $3, auto_transfer_frequency = $4,
+            next_auto_transfer_at = $5, last_auto_transfer_error = NULL, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+            auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+            last_auto_transfer_error, created_at, updated_at
+        "#,
+        goal_id,
+        rule.map(|rule| rule.from_account_id),
+        rule.map(|rule| rule.amount.to_decimal()),
+        rule.map(|rule| rule.frequency) as _,
+        rule.map(|rule| rule.starts_at)
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to update savings goal {}: {}", goal_id, e);
+        db_error(&e, "Failed to update savings goal")
+    })
+}
+
+// Starts saving towards a target in one of the user's accounts other than the main one
+pub async fn create_savings_goal(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(user_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateSavingsGoal>,
+) -> Result<Json<SavingsGoalDetails>, AppError> {
+    info!("Creating savings goal for user {}: {:?}", user_id, payload);
+
+    check_deadline(payload.deadline)?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    // Serializes against another goal being created for the same account
+    users::lock(&mut *tx, user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to lock user {}: {}", user_id, e);
+            db_error(&e, "Failed to create savings goal")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    let account = open_account(&mut tx, user_id, payload.account_id).await?;
+    if account.is_primary {
+        return Err(AppError::BadRequest("Savings goals need an account other than the main one".to_string()));
+    }
+    let target = check_target(&payload.target_amount, account_currency(&account)?)?;
+    let rule = match &payload.auto_transfer {
+        Some(rule) => Some(check_rule(&mut tx, &config, user_id, &account, rule).await?),
+        None => None,
+    };
+
+    let taken = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM savings_goals WHERE account_id = $1) as \"exists!\"",
+        account.id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to check savings goals: {}", e);
+        db_error(&e, "Failed to create savings goal")
+    })?;
+    if taken {
+        return Err(AppError::Conflict("Account already has a savings goal".to_string()));
+    }
+
+    let goal = sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        INSERT INTO savings_goals
+            (user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+             auto_transfer_frequency, next_auto_transfer_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+            auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+            last_auto_transfer_error, created_at, updated_at
+        "#,
+        user_id,
+        account.id,
+        payload.name.trim(),
+        target.to_decimal(),
+        payload.deadline,
+        rule.as_ref().map(|rule| rule.from_account_id),
+        rule.as_ref().map(|rule| rule.amount.to_decimal()),
+        rule.as_ref().map(|rule| rule.frequency) as _,
+        rule.as_ref().map(|rule| rule.starts_at)
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to create savings goal: {}", e);
+        db_error(&e, "Failed to create savings goal")
+    })?;
+    let details = with_progress(&mut tx, goal).await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!("Created savings goal {} for user {}", details.goal.id, user_id);
+    Ok(Json(details))
+}
+
+pub async fn get_savings_goals(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<SavingsGoalDetails>>, AppError> {
+    info!("Fetching savings goals for user {}", user_id);
+
+    let mut conn = pool.acquire().await.map_err(|e| {
+        error!("Failed to acquire connection: {}", e);
+        db_error(&e, "Failed to fetch savings goals")
+    })?;
+    let goals = sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        SELECT id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+            auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+            last_auto_transfer_error, created_at, updated_at
+        FROM savings_goals
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch savings goals: {}", e);
+        db_error(&e, "Failed to fetch savings goals")
+    })?;
+
+    let mut details = Vec::with_capacity(goals.len());
+    for goal in goals {
+        details.push(with_progress(&mut conn, goal).await?);
+    }
+    Ok(Json(details))
+}
+
+pub async fn get_savings_goal(
+    State(pool): State<PgPool>,
+    Path((user_id, goal_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<SavingsGoalDetails>, AppError> {
+    info!("Fetching savings goal {} for user {}", goal_id, user_id);
+
+    let mut conn = pool.acquire().await.map_err(|e| {
+        error!("Failed to acquire connection: {}", e);
+        db_error(&e, "Failed to fetch savings goal")
+    })?;
+    let goal = sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        SELECT id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+            auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+            last_auto_transfer_error, created_at, updated_at
+        FROM savings_goals
+        WHERE id = $1 AND user_id = $2
+        "#,
+        goal_id,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch savings goal: {}", e);
+        db_error(&e, "Failed to fetch savings goal")
+    })?
+    .ok_or(AppError::NotFound("Savings goal not found".to_string()))?;
+
+    with_progress(&mut conn, goal).await.map(Json)
+}
+
+pub async fn update_savings_goal(
+    State(pool): State<PgPool>,
+    Path((user_id, goal_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<UpdateSavingsGoal>,
+) -> Result<Json<SavingsGoalDetails>, AppError> {
+    info!("Updating savings goal {} for user {}: {:?}", goal_id, user_id, payload);
+
+    check_deadline(payload.deadline)?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    let existing = lock_goal(&mut tx, user_id, goal_id).await?;
+    let target = match &payload.target_amount {
+        Some(amount) => {
+            let account = accounts::find(&mut *tx, user_id, existing.account_id)
+                .await
+                .map_err(|e| {
+                    error!("Failed to fetch account: {}", e);
+                    db_error(&e, "Failed to update savings goal")
+                })?
+                .ok_or(AppError::NotFound("Account not found".to_string()))?;
+            Some(check_target(amount, account_currency(&account)?)?)
+        }
+        None => None,
+    };
+
+    let goal = sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        UPDATE savings_goals
+        SET name = COALESCE($2, name),
+            target_amount = COALESCE($3, target_amount),
+            deadline = COALESCE($4, deadline),
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+            auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+            last_auto_transfer_error, created_at, updated_at
+        "#,
+        goal_id,
+        payload.name.as_deref().map(str::trim),
+        target.map(|target| target.to_decimal()),
+        payload.deadline
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to update savings goal {}: {}", goal_id, e);
+        db_error(&e, "Failed to update savings goal")
+    })?;
+    let details = with_progress(&mut tx, goal).await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    Ok(Json(details))
+}
+
+// Deletes the goal only; the money saved stays in its account
+pub async fn delete_savings_goal(
+    State(pool): State<PgPool>,
+    Path((user_id, goal_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<SavingsGoal>, AppError> {
+    info!("Deleting savings goal {} for user {}", goal_id, user_id);
+
+    let goal = sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        DELETE FROM savings_goals
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+            auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+            last_auto_transfer_error, created_at, updated_at
+        "#,
+        goal_id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to delete savings goal: {}", e);
+        db_error(&e, "Failed to delete savings goal")
+    })?
+    .ok_or(AppError::NotFound("Savings goal not found".to_string()))?;
+
+    info!("Deleted savings goal {}", goal.id);
+    Ok(Json(goal))
+}
+
+// Sets or replaces the goal's auto-transfer rule, which also restarts one that stopped
+pub async fn set_auto_transfer(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path((user_id, goal_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<AutoTransferRule>,
+) -> Result<Json<SavingsGoalDetails>, AppError> {
+    info!("Setting auto-transfer of savings goal {} for user {}: {:?}", goal_id, user_id, payload);
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    let existing = lock_goal(&mut tx, user_id, goal_id).await?;
+    let account = open_account(&mut tx, user_id, existing.account_id).await?;
+    let rule = check_rule(&mut tx, &config, user_id, &account, &payload).await?;
+    let goal = store_rule(&mut tx, goal_id, Some(&rule)).await?;
+    let details = with_progress(&mut tx, goal).await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    Ok(Json(details))
+}
+
+pub async fn remove_auto_transfer(
+    State(pool): State<PgPool>,
+    Path((user_id, goal_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<SavingsGoalDetails>, AppError> {
+    info!("Removing auto-transfer of savings goal {} for user {}", goal_id, user_id);
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    lock_goal(&mut tx, user_id, goal_id).await?;
+    let goal = store_rule(&mut tx, goal_id, None).await?;
+    let details = with_progress(&mut tx, goal).await?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    Ok(Json(details))
+}
+
+// Runs every auto-transfer that has come due, one DB transaction per goal. Goals are claimed with
+// SKIP LOCKED so several instances can run the scheduler without moving money twice.
+pub async fn run_due_auto_transfers(pool: &PgPool, config: &Config) -> Result<usize, sqlx::Error> {
+    let mut transferred = 0;
+
+    loop {
+        let mut tx = pool.begin().await?;
+
+        let Some(goal) = sqlx::query_as!(
+            SavingsGoal,
+            r#"
+            SELECT id, user_id, account_id, name, target_amount, deadline, auto_transfer_from_account_id, auto_transfer_amount,
+                auto_transfer_frequency as "auto_transfer_frequency: _", next_auto_transfer_at, last_auto_transfer_at,
+                last_auto_transfer_error, created_at, updated_at
+            FROM savings_goals
+            WHERE next_auto_transfer_at <= NOW()
+            ORDER BY next_auto_transfer_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            break;
+        };
+
+        let outcome = auto_transfer(&mut tx, config, &goal).await?;
+
+        // A skipped transfer is retried at the next period rather than straight away; a reached
+        // goal stops its auto-transfers
+        let (next_auto_transfer_at, last_error) = match outcome {
+            AutoTransferOutcome::Transferred { reached: true } | AutoTransferOutcome::Reached => (None, None),
+            AutoTransferOutcome::Transferred { reached: false } => (next_run(&goal), None),
+            AutoTransferOutcome::Skipped(reason) => (next_run(&goal), Some(reason)),
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE savings_goals
+            SET next_auto_transfer_at = $2, last_auto_transfer_at = NOW(), last_auto_transfer_error = $3, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            goal.id,
+            next_auto_transfer_at,
+            last_error
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        transferred += 1;
+    }
+
+    Ok(transferred)
+}
+
+fn next_run(goal: &SavingsGoal) -> Option<OffsetDateTime> {
+    let next = goal.next_auto_transfer_at?;
+    goal.auto_transfer_frequency?.advance(next, 1, next.day())
+}
+
+// Moves the rule's amount, or just what the goal still needs if that is less, into the goal's account
+async fn auto_transfer(conn: &mut PgConnection, config: &Config, goal: &SavingsGoal) -> Result<AutoTransferOutcome, sqlx::Error> {
+    let skip = |reason: &str| {
+        error!("Skipping auto-transfer of savings goal {}: {}", goal.id, reason);
+        Ok(AutoTransferOutcome::Skipped(reason.to_string()))
+    };
+
+    if users::lock(&mut *conn, goal.user_id).await? != Some(UserStatus::Active) {
+        return skip("Account is deactivated");
+    }
+    let (Some(from_account_id), Some(rule_amount)) = (goal.auto_transfer_from_account_id, &goal.auto_transfer_amount) else {
+        return Ok(AutoTransferOutcome::Reached);
+    };
+
+    let from = accounts::find(&mut *conn, goal.user_id, from_account_id).await?;
+    let to = accounts::find(&mut *conn, goal.user_id, goal.account_id).await?;
+    let (Some(from), Some(to)) = (from, to) else {
+        return skip("Account not found");
+    };
+    if from.closed_at.is_some() || to.closed_at.is_some() {
+        return skip("Account is closed");
+    }
+
+    let saved = accounts::settled_balance(&mut *conn, to.id, true).await?;
+    if saved >= goal.target_amount {
+        info!("Savings goal {} is reached; stopping its auto-transfers", goal.id);
+        return Ok(AutoTransferOutcome::Reached);
+    }
+    let remaining = &goal.target_amount - saved;
+    let amount = rule_amount.min(&remaining);
+    let Some(money) = Money::from_stored(amount, &to.currency) else {
+        return skip("Amount cannot be represented in its currency");
+    };
+
+    let balance = lock_account_balance(conn, goal.user_id, from.id, true).await?;
+    if &balance - money.to_decimal() < -&config.overdraft_limit {
+        return skip("Insufficient funds");
+    }
+
+    let response = post_account_transfer(conn, goal.user_id, &from, &to, &money, Some(&goal.name)).await?;
+    info!("Auto-transferred {} to savings goal {} as transfer {}", money, goal.id, response.transfer.id);
+    Ok(AutoTransferOutcome::Transferred { reached: *amount == remaining })
+}
@@ -0,0 +1,228 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::services::hold::{decide_held_debit, lock_held_debit, settle_held_debit};
+use crate::middleware::auth::AuthContext;
+use crate::models::screening::{ReviewScreening, ScreeningDecision, ScreeningOperation, ScreeningQuery, ScreeningResult};
+use crate::models::transaction::TransactionStatus;
+use crate::models::user::UserStatus;
+use crate::repositories::user as users;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::validation::ValidatedJson;
+
+// The review queue of hits and errors, oldest first, or with `reviewed=true` the ones already
+// reviewed, newest first. Clear results are kept as a record but never queued.
+pub async fn get_screening_results(
+    State(pool): State<PgPool>,
+    Query(query): Query<ScreeningQuery>,
+) -> Result<Json<Vec<ScreeningResult>>, AppError> {
+    info!("Fetching screening results: {:?}", query);
+
+    let results = sqlx::query_as!(
+        ScreeningResult,
+        r#"
+        SELECT id, operation as "operation: _", user_id, transfer_id, provider, outcome as "outcome: _", reason,
+            created_at, reviewed_by, reviewed_at, decision as "decision: _", review_note
+        FROM screening_results
+        WHERE outcome <> 'clear' AND (reviewed_at IS NOT NULL) = $1 AND ($2::uuid IS NULL OR user_id = $2)
+        ORDER BY
+            CASE WHEN $1 THEN created_at END DESC,
+            CASE WHEN NOT $1 THEN created_at END ASC
+        LIMIT 500
+        "#,
+        query.reviewed,
+        query.user_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch screening results: {}", e);
+        db_error(&e, "Failed to fetch screening results")
+    })?;
+
+    Ok(Json(results))
+}
+
+// Clears or confirms a flagged result, deciding the operation it held. A cleared registration lets
+// the user sign in; a confirmed one leaves them deactivated. A flagged transfer is denied as soon as
+// one of its parties is confirmed and released once all of them are cleared.
+pub async fn review_screening_result(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path(result_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<ReviewScreening>,
+) -> Result<Json<ScreeningResult>, AppError> {
+    info!("Admin {} reviewing screening result {}: {:?}", auth.user_id, result_id, payload.decision);
+
+    let note = payload.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+
+    let mut tx = pool.begin().await
+        .map_err(|e| {
+            error!("Failed to start transaction: {}", e);
+            db_error(&e, "Failed to start transaction")
+        })?;
+
+    let before = sqlx::query_as!(
+        ScreeningResult,
+        r#"
+        SELECT id, operation as "operation: _", user_id, transfer_id, provider, outcome as "outcome: _", reason,
+            created_at, reviewed_by, reviewed_at, decision as "decision: _", review_note
+        FROM screening_results
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        result_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch screening result: {}", e);
+        db_error(&e, "Failed to fetch screening result")
+    })?
+    .ok_or(AppError::NotFound("Screening result not found".to_string()))?;
+    if !before.outcome.is_flagged() {
+        return Err(AppError::Conflict("Only flagged screening results are reviewed".to_string()));
+    }
+    if before.reviewed_at.is_some() {
+        return Err(AppError::Conflict("Screening result has already been reviewed".to_string()));
+    }
+
+    let result = sqlx::query_as!(
+        ScreeningResult,
+        r#"
+        UPDATE screening_results
+        SET reviewed_by = $2, reviewed_at = NOW(), decision = $3, review_note = $4
+        WHERE id = $1
+        RETURNING id, operation as "operation: _", user_id, transfer_id, provider, outcome as "outcome: _", reason,
+            created_at, reviewed_by, reviewed_at, decision as "decision: _", review_note
+        "#,
+        result_id,
+        auth.user_id,
+        payload.decision as _,
+        note
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to review screening result: {}", e);
+        db_error(&e, "Failed to review screening result")
+    })?;
+    let record = AuditRecord::new(AuditAction::ScreeningReviewed, auth.user_id, Some(result.user_id))
+        .target(result.id)
+        .before(&before)
+        .after(&result);
+    audit::record(&mut *tx, record).await?;
+
+    match (result.operation, result.transfer_id) {
+        (ScreeningOperation::Transfer, Some(transfer_id)) => {
+            decide_transfer(&mut tx, &config, transfer_id, payload.decision, auth.user_id).await?
+        }
+        _ => decide_registrant(&mut tx, result.user_id, payload.decision, auth.user_id).await?,
+    }
+
+    tx.commit().await
+        .map_err(|e| {
+            error!("Failed to commit transaction: {}", e);
+            db_error(&e, "Failed to commit transaction")
+        })?;
+
+    info!(target: "audit", "Screening result {} {:?} by admin {}", result.id, payload.decision, auth.user_id);
+    Ok(Json(result))
+}
+
+// Activates a registrant screening held for review when the hit is cleared, and deactivates them when
+// it's confirmed. A registrant an admin already reactivated or deactivated is left as they are.
+async fn decide_registrant(conn: &mut PgConnection, user_id: Uuid, decision: ScreeningDecision, admin_id: Uuid) -> Result<(), AppError> {
+    let status = users::lock(&mut *conn, user_id).await
+        .map_err(|e| {
+            error!("Failed to lock user: {}", e);
+            db_error(&e, "Failed to review screening result")
+        })?;
+    if status != Some(UserStatus::PendingReview) {
+        return Ok(());
+    }
+
+    let (status, action) = match decision {
+        ScreeningDecision::Cleared => (UserStatus::Active, AuditAction::UserReactivated),
+        ScreeningDecision::Confirmed => (UserStatus::Deactivated, AuditAction::UserDeactivated),
+    };
+    let user = users::set_status(&mut *conn, user_id, status)
+        .await
+        .map_err(|e| {
+            error!("Failed to update user status: {}", e);
+            db_error(&e, "Failed to review screening result")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+    let record = AuditRecord::new(action, admin_id, Some(user_id))
+        .target(user_id)
+        .after(&user);
+    audit::record(&mut *conn, record).await?;
+    Ok(())
+}
+
+// Decides the transfer's held debit if screening queued it and it's still waiting. Debits a debit
+// hold caught stay in the held debit queue.
+async fn decide_transfer(conn: &mut PgConnection, config: &Config, transfer_id: Uuid, decision: ScreeningDecision, admin_id: Uuid) -> Result<(), AppError> {
+    let transaction_id = sqlx::query_scalar!(
+        r#"
+        SELECT h.transaction_id
+        FROM held_debits h
+        JOIN transactions t ON t.id = h.transaction_id
+        WHERE t.transfer_id = $1 AND h.screening_result_id IS NOT NULL AND h.decided_at IS NULL
+        "#,
+        transfer_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch held transfer debit: {}", e);
+        db_error(&e, "Failed to review screening result")
+    })?;
+    let Some(transaction_id) = transaction_id else {
+        return Ok(());
+    };
+    let held = lock_held_debit(&mut *conn, transaction_id).await?;
+
+    let (action, decided) = match decision {
+        ScreeningDecision::Confirmed => {
+            (AuditAction::HeldDebitDenied, decide_held_debit(&mut *conn, &held, TransactionStatus::Denied, admin_id).await?)
+        }
+        ScreeningDecision::Cleared => {
+            let outstanding = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM screening_results
+                    WHERE transfer_id = $1 AND outcome <> 'clear' AND decision IS DISTINCT FROM 'cleared'
+                ) as "exists!"
+                "#,
+                transfer_id
+            )
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to check outstanding screening results: {}", e);
+                db_error(&e, "Failed to review screening result")
+            })?;
+            if outstanding {
+                return Ok(());
+            }
+            (AuditAction::HeldDebitReleased, settle_held_debit(&mut *conn, config, &held, admin_id).await?)
+        }
+    };
+    let record = AuditRecord::new(action, admin_id, Some(held.user_id))
+        .target(held.transaction_id)
+        .before(&held)
+        .after(&decided);
+    audit::record(&mut *conn, record).await?;
+    Ok(())
+}
@@ -1,150 +1,181 @@
 use axum::{
-    extract::{State, Path},
-    http::StatusCode,
+    extract::{State, Path, Query},
     Json,
 };
 use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use uuid::Uuid;
-use bigdecimal::BigDecimal;
 use tracing::{info, error};
 
-use crate::models::transaction::{Transaction, CreateTransaction, AccountBalance};
+use crate::balance_cache::{cached_balance, store_balance};
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::middleware::auth::Livemode;
+use crate::models::transaction::{
+    Transaction, CreateTransaction, AccountBalance, AccountSummary, TransactionCursor, TransactionPage, TransactionQuery, TransactionReversal,
+};
+use crate::repositories::transaction as transactions;
+use crate::services::ledger;
+use crate::validation::ValidatedJson;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
 
 pub async fn create_transaction(
     State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
     Path(user_id): Path<Uuid>,
-    Json(payload): Json<CreateTransaction>,
-) -> Result<Json<Transaction>, (StatusCode, String)> {
-    info!("Creating transaction for user {}: {:?}", user_id, payload);
-    
-    // Check if user exists
-    let user_exists = sqlx::query_scalar!(
-        "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1) as \"exists!\"",
-        user_id
-    )
-    .fetch_one(&pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to check user existence: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to check user existence".to_string())
-    })?;
-    if !user_exists {
-        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
-    }
+    Livemode(livemode): Livemode,
+    ValidatedJson(payload): ValidatedJson<CreateTransaction>,
+) -> Result<Json<Transaction>, AppError> {
+    info!("Creating {} transaction for user {}: {:?}", if livemode { "live" } else { "sandbox" }, user_id, payload);
+    ledger::create_transaction(&pool, &config, user_id, payload, livemode).await.map(Json)
+}
 
-    let mut tx = pool.begin().await
-        .map_err(|e| {
-            error!("Failed to start transaction: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to start transaction".to_string())
-        })?;
+pub async fn get_transactions(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Livemode(livemode): Livemode,
+    Query(query): Query<TransactionQuery>,
+) -> Result<TransactionPage, AppError> {
+    info!("Fetching transactions for user {}: {:?}", user_id, query);
 
-    let transaction = sqlx::query_as!(
-        Transaction,
-        r#"
-        INSERT INTO transactions (user_id, amount, transaction_type, description)
-        VALUES ($1, $2, $3, $4)
-        RETURNING id, user_id, amount, transaction_type as "transaction_type: _", description, created_at
-        "#,
-        user_id,
-        payload.amount,
-        payload.transaction_type as _,
-        payload.description
-    )
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| {
-        error!("Failed to create transaction: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create transaction".to_string())
-    })?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let cursor = match query.cursor.as_deref() {
+        Some(cursor) => Some(
+            TransactionCursor::decode(cursor)
+                .ok_or(AppError::BadRequest("Invalid cursor".to_string()))?,
+        ),
+        None => None,
+    };
 
-    tx.commit().await
+    // Fetch one extra row to learn whether another page follows
+    let mut transactions = transactions::list(&pool, user_id, livemode, cursor.as_ref(), limit + 1)
+        .await
         .map_err(|e| {
-            error!("Failed to commit transaction: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to commit transaction".to_string())
+            error!("Failed to fetch transactions: {}", e);
+            db_error(&e, "Failed to fetch transactions")
         })?;
 
-    info!("Successfully created transaction: {:?}", transaction);
-    Ok(Json(transaction))
+    let next_cursor = if transactions.len() as i64 > limit {
+        transactions.truncate(limit as usize);
+        transactions.last().map(|last| TransactionCursor {
+            created_at: last.created_at,
+            id: last.id,
+        }.encode())
+    } else {
+        None
+    };
+
+    info!("Found {} transactions for user {}", transactions.len(), user_id);
+    Ok(TransactionPage { transactions, next_cursor, signed_amounts: query.signed_amounts })
 }
 
-pub async fn get_transactions(
+pub async fn reverse_transaction(
     State(pool): State<PgPool>,
-    Path(user_id): Path<Uuid>,
-) -> Result<Json<Vec<Transaction>>, (StatusCode, String)> {
-    info!("Fetching transactions for user {}", user_id);
-    
-    let transactions = sqlx::query_as!(
-        Transaction,
-        r#"
-        SELECT id, user_id, amount, transaction_type as "transaction_type: _", description, created_at
-        FROM transactions
-        WHERE user_id = $1
-        ORDER BY created_at DESC
-        "#,
-        user_id
-    )
-    .fetch_all(&pool)
-    .await
-    .map_err(|e| {
-        error!("Failed to fetch transactions: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch transactions".to_string())
-    })?;
+    State(config): State<Arc<Config>>,
+    Path((user_id, transaction_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<TransactionReversal>, AppError> {
+    info!("Reversing transaction {} for user {}", transaction_id, user_id);
+    ledger::reverse_transaction(&pool, &config, user_id, transaction_id, livemode).await.map(Json)
+}
 
-    info!("Found {} transactions for user {}", transactions.len(), user_id);
-    Ok(Json(transactions))
+pub async fn cancel_transaction(
+    State(pool): State<PgPool>,
+    Path((user_id, transaction_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<Transaction>, AppError> {
+    info!("Cancelling transaction {} for user {}", transaction_id, user_id);
+    ledger::cancel_transaction(&pool, user_id, transaction_id, livemode).await.map(Json)
+}
+
+pub async fn settle_transaction(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    Path((user_id, transaction_id)): Path<(Uuid, Uuid)>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<Transaction>, AppError> {
+    info!("Settling transaction {} for user {}", transaction_id, user_id);
+    ledger::settle_transaction(&pool, &config, user_id, transaction_id, livemode).await.map(Json)
 }
 
 pub async fn get_account_balance(
     State(pool): State<PgPool>,
+    State(config): State<Arc<Config>>,
+    State(cache): State<Arc<dyn Cache>>,
     Path(user_id): Path<Uuid>,
-) -> Result<Json<AccountBalance>, (StatusCode, String)> {
+    Livemode(livemode): Livemode,
+) -> Result<Json<AccountBalance>, AppError> {
     info!("Fetching balance for user {}", user_id);
-    
-    let balance = sqlx::query!(
-        r#"
-        SELECT 
-            user_id,
-            COALESCE(
-                SUM(
-                    CASE 
-                        WHEN transaction_type = 'credit' THEN amount
-                        WHEN transaction_type = 'debit' THEN -amount
-                    END
-                ),
-                0
-            ) as balance,
-            MAX(created_at) as last_updated
-        FROM transactions
-        WHERE user_id = $1
-        GROUP BY user_id
-        "#,
-        user_id
-    )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|e| {
+
+    // Only live balances are cached; sandbox reads go straight to the database
+    if livemode {
+        if let Some(balance) = cached_balance(cache.as_ref(), user_id).await {
+            return Ok(Json(balance));
+        }
+    }
+
+    let rows = transactions::currency_balances(&pool, user_id, livemode).await.map_err(|e| {
         error!("Failed to fetch balance: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch balance".to_string())
-    })?
-    .ok_or((StatusCode::NOT_FOUND, "No transactions found".to_string()))?;
-
-    let account_balance = AccountBalance {
-        user_id: balance.user_id,
-        balance: balance.balance.unwrap_or(BigDecimal::from(0)),
-        last_updated: balance.last_updated,
+        db_error(&e, "Failed to fetch balance")
+    })?;
+
+    if rows.is_empty() {
+        return Err(AppError::NotFound("No transactions found".to_string()));
+    }
+
+    let mut account_balance = AccountBalance {
+        user_id,
+        last_updated: rows.iter().filter_map(|row| row.last_updated).max(),
+        balances: BTreeMap::new(),
+        available: BTreeMap::new(),
+        pending: BTreeMap::new(),
     };
+    for row in rows {
+        account_balance.available.insert(row.currency.clone(), row.available());
+        account_balance.pending.insert(row.currency.clone(), row.pending);
+        account_balance.balances.insert(row.currency, row.balance);
+    }
 
     info!("Balance for user {}: {:?}", user_id, account_balance);
+    if livemode {
+        store_balance(cache.as_ref(), &config, &account_balance).await;
+    }
     Ok(Json(account_balance))
 }
 
+// Posted credit and debit totals, balance, entry count and first and last activity per currency
+pub async fn get_account_summary(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Livemode(livemode): Livemode,
+) -> Result<Json<AccountSummary>, AppError> {
+    info!("Fetching account summary for user {}", user_id);
+
+    let currencies = transactions::summarize(&pool, user_id, livemode).await.map_err(|e| {
+        error!("Failed to fetch account summary: {}", e);
+        db_error(&e, "Failed to fetch account summary")
+    })?;
+
+    Ok(Json(AccountSummary { user_id, currencies }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::NoCache;
+    use crate::models::transaction::{TransactionStatus, TransactionType};
+    use crate::services::ledger::execute_due_transactions;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
     use sqlx::postgres::PgPoolOptions;
     use std::str::FromStr;
     use bigdecimal::BigDecimal;
-    use crate::models::transaction::TransactionType;
+    use time::OffsetDateTime;
 
     async fn setup_test_db() -> PgPool {
         // Use a test database URL
@@ -158,12 +189,17 @@ mod tests {
             .expect("Failed to connect to database")
     }
 
+    fn no_cache() -> State<Arc<dyn Cache>> {
+        State(Arc::new(NoCache))
+    }
+
+    fn default_config() -> State<Arc<Config>> {
+        State(Arc::new(Config::default()))
+    }
+
     // Helper function to clean up test data
     async fn cleanup_test_data(pool: &PgPool, user_id: Uuid) {
-        sqlx::query!("DELETE FROM transactions WHERE user_id = $1", user_id)
-            .execute(pool)
-            .await
-            .unwrap();
+        crate::db::purge_transactions(pool, &[user_id]).await;
         sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
             .execute(pool)
             .await
@@ -196,14 +232,22 @@ mod tests {
 
         let transaction = CreateTransaction {
             amount: BigDecimal::from_str("100.50").unwrap(),
+            currency: "USD".to_string(),
             transaction_type: TransactionType::Credit,
             description: Some("Test credit".to_string()),
+            execute_at: None,
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
         };
 
         let result = create_transaction(
             State(pool.clone()),
+            default_config(),
             Path(user_id),
-            Json(transaction),
+            Livemode(true),
+            ValidatedJson(transaction),
         )
         .await;
 
@@ -225,14 +269,22 @@ mod tests {
         // Create initial credit
         let credit = CreateTransaction {
             amount: BigDecimal::from_str("200.00").unwrap(),
+            currency: "USD".to_string(),
             transaction_type: TransactionType::Credit,
             description: Some("Initial deposit".to_string()),
+            execute_at: None,
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
         };
 
         let _ = create_transaction(
             State(pool.clone()),
+            default_config(),
             Path(user_id),
-            Json(credit),
+            Livemode(true),
+            ValidatedJson(credit),
         )
         .await
         .unwrap();
@@ -240,14 +292,22 @@ mod tests {
         // Create debit
         let debit = CreateTransaction {
             amount: BigDecimal::from_str("50.25").unwrap(),
+            currency: "USD".to_string(),
             transaction_type: TransactionType::Debit,
             description: Some("Test debit".to_string()),
+            execute_at: None,
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
         };
 
         let result = create_transaction(
             State(pool.clone()),
+            default_config(),
             Path(user_id),
-            Json(debit),
+            Livemode(true),
+            ValidatedJson(debit),
         )
         .await;
 
@@ -259,6 +319,65 @@ mod tests {
         cleanup_test_data(&pool, user_id).await;
     }
 
+    #[tokio::test]
+    async fn test_debit_rejected_when_funds_insufficient() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        
+        create_test_user(&pool, user_id, &format!("test_insufficient_{}@example.com", user_id)).await;
+
+        let credit = CreateTransaction {
+            amount: BigDecimal::from_str("50.00").unwrap(),
+            currency: "USD".to_string(),
+            transaction_type: TransactionType::Credit,
+            description: Some("Initial deposit".to_string()),
+            execute_at: None,
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
+        };
+
+        let _ = create_transaction(
+            State(pool.clone()),
+            default_config(),
+            Path(user_id),
+            Livemode(true),
+            ValidatedJson(credit),
+        )
+        .await
+        .unwrap();
+
+        let debit = CreateTransaction {
+            amount: BigDecimal::from_str("50.01").unwrap(),
+            currency: "USD".to_string(),
+            transaction_type: TransactionType::Debit,
+            description: Some("Too large".to_string()),
+            execute_at: None,
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
+        };
+
+        let result = create_transaction(
+            State(pool.clone()),
+            default_config(),
+            Path(user_id),
+            Livemode(true),
+            ValidatedJson(debit),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), AppError::InsufficientFunds);
+
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap();
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from_str("50.00").unwrap());
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
     #[tokio::test]
     async fn test_get_transactions() {
         let pool = setup_test_db().await;
@@ -270,33 +389,48 @@ mod tests {
         let transactions = vec![
             CreateTransaction {
                 amount: BigDecimal::from_str("100.50").unwrap(),
+                currency: "USD".to_string(),
                 transaction_type: TransactionType::Credit,
                 description: Some("First credit".to_string()),
+                execute_at: None,
+                pending: false,
+                category: None,
+                payee_id: None,
+                account_id: None,
             },
             CreateTransaction {
                 amount: BigDecimal::from_str("25.75").unwrap(),
+                currency: "USD".to_string(),
                 transaction_type: TransactionType::Debit,
                 description: Some("First debit".to_string()),
+                execute_at: None,
+                pending: false,
+                category: None,
+                payee_id: None,
+                account_id: None,
             },
         ];
 
         for transaction in transactions {
             let _ = create_transaction(
                 State(pool.clone()),
+                default_config(),
                 Path(user_id),
-                Json(transaction),
+                Livemode(true),
+                ValidatedJson(transaction),
             )
             .await
             .unwrap();
         }
 
-        let result = get_transactions(State(pool.clone()), Path(user_id)).await;
+        let result = get_transactions(State(pool.clone()), Path(user_id), Livemode(true), Query(TransactionQuery::default())).await;
         assert!(result.is_ok());
         
-        let transactions = result.unwrap();
-        assert_eq!(transactions.0.len(), 2);
+        let page = result.unwrap();
+        assert_eq!(page.transactions.len(), 2);
+        assert!(page.next_cursor.is_none());
         // Check that both transactions exist, regardless of order
-        let mut amounts: Vec<BigDecimal> = transactions.0.iter().map(|t| t.amount.clone()).collect();
+        let mut amounts: Vec<BigDecimal> = page.transactions.iter().map(|t| t.amount.clone()).collect();
         amounts.sort();
         let mut expected = vec![BigDecimal::from_str("25.75").unwrap(), BigDecimal::from_str("100.50").unwrap()];
         expected.sort();
@@ -305,6 +439,110 @@ mod tests {
         cleanup_test_data(&pool, user_id).await;
     }
 
+    #[tokio::test]
+    async fn test_get_transactions_paginates_with_cursor() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        
+        create_test_user(&pool, user_id, &format!("test_pagination_{}@example.com", user_id)).await;
+
+        for amount in ["1.00", "2.00", "3.00"] {
+            let _ = create_transaction(
+                State(pool.clone()),
+                default_config(),
+                Path(user_id),
+                Livemode(true),
+                ValidatedJson(CreateTransaction {
+                    amount: BigDecimal::from_str(amount).unwrap(),
+                    currency: "USD".to_string(),
+                    transaction_type: TransactionType::Credit,
+                    description: None,
+                    execute_at: None,
+                    pending: false,
+                    category: None,
+                    payee_id: None,
+                    account_id: None,
+                }),
+            )
+            .await
+            .unwrap();
+        }
+
+        let first_page = get_transactions(
+            State(pool.clone()),
+            Path(user_id),
+            Livemode(true),
+            Query(TransactionQuery { cursor: None, limit: Some(2), ..Default::default() }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(first_page.transactions.len(), 2);
+        assert!(first_page.next_cursor.is_some());
+
+        let second_page = get_transactions(
+            State(pool.clone()),
+            Path(user_id),
+            Livemode(true),
+            Query(TransactionQuery { cursor: first_page.next_cursor, limit: Some(2), ..Default::default() }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(second_page.transactions.len(), 1);
+        assert!(second_page.next_cursor.is_none());
+        assert!(first_page.transactions.iter().all(|t| t.id != second_page.transactions[0].id));
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_signed_amounts_negate_debits() {
+        let debit = Transaction {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            amount: BigDecimal::from_str("25.75").unwrap(),
+            currency: "USD".to_string(),
+            transaction_type: TransactionType::Debit,
+            description: None,
+            transfer_id: None,
+            status: TransactionStatus::Settled,
+            reverses: None,
+            reversed_by: None,
+            execute_at: None,
+            created_at: OffsetDateTime::now_utc(),
+            livemode: true,
+            category: None,
+            payee_id: None,
+            fx_rate: None,
+        };
+        let unsigned = serde_json::to_value(&debit).unwrap();
+
+        let page = TransactionPage { transactions: vec![debit], next_cursor: None, signed_amounts: true };
+        let body = axum::body::to_bytes(page.into_response().into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body[0]["amount"], "-25.75");
+        assert_eq!(body[0]["transaction_type"], "Debit");
+        let mut rest = body[0].clone();
+        rest["amount"] = unsigned["amount"].clone();
+        assert_eq!(rest, unsigned);
+    }
+
+    #[tokio::test]
+    async fn test_get_transactions_rejects_invalid_cursor() {
+        let pool = setup_test_db().await;
+
+        let result = get_transactions(
+            State(pool),
+            Path(Uuid::new_v4()),
+            Livemode(true),
+            Query(TransactionQuery { cursor: Some("not-a-cursor".to_string()), ..Default::default() }),
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_get_balance() {
         let pool = setup_test_db().await;
@@ -316,31 +554,478 @@ mod tests {
         let transactions = vec![
             CreateTransaction {
                 amount: BigDecimal::from_str("100.50").unwrap(),
+                currency: "USD".to_string(),
                 transaction_type: TransactionType::Credit,
                 description: Some("First credit".to_string()),
+                execute_at: None,
+                pending: false,
+                category: None,
+                payee_id: None,
+                account_id: None,
             },
             CreateTransaction {
                 amount: BigDecimal::from_str("25.75").unwrap(),
+                currency: "USD".to_string(),
                 transaction_type: TransactionType::Debit,
                 description: Some("First debit".to_string()),
+                execute_at: None,
+                pending: false,
+                category: None,
+                payee_id: None,
+                account_id: None,
             },
         ];
 
         for transaction in transactions {
             let _ = create_transaction(
                 State(pool.clone()),
+                default_config(),
                 Path(user_id),
-                Json(transaction),
+                Livemode(true),
+                ValidatedJson(transaction),
             )
             .await
             .unwrap();
         }
 
-        let result = get_account_balance(State(pool.clone()), Path(user_id)).await;
+        let result = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await;
         assert!(result.is_ok());
         
         let balance = result.unwrap();
-        assert_eq!(balance.0.balance, BigDecimal::from_str("74.75").unwrap());
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from_str("74.75").unwrap());
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_reverse_transaction_only_once() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+
+        create_test_user(&pool, user_id, &format!("test_reverse_{}@example.com", user_id)).await;
+
+        let credit = create_transaction(
+            State(pool.clone()),
+            default_config(),
+            Path(user_id),
+            Livemode(true),
+            ValidatedJson(CreateTransaction {
+                amount: BigDecimal::from_str("80.00").unwrap(),
+                currency: "USD".to_string(),
+                transaction_type: TransactionType::Credit,
+                description: Some("Deposit".to_string()),
+                execute_at: None,
+                pending: false,
+                category: None,
+                payee_id: None,
+                account_id: None,
+            }),
+        )
+        .await
+        .unwrap()
+        .0;
+
+        let reversed = reverse_transaction(State(pool.clone()), default_config(), Path((user_id, credit.id)), Livemode(true))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(reversed.original.status, TransactionStatus::Reversed);
+        assert_eq!(reversed.original.reversed_by, Some(reversed.reversal.id));
+        assert_eq!(reversed.reversal.reverses, Some(credit.id));
+        assert_eq!(reversed.reversal.transaction_type, TransactionType::Debit);
+
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap();
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from(0));
+
+        let again = reverse_transaction(State(pool.clone()), default_config(), Path((user_id, credit.id)), Livemode(true)).await;
+        assert_eq!(again.unwrap_err().status(), StatusCode::CONFLICT);
+
+        let of_reversal = reverse_transaction(State(pool.clone()), default_config(), Path((user_id, reversed.reversal.id)), Livemode(true)).await;
+        assert_eq!(of_reversal.unwrap_err().status(), StatusCode::CONFLICT);
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_transactions_are_kept_apart_from_live() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+
+        create_test_user(&pool, user_id, &format!("test_sandbox_{}@example.com", user_id)).await;
+
+        let credit = |amount: &str| CreateTransaction {
+            amount: BigDecimal::from_str(amount).unwrap(),
+            currency: "USD".to_string(),
+            transaction_type: TransactionType::Credit,
+            description: None,
+            execute_at: None,
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
+        };
+        let live = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(credit("40.00")))
+            .await
+            .unwrap()
+            .0;
+        let sandbox = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(false), ValidatedJson(credit("1000.00")))
+            .await
+            .unwrap()
+            .0;
+        assert!(live.livemode);
+        assert!(!sandbox.livemode);
+
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap();
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from_str("40.00").unwrap());
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(false)).await.unwrap();
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from_str("1000.00").unwrap());
+
+        // Sandbox funds can't cover a live debit
+        let overdraw = create_transaction(
+            State(pool.clone()),
+            default_config(),
+            Path(user_id),
+            Livemode(true),
+            ValidatedJson(CreateTransaction { transaction_type: TransactionType::Debit, ..credit("100.00") }),
+        )
+        .await;
+        assert_eq!(overdraw.unwrap_err().status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let page = get_transactions(State(pool.clone()), Path(user_id), Livemode(false), Query(TransactionQuery::default()))
+            .await
+            .unwrap();
+        assert_eq!(page.transactions.iter().map(|t| t.id).collect::<Vec<_>>(), vec![sandbox.id]);
+
+        // Neither mode can see or touch the other's entries
+        let reversal = reverse_transaction(State(pool.clone()), default_config(), Path((user_id, live.id)), Livemode(false)).await;
+        assert_eq!(reversal.unwrap_err().status(), StatusCode::NOT_FOUND);
+        let reversal = reverse_transaction(State(pool.clone()), default_config(), Path((user_id, sandbox.id)), Livemode(false))
+            .await
+            .unwrap()
+            .0;
+        assert!(!reversal.reversal.livemode);
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_posted_transactions_are_immutable() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+
+        create_test_user(&pool, user_id, &format!("test_immutable_{}@example.com", user_id)).await;
+
+        let mut created = Vec::new();
+        for pending in [false, true] {
+            let transaction = create_transaction(
+                State(pool.clone()),
+                default_config(),
+                Path(user_id),
+                Livemode(true),
+                ValidatedJson(CreateTransaction {
+                    amount: BigDecimal::from_str("50.00").unwrap(),
+                    currency: "USD".to_string(),
+                    transaction_type: TransactionType::Credit,
+                    description: None,
+                    execute_at: None,
+                    pending,
+                    category: None,
+                    payee_id: None,
+                    account_id: None,
+                }),
+            )
+            .await
+            .unwrap()
+            .0;
+            created.push(transaction);
+        }
+        let (settled, pending) = (&created[0], &created[1]);
+
+        // Amounts, currency, direction, owner and status of a posted entry are fixed
+        for statement in [
+            "UPDATE transactions SET amount = 5 WHERE id = $1",
+            "UPDATE transactions SET currency = 'EUR' WHERE id = $1",
+            "UPDATE transactions SET transaction_type = 'debit' WHERE id = $1",
+            "UPDATE transactions SET status = 'cancelled' WHERE id = $1",
+            "UPDATE transactions SET created_at = NOW() - INTERVAL '1 day' WHERE id = $1",
+            "DELETE FROM transactions WHERE id = $1",
+        ] {
+            let e = sqlx::query(statement).bind(settled.id).execute(&pool).await.unwrap_err();
+            assert_eq!(db_error(&e, "Failed").status(), StatusCode::CONFLICT, "{}", statement);
+        }
+
+        // Entries that haven't posted yet can still change
+        sqlx::query!("UPDATE transactions SET amount = 40 WHERE id = $1", pending.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Reversing is the way to correct a posted entry, and happens only once
+        let reversed = reverse_transaction(State(pool.clone()), default_config(), Path((user_id, settled.id)), Livemode(true)).await.unwrap().0;
+        let e = sqlx::query!("UPDATE transactions SET reversed_by = NULL WHERE id = $1", settled.id)
+            .execute(&pool)
+            .await
+            .unwrap_err();
+        assert_eq!(db_error(&e, "Failed").status(), StatusCode::CONFLICT);
+        let e = sqlx::query!("UPDATE transactions SET status = 'settled' WHERE id = $1", settled.id)
+            .execute(&pool)
+            .await
+            .unwrap_err();
+        assert_eq!(db_error(&e, "Failed").status(), StatusCode::CONFLICT);
+        let e = sqlx::query!("DELETE FROM transactions WHERE id = $1", reversed.reversal.id)
+            .execute(&pool)
+            .await
+            .unwrap_err();
+        assert_eq!(db_error(&e, "Failed").status(), StatusCode::CONFLICT);
+
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap();
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from(0));
+        assert_eq!(balance.0.pending["USD"], BigDecimal::from(40));
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_balances_are_tracked_per_currency() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+
+        create_test_user(&pool, user_id, &format!("test_currency_{}@example.com", user_id)).await;
+
+        for (amount, currency) in [("100.00", "usd"), ("40.00", "EUR")] {
+            let _ = create_transaction(
+                State(pool.clone()),
+                default_config(),
+                Path(user_id),
+                Livemode(true),
+                ValidatedJson(CreateTransaction {
+                    amount: BigDecimal::from_str(amount).unwrap(),
+                    currency: currency.to_string(),
+                    transaction_type: TransactionType::Credit,
+                    description: None,
+                    execute_at: None,
+                    pending: false,
+                    category: None,
+                    payee_id: None,
+                    account_id: None,
+                }),
+            )
+            .await
+            .unwrap();
+        }
+
+        // EUR funds can't cover a EUR debit larger than the EUR balance, whatever the USD balance
+        let result = create_transaction(
+            State(pool.clone()),
+            default_config(),
+            Path(user_id),
+            Livemode(true),
+            ValidatedJson(CreateTransaction {
+                amount: BigDecimal::from_str("50.00").unwrap(),
+                currency: "EUR".to_string(),
+                transaction_type: TransactionType::Debit,
+                description: None,
+                execute_at: None,
+                pending: false,
+                category: None,
+                payee_id: None,
+                account_id: None,
+            }),
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), AppError::InsufficientFunds);
+
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap();
+        assert_eq!(balance.0.balances.len(), 2);
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from_str("100.00").unwrap());
+        assert_eq!(balance.0.balances["EUR"], BigDecimal::from_str("40.00").unwrap());
+
+        let invalid = create_transaction(
+            State(pool.clone()),
+            default_config(),
+            Path(user_id),
+            Livemode(true),
+            ValidatedJson(CreateTransaction {
+                amount: BigDecimal::from_str("1.00").unwrap(),
+                currency: "EURO".to_string(),
+                transaction_type: TransactionType::Credit,
+                description: None,
+                execute_at: None,
+                pending: false,
+                category: None,
+                payee_id: None,
+                account_id: None,
+            }),
+        )
+        .await;
+        assert_eq!(invalid.unwrap_err().status(), StatusCode::BAD_REQUEST);
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_transaction_lifecycle() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+
+        create_test_user(&pool, user_id, &format!("test_scheduled_{}@example.com", user_id)).await;
+
+        let schedule = |amount: &str| CreateTransaction {
+            amount: BigDecimal::from_str(amount).unwrap(),
+            currency: "USD".to_string(),
+            transaction_type: TransactionType::Credit,
+            description: Some("Payday".to_string()),
+            execute_at: Some(OffsetDateTime::now_utc() + time::Duration::hours(1)),
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
+        };
+
+        let first = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(schedule("60.00")))
+            .await
+            .unwrap()
+            .0;
+        let second = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(schedule("15.00")))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(first.status, TransactionStatus::Scheduled);
+
+        // Scheduled transactions don't count towards the balance
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await;
+        assert_eq!(balance.unwrap_err().status(), StatusCode::NOT_FOUND);
+
+        let cancelled = cancel_transaction(State(pool.clone()), Path((user_id, second.id)), Livemode(true))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(cancelled.status, TransactionStatus::Cancelled);
+
+        let again = cancel_transaction(State(pool.clone()), Path((user_id, second.id)), Livemode(true)).await;
+        assert_eq!(again.unwrap_err().status(), StatusCode::CONFLICT);
+
+        // Bring the first one due and let the worker post it
+        sqlx::query!("UPDATE transactions SET execute_at = NOW() WHERE id = $1", first.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        execute_due_transactions(&pool, &Config::default()).await.unwrap();
+
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap();
+        assert_eq!(balance.0.balances["USD"], BigDecimal::from_str("60.00").unwrap());
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_pending_transaction_lifecycle() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+
+        create_test_user(&pool, user_id, &format!("test_pending_{}@example.com", user_id)).await;
+
+        let create = |amount: &str, transaction_type: TransactionType, pending: bool| CreateTransaction {
+            amount: BigDecimal::from_str(amount).unwrap(),
+            currency: "USD".to_string(),
+            transaction_type,
+            description: None,
+            execute_at: None,
+            pending,
+            category: None,
+            payee_id: None,
+            account_id: None,
+        };
+
+        let _ = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(create("100.00", TransactionType::Credit, false)))
+            .await
+            .unwrap();
+        let hold = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(create("30.00", TransactionType::Debit, true)))
+            .await
+            .unwrap()
+            .0;
+        let incoming = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(create("50.00", TransactionType::Credit, true)))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(hold.status, TransactionStatus::Pending);
+
+        // The pending debit is held from the available balance; the pending credit is not spendable yet
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap().0;
+        assert_eq!(balance.balances["USD"], BigDecimal::from_str("100.00").unwrap());
+        assert_eq!(balance.available["USD"], BigDecimal::from_str("70.00").unwrap());
+        assert_eq!(balance.pending["USD"], BigDecimal::from_str("20.00").unwrap());
+
+        // Debits are checked against the available balance, not the ledger balance
+        let overdraft = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(create("80.00", TransactionType::Debit, false))).await;
+        assert_eq!(overdraft.unwrap_err(), AppError::InsufficientFunds);
+
+        let settled = settle_transaction(State(pool.clone()), default_config(), Path((user_id, hold.id)), Livemode(true))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(settled.status, TransactionStatus::Settled);
+        let again = settle_transaction(State(pool.clone()), default_config(), Path((user_id, hold.id)), Livemode(true)).await;
+        assert_eq!(again.unwrap_err().status(), StatusCode::CONFLICT);
+
+        let cancelled = cancel_transaction(State(pool.clone()), Path((user_id, incoming.id)), Livemode(true))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(cancelled.status, TransactionStatus::Cancelled);
+
+        let balance = get_account_balance(State(pool.clone()), default_config(), no_cache(), Path(user_id), Livemode(true)).await.unwrap().0;
+        assert_eq!(balance.balances["USD"], BigDecimal::from_str("70.00").unwrap());
+        assert_eq!(balance.available["USD"], BigDecimal::from_str("70.00").unwrap());
+        assert_eq!(balance.pending["USD"], BigDecimal::from(0));
+
+        let missing = settle_transaction(State(pool.clone()), default_config(), Path((user_id, Uuid::new_v4())), Livemode(true)).await;
+        assert_eq!(missing.unwrap_err().status(), StatusCode::NOT_FOUND);
+
+        cleanup_test_data(&pool, user_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_account_summary_totals_posted_entries() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        create_test_user(&pool, user_id, &format!("test_summary_{}@example.com", user_id)).await;
+
+        let entries = [
+            ("100.00", "USD", TransactionType::Credit, false),
+            ("30.00", "USD", TransactionType::Debit, false),
+            ("20.00", "USD", TransactionType::Credit, true),
+            ("40.00", "EUR", TransactionType::Credit, false),
+        ];
+        for (amount, currency, transaction_type, pending) in entries {
+            let entry = CreateTransaction {
+                amount: BigDecimal::from_str(amount).unwrap(),
+                currency: currency.to_string(),
+                transaction_type,
+                description: None,
+                execute_at: None,
+                pending,
+                category: None,
+                payee_id: None,
+                account_id: None,
+            };
+            let _ = create_transaction(State(pool.clone()), default_config(), Path(user_id), Livemode(true), ValidatedJson(entry)).await.unwrap();
+        }
+
+        let summary = get_account_summary(State(pool.clone()), Path(user_id), Livemode(true)).await.unwrap().0;
+        let currencies: Vec<&str> = summary.currencies.iter().map(|c| c.currency.as_str()).collect();
+        assert_eq!(currencies, vec!["EUR", "USD"]);
+
+        // The pending credit isn't posted yet, so it's left out
+        let usd = &summary.currencies[1];
+        assert_eq!(usd.total_credits, BigDecimal::from(100));
+        assert_eq!(usd.total_debits, BigDecimal::from(30));
+        assert_eq!(usd.balance, BigDecimal::from(70));
+        assert_eq!(usd.transaction_count, 2);
+        assert!(usd.first_activity <= usd.last_activity);
+
+        let sandbox = get_account_summary(State(pool.clone()), Path(user_id), Livemode(false)).await.unwrap().0;
+        assert!(sandbox.currencies.is_empty());
 
         cleanup_test_data(&pool, user_id).await;
     }
@@ -352,19 +1037,27 @@ mod tests {
 
         let transaction = CreateTransaction {
             amount: BigDecimal::from_str("100.50").unwrap(),
+            currency: "USD".to_string(),
             transaction_type: TransactionType::Credit,
             description: Some("Test credit".to_string()),
+            execute_at: None,
+            pending: false,
+            category: None,
+            payee_id: None,
+            account_id: None,
         };
 
         let result = create_transaction(
             State(pool),
+            default_config(),
             Path(invalid_user_id),
-            Json(transaction),
+            Livemode(true),
+            ValidatedJson(transaction),
         )
         .await;
 
         assert!(result.is_err());
         let error = result.unwrap_err();
-        assert_eq!(error.0, StatusCode::NOT_FOUND);
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
     }
 } 
\ No newline at end of file
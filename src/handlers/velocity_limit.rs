@@ -0,0 +1,138 @@
+use axum::{
+    extract::{Extension, Path, State},
+    Json,
+};
+use bigdecimal::BigDecimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::middleware::auth::AuthContext;
+use crate::models::money::Currency;
+use crate::models::velocity_limit::{UpdateVelocityLimits, VelocityLimits};
+use crate::repositories::user as users;
+use crate::services::audit::{self, AuditAction, AuditRecord};
+
+pub fn check_positive_amount(field: &str, amount: Option<&BigDecimal>) -> Result<(), AppError> {
+    match amount {
+        Some(amount) if *amount <= 0 => Err(AppError::BadRequest(format!("`{}` must be greater than zero", field))),
+        _ => Ok(()),
+    }
+}
+
+pub async fn get_velocity_limits(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<VelocityLimits>, AppError> {
+    sqlx::query_as!(
+        VelocityLimits,
+        r#"
+        SELECT user_id, currency, max_transaction_amount, max_daily_debit_total, max_transactions_per_hour, updated_by,
+            created_at, updated_at
+        FROM velocity_limits
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch velocity limits: {}", e);
+        db_error(&e, "Failed to fetch velocity limits")
+    })?
+    .ok_or(AppError::NotFound("Velocity limits are not set".to_string()))
+    .map(Json)
+}
+
+// Sets all of a user's velocity limits at once; they apply from the user's next transaction
+pub async fn update_velocity_limits(
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<UpdateVelocityLimits>,
+) -> Result<Json<VelocityLimits>, AppError> {
+    info!("Updating velocity limits for user {}: {:?}", user_id, payload);
+
+    let currency = Currency::parse(&payload.currency)
+        .ok_or(AppError::BadRequest("Invalid currency code".to_string()))?;
+    check_positive_amount("max_transaction_amount", payload.max_transaction_amount.as_ref())?;
+    check_positive_amount("max_daily_debit_total", payload.max_daily_debit_total.as_ref())?;
+    if payload.max_transactions_per_hour.is_some_and(|max| max <= 0) {
+        return Err(AppError::BadRequest("`max_transactions_per_hour` must be greater than zero".to_string()));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+
+    users::lock(&mut *tx, user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to lock user: {}", e);
+            db_error(&e, "Failed to update velocity limits")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+    let before = sqlx::query_as!(
+        VelocityLimits,
+        r#"
+        SELECT user_id, currency, max_transaction_amount, max_daily_debit_total, max_transactions_per_hour, updated_by,
+            created_at, updated_at
+        FROM velocity_limits
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch velocity limits: {}", e);
+        db_error(&e, "Failed to update velocity limits")
+    })?;
+
+    let limits = sqlx::query_as!(
+        VelocityLimits,
+        r#"
+        INSERT INTO velocity_limits (user_id, currency, max_transaction_amount, max_daily_debit_total, max_transactions_per_hour, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id) DO UPDATE
+        SET currency = EXCLUDED.currency,
+            max_transaction_amount = EXCLUDED.max_transaction_amount,
+            max_daily_debit_total = EXCLUDED.max_daily_debit_total,
+            max_transactions_per_hour = EXCLUDED.max_transactions_per_hour,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING user_id, currency, max_transaction_amount, max_daily_debit_total, max_transactions_per_hour, updated_by,
+            created_at, updated_at
+        "#,
+        user_id,
+        currency.as_str(),
+        payload.max_transaction_amount,
+        payload.max_daily_debit_total,
+        payload.max_transactions_per_hour,
+        auth.user_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to update velocity limits: {}", e);
+        db_error(&e, "Failed to update velocity limits")
+    })?;
+
+    let mut record = AuditRecord::new(AuditAction::VelocityLimitsChanged, auth.user_id, Some(user_id))
+        .target(user_id)
+        .after(&limits);
+    if let Some(before) = &before {
+        record = record.before(before);
+    }
+    audit::record(&mut *tx, record).await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    info!(target: "audit", "Velocity limits of user {} changed by admin {}", user_id, auth.user_id);
+    Ok(Json(limits))
+}
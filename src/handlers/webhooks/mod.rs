@@ -0,0 +1,241 @@
+pub mod stripe;
+
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{MatchedPath, State},
+    http::HeaderMap,
+    Json,
+};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+
+// Inbound webhooks are posted to `/v1/webhooks/<provider>`
+const PATH_PREFIX: &str = "/v1/webhooks/";
+
+// A delivery that passed verification, identified by the provider's own event id
+#[derive(Debug)]
+pub struct InboundEvent {
+    pub id: String,
+    pub event_type: String,
+    pub payload: Value,
+}
+
+// What one provider's webhooks need beyond the shared receiving: proving a delivery came from the
+// provider, naming the event it carries and applying it.
+#[async_trait]
+pub trait WebhookProcessor: Send + Sync {
+    // Checks the delivery's signature against the body exactly as sent, before anything parses it.
+    // Signatures with a timestamp should also refuse stale deliveries.
+    fn verify(&self, headers: &HeaderMap, body: &[u8], now: OffsetDateTime) -> Result<(), String>;
+    fn parse(&self, payload: Value) -> Result<InboundEvent, String>;
+    // Runs in the DB transaction that records the event as processed, so it commits or rolls back
+    // together with that record
+    async fn process(&self, conn: &mut PgConnection, event: &InboundEvent) -> Result<(), sqlx::Error>;
+}
+
+// The configured providers by name; a provider without credentials isn't registered
+#[derive(Default)]
+pub struct WebhookProcessors {
+    processors: HashMap<&'static str, Arc<dyn WebhookProcessor>>,
+}
+
+impl WebhookProcessors {
+    pub fn from_config(config: &Config) -> Self {
+        let mut processors = Self::default();
+        if let Some(secret) = config.stripe_webhook_secret.as_deref() {
+            processors.register(stripe::PROVIDER, Arc::new(stripe::StripeWebhooks::new(secret)));
+        }
+        processors
+    }
+
+    fn register(&mut self, provider: &'static str, processor: Arc<dyn WebhookProcessor>) {
+        self.processors.insert(provider, processor);
+    }
+
+    fn get(&self, provider: &str) -> Option<Arc<dyn WebhookProcessor>> {
+        self.processors.get(provider).cloned()
+    }
+}
+
+// Receives a provider's webhook deliveries, which are authenticated by signature rather than by
+// credential. The body is taken raw and only parsed once verified. Each event is processed at most
+// once: redeliveries are acknowledged without effect, and a failed attempt leaves nothing behind
+// for the provider's retry to trip over.
+pub async fn receive_webhook(
+    State(pool): State<PgPool>,
+    State(processors): State<Arc<WebhookProcessors>>,
+    path: MatchedPath,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Value>, AppError> {
+    let provider = path.as_str().trim_start_matches(PATH_PREFIX);
+    let Some(processor) = processors.get(provider) else {
+        error!("Received a {} webhook, but {} isn't configured", provider, provider);
+        return Err(AppError::Unavailable);
+    };
+
+    processor.verify(&headers, &body, OffsetDateTime::now_utc()).map_err(|e| {
+        error!("Refused a {} webhook: {}", provider, e);
+        AppError::BadRequest("Invalid webhook signature".to_string())
+    })?;
+    let payload: Value = serde_json::from_slice(&body)
+        .map_err(|e| AppError::BadRequest(format!("Invalid webhook body: {}", e)))?;
+    let event = processor.parse(payload).map_err(|e| AppError::BadRequest(format!("Invalid webhook event: {}", e)))?;
+    info!("Received {} event {} of type {}", provider, event.id, event.event_type);
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Failed to begin transaction: {}", e);
+        db_error(&e, "Failed to process webhook")
+    })?;
+
+    let first_delivery = sqlx::query_scalar!(
+        r#"
+        INSERT INTO inbound_webhook_events (provider, event_id, event_type)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (provider, event_id) DO NOTHING
+        RETURNING event_id
+        "#,
+        provider,
+        event.id,
+        event.event_type
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        error!("Failed to record {} event {}: {}", provider, event.id, e);
+        db_error(&e, "Failed to process webhook")
+    })?
+    .is_some();
+    if !first_delivery {
+        info!("{} event {} was already processed", provider, event.id);
+        return Ok(Json(json!({ "received": true })));
+    }
+
+    processor.process(&mut tx, &event).await.map_err(|e| {
+        error!("Failed to process {} event {}: {}", provider, event.id, e);
+        db_error(&e, "Failed to process webhook")
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to process webhook")
+    })?;
+
+    Ok(Json(json!({ "received": true })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    use crate::blob_store::NoBlobStore;
+    use crate::cache::NoCache;
+    use crate::state::AppState;
+    use crate::test_support::test_config;
+
+    const SIGNATURE_HEADER: &str = "x-test-signature";
+
+    // Accepts deliveries signed `valid`, counting the events it processes. `fail_next` makes the
+    // next one fail after it was counted.
+    #[derive(Default)]
+    struct CountingWebhooks {
+        processed: AtomicUsize,
+        fail_next: AtomicBool,
+    }
+
+    #[async_trait]
+    impl WebhookProcessor for CountingWebhooks {
+        fn verify(&self, headers: &HeaderMap, _body: &[u8], _now: OffsetDateTime) -> Result<(), String> {
+            match headers.get(SIGNATURE_HEADER).and_then(|value| value.to_str().ok()) {
+                Some("valid") => Ok(()),
+                _ => Err("bad signature".to_string()),
+            }
+        }
+
+        fn parse(&self, payload: Value) -> Result<InboundEvent, String> {
+            let id = payload["id"].as_str().ok_or("missing id")?.to_string();
+            Ok(InboundEvent { id, event_type: "test.event".to_string(), payload })
+        }
+
+        async fn process(&self, _conn: &mut PgConnection, _event: &InboundEvent) -> Result<(), sqlx::Error> {
+            self.processed.fetch_add(1, Ordering::SeqCst);
+            if self.fail_next.swap(false, Ordering::SeqCst) {
+                return Err(sqlx::Error::Protocol("processing failed".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    fn app(pool: PgPool, webhooks: Arc<CountingWebhooks>) -> Router {
+        let mut processors = WebhookProcessors::default();
+        processors.register("test", webhooks);
+        let mut state = AppState::new(pool, Arc::new(test_config()), Arc::new(NoCache), Arc::new(NoBlobStore));
+        state.webhooks = Arc::new(processors);
+        Router::new().route("/v1/webhooks/test", post(receive_webhook)).with_state(state)
+    }
+
+    async fn deliver(app: &Router, signature: &str, event_id: &str) -> StatusCode {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/webhooks/test")
+            .header(SIGNATURE_HEADER, signature)
+            .body(Body::from(json!({ "id": event_id }).to_string()))
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    async fn recorded(pool: &PgPool, event_id: &str) -> i64 {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM inbound_webhook_events WHERE provider = 'test' AND event_id = $1"#,
+            event_id
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_deliveries_with_a_bad_signature_are_refused(pool: PgPool) {
+        let webhooks = Arc::new(CountingWebhooks::default());
+        let app = app(pool.clone(), webhooks.clone());
+
+        assert_eq!(deliver(&app, "forged", "evt_forged").await, StatusCode::BAD_REQUEST);
+        assert_eq!(webhooks.processed.load(Ordering::SeqCst), 0);
+        assert_eq!(recorded(&pool, "evt_forged").await, 0);
+    }
+
+    #[sqlx::test]
+    async fn test_redelivered_events_are_recorded_and_processed_once(pool: PgPool) {
+        let webhooks = Arc::new(CountingWebhooks::default());
+        let app = app(pool.clone(), webhooks.clone());
+
+        for _ in 0..2 {
+            assert_eq!(deliver(&app, "valid", "evt_1").await, StatusCode::OK);
+        }
+        assert_eq!(webhooks.processed.load(Ordering::SeqCst), 1);
+        assert_eq!(recorded(&pool, "evt_1").await, 1);
+
+        // A failed attempt isn't recorded, so the provider's retry processes the event
+        webhooks.fail_next.store(true, Ordering::SeqCst);
+        assert_eq!(deliver(&app, "valid", "evt_2").await, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(recorded(&pool, "evt_2").await, 0);
+        assert_eq!(deliver(&app, "valid", "evt_2").await, StatusCode::OK);
+        assert_eq!(webhooks.processed.load(Ordering::SeqCst), 3);
+        assert_eq!(recorded(&pool, "evt_2").await, 1);
+    }
+}
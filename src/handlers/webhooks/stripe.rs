@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use serde_json::Value;
+use sqlx::PgConnection;
+use time::OffsetDateTime;
+use tracing::error;
+
+use crate::handlers::webhooks::{InboundEvent, WebhookProcessor};
+use crate::payments::stripe::{self, Event, SIGNATURE_HEADER};
+
+pub const PROVIDER: &str = "stripe";
+
+// Stripe's deliveries, which report the outcome of card deposits
+pub struct StripeWebhooks {
+    secret: String,
+}
+
+impl StripeWebhooks {
+    pub fn new(secret: &str) -> Self {
+        Self { secret: secret.to_string() }
+    }
+}
+
+#[async_trait]
+impl WebhookProcessor for StripeWebhooks {
+    fn verify(&self, headers: &HeaderMap, body: &[u8], now: OffsetDateTime) -> Result<(), String> {
+        let signature = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or("missing Stripe-Signature header")?;
+        stripe::verify_signature(signature, body, &self.secret, now).map_err(|e| e.to_string())
+    }
+
+    fn parse(&self, payload: Value) -> Result<InboundEvent, String> {
+        let id = payload["id"].as_str().ok_or("missing id")?.to_string();
+        let event_type = payload["type"].as_str().ok_or("missing type")?.to_string();
+        Ok(InboundEvent { id, event_type, payload })
+    }
+
+    async fn process(&self, conn: &mut PgConnection, event: &InboundEvent) -> Result<(), sqlx::Error> {
+        match serde_json::from_value::<Event>(event.payload.clone()) {
+            Ok(event) => stripe::apply_event(conn, &event).await,
+            Err(e) => {
+                error!("Stripe event {} is unreadable: {}", event.id, e);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::handlers::statement::store_statement;
+use crate::mailer::{EmailTemplate, Mailer};
+use crate::models::user::UserStatus;
+use crate::repositories::user as users;
+use crate::shutdown;
+
+// Work that runs outside a request. A job is queued in the caller's DB transaction, so it only runs
+// if that commits, and is picked up by whichever worker claims it first. Jobs that fail are retried
+// with exponential backoff and marked dead once they run out of attempts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    // Renders and stores a user's statement for a month that has ended
+    GenerateStatement { user_id: Uuid, year: i32, month: u8 },
+    // Emails the user, unless their account has been closed since
+    SendEmail { user_id: Uuid, email: EmailTemplate },
+}
+
+// What jobs need to do their work
+#[derive(Clone)]
+pub struct JobContext {
+    pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub mailer: Arc<dyn Mailer>,
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::GenerateStatement { .. } => "generate_statement",
+            Job::SendEmail { .. } => "send_email",
+        }
+    }
+
+    // Jobs sharing a kind and key are queued at most once, whatever their status
+    fn unique_key(&self) -> Option<String> {
+        match self {
+            Job::GenerateStatement { user_id, year, month } => Some(format!("{}:{}-{:02}", user_id, year, month)),
+            // Each statement is announced once
+            Job::SendEmail { user_id, email: EmailTemplate::StatementReady { year, month } } => {
+                Some(format!("statement_ready:{}:{}-{:02}", user_id, year, month))
+            }
+            Job::SendEmail { .. } => None,
+        }
+    }
+
+    async fn run(&self, context: &JobContext) -> Result<(), String> {
+        match self {
+            Job::GenerateStatement { user_id, year, month } => {
+                let stored = store_statement(&context.pool, *user_id, *year, *month)
+                    .await
+                    .map_err(|e| format!("Failed to generate statement: {}", e))?;
+                if stored.is_some() {
+                    let email = EmailTemplate::StatementReady { year: *year, month: *month };
+                    enqueue(&context.pool, &context.config, &Job::SendEmail { user_id: *user_id, email })
+                        .await
+                        .map_err(|e| format!("Failed to queue statement email: {}", e))?;
+                }
+                Ok(())
+            }
+            Job::SendEmail { user_id, email } => {
+                let user = users::find_by_id(&context.pool, *user_id)
+                    .await
+                    .map_err(|e| format!("Failed to load recipient: {}", e))?;
+                let Some(user) = user.filter(|user| user.status == UserStatus::Active) else {
+                    info!("Not emailing user {}, whose account is closed", user_id);
+                    return Ok(());
+                };
+                // Verification goes to the address being confirmed, while the change is still pending
+                let to = match email {
+                    EmailTemplate::Verification { .. } => {
+                        let pending = sqlx::query_scalar!(
+                            "SELECT new_email FROM email_changes WHERE user_id = $1 AND expires_at > NOW()",
+                            user_id
+                        )
+                        .fetch_optional(&context.pool)
+                        .await
+                        .map_err(|e| format!("Failed to load pending email change: {}", e))?;
+                        let Some(pending) = pending else {
+                            info!("Not sending verification to user {}, whose email change is no longer pending", user_id);
+                            return Ok(());
+                        };
+                        pending
+                    }
+                    _ => user.email,
+                };
+                context
+                    .mailer
+                    .send(&email.render(&to, &user.name))
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+// Each retry waits twice as long as the one before, starting from the configured delay
+fn retry_delay(config: &Config, attempts: i32) -> time::Duration {
+    time::Duration::seconds(config.job_retry_base_seconds) * 2i32.pow((attempts - 1).clamp(0, 16) as u32)
+}
+
+// Queues the job to run as soon as a worker is free, with the configured number of attempts.
+// Returns false if a job with the same unique key was queued before.
+pub async fn enqueue(executor: impl PgExecutor<'_>, config: &Config, job: &Job) -> Result<bool, sqlx::Error> {
+    let payload = serde_json::to_value(job).expect("jobs serialize to JSON");
+    let queued = sqlx::query!(
+        r#"
+        INSERT INTO jobs (kind, payload, unique_key, max_attempts)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (kind, unique_key) DO NOTHING
+        "#,
+        job.kind(),
+        payload,
+        job.unique_key(),
+        config.job_max_attempts
+    )
+    .execute(executor)
+    .await?
+    .rows_affected();
+    Ok(queued > 0)
+}
+
+// Runs due jobs one at a time until none are left or the worker is told to stop. The row stays
+// locked while the job runs so other workers skip it.
+pub async fn run_due_jobs(context: &JobContext, stop: &watch::Receiver<bool>) -> Result<usize, sqlx::Error> {
+    let mut ran = 0;
+
+    while !*stop.borrow() {
+        let mut tx = context.pool.begin().await?;
+
+        let Some(claimed) = sqlx::query!(
+            r#"
+            SELECT id, kind, payload, attempts, max_attempts
+            FROM jobs
+            WHERE status = 'queued' AND run_at <= NOW()
+            ORDER BY run_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            break;
+        };
+
+        let attempts = claimed.attempts + 1;
+        let outcome = match serde_json::from_value::<Job>(claimed.payload) {
+            Ok(job) => job.run(context).await,
+            // Left over from a kind that no longer exists; retrying won't help
+            Err(e) => Err(format!("Unknown {} job: {}", claimed.kind, e)),
+        };
+
+        match outcome {
+            Ok(()) => {
+                sqlx::query!(
+                    "UPDATE jobs SET status = 'completed', attempts = $2, last_error = NULL, finished_at = NOW() WHERE id = $1",
+                    claimed.id,
+                    attempts
+                )
+                .execute(&mut *tx)
+                .await?;
+                info!("Completed {} job {}", claimed.kind, claimed.id);
+            }
+            Err(failure) if attempts >= claimed.max_attempts => {
+                error!("{} job {} failed on its last attempt and is dead: {}", claimed.kind, claimed.id, failure);
+                sqlx::query!(
+                    "UPDATE jobs SET status = 'dead', attempts = $2, last_error = $3, finished_at = NOW() WHERE id = $1",
+                    claimed.id,
+                    attempts,
+                    failure
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            Err(failure) => {
+                error!("{} job {} failed (attempt {}): {}", claimed.kind, claimed.id, attempts, failure);
+                sqlx::query!(
+                    "UPDATE jobs SET attempts = $2, last_error = $3, run_at = $4 WHERE id = $1",
+                    claimed.id,
+                    attempts,
+                    failure,
+                    OffsetDateTime::now_utc() + retry_delay(&context.config, attempts)
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+        ran += 1;
+    }
+
+    Ok(ran)
+}
+
+// Background task that runs due jobs. On shutdown the job in progress finishes and no new one starts.
+pub async fn run_worker(context: JobContext, period: Duration, mut stop: watch::Receiver<bool>) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        if let Err(e) = run_due_jobs(&context, &stop).await {
+            error!("Job worker failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailer::NoopMailer;
+
+    fn context(pool: &PgPool) -> JobContext {
+        JobContext { pool: pool.clone(), config: Arc::new(Config::default()), mailer: Arc::new(NoopMailer) }
+    }
+
+    #[test]
+    fn test_jobs_round_trip_through_their_payload() {
+        let job = Job::GenerateStatement { user_id: Uuid::new_v4(), year: 2024, month: 3 };
+        let payload = serde_json::to_value(&job).unwrap();
+        assert_eq!(payload["kind"], job.kind());
+        assert_eq!(serde_json::from_value::<Job>(payload).unwrap(), job);
+    }
+
+    #[sqlx::test]
+    async fn test_failing_jobs_back_off_then_die(pool: PgPool) {
+        // Queue a kind that no longer exists to force failures
+        sqlx::query!("INSERT INTO jobs (kind, payload, max_attempts) VALUES ('retired', '{\"kind\": \"retired\"}', 2)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (_stop, stopped) = watch::channel(false);
+
+        assert_eq!(run_due_jobs(&context(&pool), &stopped).await.unwrap(), 1);
+        let job = sqlx::query!(r#"SELECT status::text as "status!", attempts, run_at > NOW() as "backed_off!" FROM jobs"#)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((job.status.as_str(), job.attempts, job.backed_off), ("queued", 1, true));
+
+        // Not due yet
+        assert_eq!(run_due_jobs(&context(&pool), &stopped).await.unwrap(), 0);
+
+        sqlx::query!("UPDATE jobs SET run_at = NOW()").execute(&pool).await.unwrap();
+        assert_eq!(run_due_jobs(&context(&pool), &stopped).await.unwrap(), 1);
+        let job = sqlx::query!(r#"SELECT status::text as "status!", attempts, last_error FROM jobs"#)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((job.status.as_str(), job.attempts), ("dead", 2));
+        assert!(job.last_error.unwrap().starts_with("Unknown retired job"));
+    }
+
+    #[sqlx::test]
+    async fn test_unique_jobs_are_queued_once(pool: PgPool) {
+        let user_id = Uuid::new_v4();
+        let job = Job::GenerateStatement { user_id, year: 2024, month: 3 };
+        let config = Config::default();
+        assert!(enqueue(&pool, &config, &job).await.unwrap());
+        assert!(!enqueue(&pool, &config, &job).await.unwrap());
+        assert!(enqueue(&pool, &config, &Job::GenerateStatement { user_id, year: 2024, month: 4 }).await.unwrap());
+
+        // A stopped worker leaves them queued
+        let (stop, stopped) = watch::channel(false);
+        stop.send(true).unwrap();
+        assert_eq!(run_due_jobs(&context(&pool), &stopped).await.unwrap(), 0);
+
+        let (_stop, stopped) = watch::channel(false);
+        assert_eq!(run_due_jobs(&context(&pool), &stopped).await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,134 @@
+use bigdecimal::BigDecimal;
+use sqlx::PgConnection;
+use time::OffsetDateTime;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::fx;
+use crate::models::kyc::{KycLimits, KycStatus, KycVerification};
+use crate::models::money::Money;
+use crate::models::transaction::TransactionType;
+use crate::repositories::transaction as transactions;
+use crate::repositories::user as users;
+
+// The user's verification, unverified with nothing submitted if they never started one; `None` if
+// the user doesn't exist
+pub async fn verification(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<KycVerification>, sqlx::Error> {
+    sqlx::query_as!(
+        KycVerification,
+        r#"
+        SELECT u.id as user_id, COALESCE(k.status, 'unverified') as "status!: KycStatus", k.submitted_at as "submitted_at?",
+            k.reviewed_by as "reviewed_by?", k.reviewed_at as "reviewed_at?", k.rejection_reason as "rejection_reason?",
+            COALESCE(k.updated_at, u.created_at) as "updated_at!"
+        FROM users u
+        LEFT JOIN kyc_verifications k ON k.user_id = u.id
+        WHERE u.id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await
+}
+
+// The limits on users with `status`, if an admin has set any
+pub async fn limits_for(conn: &mut PgConnection, status: KycStatus) -> Result<Option<KycLimits>, sqlx::Error> {
+    sqlx::query_as!(
+        KycLimits,
+        r#"
+        SELECT status as "status: _", currency, max_transaction_amount, max_daily_debit_total, updated_by, updated_at
+        FROM kyc_limits
+        WHERE status = $1
+        "#,
+        status as _
+    )
+    .fetch_optional(conn)
+    .await
+}
+
+// Why a transaction of `amount` breaks `limits`, given the debits of the last 24 hours; both are in
+// the limits' currency
+pub fn breached(limits: &KycLimits, amount: &BigDecimal, is_debit: bool, daily_debit_total: &BigDecimal) -> Option<String> {
+    if let Some(max) = limits.max_transaction_amount.as_ref().filter(|max| amount > *max) {
+        return Some(format!("Transactions are limited to {} {} until your identity is verified", max, limits.currency));
+    }
+    match limits.max_daily_debit_total.as_ref() {
+        Some(max) if is_debit && daily_debit_total + amount > *max => Some(format!(
+            "Debits are limited to {} {} in 24 hours until your identity is verified",
+            max, limits.currency
+        )),
+        _ => None,
+    }
+}
+
+// Checks a new live transaction, or the outgoing leg of a transfer, against the limits of the user's
+// KYC status, if there are any, converting it and the user's debits in other currencies into the
+// limits' currency. Like velocity limits, locks the user's row for the rest of the DB
+// transaction so concurrent requests can't both fit under a limit.
+pub async fn enforce_limits(conn: &mut PgConnection, user_id: Uuid, amount: &Money, transaction_type: TransactionType) -> Result<(), AppError> {
+    let status = verification(&mut *conn, user_id).await
+        .map_err(|e| {
+            error!("Failed to fetch KYC status: {}", e);
+            db_error(&e, "Failed to create transaction")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?
+        .status;
+    let limits = limits_for(&mut *conn, status).await.map_err(|e| {
+        error!("Failed to fetch KYC limits: {}", e);
+        db_error(&e, "Failed to create transaction")
+    })?;
+    let Some(limits) = limits else {
+        return Ok(());
+    };
+
+    users::lock(&mut *conn, user_id).await.map_err(|e| {
+        error!("Failed to lock user: {}", e);
+        db_error(&e, "Failed to create transaction")
+    })?;
+    let totals = transactions::daily_debit_totals(&mut *conn, user_id, OffsetDateTime::now_utc())
+        .await
+        .map_err(|e| {
+            error!("Failed to compute KYC limit usage: {}", e);
+            db_error(&e, "Failed to create transaction")
+        })?;
+    let used: Vec<_> = totals.iter().map(|row| (row.currency.as_str(), &row.total)).collect();
+    let (requested, daily_debit_total) = fx::limit_values(&mut *conn, &limits.currency, amount, &used).await?;
+
+    let is_debit = transaction_type == TransactionType::Debit;
+    match breached(&limits, &requested, is_debit, &daily_debit_total) {
+        Some(message) => {
+            info!("Transaction for user {} refused by the limits on {:?} users", user_id, status);
+            Err(AppError::KycLimitExceeded(message))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_cap_single_amounts_and_daily_debits() {
+        let limits = KycLimits {
+            status: KycStatus::Unverified,
+            currency: "USD".to_string(),
+            max_transaction_amount: Some(BigDecimal::from(500)),
+            max_daily_debit_total: Some(BigDecimal::from(1000)),
+            updated_by: Uuid::new_v4(),
+            updated_at: OffsetDateTime::now_utc(),
+        };
+        let amount = |value: i32| BigDecimal::from(value);
+
+        assert_eq!(breached(&limits, &amount(500), true, &amount(500)), None);
+        assert!(breached(&limits, &amount(501), false, &amount(0)).is_some());
+        assert!(breached(&limits, &amount(100), true, &amount(950)).is_some());
+        // Credits don't count towards the daily debit total
+        assert_eq!(breached(&limits, &amount(100), false, &amount(950)), None);
+        assert_eq!(
+            breached(&limits, &amount(100), true, &amount(950)).unwrap(),
+            "Debits are limited to 1000 USD in 24 hours until your identity is verified"
+        );
+    }
+}
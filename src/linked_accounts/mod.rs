@@ -0,0 +1,209 @@
+pub mod plaid;
+pub mod sealing;
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use std::fmt;
+use std::sync::Arc;
+use time::Date;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::outbound::OutboundClient;
+
+#[derive(Debug)]
+pub enum LinkError {
+    // No bank data provider is configured, so accounts can't be linked
+    NotConfigured,
+    // The provider refused a public token that is expired, already exchanged or made up
+    InvalidToken,
+    // The provider failed or refused the request
+    Provider(String),
+    // An access token couldn't be encrypted for storing or decrypted after
+    Sealing(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::NotConfigured => write!(f, "no bank data provider is configured"),
+            LinkError::InvalidToken => write!(f, "the public token is invalid or expired"),
+            LinkError::Provider(message) => write!(f, "bank data provider request failed: {}", message),
+            LinkError::Sealing(message) => write!(f, "access token sealing failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+// Short-lived token the client opens the provider's Link flow with
+#[derive(Debug, Clone)]
+pub struct LinkToken {
+    pub link_token: String,
+    pub expiration: String,
+}
+
+// Access to one login at one bank, which can hold several accounts
+#[derive(Debug, Clone)]
+pub struct LinkedItem {
+    pub item_id: String,
+    pub access_token: String,
+}
+
+// An account at the bank with its balances as of the call
+#[derive(Debug, Clone)]
+pub struct ExternalAccount {
+    pub id: String,
+    pub name: String,
+    pub mask: Option<String>,
+    pub account_type: String,
+    pub subtype: Option<String>,
+    pub currency: Option<String>,
+    pub available_balance: Option<BigDecimal>,
+    pub current_balance: Option<BigDecimal>,
+}
+
+// A transaction on an account at the bank. Positive amounts are money leaving the account.
+#[derive(Debug, Clone)]
+pub struct ExternalTransaction {
+    pub id: String,
+    pub account_id: String,
+    pub amount: BigDecimal,
+    pub currency: Option<String>,
+    pub description: String,
+    pub merchant_name: Option<String>,
+    pub posted_on: Date,
+    pub pending: bool,
+}
+
+// The changes to an item's transactions since `cursor`
+#[derive(Debug, Clone, Default)]
+pub struct TransactionChanges {
+    pub added: Vec<ExternalTransaction>,
+    pub modified: Vec<ExternalTransaction>,
+    pub removed: Vec<String>,
+    pub next_cursor: String,
+    pub has_more: bool,
+}
+
+// Links users' bank accounts and reads their balances and transactions
+#[async_trait]
+pub trait BankDataProvider: Send + Sync {
+    async fn create_link_token(&self, user_id: Uuid) -> Result<LinkToken, LinkError>;
+    // Trades the public token the Link flow hands the client for lasting access to the item
+    async fn exchange_public_token(&self, public_token: &str) -> Result<LinkedItem, LinkError>;
+    async fn get_accounts(&self, access_token: &str) -> Result<Vec<ExternalAccount>, LinkError>;
+    // One page of changes; `cursor` is `None` on the first sync of an item
+    async fn sync_transactions(&self, access_token: &str, cursor: Option<&str>) -> Result<TransactionChanges, LinkError>;
+    // Revokes the access token so the provider stops pulling the item's data
+    async fn remove_item(&self, access_token: &str) -> Result<(), LinkError>;
+}
+
+// Used when Plaid isn't configured: linking is refused
+pub struct NoBankDataProvider;
+
+#[async_trait]
+impl BankDataProvider for NoBankDataProvider {
+    async fn create_link_token(&self, _user_id: Uuid) -> Result<LinkToken, LinkError> {
+        Err(LinkError::NotConfigured)
+    }
+
+    async fn exchange_public_token(&self, _public_token: &str) -> Result<LinkedItem, LinkError> {
+        Err(LinkError::NotConfigured)
+    }
+
+    async fn get_accounts(&self, _access_token: &str) -> Result<Vec<ExternalAccount>, LinkError> {
+        Err(LinkError::NotConfigured)
+    }
+
+    async fn sync_transactions(&self, _access_token: &str, _cursor: Option<&str>) -> Result<TransactionChanges, LinkError> {
+        Err(LinkError::NotConfigured)
+    }
+
+    async fn remove_item(&self, _access_token: &str) -> Result<(), LinkError> {
+        Err(LinkError::NotConfigured)
+    }
+}
+
+// A bank with one checking account, for tests that link accounts. Public tokens starting with
+// `public-` are accepted; the first sync returns two transactions and later syncs replace the
+// pending one with its posted version.
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeBankDataProvider {
+    pub removed: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl FakeBankDataProvider {
+    fn transaction(id: &str, amount: &str, pending: bool) -> ExternalTransaction {
+        ExternalTransaction {
+            id: id.to_string(),
+            account_id: "acc_checking".to_string(),
+            amount: amount.parse().unwrap(),
+            currency: Some("USD".to_string()),
+            description: format!("Card payment {}", id),
+            merchant_name: None,
+            posted_on: time::macros::date!(2024 - 05 - 01),
+            pending,
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl BankDataProvider for FakeBankDataProvider {
+    async fn create_link_token(&self, user_id: Uuid) -> Result<LinkToken, LinkError> {
+        Ok(LinkToken { link_token: format!("link-sandbox-{}", user_id.simple()), expiration: "2024-05-01T04:00:00Z".to_string() })
+    }
+
+    async fn exchange_public_token(&self, public_token: &str) -> Result<LinkedItem, LinkError> {
+        let Some(item) = public_token.strip_prefix("public-") else {
+            return Err(LinkError::InvalidToken);
+        };
+        Ok(LinkedItem { item_id: format!("item_{}", item), access_token: format!("access-{}", item) })
+    }
+
+    async fn get_accounts(&self, _access_token: &str) -> Result<Vec<ExternalAccount>, LinkError> {
+        Ok(vec![ExternalAccount {
+            id: "acc_checking".to_string(),
+            name: "Plaid Checking".to_string(),
+            mask: Some("0000".to_string()),
+            account_type: "depository".to_string(),
+            subtype: Some("checking".to_string()),
+            currency: Some("USD".to_string()),
+            available_balance: Some("100".parse().unwrap()),
+            current_balance: Some("110".parse().unwrap()),
+        }])
+    }
+
+    async fn sync_transactions(&self, _access_token: &str, cursor: Option<&str>) -> Result<TransactionChanges, LinkError> {
+        match cursor {
+            None => Ok(TransactionChanges {
+                added: vec![Self::transaction("txn_coffee", "4.50", false), Self::transaction("txn_pending", "20", true)],
+                next_cursor: "cursor_1".to_string(),
+                ..TransactionChanges::default()
+            }),
+            Some("cursor_1") => Ok(TransactionChanges {
+                added: vec![Self::transaction("txn_posted", "20", false)],
+                removed: vec!["txn_pending".to_string()],
+                next_cursor: "cursor_2".to_string(),
+                ..TransactionChanges::default()
+            }),
+            Some(cursor) => Ok(TransactionChanges { next_cursor: cursor.to_string(), ..TransactionChanges::default() }),
+        }
+    }
+
+    async fn remove_item(&self, access_token: &str) -> Result<(), LinkError> {
+        self.removed.lock().unwrap().push(access_token.to_string());
+        Ok(())
+    }
+}
+
+// Plaid when `PLAID_CLIENT_ID` is set, otherwise `NoBankDataProvider`
+pub fn from_config(config: &Config, client: Arc<OutboundClient>) -> Arc<dyn BankDataProvider> {
+    match (config.plaid_client_id.as_deref(), config.plaid_secret.as_deref()) {
+        (Some(client_id), Some(secret)) => Arc::new(plaid::PlaidClient::new(client, &config.plaid_env, client_id, secret)),
+        _ => Arc::new(NoBankDataProvider),
+    }
+}
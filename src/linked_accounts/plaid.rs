@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Number, Value};
+use std::str::FromStr;
+use std::sync::Arc;
+use time::macros::format_description;
+use time::Date;
+use uuid::Uuid;
+
+use crate::linked_accounts::{
+    BankDataProvider, ExternalAccount, ExternalTransaction, LinkError, LinkToken, LinkedItem, TransactionChanges,
+};
+use crate::outbound::OutboundClient;
+
+// Most transactions a sync returns per page; Plaid allows up to 500
+const SYNC_PAGE_SIZE: u32 = 500;
+
+// Calls the Plaid API with the app's client id and secret
+pub struct PlaidClient {
+    client: Arc<OutboundClient>,
+    base_url: String,
+    client_id: String,
+    secret: String,
+}
+
+impl PlaidClient {
+    // `environment` is `sandbox` or `production`, as checked by config validation
+    pub fn new(client: Arc<OutboundClient>, environment: &str, client_id: &str, secret: &str) -> Self {
+        Self {
+            client,
+            base_url: format!("https://{}.plaid.com", environment),
+            client_id: client_id.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, path: &str, body: Value) -> Result<T, LinkError> {
+        let request = self
+            .client
+            .client()
+            .post(format!("{}{}", self.base_url, path))
+            .header("PLAID-CLIENT-ID", &self.client_id)
+            .header("PLAID-SECRET", &self.secret)
+            .json(&body)
+            .build()
+            .map_err(|e| LinkError::Provider(e.to_string()))?;
+
+        let response = self.client.execute(request).await.map_err(|e| LinkError::Provider(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let detail: PlaidError = response.json().await.unwrap_or_default();
+            if detail.error_code == "INVALID_PUBLIC_TOKEN" {
+                return Err(LinkError::InvalidToken);
+            }
+            return Err(LinkError::Provider(format!(
+                "Plaid returned {} on {}: {} {}",
+                status, path, detail.error_code, detail.error_message
+            )));
+        }
+        response.json().await.map_err(|e| LinkError::Provider(e.to_string()))
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct PlaidError {
+    #[serde(default)]
+    error_code: String,
+    #[serde(default)]
+    error_message: String,
+}
+
+#[derive(Deserialize)]
+struct CreatedLinkToken {
+    link_token: String,
+    expiration: String,
+}
+
+#[derive(Deserialize)]
+struct ExchangedToken {
+    item_id: String,
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct Accounts {
+    accounts: Vec<Account>,
+}
+
+#[derive(Deserialize)]
+struct Account {
+    account_id: String,
+    name: String,
+    mask: Option<String>,
+    #[serde(rename = "type")]
+    account_type: String,
+    subtype: Option<String>,
+    balances: Balances,
+}
+
+#[derive(Deserialize)]
+struct Balances {
+    available: Option<Number>,
+    current: Option<Number>,
+    iso_currency_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Sync {
+    added: Vec<Transaction>,
+    modified: Vec<Transaction>,
+    removed: Vec<RemovedTransaction>,
+    next_cursor: String,
+    has_more: bool,
+}
+
+#[derive(Deserialize)]
+struct Transaction {
+    transaction_id: String,
+    account_id: String,
+    amount: Number,
+    iso_currency_code: Option<String>,
+    date: String,
+    name: String,
+    merchant_name: Option<String>,
+    pending: bool,
+}
+
+#[derive(Deserialize)]
+struct RemovedTransaction {
+    transaction_id: String,
+}
+
+#[derive(Deserialize)]
+struct Removed {}
+
+// Plaid sends amounts as JSON numbers; going through their text keeps `12.34` exact
+fn decimal(number: &Number) -> Result<BigDecimal, LinkError> {
+    BigDecimal::from_str(&number.to_string()).map_err(|e| LinkError::Provider(format!("Invalid amount {}: {}", number, e)))
+}
+
+impl TryFrom<Transaction> for ExternalTransaction {
+    type Error = LinkError;
+
+    fn try_from(transaction: Transaction) -> Result<Self, LinkError> {
+        let posted_on = Date::parse(&transaction.date, format_description!("[year]-[month]-[day]"))
+            .map_err(|e| LinkError::Provider(format!("Invalid date {}: {}", transaction.date, e)))?;
+        Ok(ExternalTransaction {
+            amount: decimal(&transaction.amount)?,
+            id: transaction.transaction_id,
+            account_id: transaction.account_id,
+            currency: transaction.iso_currency_code,
+            description: transaction.name,
+            merchant_name: transaction.merchant_name,
+            posted_on,
+            pending: transaction.pending,
+        })
+    }
+}
+
+#[async_trait]
+impl BankDataProvider for PlaidClient {
+    async fn create_link_token(&self, user_id: Uuid) -> Result<LinkToken, LinkError> {
+        let body = json!({
+            "client_name": "Dodo",
+            "user": { "client_user_id": user_id.to_string() },
+            "products": ["transactions"],
+            "country_codes": ["US"],
+            "language": "en",
+        });
+        let token: CreatedLinkToken = self.call("/link/token/create", body).await?;
+        Ok(LinkToken { link_token: token.link_token, expiration: token.expiration })
+    }
+
+    async fn exchange_public_token(&self, public_token: &str) -> Result<LinkedItem, LinkError> {
+        let exchanged: ExchangedToken = self.call("/item/public_token/exchange", json!({ "public_token": public_token })).await?;
+        Ok(LinkedItem { item_id: exchanged.item_id, access_token: exchanged.access_token })
+    }
+
+    // Balances are fetched live from the bank rather than from Plaid's cache
+    async fn get_accounts(&self, access_token: &str) -> Result<Vec<ExternalAccount>, LinkError> {
+        let response: Accounts = self.call("/accounts/balance/get", json!({ "access_token": access_token })).await?;
+        response
+            .accounts
+            .into_iter()
+            .map(|account| {
+                Ok(ExternalAccount {
+                    available_balance: account.balances.available.as_ref().map(decimal).transpose()?,
+                    current_balance: account.balances.current.as_ref().map(decimal).transpose()?,
+                    currency: account.balances.iso_currency_code,
+                    id: account.account_id,
+                    name: account.name,
+                    mask: account.mask,
+                    account_type: account.account_type,
+                    subtype: account.subtype,
+                })
+            })
+            .collect()
+    }
+
+    async fn sync_transactions(&self, access_token: &str, cursor: Option<&str>) -> Result<TransactionChanges, LinkError> {
+        let mut body = json!({ "access_token": access_token, "count": SYNC_PAGE_SIZE });
+        if let Some(cursor) = cursor {
+            body["cursor"] = json!(cursor);
+        }
+        let sync: Sync = self.call("/transactions/sync", body).await?;
+        Ok(TransactionChanges {
+            added: sync.added.into_iter().map(ExternalTransaction::try_from).collect::<Result<_, _>>()?,
+            modified: sync.modified.into_iter().map(ExternalTransaction::try_from).collect::<Result<_, _>>()?,
+            removed: sync.removed.into_iter().map(|removed| removed.transaction_id).collect(),
+            next_cursor: sync.next_cursor,
+            has_more: sync.has_more,
+        })
+    }
+
+    async fn remove_item(&self, access_token: &str) -> Result<(), LinkError> {
+        let _: Removed = self.call("/item/remove", json!({ "access_token": access_token })).await?;
+        Ok(())
+    }
+}
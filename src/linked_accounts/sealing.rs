@@ -0,0 +1,45 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use super::LinkError;
+use crate::config::Config;
+
+// Marks the storage format, so a future key or cipher can be told apart from this one
+const SEALED_PREFIX: &str = "v1:";
+const NONCE_LENGTH: usize = 12;
+
+fn cipher(config: &Config) -> Result<Aes256Gcm, LinkError> {
+    let key = config.plaid_token_key.as_deref().ok_or(LinkError::NotConfigured)?;
+    let key = hex::decode(key)
+        .ok()
+        .filter(|key| key.len() == 32)
+        .ok_or_else(|| LinkError::Sealing("PLAID_TOKEN_KEY is not a 256-bit hex key".to_string()))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+// Encrypts an access token with `PLAID_TOKEN_KEY` for storing; every call uses a fresh nonce
+pub fn seal_access_token(config: &Config, access_token: &str) -> Result<String, LinkError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let mut sealed = nonce.to_vec();
+    sealed.extend(
+        cipher(config)?
+            .encrypt(&nonce, access_token.as_bytes())
+            .map_err(|_| LinkError::Sealing("failed to encrypt the access token".to_string()))?,
+    );
+    Ok(format!("{}{}", SEALED_PREFIX, STANDARD.encode(sealed)))
+}
+
+// Decrypts a stored access token, refusing one that was tampered with or sealed with another key
+pub fn open_access_token(config: &Config, sealed: &str) -> Result<String, LinkError> {
+    let unreadable = || LinkError::Sealing("the stored access token can't be decrypted".to_string());
+    let sealed = sealed
+        .strip_prefix(SEALED_PREFIX)
+        .and_then(|sealed| STANDARD.decode(sealed).ok())
+        .filter(|sealed| sealed.len() > NONCE_LENGTH)
+        .ok_or_else(unreadable)?;
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    let access_token = cipher(config)?.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| unreadable())?;
+    String::from_utf8(access_token).map_err(|_| unreadable())
+}
@@ -1,129 +1,381 @@
-use axum::Router;
-use axum::routing::{get, post};
-use axum::middleware;
-use axum::http::{HeaderValue, StatusCode};
-use axum::extract::State;
 use tokio::net::TcpListener;
+use tokio::sync::watch;
 use sqlx::postgres::PgPoolOptions;
-use std::env;
-
-use tower_http::cors::CorsLayer;
-use tower_http::limit::RequestBodyLimitLayer;
-
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 mod models;
+mod aws;
+mod balance_cache;
+mod blob_store;
+mod cache;
 mod handlers;
+mod repositories;
+mod services;
+mod middleware;
+mod routes;
+mod email_policy;
+mod entitlements;
+mod export_quota;
+mod feature_flags;
+mod jobs;
+mod mailer;
+mod config;
+mod db;
+mod error;
+mod db_enums;
+mod event_versions;
+mod events;
+mod fx;
+mod kyc;
+mod outbound;
+mod payments;
+mod linked_accounts;
+mod outbox;
+mod pdf;
+mod screening;
+mod shutdown;
+mod state;
+mod telemetry;
+mod validation;
+#[cfg(test)]
+mod test_support;
 
-// Logging middleware
-async fn logging_middleware(
-    req: axum::http::Request<axum::body::Body>,
-    next: middleware::Next,
-) -> axum::http::Response<axum::body::Body> {
-    let method = req.method().clone();
-    let uri = req.uri().clone();
-    
-    tracing::info!("{} {}", method, uri);
-    
-    let response = next.run(req).await;
-    
-    let status = response.status();
-    if status.is_server_error() {
-        tracing::error!("{} {} - {}", method, uri, status);
-    } else {
-        tracing::info!("{} {} - {}", method, uri, status);
+use crate::state::AppState;
+use crate::middleware::latency::LatencyTracker;
+use crate::middleware::rate_limit::{IpRateLimiters, IpRateLimits};
+
+// Background task that posts due scheduled transactions, recurring transaction occurrences,
+// savings goal auto-transfers and interest, expires unanswered payment requests, and warns owners
+// of API keys about to expire
+async fn run_scheduler(pool: sqlx::PgPool, config: Arc<config::Config>, period: Duration, mut stop: watch::Receiver<bool>) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        match services::ledger::execute_due_transactions(&pool, &config).await {
+            Ok(0) => {}
+            Ok(executed) => tracing::info!("Executed {} scheduled transactions", executed),
+            Err(e) => tracing::error!("Scheduled transaction worker failed: {}", e),
+        }
+        match handlers::recurring::run_due_occurrences(&pool, &config).await {
+            Ok(0) => {}
+            Ok(posted) => tracing::info!("Posted {} recurring transaction occurrences", posted),
+            Err(e) => tracing::error!("Recurring transaction scheduler failed: {}", e),
+        }
+        match handlers::savings_goal::run_due_auto_transfers(&pool, &config).await {
+            Ok(0) => {}
+            Ok(ran) => tracing::info!("Ran {} savings goal auto-transfers", ran),
+            Err(e) => tracing::error!("Savings goal auto-transfer scheduler failed: {}", e),
+        }
+        match handlers::payment_request::expire_due_requests(&pool).await {
+            Ok(0) => {}
+            Ok(expired) => tracing::info!("Expired {} payment requests", expired),
+            Err(e) => tracing::error!("Payment request expiry failed: {}", e),
+        }
+        match handlers::interest::accrue_due_interest(&pool, time::OffsetDateTime::now_utc().date()).await {
+            Ok(0) => {}
+            Ok(accrued) => tracing::info!("Accrued interest for {} account periods", accrued),
+            Err(e) => tracing::error!("Interest accrual failed: {}", e),
+        }
+        match handlers::api_key::warn_expiring_keys(&pool).await {
+            Ok(0) => {}
+            Ok(warned) => tracing::info!("Warned owners of {} expiring API keys", warned),
+            Err(e) => tracing::error!("API key expiry warnings failed: {}", e),
+        }
+    }
+}
+
+// Background task that queues ended notification digests and sends due webhook deliveries, including retries
+async fn run_webhook_dispatcher(
+    pool: sqlx::PgPool,
+    config: Arc<config::Config>,
+    client: Arc<outbound::OutboundClient>,
+    period: Duration,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        match handlers::notification::flush_due_digests(&pool).await {
+            Ok(0) => {}
+            Ok(queued) => tracing::info!("Queued {} notification digests", queued),
+            Err(e) => tracing::error!("Notification digest flush failed: {}", e),
+        }
+        match handlers::webhook::deliver_due_webhooks(&pool, &config, &client).await {
+            Ok(0) => {}
+            Ok(attempted) => tracing::info!("Attempted {} webhook deliveries", attempted),
+            Err(e) => tracing::error!("Webhook dispatcher failed: {}", e),
+        }
+    }
+}
+
+// Background task that queues last month's statements once the month has ended
+async fn run_statement_generator(pool: sqlx::PgPool, config: Arc<config::Config>, period: Duration, mut stop: watch::Receiver<bool>) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        match handlers::statement::queue_due_statements(&pool, &config).await {
+            Ok(0) => {}
+            Ok(queued) => tracing::info!("Queued {} monthly statements", queued),
+            Err(e) => tracing::error!("Statement generator failed: {}", e),
+        }
+    }
+}
+
+// Background task that anonymizes users whose confirmed erasure requests are due
+async fn run_erasure_processor(
+    pool: sqlx::PgPool,
+    blobs: Arc<dyn blob_store::BlobStore>,
+    period: Duration,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        match handlers::erasure::erase_due_accounts(&pool, blobs.as_ref()).await {
+            Ok(0) => {}
+            Ok(erased) => tracing::info!("Erased personal data of {} users", erased),
+            Err(e) => tracing::error!("Erasure processor failed: {}", e),
+        }
+    }
+}
+
+// Background task that checks the materialized balances against the ledger and repairs any drift
+async fn run_balance_reconciler(pool: sqlx::PgPool, config: Arc<config::Config>, period: Duration, mut stop: watch::Receiver<bool>) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        match handlers::reconciliation::reconcile_balances(&pool, &config).await {
+            Ok(0) => {}
+            Ok(repaired) => tracing::error!("Repaired {} balances that had drifted from the ledger", repaired),
+            Err(e) => tracing::error!("Balance reconciliation failed: {}", e),
+        }
+    }
+}
+
+// Background task that records the latest FX rates from the configured provider
+async fn run_fx_rate_fetcher(
+    pool: sqlx::PgPool,
+    client: Arc<outbound::OutboundClient>,
+    source: fx::fetcher::FxRateSource,
+    period: Duration,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        match fx::fetcher::fetch_rates(&pool, &client, &source).await {
+            Ok(stored) => tracing::info!("Stored {} FX rates", stored),
+            Err(e) => tracing::error!("FX rate fetcher failed: {}", e),
+        }
     }
-    
-    response
 }
 
-// Health check handler
-async fn health_check(
-    State(pool): State<sqlx::PgPool>
-) -> Result<String, (StatusCode, String)> {
-    match sqlx::query("SELECT 1").execute(&pool).await {
-        Ok(_) => Ok("Database connection OK".to_string()),
-        Err(e) => {
-            tracing::error!("Database health check failed: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Database connection error: {}", e)))
-        },
+// Background task that deletes the attachments of voided transactions from the blob store
+async fn run_attachment_sweeper(
+    pool: sqlx::PgPool,
+    blobs: Arc<dyn blob_store::BlobStore>,
+    period: Duration,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        match handlers::attachment::purge_void_attachments(&pool, blobs.as_ref()).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("Deleted {} attachments of voided transactions", purged),
+            Err(e) => tracing::error!("Attachment sweeper failed: {}", e),
+        }
+    }
+}
+
+// Background task that forgets the rate limit buckets of idle client addresses
+async fn run_rate_limit_pruner(limiters: Arc<IpRateLimiters>, period: Duration, mut stop: watch::Receiver<bool>) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        limiters.prune();
+    }
+}
+
+// Background task that closes each latency objective window and warns about routes that fell short in it
+async fn run_latency_slo_reporter(latency: Arc<LatencyTracker>, period: Duration, mut stop: watch::Receiver<bool>) {
+    // Start a period from now, so the first window is a full one
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        for breach in latency.close_window() {
+            tracing::warn!(
+                "Latency objective missed for {}: {:.2}% of {} requests within {}ms, target {:.2}%",
+                breach.route,
+                breach.within_budget_ratio * 100.0,
+                breach.requests,
+                breach.budget_ms,
+                breach.target * 100.0
+            );
+        }
     }
 }
 
 #[tokio::main]
 async fn main() {
-    // Initialize tracing with more detailed logging
-    tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env()
-            .unwrap_or_else(|_| EnvFilter::new("debug,tower_http=debug,axum=debug,sqlx=debug")))
-        .with(tracing_subscriber::fmt::layer()
-            .with_target(true)
-            .with_thread_ids(true)
-            .with_file(true)
-            .with_line_number(true)
-            .with_thread_names(true)
-            .with_level(true)
-            .with_ansi(true))
-        .init();
+    // Initialize logging, and span export when an OTLP collector is configured
+    let tracer_provider = telemetry::init();
 
     tracing::info!("Starting application...");
 
     // Load .env file
     dotenvy::dotenv().ok();
     
+    let config = match config::Config::load() {
+        Ok(config) => config,
+        Err(e) => panic!("{}", e),
+    };
+    middleware::read_only::set_read_only(config.read_only);
+    let config = Arc::new(config);
+
     // Set up database connection pool
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    tracing::info!("Connecting to database at: {}", database_url);
+    tracing::info!("Connecting to database");
     
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
+    let statement_timeout_ms = config.db_statement_timeout_ms;
+    let pool_options = PgPoolOptions::new()
+        .max_connections(config.database_max_connections)
+        // Bound every statement so a runaway query can't pin a pooled connection
+        .after_connect(move |conn, _meta| Box::pin(db::apply_statement_timeout(conn, statement_timeout_ms)));
+    let connect_timeout = Duration::from_secs(config.database_connect_timeout_seconds);
+    let pool = db::connect_with_retry(pool_options, &config.database_url, connect_timeout)
         .await
         .expect("Failed to create pool");
 
-    // Run migrations
-    tracing::info!("Running database migrations");
-    sqlx::migrate!("./migrations")
-        .run(&pool)
-        .await
-        .expect("Failed to run migrations");
-
-    // Configure CORS
-    let cors = CorsLayer::new()
-        .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
-        .allow_methods([
-            axum::http::Method::GET,
-            axum::http::Method::POST,
-            axum::http::Method::PUT,
-            axum::http::Method::DELETE,
-            axum::http::Method::OPTIONS,
-        ])
-        .allow_headers([
-            axum::http::header::AUTHORIZATION,
-            axum::http::header::CONTENT_TYPE,
-            axum::http::header::ACCEPT,
-        ])
-        .allow_credentials(true);
-
-    // Create router with shared state
-    let app = Router::new()
-        // Health check endpoint
-        .route("/health", get(health_check))
-        // Auth endpoints
-        .route("/v1/auth", post(handlers::auth::authenticate_user))
-        .route("/v1/register", post(handlers::auth::register_user))
-        
-        // Transaction endpoints
-        .route("/v1/users/{user_id}/transactions", post(handlers::transaction::create_transaction))
-        .route("/v1/users/{user_id}/transactions", get(handlers::transaction::get_transactions))
-        .route("/v1/users/{user_id}/balance", get(handlers::transaction::get_account_balance))
-        .with_state(pool)
-        // Add middleware layers
-        .layer(middleware::from_fn(logging_middleware))
-        .layer(cors)
-        .layer(RequestBodyLimitLayer::new(1024 * 1024));
-
-    let listener = TcpListener::bind("127.0.0.1:8080").await.unwrap();
-    tracing::info!("Server running on http://127.0.0.1:8080");
-    axum::serve(listener, app).await.unwrap();
+    // Run migrations, unless read-only, where the database may be a replica that can't take them
+    if !config.read_only {
+        tracing::info!("Running database migrations");
+        db::MIGRATOR
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    // Fail fast if the database enums have drifted from the models
+    if let Err(e) = db_enums::verify(&pool).await {
+        panic!("{}", e);
+    }
+
+    // Partner APIs and object storage are called through one client, isolating each host
+    let outbound_client = Arc::new(outbound::OutboundClient::new(outbound::HostPolicy::from_config(&config)));
+
+    // Store transaction attachments when a bucket is configured
+    let blobs = blob_store::from_config(&config, outbound_client.clone());
+    // Take card deposits when Stripe is configured
+    let payments = payments::from_config(&config, outbound_client.clone());
+    // Link users' bank accounts when Plaid is configured
+    let bank_data = linked_accounts::from_config(&config, outbound_client.clone());
+
+    // Background workers; on shutdown they stop in this order, so the webhook dispatcher still
+    // delivers events the others queued before it stops
+    let mut workers = shutdown::Workers::default();
+
+    // Jobs that write are held back while the service is read-only
+    if config.read_only {
+        tracing::warn!("Read-only mode: writes are rejected and background jobs that write are not started");
+    } else {
+        // Start the scheduler for future-dated and recurring transactions
+        workers.spawn("scheduler", |stop| run_scheduler(pool.clone(), config.clone(), Duration::from_secs(config.scheduler_interval_seconds), stop));
+
+        // Issue monthly statements
+        workers.spawn("statement generator", |stop| {
+            run_statement_generator(pool.clone(), config.clone(), Duration::from_secs(config.statement_interval_seconds), stop)
+        });
+
+        // Run queued jobs, several at a time
+        let job_context = jobs::JobContext { pool: pool.clone(), config: config.clone(), mailer: mailer::from_config(&config, outbound_client.clone()) };
+        for _ in 0..config.job_workers {
+            workers.spawn("job worker", |stop| jobs::run_worker(job_context.clone(), Duration::from_secs(config.job_poll_interval_seconds), stop));
+        }
+
+        // Carry out confirmed erasure requests once their grace period has passed
+        workers.spawn("erasure processor", |stop| {
+            run_erasure_processor(pool.clone(), blobs.clone(), Duration::from_secs(config.erasure_interval_seconds), stop)
+        });
+
+        // Verify the materialized balances against the ledger
+        workers.spawn("balance reconciler", |stop| {
+            run_balance_reconciler(pool.clone(), config.clone(), Duration::from_secs(config.reconcile_interval_seconds), stop)
+        });
+
+        // Record FX rates when a provider is configured
+        if let Some(source) = fx::fetcher::FxRateSource::from_config(&config) {
+            workers.spawn("FX rate fetcher", |stop| {
+                run_fx_rate_fetcher(pool.clone(), outbound_client.clone(), source, Duration::from_secs(config.fx_fetch_interval_seconds), stop)
+            });
+        }
+
+        // Start the webhook dispatcher
+        workers.spawn("webhook dispatcher", |stop| {
+            run_webhook_dispatcher(pool.clone(), config.clone(), outbound_client.clone(), Duration::from_secs(config.webhook_dispatch_interval_seconds), stop)
+        });
+
+        // Delete the attachments of cancelled, failed and denied transactions
+        workers.spawn("attachment sweeper", |stop| {
+            run_attachment_sweeper(pool.clone(), blobs.clone(), Duration::from_secs(config.attachment_sweep_interval_seconds), stop)
+        });
+
+        // Relay outbox events to the message broker when one is configured
+        if let Some(broker) = outbox::from_config(config.event_broker_url.as_deref(), &config.event_subject_prefix).await {
+            workers.spawn("outbox relay", |stop| {
+                outbox::run_relay(pool.clone(), broker, Duration::from_secs(config.outbox_relay_interval_seconds), stop)
+            });
+        }
+    }
+
+    // Cache balances when Redis is configured; writers drop cached balances as ledgers change
+    let cache = cache::from_config(config.redis_url.as_deref()).await;
+    if config.redis_url.is_some() && !config.read_only {
+        workers.spawn("balance cache invalidator", |stop| {
+            balance_cache::run_invalidator(pool.clone(), cache.clone(), stop)
+        });
+    }
+    let bind_address = config.bind_address;
+    let slo_window = Duration::from_secs(config.latency_slo_window_seconds);
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout_seconds);
+    let state = AppState::new(pool.clone(), config, cache, blobs)
+        .with_payments(payments)
+        .with_bank_data(bank_data)
+        .with_workers(workers.status());
+
+    // Forward committed account events to WebSocket clients
+    workers.spawn("account event listener", |stop| {
+        handlers::realtime::run_listener(pool.clone(), state.realtime.clone(), stop)
+    });
+
+    // Per-IP request limits, stricter on the endpoints that take passwords
+    let ip_limiters = Arc::new(IpRateLimiters::new(IpRateLimits::from_config(&state.config)));
+    workers.spawn("rate limit pruner", |stop| {
+        run_rate_limit_pruner(ip_limiters.clone(), Duration::from_secs(60), stop)
+    });
+
+    // Evaluate the latency objectives of the hot routes
+    workers.spawn("latency SLO reporter", |stop| {
+        run_latency_slo_reporter(state.latency.clone(), slo_window, stop)
+    });
+
+    let app = routes::router(state, ip_limiters.clone());
+
+    let listener = TcpListener::bind(bind_address).await.unwrap();
+    tracing::info!("Server running on http://{}", bind_address);
+
+    // Stop accepting connections on SIGINT or SIGTERM and let in-flight requests finish, up to
+    // the drain timeout
+    let (draining, mut drain_started) = watch::channel(false);
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).with_graceful_shutdown(async move {
+        shutdown::signal().await;
+        let _ = draining.send(true);
+    });
+    let drain_deadline = async {
+        let _ = drain_started.wait_for(|started| *started).await;
+        tokio::time::sleep(drain_timeout).await;
+    };
+    tokio::select! {
+        result = server => result.unwrap(),
+        _ = drain_deadline => tracing::error!("Timed out waiting for in-flight requests, shutting down anyway"),
+    }
+
+    workers.stop().await;
+    pool.close().await;
+    if let Some(provider) = tracer_provider {
+        telemetry::shutdown(provider);
+    }
+    tracing::info!("Shutdown complete");
 }
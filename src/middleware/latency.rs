@@ -0,0 +1,195 @@
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::LatencySlo;
+
+// Fewer requests than this in a window are too few to judge a route by
+const MIN_WINDOW_REQUESTS: u64 = 20;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    requests: u64,
+    within_budget: u64,
+}
+
+impl Counts {
+    fn ratio(&self) -> f64 {
+        if self.requests == 0 {
+            1.0
+        } else {
+            self.within_budget as f64 / self.requests as f64
+        }
+    }
+}
+
+struct RouteCounts {
+    // The window being evaluated, and everything since startup
+    window: Counts,
+    total: Counts,
+}
+
+struct RouteTracker {
+    slo: LatencySlo,
+    counts: Mutex<RouteCounts>,
+}
+
+// A route that fell short of its objective over the window that just ended
+#[derive(Debug)]
+pub struct SloBreach {
+    pub route: String,
+    pub requests: u64,
+    pub within_budget_ratio: f64,
+    pub target: f64,
+    pub budget_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteSloReport {
+    pub route: String,
+    pub budget_ms: u64,
+    pub target: f64,
+    pub window_requests: u64,
+    pub window_within_budget: u64,
+    pub total_requests: u64,
+    pub total_within_budget: u64,
+}
+
+// Counts, per route with a configured objective, how many requests succeeded within the latency
+// budget. Counts are kept per instance, like the rate limits.
+pub struct LatencyTracker {
+    routes: HashMap<String, RouteTracker>,
+}
+
+impl LatencyTracker {
+    pub fn new(slos: &BTreeMap<String, LatencySlo>) -> Self {
+        let routes = slos
+            .iter()
+            .map(|(route, slo)| {
+                let counts = RouteCounts { window: Counts::default(), total: Counts::default() };
+                (route.clone(), RouteTracker { slo: slo.clone(), counts: Mutex::new(counts) })
+            })
+            .collect();
+        Self { routes }
+    }
+
+    // Server errors never count as within budget, however fast they were
+    pub fn record(&self, route: &str, elapsed: Duration, success: bool) {
+        let Some(tracker) = self.routes.get(route) else {
+            return;
+        };
+        let good = success && elapsed <= Duration::from_millis(tracker.slo.budget_ms);
+
+        let mut guard = tracker.counts.lock().unwrap();
+        let RouteCounts { window, total } = &mut *guard;
+        for counts in [window, total] {
+            counts.requests += 1;
+            counts.within_budget += u64::from(good);
+        }
+    }
+
+    // Ends the current window, returning the routes that saw enough traffic in it and fell short
+    pub fn close_window(&self) -> Vec<SloBreach> {
+        let mut breaches = Vec::new();
+        for (route, tracker) in &self.routes {
+            let window = std::mem::take(&mut tracker.counts.lock().unwrap().window);
+            if window.requests >= MIN_WINDOW_REQUESTS && window.ratio() < tracker.slo.target {
+                breaches.push(SloBreach {
+                    route: route.clone(),
+                    requests: window.requests,
+                    within_budget_ratio: window.ratio(),
+                    target: tracker.slo.target,
+                    budget_ms: tracker.slo.budget_ms,
+                });
+            }
+        }
+        breaches.sort_by(|a, b| a.route.cmp(&b.route));
+        breaches
+    }
+
+    pub fn report(&self) -> Vec<RouteSloReport> {
+        let mut report: Vec<RouteSloReport> = self
+            .routes
+            .iter()
+            .map(|(route, tracker)| {
+                let counts = tracker.counts.lock().unwrap();
+                RouteSloReport {
+                    route: route.clone(),
+                    budget_ms: tracker.slo.budget_ms,
+                    target: tracker.slo.target,
+                    window_requests: counts.window.requests,
+                    window_within_budget: counts.window.within_budget,
+                    total_requests: counts.total.requests,
+                    total_within_budget: counts.total.within_budget,
+                }
+            })
+            .collect();
+        report.sort_by(|a, b| a.route.cmp(&b.route));
+        report
+    }
+}
+
+// Times each request to a route with a latency objective, keyed by method and route template
+pub async fn track_latency(State(tracker): State<Arc<LatencyTracker>>, request: Request, next: Next) -> Response {
+    let Some(path) = request.extensions().get::<MatchedPath>() else {
+        return next.run(request).await;
+    };
+    let route = format!("{} {}", request.method(), path.as_str());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    tracker.record(&route, started.elapsed(), !response.status().is_server_error());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BALANCE: &str = "GET /v1/users/{user_id}/balance";
+
+    fn tracker() -> LatencyTracker {
+        LatencyTracker::new(&BTreeMap::from([(BALANCE.to_string(), LatencySlo { budget_ms: 100, target: 0.9 })]))
+    }
+
+    #[test]
+    fn test_slow_windows_are_reported_once() {
+        let tracker = tracker();
+        for i in 0..MIN_WINDOW_REQUESTS {
+            let elapsed = Duration::from_millis(if i % 4 == 0 { 250 } else { 20 });
+            tracker.record(BALANCE, elapsed, true);
+        }
+        tracker.record("GET /v1/me", Duration::from_secs(5), true);
+
+        let breaches = tracker.close_window();
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].route, BALANCE);
+        assert_eq!(breaches[0].within_budget_ratio, 0.75);
+
+        // The next window starts empty, but the totals carry on
+        assert!(tracker.close_window().is_empty());
+        let report = tracker.report();
+        assert_eq!(report[0].window_requests, 0);
+        assert_eq!(report[0].total_requests, MIN_WINDOW_REQUESTS);
+        assert_eq!(report[0].total_within_budget, 15);
+    }
+
+    #[test]
+    fn test_quiet_or_healthy_windows_are_not_breaches() {
+        let tracker = tracker();
+        // Too few requests to judge, even though all were failures
+        for _ in 0..MIN_WINDOW_REQUESTS - 1 {
+            tracker.record(BALANCE, Duration::from_millis(10), false);
+        }
+        assert!(tracker.close_window().is_empty());
+
+        for _ in 0..MIN_WINDOW_REQUESTS {
+            tracker.record(BALANCE, Duration::from_millis(10), true);
+        }
+        assert!(tracker.close_window().is_empty());
+    }
+}
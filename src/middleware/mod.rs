@@ -0,0 +1,6 @@
+pub mod auth;
+pub mod latency;
+pub mod read_only;
+pub mod user_rate;
+pub mod request_id;
+pub mod rate_limit;
\ No newline at end of file
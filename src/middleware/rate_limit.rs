@@ -0,0 +1,171 @@
+use axum::body::Body;
+use axum::http::Request;
+use axum::response::{IntoResponse, Response};
+use governor::middleware::NoOpMiddleware;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_governor::governor::{GovernorConfig, GovernorConfigBuilder};
+use tower_governor::key_extractor::{KeyExtractor, PeerIpKeyExtractor, SmartIpKeyExtractor};
+use tower_governor::{GovernorError, GovernorLayer};
+
+use crate::config::Config;
+use crate::error::AppError;
+
+type IpGovernorConfig = GovernorConfig<ClientIp, NoOpMiddleware>;
+
+// Keys requests by the client's address: the connecting peer, or, behind a trusted reverse proxy,
+// the address it reports in `X-Forwarded-For`, `X-Real-Ip` or `Forwarded`
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp {
+    trust_proxy: bool,
+}
+
+impl KeyExtractor for ClientIp {
+    type Key = IpAddr;
+
+    fn extract<T>(&self, req: &Request<T>) -> Result<Self::Key, GovernorError> {
+        if self.trust_proxy {
+            SmartIpKeyExtractor.extract(req)
+        } else {
+            PeerIpKeyExtractor.extract(req)
+        }
+    }
+}
+
+// Per-IP request allowances, each replenished evenly over a minute
+#[derive(Debug, Clone, Copy)]
+pub struct IpRateLimits {
+    // Any request
+    pub per_minute: u32,
+    // Sign-in, registration and step-up, which take passwords
+    pub auth_per_minute: u32,
+    // Only set when every request arrives through a proxy that overwrites the forwarding headers;
+    // otherwise clients could pick their own address
+    pub trust_proxy: bool,
+}
+
+impl IpRateLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            per_minute: config.rate_limit_ip_per_minute,
+            auth_per_minute: config.rate_limit_auth_per_minute,
+            trust_proxy: config.rate_limit_trust_proxy,
+        }
+    }
+}
+
+// Answers limited requests like any other error, with a `Retry-After` header
+fn reject(error: GovernorError) -> Response<Body> {
+    match error {
+        GovernorError::TooManyRequests { wait_time, .. } => {
+            tracing::error!("Client rate limited for {}s", wait_time);
+            AppError::RateLimited { retry_after: wait_time.max(1) }.into_response()
+        }
+        GovernorError::UnableToExtractKey => {
+            tracing::error!("Failed to determine the client address for rate limiting");
+            AppError::Internal("Failed to identify the client".to_string()).into_response()
+        }
+        mut error => error.as_response(),
+    }
+}
+
+fn governor(per_minute: u32, key: ClientIp) -> Arc<IpGovernorConfig> {
+    let config = GovernorConfigBuilder::default()
+        .key_extractor(key)
+        .period(Duration::from_secs(60) / per_minute)
+        .burst_size(per_minute)
+        .error_handler(reject)
+        .finish()
+        .expect("rate limits are positive");
+    Arc::new(config)
+}
+
+// Token buckets per client address, kept per instance
+pub struct IpRateLimiters {
+    general: Arc<IpGovernorConfig>,
+    auth: Arc<IpGovernorConfig>,
+}
+
+impl IpRateLimiters {
+    pub fn new(limits: IpRateLimits) -> Self {
+        let key = ClientIp { trust_proxy: limits.trust_proxy };
+        Self {
+            general: governor(limits.per_minute, key),
+            auth: governor(limits.auth_per_minute, key),
+        }
+    }
+
+    // For every route
+    pub fn general(&self) -> GovernorLayer<ClientIp, NoOpMiddleware> {
+        GovernorLayer { config: self.general.clone() }
+    }
+
+    // For the endpoints that check passwords, which share one stricter bucket per address
+    pub fn auth(&self) -> GovernorLayer<ClientIp, NoOpMiddleware> {
+        GovernorLayer { config: self.auth.clone() }
+    }
+
+    // Drops the buckets of addresses that have been idle long enough to be full again
+    pub fn prune(&self) {
+        for config in [&self.general, &self.auth] {
+            config.limiter().retain_recent();
+            config.limiter().shrink_to_fit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::ConnectInfo;
+    use axum::http::{header, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    fn router(trust_proxy: bool) -> Router {
+        let limiters = IpRateLimiters::new(IpRateLimits { per_minute: 100, auth_per_minute: 2, trust_proxy });
+        Router::new()
+            .route("/v1/auth", post(|| async { "signed in" }).route_layer(limiters.auth()))
+            .layer(limiters.general())
+    }
+
+    fn sign_in(forwarded_for: &str) -> Request<Body> {
+        let mut request = Request::post("/v1/auth")
+            .header("x-forwarded-for", forwarded_for)
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))));
+        request
+    }
+
+    #[tokio::test]
+    async fn test_sign_ins_are_limited_per_address() {
+        let app = router(true);
+        for _ in 0..2 {
+            let response = app.clone().oneshot(sign_in("203.0.113.1")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = app.clone().oneshot(sign_in("203.0.113.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((1..=30).contains(&retry_after));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "rate_limited");
+
+        let response = app.oneshot(sign_in("203.0.113.2")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Without a trusted proxy the forwarding headers are the client's to forge, so only the
+        // peer address counts
+        let app = router(false);
+        for (i, forwarded_for) in ["203.0.113.1", "203.0.113.2", "203.0.113.3"].into_iter().enumerate() {
+            let response = app.clone().oneshot(sign_in(forwarded_for)).await.unwrap();
+            let expected = if i < 2 { StatusCode::OK } else { StatusCode::TOO_MANY_REQUESTS };
+            assert_eq!(response.status(), expected);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use axum::extract::Request;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::AppError;
+
+// POST endpoints that only read, so sign-in keeps working while writes are off
+const READ_ONLY_POSTS: [&str; 2] = ["/v1/auth", "/v1/auth/step-up"];
+
+// Set once at startup from the `read_only` setting
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+fn is_write(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => !READ_ONLY_POSTS.contains(&path),
+        _ => true,
+    }
+}
+
+// Rejects every mutating request with 503 while the service is read-only
+pub async fn reject_writes(req: Request, next: Next) -> Result<Response, AppError> {
+    if is_read_only() && is_write(req.method(), req.uri().path()) {
+        tracing::info!("Rejected {} {}: service is read-only", req.method(), req.uri().path());
+        return Err(AppError::ReadOnly);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_reads_and_sign_in_pass() {
+        assert!(!is_write(&Method::GET, "/v1/users/1/balance"));
+        assert!(!is_write(&Method::POST, "/v1/auth"));
+        assert!(!is_write(&Method::POST, "/v1/auth/step-up"));
+        assert!(is_write(&Method::POST, "/v1/register"));
+        assert!(is_write(&Method::DELETE, "/v1/api-keys/1"));
+        assert!(is_write(&Method::PATCH, "/v1/users/1/recurring/1"));
+    }
+}
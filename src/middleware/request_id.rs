@@ -0,0 +1,79 @@
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longest incoming request id that is kept; longer ones are replaced
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+// Id of the request being handled, for error responses; None outside a request
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+// Ids from upstream are kept when they are short and plain enough to log safely
+fn is_acceptable(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+// Gives every request an id, taken from `X-Request-Id` when the caller sent a usable one and
+// generated otherwise. The id is put in the request headers for the trace span, returned in the
+// response header and included in error bodies.
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_acceptable(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    // Only characters valid in a header value get this far
+    let header = HeaderValue::from_str(&id).expect("request ids are valid header values");
+    req.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+
+    let mut response = REQUEST_ID.scope(id, next.run(req)).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use crate::error::AppError;
+
+    async fn fail() -> Result<(), AppError> {
+        Err(AppError::NotFound("User not found".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_ids_are_kept_or_generated_and_reported() {
+        let app = Router::new().route("/", get(fail)).layer(axum::middleware::from_fn(assign_request_id));
+
+        let request = Request::get("/").header(REQUEST_ID_HEADER, "upstream-42").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "upstream-42");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], "upstream-42");
+
+        let request = Request::get("/").header(REQUEST_ID_HEADER, "bad id").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+    }
+}
@@ -0,0 +1,118 @@
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::entitlements::Entitlements;
+use crate::error::AppError;
+use crate::middleware::auth::{AuthContext, Credential};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Sessions and API keys are counted apart, so a busy dashboard doesn't use up an integration's allowance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Caller {
+    Session(Uuid),
+    ApiKeys(Uuid),
+}
+
+struct Windows {
+    // Start of each caller's current window and the requests counted in it
+    counts: HashMap<Caller, (Instant, u32)>,
+    pruned_at: Instant,
+}
+
+// Counts requests per user in fixed one-minute windows. Counts are kept per instance, so behind a
+// load balancer a user may get up to their allowance from each instance.
+pub struct UserRateLimiter {
+    windows: Mutex<Windows>,
+}
+
+impl Default for UserRateLimiter {
+    fn default() -> Self {
+        Self {
+            windows: Mutex::new(Windows { counts: HashMap::new(), pruned_at: Instant::now() }),
+        }
+    }
+}
+
+impl UserRateLimiter {
+    // Counts a request, or returns how long until the window ends if the caller already made
+    // `limit` in it
+    pub fn try_acquire(&self, caller: Caller, limit: u32, now: Instant) -> Result<(), Duration> {
+        let mut windows = self.windows.lock().unwrap();
+        // Forget callers whose window has ended, once per window
+        if now.duration_since(windows.pruned_at) >= WINDOW {
+            windows.counts.retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+            windows.pruned_at = now;
+        }
+
+        let (start, count) = windows.counts.entry(caller).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= limit {
+            return Err(WINDOW - now.duration_since(*start));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+// Rejects requests beyond the caller's per-minute allowance: the tier's allowance for API keys
+// and `rate_limit_session_per_minute` for sessions
+pub async fn limit_user_rate(
+    State(limiter): State<Arc<UserRateLimiter>>,
+    State(config): State<Arc<Config>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Some(AuthContext { user_id, credential }) = req.extensions().get::<AuthContext>() {
+        let (caller, limit) = match credential {
+            Credential::Session { .. } => (Caller::Session(*user_id), config.rate_limit_session_per_minute),
+            Credential::ApiKey { tier, .. } => {
+                (Caller::ApiKeys(*user_id), Entitlements::for_tier(*tier).api_requests_per_minute)
+            }
+        };
+        if let Err(wait) = limiter.try_acquire(caller, limit, Instant::now()) {
+            tracing::error!("{:?} exceeded {} requests per minute", caller, limit);
+            return Err(AppError::RateLimited { retry_after: wait.as_secs_f64().ceil() as u64 });
+        }
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_limited_per_caller_and_window() {
+        let limiter = UserRateLimiter::default();
+        let alice_id = Uuid::new_v4();
+        let alice = Caller::ApiKeys(alice_id);
+        let bob = Caller::ApiKeys(Uuid::new_v4());
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire(alice, 2, start).is_ok());
+        assert!(limiter.try_acquire(alice, 2, start).is_ok());
+        assert_eq!(
+            limiter.try_acquire(alice, 2, start + Duration::from_secs(59)),
+            Err(Duration::from_secs(1))
+        );
+        assert!(limiter.try_acquire(bob, 2, start).is_ok());
+
+        // Alice's sessions have their own window
+        assert!(limiter.try_acquire(Caller::Session(alice_id), 2, start).is_ok());
+
+        assert!(limiter.try_acquire(alice, 2, start + WINDOW).is_ok());
+        // Bob's ended window is pruned on the way
+        assert!(!limiter.windows.lock().unwrap().counts.contains_key(&bob));
+    }
+}
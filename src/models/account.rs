@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+use validator::Validate;
+
+use crate::models::transaction::default_currency;
+
+// One of a user's balances in a single currency. Each user has a main account per currency they
+// hold, created with its first entry, and may open more alongside it.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Account {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub account_type: AccountType,
+    pub currency: String,
+    // Whether entries posted without an account land here
+    pub is_primary: bool,
+    pub closed_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "account_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AccountType {
+    Checking,
+    Savings,
+    Wallet,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAccount {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: String,
+    pub account_type: AccountType,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateAccount {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: String,
+}
+
+// An account's own entries, with the same figures as a wallet
+#[derive(Debug, Serialize, FromRow)]
+pub struct AccountBalanceDetails {
+    pub account_id: Uuid,
+    pub currency: String,
+    // Settled balance
+    pub balance: BigDecimal,
+    // Settled balance less pending debits, i.e. what can be spent right now
+    pub available: BigDecimal,
+    // Net amount of pending transactions
+    pub pending: BigDecimal,
+    pub last_activity: Option<OffsetDateTime>,
+}
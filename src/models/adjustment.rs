@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::{default_currency, TransactionType};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Adjustment {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub reason_code: AdjustmentReason,
+    pub note: String,
+    pub status: AdjustmentStatus,
+    pub requested_by: Uuid,
+    pub decided_by: Option<Uuid>,
+    pub transaction_id: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+    pub decided_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "adjustment_reason", rename_all = "snake_case")]
+pub enum AdjustmentReason {
+    GoodwillCredit,
+    FeeRefund,
+    Chargeback,
+    DuplicateCorrection,
+    FraudRecovery,
+    SystemError,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "adjustment_status", rename_all = "snake_case")]
+pub enum AdjustmentStatus {
+    PendingApproval,
+    Posted,
+    Rejected,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAdjustment {
+    pub amount: BigDecimal,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub reason_code: AdjustmentReason,
+    pub note: String,
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use time::{Duration, OffsetDateTime};
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::default_currency;
+
+// Length of each bucket in an analytics series
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsPeriod {
+    Day,
+    // Weeks start on Monday
+    Week,
+    #[default]
+    Month,
+}
+
+impl AnalyticsPeriod {
+    // The matching `date_trunc` field
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnalyticsPeriod::Day => "day",
+            AnalyticsPeriod::Week => "week",
+            AnalyticsPeriod::Month => "month",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    #[serde(default)]
+    pub group_by: AnalyticsPeriod,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    // Also break each bucket down by transaction category
+    #[serde(default)]
+    pub by_category: bool,
+    // Only count transactions created at or after `from` and before `to`
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+// Posted totals in one bucket, and category when broken down by it
+#[derive(Debug, FromRow)]
+pub struct PeriodTotals {
+    pub period_start: OffsetDateTime,
+    pub category: Option<String>,
+    pub credits: BigDecimal,
+    pub debits: BigDecimal,
+    pub transaction_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryTotals {
+    // Null for transactions posted without a category
+    pub category: Option<String>,
+    pub credits: BigDecimal,
+    pub debits: BigDecimal,
+    // Credits less debits
+    pub net: BigDecimal,
+    pub transaction_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalyticsPoint {
+    pub period_start: OffsetDateTime,
+    pub credits: BigDecimal,
+    pub debits: BigDecimal,
+    // Credits less debits
+    pub net: BigDecimal,
+    pub transaction_count: i64,
+    // Only present when a breakdown by category was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<CategoryTotals>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Analytics {
+    pub user_id: Uuid,
+    pub currency: String,
+    pub group_by: AnalyticsPeriod,
+    // Buckets with at least one transaction, oldest first
+    pub series: Vec<AnalyticsPoint>,
+}
+
+// Spacing of the points in a balance history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BalanceGranularity {
+    #[default]
+    Daily,
+    // Weeks start on Monday
+    Weekly,
+    Monthly,
+}
+
+impl BalanceGranularity {
+    // The matching `date_trunc` field
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BalanceGranularity::Daily => "day",
+            BalanceGranularity::Weekly => "week",
+            BalanceGranularity::Monthly => "month",
+        }
+    }
+
+    // How far back a history goes when no start is given: 30 days, 26 weeks or 12 months
+    pub fn default_range(self) -> Duration {
+        match self {
+            BalanceGranularity::Daily => Duration::days(30),
+            BalanceGranularity::Weekly => Duration::weeks(26),
+            BalanceGranularity::Monthly => Duration::days(365),
+        }
+    }
+
+    // The shortest a period can be, for bounding how many points a range covers
+    pub fn min_length(self) -> Duration {
+        match self {
+            BalanceGranularity::Daily => Duration::days(1),
+            BalanceGranularity::Weekly => Duration::weeks(1),
+            BalanceGranularity::Monthly => Duration::days(28),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BalanceHistoryQuery {
+    #[serde(default)]
+    pub granularity: BalanceGranularity,
+    // The first period is the one containing `from`; the last is the one containing `to`, which
+    // defaults to now
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+// A currency's settled balance at the end of one period
+#[derive(Debug, FromRow)]
+pub struct BalanceHistoryRow {
+    pub currency: String,
+    pub period_start: OffsetDateTime,
+    pub balance: BigDecimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalancePoint {
+    pub period_start: OffsetDateTime,
+    pub balance: BigDecimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalanceHistory {
+    pub user_id: Uuid,
+    pub granularity: BalanceGranularity,
+    // One point per period, oldest first, for each currency with posted transactions by `to`
+    pub balances: BTreeMap<String, Vec<BalancePoint>>,
+}
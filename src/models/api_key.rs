@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+pub const SCOPE_TRANSACTIONS_READ: &str = "transactions:read";
+pub const SCOPE_TRANSACTIONS_WRITE: &str = "transactions:write";
+pub const SCOPE_BALANCE_READ: &str = "balance:read";
+pub const SCOPE_TRANSFERS_WRITE: &str = "transfers:write";
+
+pub const ALL_SCOPES: &[&str] = &[
+    SCOPE_TRANSACTIONS_READ,
+    SCOPE_TRANSACTIONS_WRITE,
+    SCOPE_BALANCE_READ,
+    SCOPE_TRANSFERS_WRITE,
+];
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    // False for sandbox keys, which only see and create test data
+    pub livemode: bool,
+    pub created_at: OffsetDateTime,
+    pub last_used_at: Option<OffsetDateTime>,
+    // Null for keys that never expire
+    pub expires_at: Option<OffsetDateTime>,
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    pub scopes: Vec<String>,
+    // Set to false for a sandbox key
+    #[serde(default = "live")]
+    pub livemode: bool,
+    // The key never expires when omitted
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+fn live() -> bool {
+    true
+}
+
+// The plaintext key is only ever returned once, at creation time
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    pub api_key: ApiKey,
+}
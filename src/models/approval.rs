@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::{TransactionStatus, TransactionType};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "approval_status", rename_all = "lowercase")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+// A transaction above the approval threshold, with the decision a second person made on it
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TransactionApproval {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub user_id: Uuid,
+    pub account_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub transaction_status: TransactionStatus,
+    pub status: ApprovalStatus,
+    pub created_at: OffsetDateTime,
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<OffsetDateTime>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub enum ApprovalDecision {
+    Approve,
+    Reject,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DecideApproval {
+    pub decision: ApprovalDecision,
+    pub note: Option<String>,
+}
+
+// Someone the user trusts to approve their large transactions, alongside admins
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TransactionApprover {
+    pub user_id: Uuid,
+    pub approver_id: Uuid,
+    pub created_at: OffsetDateTime,
+}
@@ -0,0 +1,39 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+// Largest file a single upload may carry
+pub const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+
+// Most attachments one transaction may have
+pub const MAX_ATTACHMENTS_PER_TRANSACTION: i64 = 10;
+
+// Content types accepted for upload; the file's leading bytes must match the declared type
+pub const ALLOWED_CONTENT_TYPES: [&str; 3] = ["image/jpeg", "image/png", "application/pdf"];
+
+// A receipt or other file uploaded against one of the user's transactions
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub user_id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    // Hex SHA-256 of the file, for checking a download against
+    pub sha256: String,
+    // Where the file is kept in the blob store; downloads go through pre-signed URLs instead
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created_at: OffsetDateTime,
+}
+
+// An attachment with a short-lived URL to download it from
+#[derive(Debug, Serialize)]
+pub struct AttachmentDownload {
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    pub download_url: String,
+    pub download_url_expires_at: OffsetDateTime,
+}
@@ -0,0 +1,54 @@
+use axum::http::HeaderValue;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::transaction::NEXT_CURSOR_HEADER;
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub action: String,
+    pub target_id: Option<Uuid>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditLogQuery {
+    // Whose account the action affected
+    pub user_id: Option<Uuid>,
+    // Who performed the action
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>,
+    // Only entries recorded at or after `from` and before `to`
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+// A page of audit log entries; the cursor for the following page is returned in `X-Next-Cursor`
+#[derive(Debug)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLogEntry>,
+    pub next_cursor: Option<String>,
+}
+
+impl IntoResponse for AuditLogPage {
+    fn into_response(self) -> Response {
+        let mut response = Json(self.entries).into_response();
+        if let Some(cursor) = self.next_cursor.and_then(|cursor| HeaderValue::from_str(&cursor).ok()) {
+            response.headers_mut().insert(NEXT_CURSOR_HEADER, cursor);
+        }
+        response
+    }
+}
@@ -0,0 +1,32 @@
+use bigdecimal::BigDecimal;
+use serde::Serialize;
+use uuid::Uuid;
+
+// One user's stored totals in a currency, as kept in `account_balances`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceTotals {
+    pub balance: BigDecimal,
+    pub pending: BigDecimal,
+    pub held: BigDecimal,
+    // Ledger entries counted in the totals
+    pub entries: i32,
+}
+
+// A currency's stored totals before and after they were recomputed from the ledger
+#[derive(Debug, Serialize)]
+pub struct BalanceRepair {
+    pub currency: String,
+    // Whether these are the live or the sandbox totals
+    pub livemode: bool,
+    pub before: BalanceTotals,
+    pub after: BalanceTotals,
+    // Whether the stored totals disagreed with the ledger
+    pub drifted: bool,
+}
+
+// Every live and sandbox currency of one user, rebuilt from the ledger
+#[derive(Debug, Serialize)]
+pub struct BalanceRecalculation {
+    pub user_id: Uuid,
+    pub balances: Vec<BalanceRepair>,
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::{BigDecimal, RoundingMode};
+
+use crate::models::transaction::default_currency;
+
+// Percentages of a budget's limit at which spending is reported, lowest first
+pub const ALERT_THRESHOLDS: [i16; 2] = [80, 100];
+
+// A limit on what may be spent in one category and currency each period
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Budget {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub category: String,
+    pub currency: String,
+    pub period: BudgetPeriod,
+    pub limit_amount: BigDecimal,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "budget_period", rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    // Weeks start on Monday
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl BudgetPeriod {
+    // The matching `date_trunc` field; periods are aligned to UTC
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BudgetPeriod::Weekly => "week",
+            BudgetPeriod::Monthly => "month",
+            BudgetPeriod::Yearly => "year",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBudget {
+    pub category: String,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub period: BudgetPeriod,
+    pub limit_amount: BigDecimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateBudget {
+    pub limit_amount: BigDecimal,
+}
+
+// Net spending in a budget's category during one of its periods
+#[derive(Debug, FromRow)]
+pub struct PeriodSpending {
+    pub period_start: OffsetDateTime,
+    pub period_end: OffsetDateTime,
+    // Debits less credits, so refunds and reversals count back
+    pub spent: BigDecimal,
+}
+
+// A budget against what has been spent in its current period
+#[derive(Debug, Serialize)]
+pub struct BudgetStatus {
+    #[serde(flatten)]
+    pub budget: Budget,
+    pub period_start: OffsetDateTime,
+    pub period_end: OffsetDateTime,
+    pub spent: BigDecimal,
+    // What can still be spent, never below zero
+    pub remaining: BigDecimal,
+    // Spent as a share of the limit, with two decimal places; above 100 once overspent
+    pub percent_used: BigDecimal,
+}
+
+impl BudgetStatus {
+    pub fn new(budget: Budget, spending: PeriodSpending) -> BudgetStatus {
+        let zero = BigDecimal::from(0);
+        let spent = spending.spent.max(zero.clone());
+        let remaining = (&budget.limit_amount - &spent).max(zero);
+        let percent_used = (&spent * BigDecimal::from(100) / &budget.limit_amount).with_scale_round(2, RoundingMode::Down);
+        BudgetStatus {
+            budget,
+            period_start: spending.period_start,
+            period_end: spending.period_end,
+            spent,
+            remaining,
+            percent_used,
+        }
+    }
+
+    // The highest alert threshold spending has reached, if any
+    pub fn threshold_reached(&self) -> Option<i16> {
+        ALERT_THRESHOLDS
+            .into_iter()
+            .rev()
+            .find(|threshold| self.percent_used >= *threshold)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BudgetReport {
+    pub user_id: Uuid,
+    pub budgets: Vec<BudgetStatus>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn status(limit: &str, spent: &str) -> BudgetStatus {
+        let now = OffsetDateTime::now_utc();
+        let budget = Budget {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            category: "groceries".to_string(),
+            currency: "USD".to_string(),
+            period: BudgetPeriod::Monthly,
+            limit_amount: BigDecimal::from_str(limit).unwrap(),
+            created_at: now,
+            updated_at: now,
+        };
+        BudgetStatus::new(budget, PeriodSpending { period_start: now, period_end: now, spent: BigDecimal::from_str(spent).unwrap() })
+    }
+
+    #[test]
+    fn test_thresholds_are_reached_at_80_and_100_percent() {
+        assert_eq!(status("200", "159.99").threshold_reached(), None);
+        assert_eq!(status("200", "160").threshold_reached(), Some(80));
+        assert_eq!(status("200", "200").threshold_reached(), Some(100));
+
+        let overspent = status("200", "250");
+        assert_eq!(overspent.threshold_reached(), Some(100));
+        assert_eq!((overspent.remaining.to_string(), overspent.percent_used.to_string()), ("0".to_string(), "125.00".to_string()));
+
+        // Refunds can't push spending below zero
+        assert_eq!(status("200", "-30").spent, BigDecimal::from(0));
+    }
+}
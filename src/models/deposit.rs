@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::default_currency;
+
+// Deposits start pending and end succeeded or cancelled. A failed card payment can still be
+// retried by the user, so a failed deposit may go on to succeed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "deposit_status", rename_all = "lowercase")]
+pub enum DepositStatus {
+    Pending,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+// A card top-up of the user's balance, paid through a Stripe PaymentIntent
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Deposit {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub status: DepositStatus,
+    pub payment_intent_id: Option<String>,
+    // The credit that added the deposit to the balance, once it succeeded
+    pub transaction_id: Option<Uuid>,
+    // Why the last payment attempt failed, as reported by Stripe
+    pub failure_reason: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDeposit {
+    pub amount: BigDecimal,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+// A new deposit with the secret the client confirms the card payment with. The secret is only
+// returned here and never stored.
+#[derive(Debug, Serialize)]
+pub struct DepositResponse {
+    #[serde(flatten)]
+    pub deposit: Deposit,
+    pub client_secret: String,
+}
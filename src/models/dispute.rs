@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+use validator::Validate;
+
+// Disputes start open, may be taken under review, and end resolved against the user or refunded
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "dispute_status", rename_all = "snake_case")]
+pub enum DisputeStatus {
+    Open,
+    UnderReview,
+    Resolved,
+    Refunded,
+}
+
+impl DisputeStatus {
+    pub fn is_decided(self) -> bool {
+        matches!(self, DisputeStatus::Resolved | DisputeStatus::Refunded)
+    }
+}
+
+// A user's challenge of one of their settled debits
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Dispute {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub user_id: Uuid,
+    pub livemode: bool,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub reason: String,
+    pub status: DisputeStatus,
+    // The admin who took it under review
+    pub reviewed_by: Option<Uuid>,
+    pub resolved_by: Option<Uuid>,
+    pub resolved_at: Option<OffsetDateTime>,
+    pub resolution_note: Option<String>,
+    // The reversal crediting the disputed amount back, once refunded
+    pub refund_transaction_id: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct OpenDispute {
+    #[validate(length(min = 1, max = 1000, message = "Reason must be between 1 and 1000 characters"))]
+    pub reason: String,
+}
+
+// Upholding a dispute refunds the debit; rejecting it leaves the debit standing
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub enum DisputeOutcome {
+    Upheld,
+    Rejected,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveDispute {
+    pub outcome: DisputeOutcome,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisputeQuery {
+    // Defaults to disputes still waiting for a decision, whether open or under review
+    pub status: Option<DisputeStatus>,
+}
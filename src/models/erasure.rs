@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "erasure_status", rename_all = "lowercase")]
+pub enum ErasureStatus {
+    // Waiting for the confirmation token
+    Pending,
+    // Confirmed, and carried out once `scheduled_for` passes unless cancelled first
+    Confirmed,
+    Completed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErasureRequest {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub status: ErasureStatus,
+    pub token_expires_at: OffsetDateTime,
+    pub requested_at: OffsetDateTime,
+    pub confirmed_at: Option<OffsetDateTime>,
+    pub scheduled_for: Option<OffsetDateTime>,
+    pub completed_at: Option<OffsetDateTime>,
+    pub cancelled_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmErasure {
+    pub confirmation_token: String,
+}
+
+// The plaintext token is only ever returned once, when the request is made
+#[derive(Debug, Serialize)]
+pub struct IssuedErasureRequest {
+    pub confirmation_token: String,
+    pub request: ErasureRequest,
+}
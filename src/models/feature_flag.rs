@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub enabled: bool,
+    // Why the flag was last changed, e.g. the incident a kill switch was thrown for
+    pub reason: Option<String>,
+    // None while the flag has never been changed from its default
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFeatureFlag {
+    pub enabled: bool,
+    pub reason: Option<String>,
+}
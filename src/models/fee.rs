@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+use validator::Validate;
+
+use crate::models::transaction::default_currency;
+
+// What a fee is charged on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "fee_event", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum FeeEvent {
+    // Transfers to other users, including currency conversions and paid payment requests
+    Transfer,
+    // Debits posted through the transaction API
+    Withdrawal,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "fee_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum FeeKind {
+    // `flat_amount` on every charge
+    Flat,
+    // `percentage_bps` basis points of the amount
+    Percentage,
+    // The first of `tiers` whose `up_to` covers the amount
+    Tiered,
+}
+
+// One band of a tiered fee: amounts up to `up_to`, or any amount when it is null
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeeTier {
+    pub up_to: Option<BigDecimal>,
+    #[serde(default)]
+    pub flat_amount: BigDecimal,
+    #[serde(default)]
+    pub percentage_bps: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FeeRule {
+    pub id: Uuid,
+    pub name: String,
+    pub event: FeeEvent,
+    pub currency: String,
+    pub kind: FeeKind,
+    pub flat_amount: Option<BigDecimal>,
+    pub percentage_bps: Option<i32>,
+    pub tiers: Value,
+    // Bounds on the fee worked out, before rounding to the currency's minor unit
+    pub min_fee: Option<BigDecimal>,
+    pub max_fee: Option<BigDecimal>,
+    pub active: bool,
+    pub created_by: Uuid,
+    pub updated_by: Uuid,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+// Creates a rule, or replaces every setting of one
+#[derive(Debug, Deserialize, Validate)]
+pub struct FeeRuleInput {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: String,
+    pub event: FeeEvent,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub kind: FeeKind,
+    pub flat_amount: Option<BigDecimal>,
+    pub percentage_bps: Option<i32>,
+    #[serde(default)]
+    pub tiers: Vec<FeeTier>,
+    pub min_fee: Option<BigDecimal>,
+    pub max_fee: Option<BigDecimal>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeeRuleQuery {
+    // Lists switched-off rules too
+    #[serde(default)]
+    pub include_inactive: bool,
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::{Duration, OffsetDateTime};
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::default_currency;
+use crate::models::transfer::TransferResponse;
+
+// How long a quoted rate is held for
+pub const QUOTE_TTL: Duration = Duration::seconds(30);
+
+// A rate locked for the user: `amount` of `from_currency` buys `converted_amount` of `to_currency`
+// at `rate`, if used before `expires_at`
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct FxQuote {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub amount: BigDecimal,
+    pub converted_amount: BigDecimal,
+    pub mid_rate: BigDecimal,
+    // The rate applied, after the spread
+    pub rate: BigDecimal,
+    pub spread_bps: i32,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+    // The transfer the quote was used for
+    pub transfer_id: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFxQuote {
+    pub amount: BigDecimal,
+    #[serde(default = "default_currency")]
+    pub from_currency: String,
+    pub to_currency: String,
+}
+
+// Transfers a quote's amount at its rate, to another user or into the caller's own wallet in the
+// quote's target currency when `to_user_id` is left out
+#[derive(Debug, Deserialize)]
+pub struct CreateFxTransfer {
+    pub quote_id: Uuid,
+    pub to_user_id: Option<Uuid>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FxTransferResponse {
+    pub quote: FxQuote,
+    #[serde(flatten)]
+    pub transfer: TransferResponse,
+}
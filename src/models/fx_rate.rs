@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::money::Currency;
+
+// One quote from the FX provider: 1 unit of `base_currency` buys `rate` units of `quote_currency`
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct FxRate {
+    pub id: Uuid,
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub rate: BigDecimal,
+    // Host the rate was fetched from
+    pub source: String,
+    pub fetched_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FxRateQuery {
+    pub base: String,
+    pub quote: String,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LatestRatesQuery {
+    pub base: String,
+}
+
+// What one unit of the base buys in `currency`
+#[derive(Debug, Serialize)]
+pub struct LatestRate {
+    pub currency: Currency,
+    // The rate conversions are made at, after the spread
+    pub rate: BigDecimal,
+    pub mid_rate: BigDecimal,
+    // When the oldest rate it was derived from was fetched
+    pub fetched_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatestRates {
+    pub base: Currency,
+    pub spread_bps: u32,
+    pub rates: Vec<LatestRate>,
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::{TransactionStatus, TransactionType};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct DebitHold {
+    pub id: Uuid,
+    pub counterparty_id: Option<Uuid>,
+    // Case-insensitive SQL LIKE pattern matched against the debit's description, e.g. `%ACME%`
+    pub description_pattern: Option<String>,
+    pub reason: String,
+    pub created_by: Uuid,
+    pub created_at: OffsetDateTime,
+    pub lifted_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDebitHold {
+    pub counterparty_id: Option<Uuid>,
+    pub description_pattern: Option<String>,
+    pub reason: String,
+}
+
+// A debit in the review queue, with the ledger entry it was caught as. It was caught by a debit hold,
+// by a fraud rule whose risk event explains why, or by a screening hit on one of the transfer's parties.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct HeldDebit {
+    pub transaction_id: Uuid,
+    pub hold_id: Option<Uuid>,
+    pub risk_event_id: Option<Uuid>,
+    pub screening_result_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub account_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub description: Option<String>,
+    pub transfer_id: Option<Uuid>,
+    pub status: TransactionStatus,
+    pub created_at: OffsetDateTime,
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<OffsetDateTime>,
+}
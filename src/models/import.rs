@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+// Outcome of a CSV import: accepted rows are posted together, rejected ones are reported by line
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    // Imported debits caught by a debit hold and queued for review instead of settling
+    pub held: usize,
+    pub rejected: Vec<RejectedRow>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RejectedRow {
+    // 1-based line in the uploaded file, counting the header
+    pub line: u64,
+    pub error: String,
+}
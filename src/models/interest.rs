@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::{Date, OffsetDateTime};
+use bigdecimal::BigDecimal;
+
+use crate::models::account::AccountType;
+
+// Highest APY accepted, in percent
+pub const MAX_APY: i32 = 100;
+
+// How often accrued interest is paid, and so starts earning interest itself
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "compounding_period", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CompoundingPeriod {
+    Daily,
+    Monthly,
+}
+
+impl CompoundingPeriod {
+    // Periods in a year, for spreading the APY over them
+    pub fn per_year(self) -> i32 {
+        match self {
+            CompoundingPeriod::Daily => 365,
+            CompoundingPeriod::Monthly => 12,
+        }
+    }
+
+    // The start of the period containing `day`
+    pub fn start_of(self, day: Date) -> Date {
+        match self {
+            CompoundingPeriod::Daily => day,
+            CompoundingPeriod::Monthly => day.replace_day(1).expect("every month has a first day"),
+        }
+    }
+
+    // The day after the last of the period containing `day`
+    pub fn end_of(self, day: Date) -> Date {
+        match self {
+            CompoundingPeriod::Daily => day.next_day().expect("date in range"),
+            CompoundingPeriod::Monthly => {
+                let (year, month) = match day.month().next() {
+                    time::Month::January => (day.year() + 1, time::Month::January),
+                    month => (day.year(), month),
+                };
+                Date::from_calendar_date(year, month, 1).expect("every month has a first day")
+            }
+        }
+    }
+}
+
+// The interest paid on accounts of one type
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct InterestRate {
+    pub account_type: AccountType,
+    // Annual percentage yield, in percent
+    pub apy: BigDecimal,
+    pub compounding: CompoundingPeriod,
+    pub effective_from: Date,
+    pub updated_by: Uuid,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateInterestRate {
+    pub apy: BigDecimal,
+    pub compounding: CompoundingPeriod,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InterestAccrualQuery {
+    pub limit: Option<i64>,
+}
+
+// The interest worked out for one account over one period
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct InterestAccrual {
+    pub id: Uuid,
+    pub account_id: Uuid,
+    pub user_id: Uuid,
+    pub period_start: Date,
+    // The day after the period's last
+    pub period_end: Date,
+    pub average_balance: BigDecimal,
+    pub apy: BigDecimal,
+    pub compounding: CompoundingPeriod,
+    pub amount: BigDecimal,
+    pub currency: String,
+    // The interest credit, if the period earned any
+    pub transaction_id: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+use validator::Validate;
+
+use crate::models::attachment::MAX_ATTACHMENT_BYTES;
+use crate::models::transaction::default_currency;
+
+// Largest identity document a single upload may carry; uploads share the attachments' body limit
+pub const MAX_KYC_DOCUMENT_BYTES: usize = MAX_ATTACHMENT_BYTES;
+
+// Most documents a user may upload, across every submission
+pub const MAX_KYC_DOCUMENTS: i64 = 20;
+
+// Users start unverified, are pending once they submit documents, and are then verified or
+// rejected by an admin. A rejected user can submit again.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "kyc_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum KycStatus {
+    Unverified,
+    Pending,
+    Verified,
+    Rejected,
+}
+
+impl KycStatus {
+    // Documents are added and submitted before the first review and after a rejection, but not
+    // while a review is pending or once verified
+    pub fn can_submit(self) -> bool {
+        matches!(self, KycStatus::Unverified | KycStatus::Rejected)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "kyc_document_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum KycDocumentType {
+    Passport,
+    DriversLicense,
+    NationalId,
+    ProofOfAddress,
+}
+
+impl KycDocumentType {
+    // Documents that prove who the user is, at least one of which a submission needs
+    pub fn proves_identity(self) -> bool {
+        !matches!(self, KycDocumentType::ProofOfAddress)
+    }
+}
+
+// Where a user is in verification
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct KycVerification {
+    pub user_id: Uuid,
+    pub status: KycStatus,
+    pub submitted_at: Option<OffsetDateTime>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<OffsetDateTime>,
+    // Shown to the user while rejected
+    pub rejection_reason: Option<String>,
+    pub updated_at: OffsetDateTime,
+}
+
+// A user's verification with the limits their status brings
+#[derive(Debug, Serialize)]
+pub struct KycSummary {
+    #[serde(flatten)]
+    pub verification: KycVerification,
+    pub limits: Option<KycLimits>,
+}
+
+// An identity document uploaded for verification
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct KycDocument {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub document_type: KycDocumentType,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    // Hex SHA-256 of the file, for checking a download against
+    pub sha256: String,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadKycDocument {
+    pub document_type: KycDocumentType,
+}
+
+// A document with a short-lived URL for the reviewing admin to download it from
+#[derive(Debug, Serialize)]
+pub struct KycDocumentDownload {
+    #[serde(flatten)]
+    pub document: KycDocument,
+    pub download_url: String,
+    pub download_url_expires_at: OffsetDateTime,
+}
+
+// What a reviewing admin sees: the verification and every document the user uploaded
+#[derive(Debug, Serialize)]
+pub struct KycReviewView {
+    #[serde(flatten)]
+    pub verification: KycVerification,
+    pub documents: Vec<KycDocumentDownload>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KycDecision {
+    Verified,
+    Rejected,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewKyc {
+    pub decision: KycDecision,
+    // Required to reject, and shown to the user
+    #[validate(length(min = 1, max = 1000, message = "Reason must be between 1 and 1000 characters"))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KycQuery {
+    // Defaults to users waiting for review
+    pub status: Option<KycStatus>,
+}
+
+// Limits on the live transactions of users with one KYC status. A null limit doesn't apply.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct KycLimits {
+    pub status: KycStatus,
+    // Amounts are in this currency; transactions in others are converted into it
+    pub currency: String,
+    pub max_transaction_amount: Option<BigDecimal>,
+    // Debits created in the last 24 hours, in any currency
+    pub max_daily_debit_total: Option<BigDecimal>,
+    pub updated_by: Uuid,
+    pub updated_at: OffsetDateTime,
+}
+
+// Replaces both limits; leaving one out removes it
+#[derive(Debug, Deserialize)]
+pub struct UpdateKycLimits {
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub max_transaction_amount: Option<BigDecimal>,
+    pub max_daily_debit_total: Option<BigDecimal>,
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::{Date, OffsetDateTime};
+use bigdecimal::BigDecimal;
+
+// An account at an outside bank the user linked through Plaid, with its balances as last pulled
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LinkedAccount {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    // The bank login the account was linked through; accounts linked together share it
+    pub item_id: Uuid,
+    pub name: String,
+    // Last digits of the account number, for telling accounts apart
+    pub mask: Option<String>,
+    // Plaid's type and subtype, e.g. `depository` and `checking`
+    pub account_type: String,
+    pub subtype: Option<String>,
+    pub currency: Option<String>,
+    pub available_balance: Option<BigDecimal>,
+    pub current_balance: Option<BigDecimal>,
+    pub balances_updated_at: OffsetDateTime,
+    pub created_at: OffsetDateTime,
+}
+
+// A transaction pulled from a linked account. Positive amounts are money leaving the account.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LinkedAccountTransaction {
+    pub id: Uuid,
+    pub linked_account_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: Option<String>,
+    pub description: String,
+    pub merchant_name: Option<String>,
+    pub posted_on: Date,
+    pub pending: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+// The one-time token Plaid Link hands the client once the user logs in to their bank
+#[derive(Debug, Deserialize)]
+pub struct LinkAccounts {
+    pub public_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkTokenResponse {
+    pub link_token: String,
+    pub expiration: String,
+}
+
+// What a refresh pulled in
+#[derive(Debug, Serialize)]
+pub struct RefreshResponse {
+    pub account: LinkedAccount,
+    pub transactions_added: usize,
+    pub transactions_modified: usize,
+    pub transactions_removed: usize,
+}
@@ -1,2 +1,38 @@
 pub mod user;
-pub mod transaction; 
\ No newline at end of file
+pub mod transaction;
+pub mod api_key;
+pub mod adjustment;
+pub mod transfer;
+pub mod recurring;
+pub mod hold;
+pub mod webhook;
+pub mod import;
+pub mod fx_rate;
+pub mod notification;
+pub mod statement;
+pub mod feature_flag;
+pub mod wallet;
+pub mod balance;
+pub mod analytics;
+pub mod erasure;
+pub mod audit;
+pub mod job;
+pub mod money;
+pub mod account;
+pub mod savings_goal;
+pub mod round_up;
+pub mod budget;
+pub mod velocity_limit;
+pub mod approval;
+pub mod risk;
+pub mod dispute;
+pub mod attachment;
+pub mod payee;
+pub mod payment_request;
+pub mod deposit;
+pub mod linked_account;
+pub mod fx_quote;
+pub mod interest;
+pub mod fee;
+pub mod kyc;
+pub mod screening;
\ No newline at end of file
@@ -0,0 +1,238 @@
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// An upper-case ISO 4217 alphabetic currency code, e.g. `USD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    // Accepts any three letters in either case, rejecting anything not shaped like a code
+    pub fn parse(code: &str) -> Option<Currency> {
+        let code: [u8; 3] = code.trim().as_bytes().try_into().ok()?;
+        if code.iter().all(u8::is_ascii_alphabetic) {
+            Some(Currency(code.map(|c| c.to_ascii_uppercase())))
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("currency codes are ASCII")
+    }
+
+    // Decimal places of the currency's minor unit, e.g. 2 for US cents and 0 for yen; amounts can't
+    // be finer than that
+    pub fn minor_units(&self) -> i64 {
+        match self.as_str() {
+            "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX" | "VND" | "VUV" | "XAF"
+            | "XOF" | "XPF" => 0,
+            "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Currency::parse(&code).ok_or_else(|| D::Error::custom("invalid currency code"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoneyError {
+    // The amount has more decimal places than the currency's minor unit
+    TooPrecise(Currency),
+    // The amount doesn't fit in 64 bits of minor units
+    OutOfRange,
+    // Arithmetic on amounts in two different currencies
+    #[allow(dead_code)]
+    CurrencyMismatch(Currency, Currency),
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::TooPrecise(currency) => {
+                write!(f, "{} amounts can have at most {} decimal places", currency, currency.minor_units())
+            }
+            MoneyError::OutOfRange => f.write_str("Amount is out of range"),
+            MoneyError::CurrencyMismatch(a, b) => write!(f, "Cannot combine {} and {} amounts", a, b),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+// An exact amount of a currency, held as a whole number of its minor unit (cents for USD, yen for
+// JPY). Amounts are converted to and from decimals only at the edges, never through floats, and
+// are serialized as `{"amount": "12.50", "currency": "USD"}` with the currency's decimal places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    amount_minor: i64,
+    currency: Currency,
+}
+
+impl Money {
+    // Fails rather than round if the amount is finer than the currency's minor unit
+    pub fn from_decimal(amount: &BigDecimal, currency: Currency) -> Result<Money, MoneyError> {
+        let units = currency.minor_units();
+        let scaled = amount.with_scale(units);
+        if scaled != *amount {
+            return Err(MoneyError::TooPrecise(currency));
+        }
+        let (digits, _) = scaled.into_bigint_and_exponent();
+        let amount_minor = digits.to_i64().ok_or(MoneyError::OutOfRange)?;
+        Ok(Money { amount_minor, currency })
+    }
+
+    // An amount and currency code as stored; None for an unknown code, or an amount finer than the
+    // currency's minor unit as in rows from before amounts were held to it
+    pub fn from_stored(amount: &BigDecimal, currency: &str) -> Option<Money> {
+        Money::from_decimal(amount, Currency::parse(currency)?).ok()
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    // The amount in major units, with exactly the currency's decimal places
+    pub fn to_decimal(self) -> BigDecimal {
+        BigDecimal::new(BigInt::from(self.amount_minor), self.currency.minor_units())
+    }
+}
+
+// Whole-minor-unit access and arithmetic that never rounds or mixes currencies, for code that
+// totals amounts rather than passing them through
+#[allow(dead_code)]
+impl Money {
+    pub fn from_minor(amount_minor: i64, currency: Currency) -> Money {
+        Money { amount_minor, currency }
+    }
+
+    pub fn amount_minor(&self) -> i64 {
+        self.amount_minor
+    }
+
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.same_currency(other)?;
+        let amount_minor = self.amount_minor.checked_add(other.amount_minor).ok_or(MoneyError::OutOfRange)?;
+        Ok(Money { amount_minor, currency: self.currency })
+    }
+
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.same_currency(other)?;
+        let amount_minor = self.amount_minor.checked_sub(other.amount_minor).ok_or(MoneyError::OutOfRange)?;
+        Ok(Money { amount_minor, currency: self.currency })
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(self.currency, other.currency));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.to_decimal(), self.currency)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct MoneyRepr {
+    amount: String,
+    currency: Currency,
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoneyRepr { amount: self.to_decimal().to_string(), currency: self.currency }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MoneyRepr::deserialize(deserializer)?;
+        let amount = BigDecimal::from_str(&repr.amount).map_err(|_| D::Error::custom("invalid amount"))?;
+        Money::from_decimal(&amount, repr.currency).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn currency(code: &str) -> Currency {
+        Currency::parse(code).unwrap()
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_decimals_convert_exactly_to_minor_units() {
+        let usd = currency("usd");
+        assert_eq!(usd.as_str(), "USD");
+        assert_eq!(Money::from_decimal(&decimal("12.5"), usd).unwrap().amount_minor(), 1250);
+        assert_eq!(Money::from_decimal(&decimal("12.5000"), usd).unwrap().amount_minor(), 1250);
+        assert_eq!(Money::from_decimal(&decimal("-0.01"), usd).unwrap().amount_minor(), -1);
+        assert_eq!(Money::from_decimal(&decimal("1500"), currency("JPY")).unwrap().amount_minor(), 1500);
+        assert_eq!(Money::from_decimal(&decimal("1.234"), currency("KWD")).unwrap().amount_minor(), 1234);
+        assert_eq!(Money::from_minor(1250, usd).to_decimal().to_string(), "12.50");
+
+        assert_eq!(Money::from_decimal(&decimal("0.005"), usd), Err(MoneyError::TooPrecise(usd)));
+        assert_eq!(Money::from_decimal(&decimal("1e30"), usd), Err(MoneyError::OutOfRange));
+        assert!(Currency::parse("US").is_none());
+        assert!(Currency::parse("U5D").is_none());
+
+        assert_eq!(Money::from_stored(&decimal("12.50"), "USD"), Some(Money::from_minor(1250, usd)));
+        assert_eq!(Money::from_stored(&decimal("12.505"), "USD"), None);
+        assert_eq!(Money::from_stored(&decimal("12.50"), "???"), None);
+    }
+
+    #[test]
+    fn test_arithmetic_stays_in_one_currency() {
+        let usd = currency("USD");
+        let a = Money::from_minor(10, usd);
+        let b = Money::from_minor(20, usd);
+        // Would be 0.30000000000000004 in floating point
+        assert_eq!(a.checked_add(&b).unwrap().to_decimal(), decimal("0.3"));
+        assert_eq!(a.checked_sub(&b).unwrap().amount_minor(), -10);
+        assert_eq!(Money::from_minor(i64::MAX, usd).checked_add(&a), Err(MoneyError::OutOfRange));
+
+        let eur = currency("EUR");
+        assert_eq!(a.checked_add(&Money::from_minor(10, eur)), Err(MoneyError::CurrencyMismatch(usd, eur)));
+    }
+
+    #[test]
+    fn test_money_serializes_with_the_currency_decimal_places() {
+        let money = Money::from_minor(1250, currency("USD"));
+        let value = serde_json::to_value(money).unwrap();
+        assert_eq!(value, json!({ "amount": "12.50", "currency": "USD" }));
+        assert_eq!(serde_json::from_value::<Money>(value).unwrap(), money);
+        assert_eq!(money.to_string(), "12.50 USD");
+
+        assert!(serde_json::from_value::<Money>(json!({ "amount": "1.234", "currency": "USD" })).is_err());
+        assert!(serde_json::from_value::<Money>(json!({ "amount": "1", "currency": "usd!" })).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, Time};
+
+// Webhook deliveries of digests carry this event type; `data.event_type` names the batched events
+pub const EVENT_DIGEST: &str = "digest";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "notification_mode", rename_all = "lowercase")]
+pub enum NotificationMode {
+    // Each event is delivered as it happens
+    Instant,
+    // Events are delivered together once per UTC hour
+    Hourly,
+    // Events are delivered together once per UTC day
+    Daily,
+}
+
+impl NotificationMode {
+    // The digest period containing `at`, or None for instant delivery
+    pub fn period(self, at: OffsetDateTime) -> Option<(OffsetDateTime, OffsetDateTime)> {
+        let at = at.to_offset(time::UtcOffset::UTC);
+        match self {
+            NotificationMode::Instant => None,
+            NotificationMode::Hourly => {
+                let start = at.replace_time(Time::from_hms(at.hour(), 0, 0).unwrap());
+                Some((start, start + time::Duration::HOUR))
+            }
+            NotificationMode::Daily => {
+                let start = at.replace_time(Time::MIDNIGHT);
+                Some((start, start + time::Duration::DAY))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct NotificationPreference {
+    pub event_type: String,
+    pub mode: NotificationMode,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationPreference {
+    pub mode: NotificationMode,
+}
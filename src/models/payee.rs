@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use validator::Validate;
+
+// Shortest and longest account identifier accepted once normalized; IBANs run to 34 characters
+pub const MIN_ACCOUNT_IDENTIFIER_LENGTH: usize = 4;
+pub const MAX_ACCOUNT_IDENTIFIER_LENGTH: usize = 34;
+
+// Drops the spaces and dashes account numbers and IBANs are often written with and upper-cases
+// the rest, so `gb29 nwbk 6016` and `GB29NWBK6016` are the same payee; `None` when what's left
+// isn't alphanumeric or has the wrong length
+pub fn normalize_account_identifier(identifier: &str) -> Option<String> {
+    let normalized: String = identifier
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let valid = normalized.chars().all(|c| c.is_ascii_alphanumeric())
+        && (MIN_ACCOUNT_IDENTIFIER_LENGTH..=MAX_ACCOUNT_IDENTIFIER_LENGTH).contains(&normalized.len());
+    valid.then_some(normalized)
+}
+
+// A recipient the user has saved: another Dodo user, or an outside account identified by its
+// account number or IBAN
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Payee {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub recipient_user_id: Option<Uuid>,
+    pub account_identifier: Option<String>,
+    // The last time a transaction or transfer was made to the payee
+    pub last_used_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+// Exactly one of `recipient_user_id` and `account_identifier` must be given
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePayee {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+    pub recipient_user_id: Option<Uuid>,
+    pub account_identifier: Option<String>,
+}
+
+// Only the name can change; a payee with a different recipient is a different payee
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePayee {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_identifiers_are_normalized_for_duplicate_detection() {
+        assert_eq!(normalize_account_identifier("gb29 nwbk-6016 1331 9268 19").as_deref(), Some("GB29NWBK60161331926819"));
+        assert_eq!(normalize_account_identifier("12345678").as_deref(), Some("12345678"));
+        assert_eq!(normalize_account_identifier("1 2 3"), None);
+        assert_eq!(normalize_account_identifier("1234/5678"), None);
+        assert_eq!(normalize_account_identifier(&"1".repeat(35)), None);
+    }
+}
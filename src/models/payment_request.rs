@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::{Duration, OffsetDateTime};
+use bigdecimal::BigDecimal;
+use validator::Validate;
+
+use crate::models::transaction::default_currency;
+use crate::models::transfer::TransferResponse;
+
+// How long a request stays open when no expiry is given, and the longest it may stay open
+pub const DEFAULT_EXPIRY: Duration = Duration::days(7);
+pub const MAX_EXPIRY: Duration = Duration::days(30);
+
+// Requests start pending and end accepted or declined by the payer, cancelled by the requester,
+// or expired
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "payment_request_status", rename_all = "lowercase")]
+pub enum PaymentRequestStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Cancelled,
+    Expired,
+}
+
+// One user asking another for money. Accepting it transfers the amount from the payer to the
+// requester.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PaymentRequest {
+    pub id: Uuid,
+    pub requester_id: Uuid,
+    pub payer_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub description: Option<String>,
+    pub status: PaymentRequestStatus,
+    pub expires_at: OffsetDateTime,
+    // The transfer that paid the request, once accepted
+    pub transfer_id: Option<Uuid>,
+    // When the request left `Pending`
+    pub decided_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePaymentRequest {
+    pub payer_id: Uuid,
+    pub amount: BigDecimal,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[validate(length(max = 500, message = "Description must be at most 500 characters"))]
+    pub description: Option<String>,
+    // `DEFAULT_EXPIRY` from now when omitted
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaymentRequestQuery {
+    pub status: Option<PaymentRequestStatus>,
+}
+
+// An accepted request alongside the transfer that paid it
+#[derive(Debug, Serialize)]
+pub struct PaymentRequestAcceptance {
+    pub payment_request: PaymentRequest,
+    pub transfer: TransferResponse,
+}
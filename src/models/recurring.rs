@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::{Duration, Month, OffsetDateTime};
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::{default_currency, TransactionType};
+
+// Longest gap between occurrences, in periods of the schedule's frequency
+pub const MAX_INTERVAL_COUNT: i32 = 1000;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RecurringTransaction {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub description: Option<String>,
+    pub frequency: RecurrenceFrequency,
+    pub interval_count: i32,
+    pub remaining_occurrences: Option<i32>,
+    pub until: Option<OffsetDateTime>,
+    // The first occurrence, whose day of month monthly and yearly schedules keep
+    pub starts_at: OffsetDateTime,
+    pub next_run_at: OffsetDateTime,
+    pub status: RecurringStatus,
+    pub last_run_at: Option<OffsetDateTime>,
+    pub last_error: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl RecurringTransaction {
+    // The occurrence after `occurrence`, or `None` if it would fall past the dates `time` can represent
+    pub fn next_after(&self, occurrence: OffsetDateTime) -> Option<OffsetDateTime> {
+        self.frequency.advance(occurrence, self.interval_count, self.starts_at.day())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "recurrence_frequency", rename_all = "lowercase")]
+pub enum RecurrenceFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl RecurrenceFrequency {
+    // Returns the occurrence `interval` periods after `from`, or `None` past the dates `time` can
+    // represent. Monthly and yearly schedules land on `day` of the month, clamped to the last day of
+    // shorter months; passing the first occurrence's day keeps a clamp from carrying over
+    // (Jan 31 -> Feb 29 -> Mar 31).
+    pub fn advance(self, from: OffsetDateTime, interval: i32, day: u8) -> Option<OffsetDateTime> {
+        match self {
+            RecurrenceFrequency::Daily => from.checked_add(Duration::days(interval.into())),
+            RecurrenceFrequency::Weekly => from.checked_add(Duration::weeks(interval.into())),
+            RecurrenceFrequency::Monthly => add_months(from, interval, day),
+            RecurrenceFrequency::Yearly => add_months(from, interval.checked_mul(12)?, day),
+        }
+    }
+}
+
+fn add_months(from: OffsetDateTime, months: i32, day: u8) -> Option<OffsetDateTime> {
+    let total = (from.year() * 12 + from.month() as i32 - 1).checked_add(months)?;
+    let year = total.div_euclid(12);
+    let month = Month::try_from((total.rem_euclid(12) + 1) as u8).ok()?;
+    let day = day.min(month.length(year));
+    from.replace_day(1)
+        .and_then(|d| d.replace_year(year))
+        .and_then(|d| d.replace_month(month))
+        .and_then(|d| d.replace_day(day))
+        .ok()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "recurring_status", rename_all = "lowercase")]
+pub enum RecurringStatus {
+    Active,
+    Paused,
+    Completed,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRecurringTransaction {
+    pub amount: BigDecimal,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub description: Option<String>,
+    pub frequency: RecurrenceFrequency,
+    pub interval_count: Option<i32>,
+    // Total number of occurrences to post (RRULE COUNT)
+    pub count: Option<i32>,
+    // No occurrences are posted after this instant (RRULE UNTIL)
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub until: Option<OffsetDateTime>,
+    // First occurrence; defaults to now
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateRecurringTransaction {
+    pub amount: Option<BigDecimal>,
+    pub description: Option<String>,
+    // Only `Active` and `Paused` may be set by clients
+    pub status: Option<RecurringStatus>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_advance_daily_and_weekly() {
+        let start = datetime!(2024-03-30 09:00 UTC);
+        assert_eq!(RecurrenceFrequency::Daily.advance(start, 3, 30), Some(datetime!(2024-04-02 09:00 UTC)));
+        assert_eq!(RecurrenceFrequency::Weekly.advance(start, 2, 30), Some(datetime!(2024-04-13 09:00 UTC)));
+    }
+
+    #[test]
+    fn test_advance_monthly_clamps_to_month_end() {
+        let start = datetime!(2024-01-31 09:00 UTC);
+        assert_eq!(RecurrenceFrequency::Monthly.advance(start, 1, 31), Some(datetime!(2024-02-29 09:00 UTC)));
+        assert_eq!(RecurrenceFrequency::Monthly.advance(start, 11, 31), Some(datetime!(2024-12-31 09:00 UTC)));
+        assert_eq!(RecurrenceFrequency::Monthly.advance(start, 13, 31), Some(datetime!(2025-02-28 09:00 UTC)));
+        assert_eq!(RecurrenceFrequency::Yearly.advance(datetime!(2024-02-29 00:00 UTC), 1, 29), Some(datetime!(2025-02-28 00:00 UTC)));
+    }
+
+    #[test]
+    fn test_advance_monthly_returns_to_the_start_day_after_a_short_month() {
+        let february = RecurrenceFrequency::Monthly.advance(datetime!(2024-01-31 09:00 UTC), 1, 31).unwrap();
+        assert_eq!(RecurrenceFrequency::Monthly.advance(february, 1, 31), Some(datetime!(2024-03-31 09:00 UTC)));
+        assert_eq!(RecurrenceFrequency::Monthly.advance(february, 2, 31), Some(datetime!(2024-04-30 09:00 UTC)));
+        let leap = RecurrenceFrequency::Yearly.advance(datetime!(2025-02-28 00:00 UTC), 3, 29);
+        assert_eq!(leap, Some(datetime!(2028-02-29 00:00 UTC)));
+    }
+
+    #[test]
+    fn test_advance_past_the_calendar_is_none() {
+        let start = datetime!(2024-01-31 09:00 UTC);
+        assert_eq!(RecurrenceFrequency::Daily.advance(start, i32::MAX, 31), None);
+        assert_eq!(RecurrenceFrequency::Monthly.advance(start, i32::MAX, 31), None);
+        assert_eq!(RecurrenceFrequency::Yearly.advance(start, i32::MAX, 31), None);
+    }
+}
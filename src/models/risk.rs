@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::TransactionType;
+
+// What a fraud rule does to a transaction that trips it, least severe first. Flagged transactions
+// post as usual, held ones join the held debit review queue and blocked ones are refused.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, PartialOrd, Ord)]
+#[sqlx(type_name = "risk_action", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum RiskAction {
+    Flag,
+    Hold,
+    Block,
+}
+
+// A fraud rule a transaction tripped, kept for admins to review
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RiskEvent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    // Null when the transaction was blocked, and so never stored
+    pub transaction_id: Option<Uuid>,
+    pub rule: String,
+    pub action: RiskAction,
+    pub reason: String,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub created_at: OffsetDateTime,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<OffsetDateTime>,
+    pub review_note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RiskEventQuery {
+    // Lists events already reviewed instead of the review queue
+    #[serde(default)]
+    pub reviewed: bool,
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReviewRiskEvent {
+    pub note: Option<String>,
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+// A user's round-up setting: while enabled, the spare change of each debit in the account's
+// currency is saved in `account_id`
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RoundUpRule {
+    pub user_id: Uuid,
+    pub account_id: Uuid,
+    pub enabled: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoundUpRule {
+    pub account_id: Uuid,
+    pub enabled: bool,
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::{BigDecimal, RoundingMode};
+use validator::Validate;
+
+use crate::models::recurring::RecurrenceFrequency;
+
+// A target amount to save up in one of the user's accounts, optionally fed by an auto-transfer
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SavingsGoal {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    // The account being saved in; its balance is the goal's progress
+    pub account_id: Uuid,
+    pub name: String,
+    pub target_amount: BigDecimal,
+    pub deadline: Option<OffsetDateTime>,
+    // The auto-transfer rule, either all set or all null
+    pub auto_transfer_from_account_id: Option<Uuid>,
+    pub auto_transfer_amount: Option<BigDecimal>,
+    pub auto_transfer_frequency: Option<RecurrenceFrequency>,
+    // Null once the goal is reached or when there is no rule
+    pub next_auto_transfer_at: Option<OffsetDateTime>,
+    pub last_auto_transfer_at: Option<OffsetDateTime>,
+    pub last_auto_transfer_error: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+// Moves `amount` from another of the user's accounts into the goal's account every `frequency`
+#[derive(Debug, Deserialize)]
+pub struct AutoTransferRule {
+    pub from_account_id: Uuid,
+    pub amount: BigDecimal,
+    pub frequency: RecurrenceFrequency,
+    // First transfer; defaults to now
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateSavingsGoal {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: String,
+    pub account_id: Uuid,
+    pub target_amount: BigDecimal,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub deadline: Option<OffsetDateTime>,
+    pub auto_transfer: Option<AutoTransferRule>,
+}
+
+// Fields left out stay as they are; the auto-transfer rule has its own endpoints
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct UpdateSavingsGoal {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: Option<String>,
+    pub target_amount: Option<BigDecimal>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub deadline: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GoalProgress {
+    // Settled balance of the goal's account
+    pub saved: BigDecimal,
+    // What is still missing, never below zero
+    pub remaining: BigDecimal,
+    // Share of the target saved, from 0 to 100 with two decimal places
+    pub percent_complete: BigDecimal,
+    pub achieved: bool,
+}
+
+impl GoalProgress {
+    pub fn new(saved: BigDecimal, target: &BigDecimal) -> GoalProgress {
+        let zero = BigDecimal::from(0);
+        let remaining = (target - &saved).max(zero.clone());
+        let percent_complete = (&saved * BigDecimal::from(100) / target)
+            .clamp(zero, BigDecimal::from(100))
+            .with_scale_round(2, RoundingMode::Down);
+        let achieved = saved >= *target;
+        GoalProgress { saved, remaining, percent_complete, achieved }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavingsGoalDetails {
+    #[serde(flatten)]
+    pub goal: SavingsGoal,
+    pub progress: GoalProgress,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_progress_is_clamped_to_the_target() {
+        let target = decimal("300");
+        let progress = GoalProgress::new(decimal("100"), &target);
+        assert_eq!(progress.remaining, decimal("200"));
+        assert_eq!(progress.percent_complete.to_string(), "33.33");
+        assert!(!progress.achieved);
+
+        let over = GoalProgress::new(decimal("450"), &target);
+        assert_eq!((over.remaining, over.percent_complete), (decimal("0"), decimal("100")));
+        assert!(over.achieved);
+
+        // An overdrawn account hasn't saved anything yet
+        let overdrawn = GoalProgress::new(decimal("-20"), &target);
+        assert_eq!((overdrawn.remaining, overdrawn.percent_complete), (decimal("320"), decimal("0")));
+    }
+}
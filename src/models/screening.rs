@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "screening_operation", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ScreeningOperation {
+    Registration,
+    Transfer,
+}
+
+// What screening a party found. A provider that fails can't vouch for anyone, so errors are held for
+// review like hits.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "screening_outcome", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ScreeningOutcome {
+    Clear,
+    Hit,
+    Error,
+}
+
+impl ScreeningOutcome {
+    pub fn is_flagged(self) -> bool {
+        self != ScreeningOutcome::Clear
+    }
+}
+
+// Cleared results were false positives and let the operation through; confirmed ones stop it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "screening_decision", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ScreeningDecision {
+    Cleared,
+    Confirmed,
+}
+
+// One party screened during a registration or transfer
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ScreeningResult {
+    pub id: Uuid,
+    pub operation: ScreeningOperation,
+    // The party screened; for a transfer, either the sender or the recipient
+    pub user_id: Uuid,
+    pub transfer_id: Option<Uuid>,
+    // The `Screening` implementation that screened them
+    pub provider: String,
+    pub outcome: ScreeningOutcome,
+    // The list entry matched, or what went wrong; null when clear
+    pub reason: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<OffsetDateTime>,
+    pub decision: Option<ScreeningDecision>,
+    pub review_note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScreeningQuery {
+    // Lists flagged results already reviewed instead of the review queue
+    #[serde(default)]
+    pub reviewed: bool,
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewScreening {
+    pub decision: ScreeningDecision,
+    #[validate(length(max = 1000, message = "Note must be at most 1000 characters"))]
+    pub note: Option<String>,
+}
@@ -0,0 +1,32 @@
+use bigdecimal::BigDecimal;
+use std::collections::BTreeMap;
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::models::transaction::TransactionType;
+
+// A ledger entry as it appears on a statement
+#[derive(Debug)]
+pub struct StatementEntry {
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub transaction_type: TransactionType,
+    pub description: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+// Everything a monthly statement shows, before rendering
+#[derive(Debug)]
+pub struct Statement {
+    pub user_id: Uuid,
+    pub name: String,
+    pub email: String,
+    // First and last day of the month covered
+    pub period_start: Date,
+    pub period_end: Date,
+    // Settled balance per currency when the month began
+    pub opening_balances: BTreeMap<String, BigDecimal>,
+    // The month's settled entries, oldest first
+    pub entries: Vec<StatementEntry>,
+    pub generated_at: OffsetDateTime,
+}
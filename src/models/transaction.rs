@@ -1,36 +1,305 @@
+use axum::http::HeaderValue;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use time::OffsetDateTime;
 use bigdecimal::BigDecimal;
+use std::collections::BTreeMap;
+use validator::Validate;
+
+use crate::models::money::Currency;
+
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+// Currency assumed when a request doesn't name one
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+pub fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+// Normalizes a currency to its upper-case ISO 4217 alphabetic code, rejecting anything not shaped like one
+pub fn normalize_currency(code: &str) -> Option<String> {
+    Currency::parse(code).map(|currency| currency.to_string())
+}
+
+// Longest spending category accepted
+pub const MAX_CATEGORY_LENGTH: usize = 64;
+
+// Trims and lower-cases a category so `Groceries` and `groceries ` are grouped together; `None`
+// when it's empty or too long
+pub fn normalize_category(category: &str) -> Option<String> {
+    let category = category.trim();
+    if category.is_empty() || category.chars().count() > MAX_CATEGORY_LENGTH {
+        None
+    } else {
+        Some(category.to_lowercase())
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Transaction {
     pub id: Uuid,
     pub user_id: Uuid,
+    // The account the entry is posted to; the user's main account in its currency unless one was named
+    pub account_id: Uuid,
     pub amount: BigDecimal,
+    pub currency: String,
     pub transaction_type: TransactionType,
     pub description: Option<String>,
+    pub transfer_id: Option<Uuid>,
+    pub status: TransactionStatus,
+    pub reverses: Option<Uuid>,
+    pub reversed_by: Option<Uuid>,
+    pub execute_at: Option<OffsetDateTime>,
     pub created_at: OffsetDateTime,
+    // False for test data created with a sandbox API key, which never counts towards live balances
+    pub livemode: bool,
+    pub category: Option<String>,
+    // The saved payee the transaction was made to
+    pub payee_id: Option<Uuid>,
+    // The exchange rate applied when the entry is a leg of a converting transfer
+    pub fx_rate: Option<BigDecimal>,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
 #[sqlx(type_name = "transaction_type", rename_all = "lowercase")]
 pub enum TransactionType {
     Credit,
     Debit,
 }
 
-#[derive(Debug, Deserialize)]
+impl TransactionType {
+    // Applies the type's direction to an unsigned amount: credits positive, debits negative
+    pub fn sign(self, amount: &BigDecimal) -> BigDecimal {
+        match self {
+            TransactionType::Credit => amount.clone(),
+            TransactionType::Debit => -amount,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            TransactionType::Credit => TransactionType::Debit,
+            TransactionType::Debit => TransactionType::Credit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "transaction_status", rename_all = "lowercase")]
+pub enum TransactionStatus {
+    Settled,
+    Reversed,
+    // Future-dated, waiting for `execute_at`; doesn't count towards the balance until posted
+    Scheduled,
+    // Awaiting settlement; pending debits already reduce the available balance
+    Pending,
+    Cancelled,
+    // A scheduled debit the balance couldn't cover when it came due
+    Failed,
+    // Caught by a debit hold and waiting for an admin to release or deny it
+    Held,
+    // A held debit an admin refused to post, or a transaction whose approval was rejected
+    Denied,
+    // Above the approval threshold and waiting for a second person to approve or reject it
+    #[sqlx(rename = "awaiting_approval")]
+    AwaitingApproval,
+}
+
+impl TransactionStatus {
+    // Never posted and never will be. Reversed transactions did post, so they aren't void.
+    pub fn is_void(self) -> bool {
+        matches!(self, TransactionStatus::Cancelled | TransactionStatus::Failed | TransactionStatus::Denied)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateTransaction {
     pub amount: BigDecimal,
+    #[serde(default = "default_currency")]
+    pub currency: String,
     pub transaction_type: TransactionType,
+    #[validate(length(max = 500, message = "Description must be at most 500 characters"))]
     pub description: Option<String>,
+    // Post the transaction at this instant instead of immediately
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub execute_at: Option<OffsetDateTime>,
+    // Create the transaction as pending, to be settled or cancelled later
+    #[serde(default)]
+    pub pending: bool,
+    // Free-form spending category, e.g. `groceries`, for analytics
+    pub category: Option<String>,
+    // One of the user's accounts in the same currency; their main account when omitted
+    pub account_id: Option<Uuid>,
+    // One of the user's saved payees, recording who the transaction was made to
+    pub payee_id: Option<Uuid>,
 }
 
+// The reversed original alongside the compensating entry that offsets it
 #[derive(Debug, Serialize)]
+pub struct TransactionReversal {
+    pub original: Transaction,
+    pub reversal: Transaction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AccountBalance {
     pub user_id: Uuid,
+    // Settled balance per ISO 4217 currency code
+    pub balances: BTreeMap<String, BigDecimal>,
+    // Settled balance less pending debits, i.e. what can be spent right now
+    pub available: BTreeMap<String, BigDecimal>,
+    // Net amount of pending transactions
+    pub pending: BTreeMap<String, BigDecimal>,
+    pub last_updated: Option<OffsetDateTime>,
+}
+
+// A user's ledger totals in one currency
+#[derive(Debug, FromRow)]
+pub struct CurrencyBalance {
+    pub currency: String,
+    // Settled balance
     pub balance: BigDecimal,
+    // Net amount of pending transactions
+    pub pending: BigDecimal,
+    // Sum of pending debits, which are already held against the available balance
+    pub held: BigDecimal,
     pub last_updated: Option<OffsetDateTime>,
-} 
\ No newline at end of file
+}
+
+impl CurrencyBalance {
+    pub fn available(&self) -> BigDecimal {
+        &self.balance - &self.held
+    }
+}
+
+// A sum of a user's entries in one currency
+#[derive(Debug, FromRow)]
+pub struct CurrencyTotal {
+    pub currency: String,
+    pub total: BigDecimal,
+}
+
+// Posted (settled or reversed) totals and activity in one currency
+#[derive(Debug, Serialize, FromRow)]
+pub struct CurrencySummary {
+    pub currency: String,
+    pub total_credits: BigDecimal,
+    pub total_debits: BigDecimal,
+    // Total credits less total debits
+    pub balance: BigDecimal,
+    pub transaction_count: i64,
+    pub first_activity: Option<OffsetDateTime>,
+    pub last_activity: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountSummary {
+    pub user_id: Uuid,
+    pub currencies: Vec<CurrencySummary>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TransactionQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    // Return debits as negative amounts, for tools that expect signed amounts over a type column
+    #[serde(default)]
+    pub signed_amounts: bool,
+}
+
+// Opaque keyset position in a user's history, ordered by `(created_at, id)` descending
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionCursor {
+    pub created_at: OffsetDateTime,
+    pub id: Uuid,
+}
+
+impl TransactionCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.created_at.unix_timestamp_nanos(), self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let (nanos, id) = raw.split_once(':')?;
+        Some(Self {
+            created_at: OffsetDateTime::from_unix_timestamp_nanos(nanos.parse().ok()?).ok()?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+// A page of transactions; the cursor for the following page is returned in `X-Next-Cursor`
+#[derive(Debug)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub next_cursor: Option<String>,
+    // Serialize debits with negative amounts; stored amounts are always positive
+    pub signed_amounts: bool,
+}
+
+// A transaction as serialized with a signed amount; every other field is unchanged
+#[derive(Serialize)]
+struct SignedTransaction<'a> {
+    id: Uuid,
+    user_id: Uuid,
+    account_id: Uuid,
+    amount: BigDecimal,
+    currency: &'a str,
+    transaction_type: TransactionType,
+    description: Option<&'a str>,
+    transfer_id: Option<Uuid>,
+    status: TransactionStatus,
+    reverses: Option<Uuid>,
+    reversed_by: Option<Uuid>,
+    execute_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+    livemode: bool,
+    category: Option<&'a str>,
+    payee_id: Option<Uuid>,
+    fx_rate: Option<&'a BigDecimal>,
+}
+
+impl<'a> From<&'a Transaction> for SignedTransaction<'a> {
+    fn from(transaction: &'a Transaction) -> Self {
+        Self {
+            id: transaction.id,
+            user_id: transaction.user_id,
+            account_id: transaction.account_id,
+            amount: transaction.transaction_type.sign(&transaction.amount),
+            currency: &transaction.currency,
+            transaction_type: transaction.transaction_type,
+            description: transaction.description.as_deref(),
+            transfer_id: transaction.transfer_id,
+            status: transaction.status,
+            reverses: transaction.reverses,
+            reversed_by: transaction.reversed_by,
+            execute_at: transaction.execute_at,
+            created_at: transaction.created_at,
+            livemode: transaction.livemode,
+            category: transaction.category.as_deref(),
+            payee_id: transaction.payee_id,
+            fx_rate: transaction.fx_rate.as_ref(),
+        }
+    }
+}
+
+impl IntoResponse for TransactionPage {
+    fn into_response(self) -> Response {
+        let mut response = if self.signed_amounts {
+            Json(self.transactions.iter().map(SignedTransaction::from).collect::<Vec<_>>()).into_response()
+        } else {
+            Json(self.transactions).into_response()
+        };
+        if let Some(cursor) = self.next_cursor.and_then(|cursor| HeaderValue::from_str(&cursor).ok()) {
+            response.headers_mut().insert(NEXT_CURSOR_HEADER, cursor);
+        }
+        response
+    }
+}
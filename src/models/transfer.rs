@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::{default_currency, Transaction};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Transfer {
+    pub id: Uuid,
+    pub from_user_id: Uuid,
+    pub to_user_id: Uuid,
+    // Set when the user moves money between their own accounts
+    pub from_account_id: Option<Uuid>,
+    pub to_account_id: Option<Uuid>,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub description: Option<String>,
+    // The sender's saved payee the transfer was made to
+    pub payee_id: Option<Uuid>,
+    // Set when the recipient was credited in another currency, at `fx_rate` after the spread
+    pub converted_amount: Option<BigDecimal>,
+    pub converted_currency: Option<String>,
+    pub fx_rate: Option<BigDecimal>,
+    pub created_at: OffsetDateTime,
+}
+
+// Exactly one of `to_user_id` and `payee_id` must be given; the payee must be a Dodo user
+#[derive(Debug, Deserialize)]
+pub struct CreateTransfer {
+    pub to_user_id: Option<Uuid>,
+    pub payee_id: Option<Uuid>,
+    pub amount: BigDecimal,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    // Credit the recipient in this currency instead, converted at the latest rate less the spread
+    pub to_currency: Option<String>,
+    pub description: Option<String>,
+}
+
+// A transfer from the account in the path to another of the user's accounts in the same currency
+#[derive(Debug, Deserialize)]
+pub struct CreateAccountTransfer {
+    pub to_account_id: Uuid,
+    pub amount: BigDecimal,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferResponse {
+    pub transfer: Transfer,
+    pub debit: Transaction,
+    pub credit: Transaction,
+    // Fee debits charged on the transfer, each credited to the house
+    pub fees: Vec<Transaction>,
+}
@@ -1,7 +1,13 @@
+use axum::http::HeaderValue;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use time::OffsetDateTime;
+use validator::Validate;
+
+use crate::models::transaction::{CurrencySummary, NEXT_CURSOR_HEADER};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -10,20 +16,117 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub name: String,
+    pub role: UserRole,
+    pub tier: UserTier,
+    // Attributes integrators store for their own use; always a JSON object
+    pub metadata: serde_json::Value,
+    pub status: UserStatus,
+    pub deactivated_at: Option<OffsetDateTime>,
+    // Set when the user deleted their own account
+    pub deleted_at: Option<OffsetDateTime>,
+    // Bumped to sign out every session, e.g. when the password changes
+    #[serde(skip_serializing)]
+    pub session_version: i32,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+pub enum UserRole {
+    User,
+    Admin,
+}
+
+// Plan the user is on, which sets their usage limits
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "user_tier", rename_all = "lowercase")]
+pub enum UserTier {
+    Free,
+    Plus,
+    Business,
+}
+
+// Deactivated users keep their data but can't sign in or use their credentials. Registrants flagged
+// by screening can't either, until an admin reviews the result.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "user_status", rename_all = "lowercase")]
+pub enum UserStatus {
+    Active,
+    Deactivated,
+    #[sqlx(rename = "pending_review")]
+    PendingReview,
+}
+
 #[derive(Debug, Deserialize)]
+pub struct UpdateUserTier {
+    pub tier: UserTier,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateUser {
+    #[validate(email(message = "Invalid email format"))]
     pub email: String,
+    #[validate(custom(function = "crate::validation::password_policy"))]
     pub password: String,
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
     pub name: String,
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
+pub struct UserSearchQuery {
+    // Case-insensitive substring of the email address
+    pub email: Option<String>,
+    // Case-insensitive substring of the name
+    pub name: Option<String>,
+    // JSON object the user's metadata must contain, e.g. `{"tenant":"acme"}`
+    pub metadata: Option<String>,
+    pub status: Option<UserStatus>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+// A page of users; the cursor for the following page is returned in `X-Next-Cursor`
+#[derive(Debug)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    pub next_cursor: Option<String>,
+}
+
+impl IntoResponse for UserPage {
+    fn into_response(self) -> Response {
+        let mut response = Json(self.users).into_response();
+        if let Some(cursor) = self.next_cursor.and_then(|cursor| HeaderValue::from_str(&cursor).ok()) {
+            response.headers_mut().insert(NEXT_CURSOR_HEADER, cursor);
+        }
+        response
+    }
+}
+
+// A user as support sees them: their record and live ledger totals per currency
+#[derive(Debug, Serialize)]
+pub struct AdminUserDetail {
+    pub user: User,
+    pub currencies: Vec<CurrencySummary>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeactivateUser {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct LoginUser {
+    #[validate(email(message = "Invalid email format"))]
     pub email: String,
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StepUpRequest {
     pub password: String,
 }
 
@@ -37,4 +140,40 @@ pub struct AuthResponse {
 pub struct RegisterResponse {
     pub message: String,
     pub user: User,
-} 
\ No newline at end of file
+} 
+
+// The authenticated caller: their user record, plus the scopes when calling with an API key
+#[derive(Debug, Serialize)]
+pub struct CurrentUser {
+    pub user: User,
+    // `None` for sessions, which have full access
+    pub scopes: Option<Vec<String>>,
+}
+
+// The caller's own user record, with an email change that is still waiting for confirmation
+#[derive(Debug, Serialize)]
+pub struct Profile {
+    #[serde(flatten)]
+    pub user: User,
+    pub pending_email: Option<String>,
+}
+
+// What users may change about themselves; fields left out stay as they are
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfile {
+    pub name: Option<String>,
+    // Takes effect once the user follows the link sent to the new address
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePassword {
+    pub current_password: String,
+    #[validate(custom(function = "crate::validation::password_policy"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmail {
+    pub token: String,
+}
\ No newline at end of file
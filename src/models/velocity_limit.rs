@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+use crate::models::transaction::default_currency;
+
+// Limits on how fast a user can move money, checked when their transactions are created. A null
+// limit doesn't apply; amounts are in `currency`, and transactions in others are converted into it.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct VelocityLimits {
+    pub user_id: Uuid,
+    pub currency: String,
+    pub max_transaction_amount: Option<BigDecimal>,
+    // Debits created in the last 24 hours, in any currency
+    pub max_daily_debit_total: Option<BigDecimal>,
+    // Transactions created in the last hour, in any currency
+    pub max_transactions_per_hour: Option<i32>,
+    pub updated_by: Uuid,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+// Replaces all three limits; leaving one out removes it
+#[derive(Debug, Deserialize)]
+pub struct UpdateVelocityLimits {
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub max_transaction_amount: Option<BigDecimal>,
+    pub max_daily_debit_total: Option<BigDecimal>,
+    pub max_transactions_per_hour: Option<i32>,
+}
+
+// Named after the field setting the limit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum VelocityLimitKind {
+    #[serde(rename = "max_transaction_amount")]
+    TransactionAmount,
+    #[serde(rename = "max_daily_debit_total")]
+    DailyDebitTotal,
+    #[serde(rename = "max_transactions_per_hour")]
+    TransactionsPerHour,
+}
+
+// Which limit a transaction would break and by how much, returned to the client in the error body
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VelocityViolation {
+    pub limit: VelocityLimitKind,
+    pub max: BigDecimal,
+    // Usage in the limit's window before this transaction; absent for the per-transaction limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used: Option<BigDecimal>,
+    // What this transaction would add: its amount, or 1 for the hourly count
+    pub requested: BigDecimal,
+    // The limits' currency, which amounts are given in; absent for the hourly count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+impl VelocityViolation {
+    pub fn message(&self) -> String {
+        let unit = self.currency.as_deref().map(|currency| format!(" {}", currency)).unwrap_or_default();
+        match self.limit {
+            VelocityLimitKind::TransactionAmount => {
+                format!("Transactions are limited to {}{}", self.max, unit)
+            }
+            VelocityLimitKind::DailyDebitTotal => {
+                format!("Debits are limited to {}{} in 24 hours", self.max, unit)
+            }
+            VelocityLimitKind::TransactionsPerHour => {
+                format!("At most {} transactions can be made in an hour", self.max)
+            }
+        }
+    }
+}
+
+// Windowed usage in one currency, before the new transaction
+#[derive(Debug, FromRow)]
+pub struct CurrencyUsage {
+    pub currency: String,
+    pub daily_debit_total: BigDecimal,
+    pub hourly_transaction_count: i64,
+}
+
+// Windowed usage the limits are checked against, with debits converted into the limits' currency
+#[derive(Debug)]
+pub struct VelocityUsage {
+    pub daily_debit_total: BigDecimal,
+    pub hourly_transaction_count: i64,
+}
+
+impl VelocityLimits {
+    // The first limit a transaction of `amount`, converted into the limits' currency, would break,
+    // given the usage so far
+    pub fn check(&self, amount: &BigDecimal, is_debit: bool, usage: &VelocityUsage) -> Option<VelocityViolation> {
+        if let Some(max) = &self.max_transaction_amount {
+            if amount > max {
+                return Some(VelocityViolation {
+                    limit: VelocityLimitKind::TransactionAmount,
+                    max: max.clone(),
+                    used: None,
+                    requested: amount.clone(),
+                    currency: Some(self.currency.clone()),
+                });
+            }
+        }
+        if let Some(max) = self.max_daily_debit_total.as_ref().filter(|_| is_debit) {
+            if &usage.daily_debit_total + amount > *max {
+                return Some(VelocityViolation {
+                    limit: VelocityLimitKind::DailyDebitTotal,
+                    max: max.clone(),
+                    used: Some(usage.daily_debit_total.clone()),
+                    requested: amount.clone(),
+                    currency: Some(self.currency.clone()),
+                });
+            }
+        }
+        if let Some(max) = self.max_transactions_per_hour {
+            if usage.hourly_transaction_count >= i64::from(max) {
+                return Some(VelocityViolation {
+                    limit: VelocityLimitKind::TransactionsPerHour,
+                    max: BigDecimal::from(max),
+                    used: Some(BigDecimal::from(usage.hourly_transaction_count)),
+                    requested: BigDecimal::from(1),
+                    currency: None,
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_broken_limit_is_reported() {
+        let now = OffsetDateTime::now_utc();
+        let limits = VelocityLimits {
+            user_id: Uuid::new_v4(),
+            currency: "EUR".to_string(),
+            max_transaction_amount: Some(BigDecimal::from(500)),
+            max_daily_debit_total: Some(BigDecimal::from(1000)),
+            max_transactions_per_hour: Some(5),
+            updated_by: Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+        };
+        let usage = |debits: i32, count: i64| VelocityUsage { daily_debit_total: BigDecimal::from(debits), hourly_transaction_count: count };
+        let amount = |value: i32| BigDecimal::from(value);
+
+        assert_eq!(limits.check(&amount(500), true, &usage(500, 4)), None);
+        let kind = |violation: Option<VelocityViolation>| violation.map(|violation| violation.limit);
+        assert_eq!(kind(limits.check(&amount(501), false, &usage(0, 0))), Some(VelocityLimitKind::TransactionAmount));
+        assert_eq!(kind(limits.check(&amount(100), true, &usage(950, 0))), Some(VelocityLimitKind::DailyDebitTotal));
+        // Credits don't count towards the daily debit total
+        assert_eq!(limits.check(&amount(100), false, &usage(950, 0)), None);
+        assert_eq!(kind(limits.check(&amount(1), false, &usage(0, 5))), Some(VelocityLimitKind::TransactionsPerHour));
+
+        let violation = limits.check(&amount(100), true, &usage(950, 0)).unwrap();
+        assert_eq!(violation.message(), "Debits are limited to 1000 EUR in 24 hours");
+    }
+}
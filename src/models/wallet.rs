@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use time::OffsetDateTime;
+use bigdecimal::BigDecimal;
+
+// One currency the user holds, with the same figures as `AccountBalance`
+#[derive(Debug, Serialize)]
+pub struct Wallet {
+    pub currency: String,
+    pub balance: BigDecimal,
+    pub available: BigDecimal,
+    pub pending: BigDecimal,
+    pub last_activity: Option<OffsetDateTime>,
+    // Null when no display currency was requested or no rate is known for this pair
+    pub converted: Option<ConvertedWallet>,
+}
+
+// A wallet's figures in the display currency at the latest known rate
+#[derive(Debug, Serialize)]
+pub struct ConvertedWallet {
+    pub currency: String,
+    pub balance: BigDecimal,
+    pub available: BigDecimal,
+    pub pending: BigDecimal,
+    // Units of the display currency one unit of the wallet currency buys
+    pub rate: BigDecimal,
+    // When the rate was fetched; null for the display currency itself
+    pub rate_fetched_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletList {
+    pub user_id: Uuid,
+    pub display_currency: Option<String>,
+    pub wallets: Vec<Wallet>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WalletQuery {
+    pub display_currency: Option<String>,
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+pub const EVENT_TRANSACTION_CREATED: &str = "transaction.created";
+pub const EVENT_BALANCE_UPDATED: &str = "balance.updated";
+pub const EVENT_BUDGET_THRESHOLD_REACHED: &str = "budget.threshold_reached";
+pub const EVENT_APPROVAL_REQUESTED: &str = "approval.requested";
+pub const EVENT_APPROVAL_DECIDED: &str = "approval.decided";
+pub const EVENT_DISPUTE_OPENED: &str = "dispute.opened";
+pub const EVENT_DISPUTE_UPDATED: &str = "dispute.updated";
+pub const EVENT_PAYMENT_REQUEST_CREATED: &str = "payment_request.created";
+pub const EVENT_PAYMENT_REQUEST_UPDATED: &str = "payment_request.updated";
+pub const EVENT_API_KEY_EXPIRING: &str = "api_key.expiring";
+
+pub const ALL_EVENTS: &[&str] = &[
+    EVENT_TRANSACTION_CREATED,
+    EVENT_BALANCE_UPDATED,
+    EVENT_BUDGET_THRESHOLD_REACHED,
+    EVENT_APPROVAL_REQUESTED,
+    EVENT_APPROVAL_DECIDED,
+    EVENT_DISPUTE_OPENED,
+    EVENT_DISPUTE_UPDATED,
+    EVENT_PAYMENT_REQUEST_CREATED,
+    EVENT_PAYMENT_REQUEST_UPDATED,
+    EVENT_API_KEY_EXPIRING,
+];
+
+pub const SIGNATURE_HEADER: &str = "x-dodo-signature";
+pub const EVENT_HEADER: &str = "x-dodo-event";
+pub const DELIVERY_HEADER: &str = "x-dodo-delivery";
+
+// Shape of the payloads an endpoint receives. Endpoints stay on the version they subscribed with
+// until they are moved, so changes to event payloads don't break existing receivers.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "webhook_payload_version", rename_all = "lowercase")]
+pub enum WebhookPayloadVersion {
+    // `{ id, type, data }`, with the transaction under `data.transaction`
+    V1,
+    // Adds `version` and `created_at` to the envelope; `data` is the transaction itself
+    #[default]
+    V2,
+}
+
+impl WebhookPayloadVersion {
+    pub const ALL: [WebhookPayloadVersion; 2] = [WebhookPayloadVersion::V1, WebhookPayloadVersion::V2];
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    pub version: WebhookPayloadVersion,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookEndpoint {
+    pub url: String,
+    pub events: Vec<String>,
+    // The latest version when omitted
+    #[serde(default)]
+    pub version: WebhookPayloadVersion,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookEndpoint {
+    pub version: WebhookPayloadVersion,
+}
+
+// The signing secret is only ever returned once, at creation time
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhookEndpoint {
+    pub secret: String,
+    pub endpoint: WebhookEndpoint,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    // Gave up after the maximum number of attempts
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: OffsetDateTime,
+    pub last_response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub delivered_at: Option<OffsetDateTime>,
+}
@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    // Requests flow normally while consecutive failures stay under the threshold
+    Closed { failures: u32 },
+    // Requests are rejected without touching the network until `until`
+    Open { until: Instant },
+    // A single trial request is allowed through to probe whether the host recovered
+    HalfOpen { in_flight: bool },
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: CircuitState,
+    failure_threshold: u32,
+    open_duration: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            state: CircuitState::Closed { failures: 0 },
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+        }
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    // Returns whether a request may be attempted right now
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed { .. } => true,
+            CircuitState::Open { until } if now >= until => {
+                self.state = CircuitState::HalfOpen { in_flight: true };
+                true
+            }
+            CircuitState::Open { .. } => false,
+            CircuitState::HalfOpen { in_flight: false } => {
+                self.state = CircuitState::HalfOpen { in_flight: true };
+                true
+            }
+            CircuitState::HalfOpen { in_flight: true } => false,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.state = CircuitState::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&mut self, now: Instant) {
+        self.state = match self.state {
+            CircuitState::Closed { failures } if failures + 1 < self.failure_threshold => {
+                CircuitState::Closed { failures: failures + 1 }
+            }
+            _ => CircuitState::Open { until: now + self.open_duration },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+
+        for _ in 0..2 {
+            assert!(breaker.try_acquire(now));
+            breaker.record_failure(now);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed { failures: 2 });
+
+        breaker.record_failure(now);
+        assert!(!breaker.try_acquire(now));
+        assert!(!breaker.try_acquire(now + Duration::from_secs(29)));
+    }
+
+    #[test]
+    fn test_half_open_allows_single_probe() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        breaker.record_failure(now);
+
+        let later = now + Duration::from_secs(30);
+        assert!(breaker.try_acquire(later));
+        assert!(!breaker.try_acquire(later));
+
+        breaker.record_success();
+        assert!(breaker.try_acquire(later));
+        assert_eq!(breaker.state(), CircuitState::Closed { failures: 0 });
+    }
+
+    #[test]
+    fn test_failed_probe_reopens() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        breaker.record_failure(now);
+
+        let later = now + Duration::from_secs(30);
+        assert!(breaker.try_acquire(later));
+        breaker.record_failure(later);
+        assert!(!breaker.try_acquire(later + Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,204 @@
+pub mod circuit_breaker;
+pub mod retry_budget;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, Request, Response};
+
+use crate::config::Config;
+
+use circuit_breaker::CircuitBreaker;
+use retry_budget::RetryBudget;
+
+// Isolation settings applied to every request sent to a single host
+#[derive(Debug, Clone)]
+pub struct HostPolicy {
+    pub timeout: Duration,
+    pub max_retries: u32,
+    pub retry_ratio: f64,
+    pub failure_threshold: u32,
+    pub open_duration: Duration,
+}
+
+impl HostPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            timeout: Duration::from_millis(config.outbound_timeout_ms),
+            max_retries: config.outbound_max_retries,
+            retry_ratio: config.outbound_retry_ratio,
+            failure_threshold: config.outbound_failure_threshold,
+            open_duration: Duration::from_millis(config.outbound_circuit_open_ms),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum OutboundError {
+    CircuitOpen(String),
+    Timeout(String),
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+}
+
+impl fmt::Display for OutboundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutboundError::CircuitOpen(host) => write!(f, "circuit open for {}", host),
+            OutboundError::Timeout(host) => write!(f, "request to {} timed out", host),
+            OutboundError::Request(e) => write!(f, "request failed: {}", e),
+            OutboundError::Status(status) => write!(f, "upstream returned {}", status),
+        }
+    }
+}
+
+impl std::error::Error for OutboundError {}
+
+struct HostState {
+    policy: HostPolicy,
+    breaker: CircuitBreaker,
+    budget: RetryBudget,
+}
+
+impl HostState {
+    fn new(policy: HostPolicy) -> Self {
+        Self {
+            breaker: CircuitBreaker::new(policy.failure_threshold, policy.open_duration),
+            budget: RetryBudget::new(policy.retry_ratio, policy.max_retries),
+            policy,
+        }
+    }
+}
+
+// Shared HTTP client for webhook deliveries, FX rate fetches and payment provider calls.
+// Connections are pooled by the inner reqwest client while each host gets its own timeout,
+// retry budget and circuit breaker so one slow partner can't back up everyone else.
+pub struct OutboundClient {
+    client: Client,
+    default_policy: HostPolicy,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl OutboundClient {
+    pub fn new(default_policy: HostPolicy) -> Self {
+        let client = Client::builder()
+            .pool_idle_timeout(Duration::from_secs(90))
+            .pool_max_idle_per_host(16)
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .expect("Failed to build outbound HTTP client");
+
+        Self {
+            client,
+            default_policy,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Per-host overrides; no integration needs one yet
+    #[allow(dead_code)]
+    pub fn with_host_policy(self, host: &str, policy: HostPolicy) -> Self {
+        self.hosts
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), HostState::new(policy));
+        self
+    }
+
+    // Builder access for constructing requests; send them through `execute`
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub async fn execute(&self, request: Request) -> Result<Response, OutboundError> {
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let policy = self.acquire(&host)?;
+
+        // Streaming bodies can't be replayed, so such requests are only sent once
+        let max_retries = if request.try_clone().is_some() { policy.max_retries } else { 0 };
+
+        let mut attempt = 0;
+        let mut next_request = Some(request);
+        loop {
+            let current = match next_request.take() {
+                Some(current) if attempt < max_retries => {
+                    next_request = current.try_clone();
+                    current
+                }
+                Some(current) => current,
+                None => unreachable!("retries are only attempted for cloneable requests"),
+            };
+
+            match self.send_once(current, &policy, &host).await {
+                Ok(response) => {
+                    self.record(&host, true);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    tracing::error!("Outbound request to {} failed (attempt {}): {}", host, attempt + 1, e);
+                    self.record(&host, false);
+
+                    if attempt >= max_retries || !self.try_retry(&host) {
+                        return Err(e);
+                    }
+                }
+            }
+
+            attempt += 1;
+            tokio::time::sleep(Duration::from_millis(100 * 2u64.pow(attempt))).await;
+        }
+    }
+
+    async fn send_once(&self, mut request: Request, policy: &HostPolicy, host: &str) -> Result<Response, OutboundError> {
+        *request.timeout_mut() = Some(policy.timeout);
+
+        match self.client.execute(request).await {
+            Ok(response) if is_retryable_status(response.status()) => {
+                Err(OutboundError::Status(response.status()))
+            }
+            Ok(response) => Ok(response),
+            Err(e) if e.is_timeout() => Err(OutboundError::Timeout(host.to_string())),
+            Err(e) => Err(OutboundError::Request(e)),
+        }
+    }
+
+    fn acquire(&self, host: &str) -> Result<HostPolicy, OutboundError> {
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert_with(|| HostState::new(self.default_policy.clone()));
+
+        if !state.breaker.try_acquire(Instant::now()) {
+            tracing::error!("Circuit open for {}, rejecting outbound request", host);
+            return Err(OutboundError::CircuitOpen(host.to_string()));
+        }
+
+        state.budget.deposit();
+        Ok(state.policy.clone())
+    }
+
+    fn try_retry(&self, host: &str) -> bool {
+        let mut hosts = self.hosts.lock().unwrap();
+        match hosts.get_mut(host) {
+            Some(state) => state.budget.try_withdraw() && state.breaker.try_acquire(Instant::now()),
+            None => false,
+        }
+    }
+
+    fn record(&self, host: &str, success: bool) {
+        let mut hosts = self.hosts.lock().unwrap();
+        if let Some(state) = hosts.get_mut(host) {
+            if success {
+                state.breaker.record_success();
+            } else {
+                state.breaker.record_failure(Instant::now());
+            }
+        }
+    }
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
@@ -0,0 +1,57 @@
+// Caps retries to a fraction of recent traffic so a struggling partner isn't hammered with
+// retry storms. Every original request deposits `ratio` tokens and every retry withdraws one.
+#[derive(Debug)]
+pub struct RetryBudget {
+    balance: f64,
+    ratio: f64,
+    max_balance: f64,
+}
+
+impl RetryBudget {
+    pub fn new(ratio: f64, min_retries: u32) -> Self {
+        let min_retries = f64::from(min_retries);
+        Self {
+            balance: min_retries,
+            ratio,
+            max_balance: min_retries.max(1.0) * 10.0,
+        }
+    }
+
+    pub fn deposit(&mut self) {
+        self.balance = (self.balance + self.ratio).min(self.max_balance);
+    }
+
+    pub fn try_withdraw(&mut self) -> bool {
+        if self.balance >= 1.0 {
+            self.balance -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_is_exhausted_by_retries() {
+        let mut budget = RetryBudget::new(0.2, 2);
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+    }
+
+    #[test]
+    fn test_requests_replenish_budget() {
+        let mut budget = RetryBudget::new(0.25, 0);
+        assert!(!budget.try_withdraw());
+
+        for _ in 0..4 {
+            budget.deposit();
+        }
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+    }
+}
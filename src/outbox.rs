@@ -0,0 +1,218 @@
+use async_nats::jetstream;
+use async_nats::HeaderMap;
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::shutdown;
+
+// Events downstream systems such as fraud and analytics consume. Each is written to `outbox_events`
+// on the caller's connection, so it exists exactly when the change it describes commits, and a relay
+// publishes it to the broker afterwards. A crash between publishing and marking the event published
+// sends it again, so consumers should treat the message ID as an idempotency key.
+
+pub const EVENT_USER_REGISTERED: &str = "user.registered";
+
+// Events relayed per run; the next tick picks up the rest
+const RELAY_BATCH_SIZE: i64 = 100;
+
+// Queues an event for the relay; `payload` should carry its own `id`, which becomes the message ID
+pub async fn enqueue(conn: &mut PgConnection, user_id: Option<Uuid>, event_type: &str, payload: &Value) -> Result<(), sqlx::Error> {
+    let id = payload["id"].as_str().and_then(|id| Uuid::parse_str(id).ok()).unwrap_or_else(Uuid::new_v4);
+    sqlx::query!(
+        "INSERT INTO outbox_events (id, event_type, user_id, payload) VALUES ($1, $2, $3, $4)",
+        id,
+        event_type,
+        user_id,
+        payload
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+// Where relayed events go. Publishing returns once the broker has accepted the event.
+#[async_trait]
+pub trait EventBroker: Send + Sync {
+    async fn publish(&self, event_id: Uuid, event_type: &str, payload: &[u8]) -> Result<(), String>;
+}
+
+// Publishes to NATS JetStream on `<prefix>.<event type>`, e.g. `dodo.transaction.created`. A stream
+// must capture those subjects; its duplicate window drops events the relay sends twice.
+pub struct NatsBroker {
+    jetstream: jetstream::Context,
+    subject_prefix: String,
+}
+
+impl NatsBroker {
+    // Keeps retrying in the background if the server is down at startup, so the relay just fails
+    // its runs until the broker is reachable
+    pub async fn connect(url: &str, subject_prefix: &str) -> Result<Self, async_nats::ConnectError> {
+        let client = async_nats::ConnectOptions::new()
+            .retry_on_initial_connect()
+            .connect(url)
+            .await?;
+        Ok(Self { jetstream: jetstream::new(client), subject_prefix: subject_prefix.to_string() })
+    }
+}
+
+#[async_trait]
+impl EventBroker for NatsBroker {
+    async fn publish(&self, event_id: Uuid, event_type: &str, payload: &[u8]) -> Result<(), String> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Nats-Msg-Id", event_id.to_string().as_str());
+        let subject = format!("{}.{}", self.subject_prefix, event_type);
+
+        let ack = self
+            .jetstream
+            .publish_with_headers(subject, headers, payload.to_vec().into())
+            .await
+            .map_err(|e| e.to_string())?;
+        ack.await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+// Publishes unpublished events oldest first, marking each as it is accepted. Stops at the first
+// failure so later events don't overtake it; it is retried on the next run. Rows are claimed with
+// SKIP LOCKED so several instances can relay at once.
+pub async fn relay_events(pool: &PgPool, broker: &dyn EventBroker) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let events = sqlx::query!(
+        r#"
+        SELECT id, event_type, payload, attempts
+        FROM outbox_events
+        WHERE published_at IS NULL
+        ORDER BY sequence
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+        "#,
+        RELAY_BATCH_SIZE
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut published = 0;
+    for event in events {
+        match broker.publish(event.id, &event.event_type, event.payload.to_string().as_bytes()).await {
+            Ok(()) => {
+                sqlx::query!(
+                    "UPDATE outbox_events SET published_at = NOW(), attempts = $2, last_error = NULL WHERE id = $1",
+                    event.id,
+                    event.attempts + 1
+                )
+                .execute(&mut *tx)
+                .await?;
+                published += 1;
+            }
+            Err(failure) => {
+                error!("Failed to publish event {} ({}): {}", event.id, event.event_type, failure);
+                sqlx::query!(
+                    "UPDATE outbox_events SET attempts = $2, last_error = $3 WHERE id = $1",
+                    event.id,
+                    event.attempts + 1,
+                    failure
+                )
+                .execute(&mut *tx)
+                .await?;
+                break;
+            }
+        }
+    }
+
+    tx.commit().await?;
+    Ok(published)
+}
+
+// Background task that relays committed outbox events to the broker
+pub async fn run_relay(pool: PgPool, broker: Arc<dyn EventBroker>, period: Duration, mut stop: watch::Receiver<bool>) {
+    let mut ticker = tokio::time::interval(period);
+    while shutdown::tick(&mut ticker, &mut stop).await {
+        // Drain the backlog before waiting for the next tick
+        loop {
+            match relay_events(&pool, broker.as_ref()).await {
+                Ok(published) => {
+                    if published > 0 {
+                        info!("Published {} outbox events", published);
+                    }
+                    if (published as i64) < RELAY_BATCH_SIZE {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!("Outbox relay failed: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// The broker named by `EVENT_BROKER_URL`, or `None` when events aren't relayed anywhere
+pub async fn from_config(broker_url: Option<&str>, subject_prefix: &str) -> Option<Arc<dyn EventBroker>> {
+    let url = broker_url?;
+    match NatsBroker::connect(url, subject_prefix).await {
+        Ok(broker) => Some(Arc::new(broker)),
+        Err(e) => {
+            error!("Failed to set up the event broker, outbox events will queue up unpublished: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    // Accepts events until `fail_on` is reached, recording the IDs it accepted
+    #[derive(Default)]
+    struct RecordingBroker {
+        published: Mutex<Vec<Uuid>>,
+        fail_on: Option<Uuid>,
+    }
+
+    #[async_trait]
+    impl EventBroker for RecordingBroker {
+        async fn publish(&self, event_id: Uuid, _event_type: &str, _payload: &[u8]) -> Result<(), String> {
+            if self.fail_on == Some(event_id) {
+                return Err("broker unavailable".to_string());
+            }
+            self.published.lock().unwrap().push(event_id);
+            Ok(())
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_relay_publishes_in_order_and_stops_at_a_failure(pool: PgPool) {
+        let ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let mut conn = pool.acquire().await.unwrap();
+        for id in ids {
+            enqueue(&mut conn, None, EVENT_USER_REGISTERED, &json!({ "id": id, "type": EVENT_USER_REGISTERED })).await.unwrap();
+        }
+        drop(conn);
+
+        let broker = RecordingBroker { fail_on: Some(ids[1]), ..Default::default() };
+        assert_eq!(relay_events(&pool, &broker).await.unwrap(), 1);
+        assert_eq!(*broker.published.lock().unwrap(), [ids[0]]);
+
+        let failed = sqlx::query!("SELECT attempts, last_error FROM outbox_events WHERE id = $1", ids[1])
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_error.as_deref(), Some("broker unavailable"));
+
+        let broker = RecordingBroker::default();
+        assert_eq!(relay_events(&pool, &broker).await.unwrap(), 2);
+        assert_eq!(*broker.published.lock().unwrap(), [ids[1], ids[2]]);
+        assert_eq!(relay_events(&pool, &broker).await.unwrap(), 0);
+    }
+}
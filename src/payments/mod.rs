@@ -0,0 +1,77 @@
+pub mod stripe;
+
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::models::money::Money;
+use crate::outbound::OutboundClient;
+
+#[derive(Debug)]
+pub enum PaymentError {
+    // No payment provider is configured, so card payments can't be taken
+    NotConfigured,
+    // The provider failed or refused the request
+    Provider(String),
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::NotConfigured => write!(f, "no payment provider is configured"),
+            PaymentError::Provider(message) => write!(f, "payment provider request failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PaymentError {}
+
+// A card payment the client completes with `client_secret`
+#[derive(Debug, Clone)]
+pub struct PaymentIntent {
+    pub id: String,
+    pub client_secret: String,
+}
+
+// Takes card payments into the user's balance. The outcome arrives later through the provider's
+// webhook, not from these calls.
+#[async_trait]
+pub trait PaymentGateway: Send + Sync {
+    // Starts a payment of `amount` for the deposit. Repeating the call for the same deposit returns
+    // the same intent rather than starting a second payment.
+    async fn create_payment_intent(&self, deposit_id: Uuid, user_id: Uuid, amount: &Money) -> Result<PaymentIntent, PaymentError>;
+}
+
+// Used when Stripe isn't configured: deposits are refused
+pub struct NoPaymentGateway;
+
+#[async_trait]
+impl PaymentGateway for NoPaymentGateway {
+    async fn create_payment_intent(&self, _deposit_id: Uuid, _user_id: Uuid, _amount: &Money) -> Result<PaymentIntent, PaymentError> {
+        Err(PaymentError::NotConfigured)
+    }
+}
+
+// Hands out intents without calling anyone, for tests that take deposits
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakePaymentGateway;
+
+#[cfg(test)]
+#[async_trait]
+impl PaymentGateway for FakePaymentGateway {
+    async fn create_payment_intent(&self, deposit_id: Uuid, _user_id: Uuid, _amount: &Money) -> Result<PaymentIntent, PaymentError> {
+        let id = format!("pi_{}", deposit_id.simple());
+        Ok(PaymentIntent { client_secret: format!("{}_secret", id), id })
+    }
+}
+
+// Stripe when `STRIPE_SECRET_KEY` is set, otherwise `NoPaymentGateway`
+pub fn from_config(config: &Config, client: Arc<OutboundClient>) -> Arc<dyn PaymentGateway> {
+    match config.stripe_secret_key.as_deref() {
+        Some(secret_key) => Arc::new(stripe::StripeGateway::new(client, secret_key)),
+        None => Arc::new(NoPaymentGateway),
+    }
+}
@@ -0,0 +1,307 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::models::deposit::{Deposit, DepositStatus};
+use crate::models::money::Money;
+use crate::models::transaction::{TransactionStatus, TransactionType};
+use crate::outbound::OutboundClient;
+use crate::payments::{PaymentError, PaymentGateway, PaymentIntent};
+use crate::services::ledger::insert_transaction;
+
+const API_BASE: &str = "https://api.stripe.com";
+
+// Header Stripe signs webhook deliveries in
+pub const SIGNATURE_HEADER: &str = "stripe-signature";
+
+// How far a delivery's signed timestamp may be from now before it's refused as a replay
+const SIGNATURE_TOLERANCE_SECONDS: i64 = 300;
+
+// Creates PaymentIntents through the Stripe API
+pub struct StripeGateway {
+    client: Arc<OutboundClient>,
+    secret_key: String,
+}
+
+impl StripeGateway {
+    pub fn new(client: Arc<OutboundClient>, secret_key: &str) -> Self {
+        Self { client, secret_key: secret_key.to_string() }
+    }
+}
+
+#[derive(Deserialize)]
+struct CreatedIntent {
+    id: String,
+    client_secret: String,
+}
+
+#[async_trait]
+impl PaymentGateway for StripeGateway {
+    async fn create_payment_intent(&self, deposit_id: Uuid, user_id: Uuid, amount: &Money) -> Result<PaymentIntent, PaymentError> {
+        let form = [
+            ("amount", amount.amount_minor().to_string()),
+            ("currency", amount.currency().as_str().to_lowercase()),
+            ("automatic_payment_methods[enabled]", "true".to_string()),
+            ("metadata[deposit_id]", deposit_id.to_string()),
+            ("metadata[user_id]", user_id.to_string()),
+        ];
+        // The deposit id as idempotency key makes the outbound client's retries safe
+        let request = self
+            .client
+            .client()
+            .post(format!("{}/v1/payment_intents", API_BASE))
+            .bearer_auth(&self.secret_key)
+            .header("Idempotency-Key", deposit_id.to_string())
+            .form(&form)
+            .build()
+            .map_err(|e| PaymentError::Provider(e.to_string()))?;
+
+        let response = self.client.execute(request).await.map_err(|e| PaymentError::Provider(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let detail = response.text().await.unwrap_or_default();
+            return Err(PaymentError::Provider(format!("Stripe returned {}: {}", status, detail)));
+        }
+        let intent: CreatedIntent = response.json().await.map_err(|e| PaymentError::Provider(e.to_string()))?;
+        Ok(PaymentIntent { id: intent.id, client_secret: intent.client_secret })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SignatureError {
+    // The header is missing its timestamp or has no `v1` signature
+    Malformed,
+    // The timestamp is outside the tolerance, so the delivery may be a replay
+    Expired,
+    // No signature matches the payload
+    Mismatch,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::Malformed => write!(f, "malformed signature header"),
+            SignatureError::Expired => write!(f, "signature timestamp outside the tolerance"),
+            SignatureError::Mismatch => write!(f, "no matching signature"),
+        }
+    }
+}
+
+// Checks a `Stripe-Signature` header, `t=<unix time>,v1=<hex HMAC-SHA256>[,v1=...]`, against the
+// raw request body. The signed payload is `<t>.<body>`; several `v1` entries appear while the
+// endpoint's secret is being rolled.
+pub fn verify_signature(header: &str, payload: &[u8], secret: &str, now: OffsetDateTime) -> Result<(), SignatureError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.extend(hex::decode(value).ok()),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or(SignatureError::Malformed)?;
+    if signatures.is_empty() {
+        return Err(SignatureError::Malformed);
+    }
+    if (now.unix_timestamp() - timestamp).abs() > SIGNATURE_TOLERANCE_SECONDS {
+        return Err(SignatureError::Expired);
+    }
+
+    for signature in signatures {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(payload);
+        if mac.verify_slice(&signature).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(SignatureError::Mismatch)
+}
+
+// The header Stripe would send with `payload` at `timestamp`, for tests posting webhooks
+#[cfg(test)]
+pub fn sign(payload: &[u8], secret: &str, timestamp: i64) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(payload);
+    format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+}
+
+// A webhook event; only the fields deposits need are read
+#[derive(Debug, Deserialize)]
+pub struct Event {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: EventData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventData {
+    pub object: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntentObject {
+    id: String,
+    #[serde(default)]
+    amount_received: i64,
+    currency: String,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    last_payment_error: Option<LastPaymentError>,
+    cancellation_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastPaymentError {
+    message: Option<String>,
+}
+
+// Applies a verified event to the deposit it's about. Events deposits don't use are ignored.
+pub async fn apply_event(conn: &mut PgConnection, event: &Event) -> Result<(), sqlx::Error> {
+    match event.event_type.as_str() {
+        "payment_intent.succeeded" | "payment_intent.payment_failed" | "payment_intent.canceled" => {
+            match serde_json::from_value::<IntentObject>(event.data.object.clone()) {
+                Ok(intent) => apply_intent_event(conn, &event.event_type, &intent).await,
+                Err(e) => {
+                    error!("Stripe event {} has an unreadable PaymentIntent: {}", event.id, e);
+                    Ok(())
+                }
+            }
+        }
+        _ => {
+            info!("Ignoring Stripe event {} of type {}", event.id, event.event_type);
+            Ok(())
+        }
+    }
+}
+
+async fn apply_intent_event(conn: &mut PgConnection, event_type: &str, intent: &IntentObject) -> Result<(), sqlx::Error> {
+    let Some(deposit_id) = intent.metadata.get("deposit_id").and_then(|id| id.parse::<Uuid>().ok()) else {
+        info!("PaymentIntent {} isn't for a deposit", intent.id);
+        return Ok(());
+    };
+    let deposit = sqlx::query_as!(
+        Deposit,
+        r#"
+        SELECT id, user_id, amount, currency, status as "status: _", payment_intent_id, transaction_id, failure_reason,
+            created_at, updated_at
+        FROM deposits
+        WHERE id = $1 AND (payment_intent_id IS NULL OR payment_intent_id = $2)
+        FOR UPDATE
+        "#,
+        deposit_id,
+        intent.id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(deposit) = deposit else {
+        error!("PaymentIntent {} names deposit {}, which doesn't match it", intent.id, deposit_id);
+        return Ok(());
+    };
+    if matches!(deposit.status, DepositStatus::Succeeded | DepositStatus::Cancelled) {
+        info!("Deposit {} is already {:?}", deposit.id, deposit.status);
+        return Ok(());
+    }
+
+    match event_type {
+        "payment_intent.succeeded" => settle(conn, &deposit, intent).await,
+        "payment_intent.payment_failed" => {
+            let reason = intent.last_payment_error.as_ref().and_then(|e| e.message.as_deref()).unwrap_or("Payment failed");
+            update(conn, &deposit, &intent.id, DepositStatus::Failed, None, Some(reason)).await
+        }
+        _ => update(conn, &deposit, &intent.id, DepositStatus::Cancelled, None, intent.cancellation_reason.as_deref()).await,
+    }
+}
+
+// Credits the deposit to the user's balance, provided Stripe collected exactly the deposit's amount
+async fn settle(conn: &mut PgConnection, deposit: &Deposit, intent: &IntentObject) -> Result<(), sqlx::Error> {
+    let Some(amount) = Money::from_stored(&deposit.amount, &deposit.currency).filter(|amount| {
+        amount.amount_minor() == intent.amount_received && amount.currency().as_str().eq_ignore_ascii_case(&intent.currency)
+    }) else {
+        error!(
+            "PaymentIntent {} received {} {} for deposit {} of {} {}, not crediting it",
+            intent.id, intent.amount_received, intent.currency, deposit.id, deposit.amount, deposit.currency
+        );
+        return update(conn, deposit, &intent.id, deposit.status, None, Some("Amount received doesn't match the deposit")).await;
+    };
+
+    let credit = insert_transaction(
+        &mut *conn,
+        deposit.user_id,
+        &amount,
+        TransactionType::Credit,
+        Some("Card deposit"),
+        None,
+        TransactionStatus::Settled,
+        true,
+    )
+    .await?;
+    update(conn, deposit, &intent.id, DepositStatus::Succeeded, Some(credit.id), None).await?;
+
+    info!("Deposit {} succeeded, credited by transaction {}", deposit.id, credit.id);
+    Ok(())
+}
+
+async fn update(
+    conn: &mut PgConnection,
+    deposit: &Deposit,
+    payment_intent_id: &str,
+    status: DepositStatus,
+    transaction_id: Option<Uuid>,
+    failure_reason: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE deposits
+        SET status = $2, payment_intent_id = $3, transaction_id = COALESCE($4, transaction_id), failure_reason = $5,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        deposit.id,
+        status as _,
+        payment_intent_id,
+        transaction_id,
+        failure_reason
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signatures_are_checked_against_the_raw_body_and_timestamp() {
+        let secret = "whsec_test";
+        let payload = br#"{"id":"evt_1","type":"payment_intent.succeeded"}"#;
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let header = sign(payload, secret, now.unix_timestamp());
+
+        assert_eq!(verify_signature(&header, payload, secret, now), Ok(()));
+        assert_eq!(verify_signature(&header, b"{}", secret, now), Err(SignatureError::Mismatch));
+        assert_eq!(verify_signature(&header, payload, "whsec_other", now), Err(SignatureError::Mismatch));
+        assert_eq!(
+            verify_signature(&header, payload, secret, now + time::Duration::minutes(10)),
+            Err(SignatureError::Expired)
+        );
+        assert_eq!(verify_signature("v1=abcd", payload, secret, now), Err(SignatureError::Malformed));
+
+        // Any of several signatures may match while the secret is rolled
+        let (_, signature) = header.split_once(",v1=").unwrap();
+        let rolled = format!("t={},v1={},v1={}", now.unix_timestamp(), "00".repeat(32), signature);
+        assert_eq!(verify_signature(&rolled, payload, secret, now), Ok(()));
+    }
+}
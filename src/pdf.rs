@@ -0,0 +1,173 @@
+use std::fmt::Write;
+
+// A4 in PDF points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+
+// Courier is one of the standard 14 fonts every reader ships, so nothing is embedded, and being
+// monospaced it lines up table columns without measuring text
+const FONT_REGULAR: &str = "Courier";
+const FONT_BOLD: &str = "Courier-Bold";
+
+#[derive(Debug, Clone)]
+struct Line {
+    text: String,
+    size: f32,
+    bold: bool,
+}
+
+// Builds a text-only PDF: lines flow top to bottom and onto new pages as each one fills up
+#[derive(Debug, Default)]
+pub struct TextDocument {
+    lines: Vec<Line>,
+}
+
+impl TextDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(&mut self, text: impl Into<String>) -> &mut Self {
+        self.push(text.into(), 9.0, false)
+    }
+
+    pub fn heading(&mut self, text: impl Into<String>, size: f32) -> &mut Self {
+        self.push(text.into(), size, true)
+    }
+
+    pub fn blank(&mut self) -> &mut Self {
+        self.push(String::new(), 9.0, false)
+    }
+
+    fn push(&mut self, text: String, size: f32, bold: bool) -> &mut Self {
+        self.lines.push(Line { text, size, bold });
+        self
+    }
+
+    fn paginate(&self) -> Vec<Vec<(f32, &Line)>> {
+        let mut pages = vec![Vec::new()];
+        let mut y = PAGE_HEIGHT - MARGIN;
+
+        for line in &self.lines {
+            let height = line.size * 1.4;
+            if y - height < MARGIN {
+                pages.push(Vec::new());
+                y = PAGE_HEIGHT - MARGIN;
+            }
+            y -= height;
+            pages.last_mut().unwrap().push((y, line));
+        }
+
+        pages
+    }
+
+    pub fn render(&self) -> Vec<u8> {
+        let pages = self.paginate();
+
+        // Objects 1-4 are the catalog, page tree and two fonts; each page adds itself and its content
+        let page_id = |index: usize| 5 + index * 2;
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..pages.len()).map(|index| format!("{} 0 R", page_id(index))).collect::<Vec<_>>().join(" "),
+                pages.len()
+            ),
+            format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", FONT_REGULAR),
+            format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", FONT_BOLD),
+        ];
+
+        for (index, lines) in pages.iter().enumerate() {
+            let mut content = String::new();
+            for (y, line) in lines {
+                if line.text.is_empty() {
+                    continue;
+                }
+                let font = if line.bold { "F2" } else { "F1" };
+                let _ = writeln!(
+                    content,
+                    "BT /{} {} Tf {} {:.1} Td ({}) Tj ET",
+                    font,
+                    line.size,
+                    MARGIN,
+                    y,
+                    escape(&line.text)
+                );
+            }
+
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id(index) + 1
+            ));
+            objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content));
+        }
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", index + 1, object);
+        }
+
+        let xref = pdf.len();
+        let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            pdf,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+
+        pdf.into_bytes()
+    }
+}
+
+// Escapes a PDF string literal. Only printable ASCII is written, so byte offsets stay exact.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_writes_valid_cross_reference_table() {
+        let mut document = TextDocument::new();
+        document.heading("Statement (March)", 14.0);
+        for index in 0..60 {
+            document.line(format!("Line {} \\ café", index));
+        }
+
+        let pdf = String::from_utf8(document.render()).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("(Statement \\(March\\)) Tj"));
+        assert!(pdf.contains("(Line 0 \\\\ caf?) Tj"));
+        assert!(pdf.contains("/Count 2"));
+
+        // Every xref entry must point at the start of its object
+        let xref = pdf[pdf.rfind("startxref\n").unwrap() + 10..].lines().next().unwrap().parse::<usize>().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n"));
+        for (index, entry) in pdf[xref..].lines().skip(3).take_while(|line| line.ends_with(" n ")).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", index + 1)));
+        }
+    }
+}
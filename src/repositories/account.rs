@@ -0,0 +1,194 @@
+use bigdecimal::BigDecimal;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::models::account::{Account, AccountBalanceDetails, AccountType};
+use crate::models::transaction::{Transaction, TransactionCursor};
+
+pub async fn insert(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    name: &str,
+    account_type: AccountType,
+    currency: &str,
+) -> Result<Account, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        r#"
+        INSERT INTO accounts (user_id, name, account_type, currency)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, user_id, name, account_type as "account_type: _", currency, is_primary, closed_at, created_at, updated_at
+        "#,
+        user_id,
+        name,
+        account_type as _,
+        currency
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// The user's accounts, main accounts first, then oldest first
+pub async fn list(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<Vec<Account>, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        r#"
+        SELECT id, user_id, name, account_type as "account_type: _", currency, is_primary, closed_at, created_at, updated_at
+        FROM accounts
+        WHERE user_id = $1
+        ORDER BY is_primary DESC, currency, created_at, id
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn find(executor: impl PgExecutor<'_>, user_id: Uuid, account_id: Uuid) -> Result<Option<Account>, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        r#"
+        SELECT id, user_id, name, account_type as "account_type: _", currency, is_primary, closed_at, created_at, updated_at
+        FROM accounts
+        WHERE id = $1 AND user_id = $2
+        "#,
+        account_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn rename(executor: impl PgExecutor<'_>, user_id: Uuid, account_id: Uuid, name: &str) -> Result<Option<Account>, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        r#"
+        UPDATE accounts
+        SET name = $3, updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, name, account_type as "account_type: _", currency, is_primary, closed_at, created_at, updated_at
+        "#,
+        account_id,
+        user_id,
+        name
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn close(executor: impl PgExecutor<'_>, account_id: Uuid) -> Result<Account, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        r#"
+        UPDATE accounts
+        SET closed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, user_id, name, account_type as "account_type: _", currency, is_primary, closed_at, created_at, updated_at
+        "#,
+        account_id
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Totals of the account's own entries in one mode, summed from the ledger
+pub async fn balance(executor: impl PgExecutor<'_>, account: &Account, livemode: bool) -> Result<AccountBalanceDetails, sqlx::Error> {
+    sqlx::query_as!(
+        AccountBalanceDetails,
+        r#"
+        SELECT $1::uuid as "account_id!", $3::text as "currency!",
+            COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END)
+                FILTER (WHERE status IN ('settled', 'reversed')), 0) as "balance!",
+            COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END)
+                FILTER (WHERE status IN ('settled', 'reversed')), 0)
+                - COALESCE(SUM(amount) FILTER (WHERE status = 'pending' AND transaction_type = 'debit'), 0) as "available!",
+            COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END)
+                FILTER (WHERE status = 'pending'), 0) as "pending!",
+            MAX(created_at) FILTER (WHERE status IN ('settled', 'reversed', 'pending')) as last_activity
+        FROM transactions
+        WHERE account_id = $1 AND livemode = $2
+        "#,
+        account.id,
+        livemode,
+        account.currency
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Settled balance of the account's own entries in one mode
+pub async fn settled_balance(executor: impl PgExecutor<'_>, account_id: Uuid, livemode: bool) -> Result<BigDecimal, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END), 0) as "balance!"
+        FROM transactions
+        WHERE account_id = $1 AND livemode = $2 AND status IN ('settled', 'reversed')
+        "#,
+        account_id,
+        livemode
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Settled balance less pending debits of the account's own entries
+pub async fn available_balance(executor: impl PgExecutor<'_>, account_id: Uuid, livemode: bool) -> Result<BigDecimal, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(CASE
+            WHEN status IN ('settled', 'reversed') AND transaction_type = 'credit' THEN amount
+            WHEN status IN ('settled', 'reversed', 'pending') AND transaction_type = 'debit' THEN -amount
+            ELSE 0
+        END), 0) as "available!"
+        FROM transactions
+        WHERE account_id = $1 AND livemode = $2
+        "#,
+        account_id,
+        livemode
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Whether anything on the account is still waiting to settle, run or be reviewed
+pub async fn has_open_entries(executor: impl PgExecutor<'_>, account_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM transactions WHERE account_id = $1 AND status IN ('pending', 'scheduled', 'held', 'awaiting_approval')
+        ) as "exists!"
+        "#,
+        account_id
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Up to `limit` of the account's entries in one mode, newest first, starting after `cursor`
+pub async fn transactions(
+    executor: impl PgExecutor<'_>,
+    account_id: Uuid,
+    livemode: bool,
+    cursor: Option<&TransactionCursor>,
+    limit: i64,
+) -> Result<Vec<Transaction>, sqlx::Error> {
+    sqlx::query_as!(
+        Transaction,
+        r#"
+        SELECT id, user_id, account_id, amount, currency, transaction_type as "transaction_type: _", description, transfer_id,
+            status as "status: _", reverses, reversed_by, execute_at, created_at, livemode, category, payee_id, fx_rate
+        FROM transactions
+        WHERE account_id = $1 AND livemode = $5
+            AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+        account_id,
+        cursor.map(|c| c.created_at),
+        cursor.map(|c| c.id),
+        limit,
+        livemode
+    )
+    .fetch_all(executor)
+    .await
+}
@@ -0,0 +1,5 @@
+// Data access for the tables behind the core flows. Each function runs one statement on whatever
+// executor it is given, so callers decide which DB transaction it joins.
+pub mod account;
+pub mod transaction;
+pub mod user;
\ No newline at end of file
@@ -0,0 +1,452 @@
+use bigdecimal::BigDecimal;
+use sqlx::PgExecutor;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::analytics::{AnalyticsPeriod, BalanceGranularity, BalanceHistoryRow, PeriodTotals};
+use crate::models::budget::{BudgetPeriod, PeriodSpending};
+use crate::models::velocity_limit::CurrencyUsage;
+use crate::models::money::Money;
+use crate::models::transaction::{CurrencyBalance, CurrencySummary, CurrencyTotal, Transaction, TransactionCursor, TransactionStatus, TransactionType};
+
+// A ledger entry about to be written; everything else is filled in by the database
+#[derive(Debug)]
+pub struct NewTransaction<'a> {
+    pub user_id: Uuid,
+    // The user's main account in the entry's currency when `None`
+    pub account_id: Option<Uuid>,
+    pub amount: &'a Money,
+    pub transaction_type: TransactionType,
+    pub description: Option<&'a str>,
+    pub transfer_id: Option<Uuid>,
+    pub status: TransactionStatus,
+    // The entry this one offsets, for reversals
+    pub reverses: Option<Uuid>,
+    // When a scheduled entry comes due
+    pub execute_at: Option<OffsetDateTime>,
+    pub livemode: bool,
+    pub category: Option<&'a str>,
+    pub payee_id: Option<Uuid>,
+    // The rate a converting transfer was posted at
+    pub fx_rate: Option<&'a BigDecimal>,
+}
+
+pub async fn insert(executor: impl PgExecutor<'_>, entry: &NewTransaction<'_>) -> Result<Transaction, sqlx::Error> {
+    let (amount, currency) = (entry.amount.to_decimal(), entry.amount.currency());
+    sqlx::query_as!(
+        Transaction,
+        r#"
+        INSERT INTO transactions
+            (user_id, account_id, amount, currency, transaction_type, description, transfer_id, status, reverses, execute_at, livemode, category, payee_id,
+            fx_rate)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        RETURNING id, user_id, account_id, amount, currency, transaction_type as "transaction_type: _", description, transfer_id,
+            status as "status: _", reverses, reversed_by, execute_at, created_at, livemode, category, payee_id, fx_rate
+        "#,
+        entry.user_id,
+        entry.account_id,
+        amount,
+        currency.as_str(),
+        entry.transaction_type as _,
+        entry.description,
+        entry.transfer_id,
+        entry.status as _,
+        entry.reverses,
+        entry.execute_at,
+        entry.livemode,
+        entry.category,
+        entry.payee_id,
+        entry.fx_rate
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Up to `limit` of the user's entries in one mode, newest first, starting after `cursor`
+pub async fn list(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    livemode: bool,
+    cursor: Option<&TransactionCursor>,
+    limit: i64,
+) -> Result<Vec<Transaction>, sqlx::Error> {
+    sqlx::query_as!(
+        Transaction,
+        r#"
+        SELECT id, user_id, account_id, amount, currency, transaction_type as "transaction_type: _", description, transfer_id,
+            status as "status: _", reverses, reversed_by, execute_at, created_at, livemode, category, payee_id, fx_rate
+        FROM transactions
+        WHERE user_id = $1 AND livemode = $5
+            AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+        user_id,
+        cursor.map(|c| c.created_at),
+        cursor.map(|c| c.id),
+        limit,
+        livemode
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn find_for_update(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    transaction_id: Uuid,
+    livemode: bool,
+) -> Result<Option<Transaction>, sqlx::Error> {
+    sqlx::query_as!(
+        Transaction,
+        r#"
+        SELECT id, user_id, account_id, amount, currency, transaction_type as "transaction_type: _", description, transfer_id,
+            status as "status: _", reverses, reversed_by, execute_at, created_at, livemode, category, payee_id, fx_rate
+        FROM transactions
+        WHERE id = $1 AND user_id = $2 AND livemode = $3
+        FOR UPDATE
+        "#,
+        transaction_id,
+        user_id,
+        livemode
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn exists(executor: impl PgExecutor<'_>, user_id: Uuid, transaction_id: Uuid, livemode: bool) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM transactions WHERE id = $1 AND user_id = $2 AND livemode = $3) as \"exists!\"",
+        transaction_id,
+        user_id,
+        livemode
+    )
+    .fetch_one(executor)
+    .await
+}
+
+pub async fn mark_reversed(executor: impl PgExecutor<'_>, transaction_id: Uuid, reversal_id: Uuid) -> Result<Transaction, sqlx::Error> {
+    sqlx::query_as!(
+        Transaction,
+        r#"
+        UPDATE transactions
+        SET status = 'reversed', reversed_by = $2
+        WHERE id = $1
+        RETURNING id, user_id, account_id, amount, currency, transaction_type as "transaction_type: _", description, transfer_id,
+            status as "status: _", reverses, reversed_by, execute_at, created_at, livemode, category, payee_id, fx_rate
+        "#,
+        transaction_id,
+        reversal_id
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Moves an entry to `to` if it is currently in one of `from`, returning it; `None` if nothing matched
+pub async fn transition(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    transaction_id: Uuid,
+    livemode: bool,
+    from: &[TransactionStatus],
+    to: TransactionStatus,
+) -> Result<Option<Transaction>, sqlx::Error> {
+    sqlx::query_as!(
+        Transaction,
+        r#"
+        UPDATE transactions
+        SET status = $5
+        WHERE id = $1 AND user_id = $2 AND livemode = $3 AND status = ANY($4)
+        RETURNING id, user_id, account_id, amount, currency, transaction_type as "transaction_type: _", description, transfer_id,
+            status as "status: _", reverses, reversed_by, execute_at, created_at, livemode, category, payee_id, fx_rate
+        "#,
+        transaction_id,
+        user_id,
+        livemode,
+        from as _,
+        to as _
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn set_status(executor: impl PgExecutor<'_>, transaction_id: Uuid, status: TransactionStatus) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE transactions SET status = $2 WHERE id = $1", transaction_id, status as _)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+// The earliest scheduled entry that has come due, locked and skipped by other workers; only credits
+// unless `debits` is set
+pub async fn next_due_scheduled(executor: impl PgExecutor<'_>, debits: bool) -> Result<Option<Transaction>, sqlx::Error> {
+    sqlx::query_as!(
+        Transaction,
+        r#"
+        SELECT id, user_id, account_id, amount, currency, transaction_type as "transaction_type: _", description, transfer_id,
+            status as "status: _", reverses, reversed_by, execute_at, created_at, livemode, category, payee_id, fx_rate
+        FROM transactions
+        WHERE status = 'scheduled' AND execute_at <= NOW() AND ($1 OR transaction_type = 'credit')
+        ORDER BY execute_at
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+        "#,
+        debits
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// Settled balance less pending debits of the user's main account in `currency`: the materialized
+// totals, less whatever sits in the user's other accounts in that currency
+pub async fn available_balance(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    currency: &str,
+    livemode: bool,
+) -> Result<BigDecimal, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE((
+            SELECT balance - held FROM account_balances WHERE user_id = $1 AND currency = $2 AND livemode = $3
+        ), 0) - COALESCE((
+            SELECT SUM(CASE
+                WHEN t.status IN ('settled', 'reversed') AND t.transaction_type = 'credit' THEN t.amount
+                WHEN t.status IN ('settled', 'reversed', 'pending') AND t.transaction_type = 'debit' THEN -t.amount
+                ELSE 0
+            END)
+            FROM transactions t
+            JOIN accounts a ON a.id = t.account_id
+            WHERE a.user_id = $1 AND a.currency = $2 AND NOT a.is_primary AND t.livemode = $3
+        ), 0) as "available!"
+        "#,
+        user_id,
+        currency,
+        livemode
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Reads the user's materialized totals for every currency they have entries in; kept current by
+// the `transactions` trigger, and checked against the ledger by `reconciliation::reconcile_balances`
+pub async fn currency_balances(executor: impl PgExecutor<'_>, user_id: Uuid, livemode: bool) -> Result<Vec<CurrencyBalance>, sqlx::Error> {
+    sqlx::query_as!(
+        CurrencyBalance,
+        r#"
+        SELECT currency, balance, pending, held, last_updated
+        FROM account_balances
+        WHERE user_id = $1 AND livemode = $2 AND entries > 0
+        ORDER BY currency
+        "#,
+        user_id,
+        livemode
+    )
+    .fetch_all(executor)
+    .await
+}
+
+// Totals and activity of the user's posted entries per currency, in a single pass over the ledger.
+// Reversed entries count alongside their reversals, matching how balances are kept.
+pub async fn summarize(executor: impl PgExecutor<'_>, user_id: Uuid, livemode: bool) -> Result<Vec<CurrencySummary>, sqlx::Error> {
+    sqlx::query_as!(
+        CurrencySummary,
+        r#"
+        SELECT currency,
+            COALESCE(SUM(amount) FILTER (WHERE transaction_type = 'credit'), 0) as "total_credits!",
+            COALESCE(SUM(amount) FILTER (WHERE transaction_type = 'debit'), 0) as "total_debits!",
+            COALESCE(SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END), 0) as "balance!",
+            COUNT(*) as "transaction_count!",
+            MIN(created_at) as first_activity,
+            MAX(created_at) as last_activity
+        FROM transactions
+        WHERE user_id = $1 AND livemode = $2 AND status IN ('settled', 'reversed')
+        GROUP BY currency
+        ORDER BY currency
+        "#,
+        user_id,
+        livemode
+    )
+    .fetch_all(executor)
+    .await
+}
+
+// Totals of the user's posted entries in one currency per `period` bucket, and per category within
+// each bucket when `by_category` is set. Buckets are aligned to UTC.
+#[allow(clippy::too_many_arguments)]
+pub async fn totals_by_period(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    livemode: bool,
+    currency: &str,
+    period: AnalyticsPeriod,
+    by_category: bool,
+    from: Option<OffsetDateTime>,
+    to: Option<OffsetDateTime>,
+) -> Result<Vec<PeriodTotals>, sqlx::Error> {
+    sqlx::query_as!(
+        PeriodTotals,
+        r#"
+        SELECT date_trunc($4::text, created_at, 'UTC') as "period_start!",
+            CASE WHEN $5::boolean THEN category END as category,
+            COALESCE(SUM(amount) FILTER (WHERE transaction_type = 'credit'), 0) as "credits!",
+            COALESCE(SUM(amount) FILTER (WHERE transaction_type = 'debit'), 0) as "debits!",
+            COUNT(*) as "transaction_count!"
+        FROM transactions
+        WHERE user_id = $1 AND livemode = $2 AND currency = $3 AND status IN ('settled', 'reversed')
+            AND ($6::timestamptz IS NULL OR created_at >= $6)
+            AND ($7::timestamptz IS NULL OR created_at < $7)
+        GROUP BY 1, 2
+        ORDER BY 1, 2 NULLS LAST
+        "#,
+        user_id,
+        livemode,
+        currency,
+        period.as_str(),
+        by_category,
+        from,
+        to
+    )
+    .fetch_all(executor)
+    .await
+}
+
+// The settled balance in each currency at the end of every `granularity` period from the one
+// containing `from` to the one containing `to`, counting entries posted before `to`. Periods with
+// no entries carry the previous balance forward, so the series has no gaps.
+pub async fn balance_history(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    livemode: bool,
+    granularity: BalanceGranularity,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> Result<Vec<BalanceHistoryRow>, sqlx::Error> {
+    sqlx::query_as!(
+        BalanceHistoryRow,
+        r#"
+        WITH buckets AS (
+            SELECT currency, date_trunc($3::text, created_at, 'UTC') AS period_start,
+                SUM(CASE WHEN transaction_type = 'credit' THEN amount ELSE -amount END) AS net
+            FROM transactions
+            WHERE user_id = $1 AND livemode = $2 AND status IN ('settled', 'reversed') AND created_at < $5
+            GROUP BY 1, 2
+        ), running AS (
+            SELECT currency, period_start, SUM(net) OVER (PARTITION BY currency ORDER BY period_start) AS balance
+            FROM buckets
+        )
+        SELECT c.currency as "currency!", p.period_start as "period_start!",
+            COALESCE((
+                SELECT r.balance FROM running r
+                WHERE r.currency = c.currency AND r.period_start <= p.period_start
+                ORDER BY r.period_start DESC
+                LIMIT 1
+            ), 0) as "balance!"
+        FROM (SELECT DISTINCT currency FROM buckets) c
+        CROSS JOIN generate_series(
+            date_trunc($3::text, $4::timestamptz, 'UTC'),
+            date_trunc($3::text, $5::timestamptz, 'UTC'),
+            ('1 ' || $3::text)::interval
+        ) AS p(period_start)
+        ORDER BY 1, 2
+        "#,
+        user_id,
+        livemode,
+        granularity.as_str(),
+        from,
+        to
+    )
+    .fetch_all(executor)
+    .await
+}
+
+// Net posted spending in one category and currency during the `period` containing `at`
+pub async fn category_spending(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    livemode: bool,
+    category: &str,
+    currency: &str,
+    period: BudgetPeriod,
+    at: OffsetDateTime,
+) -> Result<PeriodSpending, sqlx::Error> {
+    sqlx::query_as!(
+        PeriodSpending,
+        r#"
+        WITH bounds AS (
+            SELECT date_trunc($5::text, $6::timestamptz, 'UTC') AS period_start,
+                date_trunc($5::text, $6::timestamptz, 'UTC') + ('1 ' || $5::text)::interval AS period_end
+        )
+        SELECT b.period_start as "period_start!", b.period_end as "period_end!",
+            COALESCE(SUM(CASE WHEN t.transaction_type = 'debit' THEN t.amount ELSE -t.amount END), 0) as "spent!"
+        FROM bounds b
+        LEFT JOIN transactions t
+            ON t.user_id = $1 AND t.livemode = $2 AND t.category = $3 AND t.currency = $4
+            AND t.status IN ('settled', 'reversed')
+            AND t.created_at >= b.period_start AND t.created_at < b.period_end
+        GROUP BY b.period_start, b.period_end
+        "#,
+        user_id,
+        livemode,
+        category,
+        currency,
+        period.as_str(),
+        at
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// What the user's live debits over the last 24 hours add up to in each currency for their KYC limits,
+// counting the outgoing legs of transfers. Entries that never moved money, and reversals, aren't
+// counted.
+pub async fn daily_debit_totals(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    now: OffsetDateTime,
+) -> Result<Vec<CurrencyTotal>, sqlx::Error> {
+    sqlx::query_as!(
+        CurrencyTotal,
+        r#"
+        SELECT currency, SUM(amount) as "total!"
+        FROM transactions
+        WHERE user_id = $1 AND livemode AND transaction_type = 'debit'
+            AND created_at > $2::timestamptz - INTERVAL '24 hours'
+            AND reverses IS NULL
+            AND status NOT IN ('failed', 'denied', 'cancelled')
+        GROUP BY currency
+        "#,
+        user_id,
+        now
+    )
+    .fetch_all(executor)
+    .await
+}
+
+// What the user's live transactions created through the API add up to for their velocity limits, per
+// currency: debits over the last 24 hours and transactions over the last hour. Entries that never
+// moved money, and transfer legs and reversals, aren't counted.
+pub async fn velocity_usage(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    now: OffsetDateTime,
+) -> Result<Vec<CurrencyUsage>, sqlx::Error> {
+    sqlx::query_as!(
+        CurrencyUsage,
+        r#"
+        SELECT
+            currency,
+            COALESCE(SUM(amount) FILTER (WHERE transaction_type = 'debit'), 0) as "daily_debit_total!",
+            COUNT(*) FILTER (WHERE created_at > $2::timestamptz - INTERVAL '1 hour') as "hourly_transaction_count!"
+        FROM transactions
+        WHERE user_id = $1 AND livemode AND created_at > $2::timestamptz - INTERVAL '24 hours'
+            AND transfer_id IS NULL AND reverses IS NULL
+            AND status NOT IN ('failed', 'denied', 'cancelled')
+        GROUP BY currency
+        "#,
+        user_id,
+        now
+    )
+    .fetch_all(executor)
+    .await
+}
@@ -0,0 +1,163 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::models::user::{User, UserStatus};
+
+pub async fn find_by_id(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT id, email, password_hash, name, role as "role: _", tier as "tier: _", metadata, status as "status: _",
+            deactivated_at, deleted_at, session_version, created_at, updated_at
+        FROM users
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn find_by_email(executor: impl PgExecutor<'_>, email: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT id, email, password_hash, name, role as "role: _", tier as "tier: _", metadata, status as "status: _",
+            deactivated_at, deleted_at, session_version, created_at, updated_at
+        FROM users
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn email_taken(executor: impl PgExecutor<'_>, email: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1) as \"exists!\"", email)
+        .fetch_one(executor)
+        .await
+}
+
+pub async fn insert(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+    password_hash: &str,
+    name: &str,
+    metadata: &serde_json::Value,
+) -> Result<User, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        INSERT INTO users (email, password_hash, name, metadata)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, email, password_hash, name, role as "role: _", tier as "tier: _", metadata, status as "status: _",
+            deactivated_at, deleted_at, session_version, created_at, updated_at
+        "#,
+        email,
+        password_hash,
+        name,
+        metadata
+    )
+    .fetch_one(executor)
+    .await
+}
+
+// Locks the user's row for the rest of the DB transaction, returning their status, or `None` if the
+// user doesn't exist
+pub async fn lock(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<Option<UserStatus>, sqlx::Error> {
+    sqlx::query_scalar!(r#"SELECT status as "status: UserStatus" FROM users WHERE id = $1 FOR UPDATE"#, user_id)
+        .fetch_optional(executor)
+        .await
+}
+
+// Sets the user's status, stamping when they were deactivated; `None` if the user doesn't exist.
+// Reactivating also undoes a self-service deletion.
+pub async fn set_status(executor: impl PgExecutor<'_>, user_id: Uuid, status: UserStatus) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET status = $2,
+            deactivated_at = CASE WHEN $2 = 'deactivated'::user_status THEN COALESCE(deactivated_at, NOW()) END,
+            deleted_at = CASE WHEN $2 = 'deactivated'::user_status THEN deleted_at END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, email, password_hash, name, role as "role: _", tier as "tier: _", metadata, status as "status: _",
+            deactivated_at, deleted_at, session_version, created_at, updated_at
+        "#,
+        user_id,
+        status as _
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// Deactivates the user on their own request, keeping the row so their ledger stays intact; `None`
+// if the user doesn't exist or already deleted their account
+pub async fn soft_delete(executor: impl PgExecutor<'_>, user_id: Uuid) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET status = 'deactivated',
+            deactivated_at = COALESCE(deactivated_at, NOW()),
+            deleted_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING id, email, password_hash, name, role as "role: _", tier as "tier: _", metadata, status as "status: _",
+            deactivated_at, deleted_at, session_version, created_at, updated_at
+        "#,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// Changes the user's name and/or email, leaving fields that are `None` as they are; `None` if the
+// user doesn't exist
+pub async fn update_profile(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    name: Option<&str>,
+    email: Option<&str>,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET name = COALESCE($2, name),
+            email = COALESCE($3, email),
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, email, password_hash, name, role as "role: _", tier as "tier: _", metadata, status as "status: _",
+            deactivated_at, deleted_at, session_version, created_at, updated_at
+        "#,
+        user_id,
+        name,
+        email
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// Stores a new password hash and bumps the session version, signing out every existing session;
+// `None` if the user doesn't exist
+pub async fn set_password(executor: impl PgExecutor<'_>, user_id: Uuid, password_hash: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET password_hash = $2,
+            session_version = session_version + 1,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, email, password_hash, name, role as "role: _", tier as "tier: _", metadata, status as "status: _",
+            deactivated_at, deleted_at, session_version, created_at, updated_at
+        "#,
+        user_id,
+        password_hash
+    )
+    .fetch_optional(executor)
+    .await
+}
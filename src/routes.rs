@@ -0,0 +1,2796 @@
+use axum::Router;
+use axum::routing::{delete, get, patch, post, put};
+use axum::middleware as axum_middleware;
+use axum::extract::{DefaultBodyLimit, Request};
+use axum::middleware::Next;
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::trace::TraceLayer;
+
+use crate::handlers;
+use crate::middleware;
+use crate::middleware::auth::{require_admin, require_auth, require_live, require_recent_auth, require_scope, require_session};
+use crate::middleware::rate_limit::IpRateLimiters;
+use crate::models;
+use crate::models::api_key::{
+    SCOPE_BALANCE_READ, SCOPE_TRANSACTIONS_READ, SCOPE_TRANSACTIONS_WRITE, SCOPE_TRANSFERS_WRITE,
+};
+use crate::state::AppState;
+use crate::telemetry;
+
+// Largest request body accepted, except by attachment uploads
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+// An attachment upload's body: the file and the multipart framing around it
+const MAX_UPLOAD_BODY_BYTES: usize = models::attachment::MAX_ATTACHMENT_BYTES + 64 * 1024;
+
+// The whole HTTP API with its middleware, shared by the server and the end-to-end tests
+pub fn router(state: AppState, ip_limiters: Arc<IpRateLimiters>) -> Router {
+    // Configure CORS
+    let origins = state.config.cors_origin_patterns();
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            origin.to_str().is_ok_and(|origin| origins.iter().any(|pattern| pattern.matches(origin)))
+        }))
+        .allow_methods([
+            axum::http::Method::GET,
+            axum::http::Method::POST,
+            axum::http::Method::PUT,
+            axum::http::Method::PATCH,
+            axum::http::Method::DELETE,
+            axum::http::Method::OPTIONS,
+        ])
+        .allow_headers([
+            axum::http::header::AUTHORIZATION,
+            axum::http::header::CONTENT_TYPE,
+            axum::http::header::ACCEPT,
+            axum::http::HeaderName::from_static(middleware::auth::API_KEY_HEADER),
+            axum::http::HeaderName::from_static(middleware::request_id::REQUEST_ID_HEADER),
+        ])
+        .expose_headers([
+            axum::http::HeaderName::from_static(middleware::auth::REFRESHED_TOKEN_HEADER),
+            axum::http::HeaderName::from_static(models::transaction::NEXT_CURSOR_HEADER),
+            axum::http::HeaderName::from_static(middleware::request_id::REQUEST_ID_HEADER),
+        ])
+        .allow_credentials(true);
+
+    // Routes that require a JWT session or an API key carrying the right scope
+    let protected = Router::new()
+        // Transaction endpoints
+        .route("/v1/users/{user_id}/transactions", post(handlers::transaction::create_transaction)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE))))
+        .route("/v1/users/{user_id}/transactions", get(handlers::transaction::get_transactions)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/transactions/import", post(handlers::import::import_transactions)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/transactions/{transaction_id}/reverse", post(handlers::transaction::reverse_transaction)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE))))
+        .route("/v1/users/{user_id}/transactions/{transaction_id}/cancel", post(handlers::transaction::cancel_transaction)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE))))
+        .route("/v1/users/{user_id}/transactions/{transaction_id}/settle", post(handlers::transaction::settle_transaction)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE))))
+        .route("/v1/users/{user_id}/transactions/{transaction_id}/dispute", post(handlers::dispute::open_dispute)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE))))
+        .route("/v1/users/{user_id}/transactions/{transaction_id}/attachments", get(handlers::attachment::get_attachments)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/transactions/{transaction_id}/attachments/{attachment_id}", get(handlers::attachment::get_attachment)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/disputes", get(handlers::dispute::get_disputes)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/balance", get(handlers::transaction::get_account_balance)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ))))
+        .route("/v1/users/{user_id}/summary", get(handlers::transaction::get_account_summary)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/analytics", get(handlers::analytics::get_analytics)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/analytics/budgets", get(handlers::budget::get_budget_report)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/balance/history", get(handlers::analytics::get_balance_history)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ))))
+        .route("/v1/users/{user_id}/wallets", get(handlers::wallet::get_wallets)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ))))
+
+        // Account endpoints; accounts are shared by both modes, so only live credentials change them
+        .route("/v1/users/{user_id}/accounts", post(handlers::account::create_account)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/accounts", get(handlers::account::get_accounts)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ))))
+        .route("/v1/users/{user_id}/accounts/{account_id}", get(handlers::account::get_account)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ))))
+        .route("/v1/users/{user_id}/accounts/{account_id}", patch(handlers::account::update_account)
+            .delete(handlers::account::close_account)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/accounts/{account_id}/balance", get(handlers::account::get_account_balance)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ))))
+        .route("/v1/users/{user_id}/accounts/{account_id}/transactions", get(handlers::account::get_account_transactions)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/accounts/{account_id}/transfer", post(handlers::transfer::create_account_transfer)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/accounts/{account_id}/interest", get(handlers::interest::get_interest_accruals)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        .route("/v1/users/{user_id}/statements/{year}/{month}", get(handlers::statement::get_statement)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Recurring transaction endpoints
+        .route("/v1/users/{user_id}/recurring", post(handlers::recurring::create_recurring_transaction)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/recurring", get(handlers::recurring::get_recurring_transactions)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/recurring/{recurring_id}", get(handlers::recurring::get_recurring_transaction)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/recurring/{recurring_id}", patch(handlers::recurring::update_recurring_transaction)
+            .delete(handlers::recurring::delete_recurring_transaction)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Budget endpoints; budgets are shared by both modes, so only live credentials change them
+        .route("/v1/users/{user_id}/budgets", post(handlers::budget::create_budget)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/budgets", get(handlers::budget::get_budgets)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/budgets/{budget_id}", get(handlers::budget::get_budget)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/budgets/{budget_id}", patch(handlers::budget::update_budget)
+            .delete(handlers::budget::delete_budget)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Payee endpoints; like budgets, payees are shared by both modes
+        .route("/v1/users/{user_id}/payees", post(handlers::payee::create_payee)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/payees", get(handlers::payee::get_payees)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/payees/{payee_id}", get(handlers::payee::get_payee)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ))))
+        .route("/v1/users/{user_id}/payees/{payee_id}", patch(handlers::payee::update_payee)
+            .delete(handlers::payee::delete_payee)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Round-up savings, which only apply to live debits
+        .route("/v1/users/{user_id}/round-up", get(handlers::round_up::get_round_up)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/round-up", put(handlers::round_up::update_round_up)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Savings goal endpoints
+        .route("/v1/users/{user_id}/savings-goals", post(handlers::savings_goal::create_savings_goal)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/savings-goals", get(handlers::savings_goal::get_savings_goals)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/savings-goals/{goal_id}", get(handlers::savings_goal::get_savings_goal)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_BALANCE_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/savings-goals/{goal_id}", patch(handlers::savings_goal::update_savings_goal)
+            .delete(handlers::savings_goal::delete_savings_goal)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/savings-goals/{goal_id}/auto-transfer", put(handlers::savings_goal::set_auto_transfer)
+            .delete(handlers::savings_goal::remove_auto_transfer)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Transfer endpoints
+        .route("/v1/transfers", post(handlers::transfer::create_transfer)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Cross-currency transfers at a rate quoted and locked beforehand
+        .route("/v1/fx/quotes", post(handlers::fx_quote::create_quote)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/fx/transfers", post(handlers::fx_quote::create_fx_transfer)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Card deposits through Stripe, which only ever credit live balances
+        .route("/v1/users/{user_id}/deposits", post(handlers::deposit::create_deposit)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/deposits", get(handlers::deposit::get_deposits)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Bank accounts linked through Plaid, with balances and transactions pulled from the bank
+        .route("/v1/users/{user_id}/linked-accounts/link-token", post(handlers::linked_account::create_link_token)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/linked-accounts", post(handlers::linked_account::link_accounts)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/linked-accounts", get(handlers::linked_account::get_linked_accounts)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/linked-accounts/{account_id}", delete(handlers::linked_account::delete_linked_account)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/linked-accounts/{account_id}/refresh", post(handlers::linked_account::refresh_linked_account)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/users/{user_id}/linked-accounts/{account_id}/transactions", get(handlers::linked_account::get_linked_account_transactions)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Payment requests, paid by a transfer from the payer when accepted
+        .route("/v1/payment-requests", post(handlers::payment_request::create_payment_request)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/payment-requests", get(handlers::payment_request::get_payment_requests)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/payment-requests/{request_id}", get(handlers::payment_request::get_payment_request)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_READ)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/payment-requests/{request_id}/accept", post(handlers::payment_request::accept_payment_request)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/payment-requests/{request_id}/decline", post(handlers::payment_request::decline_payment_request)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+        .route("/v1/payment-requests/{request_id}/cancel", post(handlers::payment_request::cancel_payment_request)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSFERS_WRITE)))
+            .route_layer(axum_middleware::from_fn(require_live)))
+
+        // Re-authentication for sensitive operations
+        .route("/v1/auth/step-up", post(handlers::auth::step_up)
+            .route_layer(axum_middleware::from_fn(require_session))
+            .route_layer(ip_limiters.auth()))
+
+        // API key management, only available to interactive sessions; creating keys needs a recent step-up
+        .route("/v1/users/{user_id}/api-keys", post(handlers::api_key::create_api_key)
+            .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_recent_auth))
+            .get(handlers::api_key::get_api_keys)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/users/{user_id}/api-keys/{key_id}", delete(handlers::api_key::revoke_api_key)
+            .route_layer(axum_middleware::from_fn(require_session)))
+
+        // Account deletion, only available to sessions that recently stepped up
+        .route("/v1/users/{user_id}", delete(handlers::auth::delete_account)
+            .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_recent_auth))
+            .route_layer(axum_middleware::from_fn(require_session)))
+
+        // Right to erasure; starting and confirming a request need a recent step-up
+        .route("/v1/users/{user_id}/erase", post(handlers::erasure::request_erasure)
+            .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_recent_auth))
+            .delete(handlers::erasure::cancel_erasure)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/users/{user_id}/erase/confirm", post(handlers::erasure::confirm_erasure)
+            .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_recent_auth))
+            .route_layer(axum_middleware::from_fn(require_session)))
+
+        // Webhook endpoint management, only available to interactive sessions
+        .route("/v1/webhooks", post(handlers::webhook::create_webhook_endpoint)
+            .get(handlers::webhook::get_webhook_endpoints)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/webhooks/{webhook_id}", patch(handlers::webhook::update_webhook_endpoint)
+            .delete(handlers::webhook::delete_webhook_endpoint)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/webhooks/{webhook_id}/deliveries", get(handlers::webhook::get_webhook_deliveries)
+            .route_layer(axum_middleware::from_fn(require_session)))
+
+        // Maker-checker approvals of large transactions, decided by people rather than integrations
+        .route("/v1/approvals", get(handlers::approval::get_pending_approvals)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/approvals/{approval_id}", post(handlers::approval::decide_approval)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/users/{user_id}/approvers", get(handlers::approval::get_approvers)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/users/{user_id}/approvers/{approver_id}", put(handlers::approval::add_approver)
+            .delete(handlers::approval::remove_approver)
+            .route_layer(axum_middleware::from_fn(require_session)))
+
+        // Per event type choice of instant or digested webhook delivery
+        .route("/v1/notification-preferences", get(handlers::notification::get_notification_preferences)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/notification-preferences/{event_type}", put(handlers::notification::update_notification_preference)
+            .route_layer(axum_middleware::from_fn(require_session)))
+
+        // Identity verification, only available to interactive sessions; uploads are below
+        .route("/v1/users/{user_id}/kyc", get(handlers::kyc::get_kyc)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/users/{user_id}/kyc/documents", get(handlers::kyc::get_kyc_documents)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/users/{user_id}/kyc/submit", post(handlers::kyc::submit_kyc)
+            .route_layer(axum_middleware::from_fn(require_session)))
+
+        // The caller's own user record, available to any authenticated caller
+        .route("/v1/me", get(handlers::auth::get_current_user))
+
+        // Profile management, only available to interactive sessions; changing the email needs a
+        // recent step-up
+        .route("/v1/users/me", get(handlers::profile::get_profile)
+            .patch(handlers::profile::update_profile)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .route("/v1/users/me/password", post(handlers::auth::change_password)
+            .route_layer(axum_middleware::from_fn(require_session))
+            .route_layer(ip_limiters.auth()))
+
+        // FX rate history and latest rates, available to any authenticated caller
+        .route("/v1/rates", get(handlers::fx_rate::get_rates))
+        .route("/v1/fx/rates", get(handlers::fx_rate::get_latest_rates))
+
+        // Realtime account events over WebSocket, authenticated on upgrade
+        .route("/v1/ws", get(handlers::realtime::connect)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        // Runs after authentication, which is added below it
+        .route_layer(axum_middleware::from_fn_with_state(state.clone(), middleware::user_rate::limit_user_rate))
+        .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_auth));
+
+    // Attachment and KYC document uploads, which carry files larger than the body limit of every other route
+    let uploads = Router::new()
+        .route("/v1/users/{user_id}/transactions/{transaction_id}/attachments", post(handlers::attachment::upload_attachment)
+            .route_layer(axum_middleware::from_fn(|req: Request, next: Next| require_scope(req, next, SCOPE_TRANSACTIONS_WRITE))))
+        .route("/v1/users/{user_id}/kyc/documents", post(handlers::kyc::upload_kyc_document)
+            .route_layer(axum_middleware::from_fn(require_session)))
+        .layer(DefaultBodyLimit::max(MAX_UPLOAD_BODY_BYTES))
+        .route_layer(axum_middleware::from_fn_with_state(state.clone(), middleware::user_rate::limit_user_rate))
+        .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_auth));
+
+    // Support tooling, only available to admin sessions
+    let admin = Router::new()
+        .route("/v1/admin/users/{user_id}/adjustments", post(handlers::adjustment::create_adjustment)
+            .get(handlers::adjustment::get_user_adjustments))
+        .route("/v1/admin/adjustments/pending", get(handlers::adjustment::get_pending_adjustments))
+        .route("/v1/admin/adjustments/{adjustment_id}/approve", post(handlers::adjustment::approve_adjustment))
+        .route("/v1/admin/adjustments/{adjustment_id}/reject", post(handlers::adjustment::reject_adjustment))
+        .route("/v1/admin/users", get(handlers::admin::search_users))
+        .route("/v1/admin/users/{user_id}", get(handlers::admin::get_user))
+        .route("/v1/admin/users/{user_id}/deactivate", post(handlers::admin::deactivate_user))
+        .route("/v1/admin/users/{user_id}/reactivate", post(handlers::admin::reactivate_user))
+        .route("/v1/admin/users/{user_id}/tier", put(handlers::admin::update_user_tier))
+        .route("/v1/admin/users/{user_id}/velocity-limits", get(handlers::velocity_limit::get_velocity_limits)
+            .put(handlers::velocity_limit::update_velocity_limits))
+        .route("/v1/admin/users/{user_id}/recalculate-balance", post(handlers::admin::recalculate_balance))
+        .route("/v1/admin/email-domains/reload", post(handlers::admin::reload_email_domain_policy))
+        .route("/v1/admin/latency-slos", get(handlers::admin::get_latency_slos))
+        .route("/v1/admin/feature-flags", get(handlers::admin::get_feature_flags))
+        .route("/v1/admin/feature-flags/{name}", put(handlers::admin::update_feature_flag))
+        .route("/v1/admin/interest-rates", get(handlers::interest::get_interest_rates))
+        .route("/v1/admin/interest-rates/{account_type}", put(handlers::interest::update_interest_rate))
+        .route("/v1/admin/kyc", get(handlers::kyc::get_kyc_verifications))
+        .route("/v1/admin/kyc/{user_id}", get(handlers::kyc::get_kyc_review))
+        .route("/v1/admin/kyc/{user_id}/review", post(handlers::kyc::review_kyc))
+        .route("/v1/admin/kyc-limits", get(handlers::kyc::get_kyc_limits))
+        .route("/v1/admin/kyc-limits/{status}", put(handlers::kyc::update_kyc_limits))
+        .route("/v1/admin/fee-rules", get(handlers::fee::get_fee_rules).post(handlers::fee::create_fee_rule))
+        .route("/v1/admin/fee-rules/{rule_id}", put(handlers::fee::update_fee_rule).delete(handlers::fee::deactivate_fee_rule))
+        .route("/v1/admin/holds", post(handlers::hold::create_hold).get(handlers::hold::get_holds))
+        .route("/v1/admin/holds/{hold_id}", delete(handlers::hold::lift_hold))
+        .route("/v1/admin/held-debits", get(handlers::hold::get_held_debits))
+        .route("/v1/admin/held-debits/{transaction_id}/release", post(handlers::hold::release_held_debit))
+        .route("/v1/admin/held-debits/{transaction_id}/deny", post(handlers::hold::deny_held_debit))
+        .route("/v1/admin/risk-events", get(handlers::risk::get_risk_events))
+        .route("/v1/admin/risk-events/{risk_event_id}/review", post(handlers::risk::review_risk_event))
+        .route("/v1/admin/screening", get(handlers::screening::get_screening_results))
+        .route("/v1/admin/screening/{result_id}/review", post(handlers::screening::review_screening_result))
+        .route("/v1/admin/disputes", get(handlers::dispute::get_admin_disputes))
+        .route("/v1/admin/disputes/{dispute_id}/review", post(handlers::dispute::start_dispute_review))
+        .route("/v1/admin/disputes/{dispute_id}/resolve", post(handlers::dispute::resolve_dispute))
+        .route("/v1/admin/audit-log", get(handlers::admin::get_audit_log))
+        .route("/v1/admin/jobs", get(handlers::admin::get_jobs))
+        .route("/v1/admin/jobs/{job_id}/retry", post(handlers::admin::retry_job))
+        .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_admin));
+
+    let latency = state.latency.clone();
+    Router::new()
+        // Health check endpoints: `/health` for load balancers, `/livez` and `/readyz` for orchestrators
+        .route("/health", get(handlers::health::health_check))
+        .route("/livez", get(handlers::health::livez))
+        .route("/readyz", get(handlers::health::readyz))
+        // Auth endpoints
+        .route("/v1/auth", post(handlers::auth::authenticate_user).route_layer(ip_limiters.auth()))
+        .route("/v1/register", post(handlers::auth::register_user).route_layer(ip_limiters.auth()))
+        .route("/v1/verify-email", post(handlers::profile::verify_email).route_layer(ip_limiters.auth()))
+        // Inbound webhooks from partners, authenticated by the provider's signature rather than a credential.
+        // Each provider is received at `/v1/webhooks/<provider>` by name.
+        .route("/v1/webhooks/stripe", post(handlers::webhooks::receive_webhook))
+        .merge(protected)
+        .merge(admin)
+        .layer(RequestBodyLimitLayer::new(MAX_BODY_BYTES))
+        .merge(uploads)
+        .with_state(state)
+        // Add middleware layers
+        .layer(axum_middleware::from_fn_with_state(latency, middleware::latency::track_latency))
+        .layer(axum_middleware::from_fn(middleware::read_only::reject_writes))
+        .layer(ip_limiters.general())
+        .layer(TraceLayer::new_for_http()
+            .make_span_with(telemetry::make_span)
+            .on_request(telemetry::on_request)
+            .on_response(telemetry::on_response)
+            .on_failure(()))
+        .layer(cors)
+        // Outermost, so every response carries the request id
+        .layer(axum_middleware::from_fn(middleware::request_id::assign_request_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use bigdecimal::BigDecimal;
+    use serde_json::{json, Value};
+    use sqlx::PgPool;
+    use std::str::FromStr;
+
+    use crate::{handlers, models};
+    use crate::linked_accounts::sealing::open_access_token;
+    use crate::test_support::{test_config, TestApp, TEST_DENIED_DOMAIN, TEST_DENIED_NAME, TEST_FX_SPREAD_BPS, TEST_PASSWORD};
+
+    #[sqlx::test]
+    async fn test_sign_up_and_post_transactions(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e@example.com").await;
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "40.00", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "50.00", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "insufficient_funds");
+
+        let (status, body) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["balances"]["USD"], "40");
+
+        let (status, body) = app.request(Method::GET, &transactions, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        // The database is this test's alone, so nothing else is in it
+        let entries = sqlx::query_scalar!("SELECT COUNT(*) as \"count!\" FROM transactions")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(entries, 1);
+    }
+
+    #[sqlx::test]
+    async fn test_invalid_bodies_report_each_field(pool: PgPool) {
+        let app = TestApp::new(pool);
+
+        let (status, body) = app
+            .request(Method::POST, "/v1/register", None, Some(json!({ "email": "nobody", "password": "short", "name": "Test User" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["errors"]["email"], json!(["Invalid email format"]));
+        assert_eq!(body["errors"]["password"], json!(["Password must be at least 8 characters long"]));
+
+        let (token, user_id) = app.sign_up("e2e-validated@example.com").await;
+        let (status, body) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/transactions", user_id),
+                Some(&token),
+                Some(json!({ "amount": "5.00", "transaction_type": "Credit", "description": "x".repeat(501) })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["errors"]["description"], json!(["Description must be at most 500 characters"]));
+
+        // Amounts are checked against the currency once the body is valid
+        let (status, body) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/transactions", user_id),
+                Some(&token),
+                Some(json!({ "amount": "5.005", "transaction_type": "Credit" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "invalid_amount");
+        assert_eq!(body["errors"]["amount"], json!(["USD amounts can have at most 2 decimal places"]));
+
+        // Bodies that don't parse are still refused before validation
+        let (status, _) = app
+            .request(Method::POST, &format!("/v1/users/{}/transactions", user_id), Some(&token), Some(json!({ "amount": "5.00" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[sqlx::test]
+    async fn test_probes_report_component_status(pool: PgPool) {
+        let app = TestApp::new(pool);
+
+        let (status, body) = app.request(Method::GET, "/livez", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+
+        let (status, body) = app.request(Method::GET, "/readyz", None, None).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["components"]["migrations"]["status"], "up");
+
+        // Forget the newest migration, as if it hadn't run yet
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let (status, body) = app.request(Method::GET, "/readyz", None, None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["components"]["database"]["status"], "up");
+        assert_eq!(body["components"]["migrations"]["status"], "down");
+    }
+
+    #[sqlx::test]
+    async fn test_credentials_are_checked_before_handlers(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-keys@example.com").await;
+
+        let (status, _) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/api-keys", user_id),
+                Some(&token),
+                Some(json!({ "name": "tests", "scopes": ["transactions:read", "transfers:write"], "livemode": false })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let key = body["key"].as_str().unwrap();
+
+        // The key lacks balance:read, and sandbox keys can't transfer
+        let (status, _) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), Some(key), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(key), Some(json!({ "to_user_id": user_id, "amount": "1.00" })))
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["message"], "This endpoint isn't available to sandbox API keys");
+    }
+
+    #[sqlx::test]
+    async fn test_admins_deactivate_and_reactivate_users(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-member@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let (status, body) = app.request(Method::GET, "/v1/admin/users?name=test&limit=1", Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["email"], "e2e-member@example.com");
+
+        let deactivate = format!("/v1/admin/users/{}/deactivate", user_id);
+        let (status, _) = app.request(Method::POST, &deactivate, Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = app
+            .request(Method::POST, &format!("/v1/admin/users/{}/deactivate", admin_id), Some(&admin_token), None)
+            .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, body) = app
+            .request(Method::POST, &deactivate, Some(&admin_token), Some(json!({ "reason": "chargeback fraud" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Deactivated");
+
+        // Existing sessions and fresh sign-ins are both refused
+        let (status, body) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "account_deactivated");
+        let (status, _) = app
+            .request(
+                Method::POST,
+                "/v1/auth",
+                None,
+                Some(json!({ "email": "e2e-member@example.com", "password": crate::test_support::TEST_PASSWORD })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = app.request(Method::GET, "/v1/admin/users?status=Deactivated", Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        let (status, _) = app
+            .request(Method::POST, &format!("/v1/admin/users/{}/reactivate", user_id), Some(&admin_token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = app.request(Method::GET, &format!("/v1/admin/users/{}", user_id), Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["user"]["status"], "Active");
+        assert!(body["user"]["deactivated_at"].is_null());
+
+        let (status, _) = app.request(Method::GET, "/v1/me", Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn test_audit_log_records_who_did_what(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-auditor@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-audited@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let (status, body) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/transactions", user_id),
+                Some(&token),
+                Some(json!({ "amount": "5.00", "transaction_type": "Credit" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let transaction_id = body["id"].as_str().unwrap().to_string();
+        let (status, _) = app
+            .request(Method::PUT, &format!("/v1/admin/users/{}/tier", user_id), Some(&admin_token), Some(json!({ "tier": "Plus" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = app.request(Method::GET, "/v1/admin/audit-log", Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = app
+            .request(Method::GET, &format!("/v1/admin/audit-log?user_id={}", user_id), Some(&admin_token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let actions: Vec<&str> = body.as_array().unwrap().iter().map(|entry| entry["action"].as_str().unwrap()).collect();
+        assert_eq!(actions, ["user.tier_changed", "transaction.created", "user.logged_in", "user.registered"]);
+
+        let tier_change = &body[0];
+        assert_eq!(tier_change["actor_id"], admin_id.to_string());
+        assert_eq!(tier_change["before"]["tier"], "Free");
+        assert_eq!(tier_change["after"]["tier"], "Plus");
+        assert!(tier_change["after"].get("password_hash").is_none());
+        assert_eq!(body[1]["target_id"], transaction_id);
+        assert!(body[1]["before"].is_null());
+
+        let (status, body) = app
+            .request(Method::GET, &format!("/v1/admin/audit-log?actor_id={}&limit=1", admin_id), Some(&admin_token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["action"], "user.tier_changed");
+
+        let (status, body) = app
+            .request(Method::GET, "/v1/admin/audit-log?action=transaction.created&to=2000-01-01T00:00:00Z", Some(&admin_token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.as_array().unwrap().is_empty());
+
+        let (status, _) = app.request(Method::GET, "/v1/admin/audit-log?action=user.hacked", Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test]
+    async fn test_email_changes_wait_for_verification(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-profile@example.com").await;
+        app.sign_up("e2e-taken@example.com").await;
+
+        let (status, body) = app
+            .request(Method::PATCH, "/v1/users/me", Some(&token), Some(json!({ "name": "  Renamed User " })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "Renamed User");
+        assert!(body["pending_email"].is_null());
+
+        let (status, _) = app
+            .request(Method::PATCH, "/v1/users/me", Some(&token), Some(json!({ "email": "e2e-taken@example.com" })))
+            .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // The current address stays until the new one is confirmed
+        let (status, body) = app
+            .request(Method::PATCH, "/v1/users/me", Some(&token), Some(json!({ "email": "e2e-moved@example.com" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["email"], "e2e-profile@example.com");
+        assert_eq!(body["pending_email"], "e2e-moved@example.com");
+
+        let payload = sqlx::query_scalar!("SELECT payload FROM jobs WHERE kind = 'send_email'")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(payload["user_id"], user_id.to_string());
+        let link = payload["email"]["link"].as_str().unwrap();
+        let (_, verification_token) = link.split_once("token=").unwrap();
+
+        let (status, _) = app.request(Method::POST, "/v1/verify-email", None, Some(json!({ "token": "verify_guess" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = app
+            .request(Method::POST, "/v1/verify-email", None, Some(json!({ "token": verification_token })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["email"], "e2e-moved@example.com");
+
+        // Links work once
+        let (status, _) = app
+            .request(Method::POST, "/v1/verify-email", None, Some(json!({ "token": verification_token })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = app.request(Method::GET, "/v1/users/me", Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["email"], "e2e-moved@example.com");
+        assert_eq!(body["name"], "Renamed User");
+        assert!(body["pending_email"].is_null());
+        assert!(body.get("password_hash").is_none());
+    }
+
+    #[sqlx::test]
+    async fn test_changing_the_password_signs_out_other_sessions(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, _) = app.sign_up("e2e-password@example.com").await;
+        let (status, body) = app
+            .request(Method::POST, "/v1/auth", None, Some(json!({ "email": "e2e-password@example.com", "password": TEST_PASSWORD })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let other_session = body["token"].as_str().unwrap().to_string();
+
+        let (status, _) = app
+            .request(
+                Method::POST,
+                "/v1/users/me/password",
+                Some(&token),
+                Some(json!({ "current_password": "not-my-password", "new_password": "a-new-password" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = app
+            .request(
+                Method::POST,
+                "/v1/users/me/password",
+                Some(&token),
+                Some(json!({ "current_password": TEST_PASSWORD, "new_password": "short" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = app
+            .request(
+                Method::POST,
+                "/v1/users/me/password",
+                Some(&token),
+                Some(json!({ "current_password": TEST_PASSWORD, "new_password": "a-new-password" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let new_token = body["token"].as_str().unwrap().to_string();
+
+        // Sessions issued before the change stop working, the one returned with it works
+        for old_token in [&token, &other_session] {
+            let (status, _) = app.request(Method::GET, "/v1/me", Some(old_token), None).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        let (status, _) = app.request(Method::GET, "/v1/me", Some(&new_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = app
+            .request(Method::POST, "/v1/auth", None, Some(json!({ "email": "e2e-password@example.com", "password": TEST_PASSWORD })))
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = app
+            .request(Method::POST, "/v1/auth", None, Some(json!({ "email": "e2e-password@example.com", "password": "a-new-password" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn test_accounts_keep_separate_balances(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-accounts@example.com").await;
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        let accounts = format!("/v1/users/{}/accounts", user_id);
+
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, savings) = app
+            .request(Method::POST, &accounts, Some(&token), Some(json!({ "name": "Rainy day", "account_type": "savings" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((savings["currency"].as_str(), savings["is_primary"].as_bool()), (Some("USD"), Some(false)));
+        let savings_id = savings["id"].as_str().unwrap();
+
+        let (status, body) = app.request(Method::GET, &accounts, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert_eq!((body[0]["name"].as_str(), body[0]["account_type"].as_str()), (Some("Main"), Some("checking")));
+        let main_id = body[0]["id"].as_str().unwrap().to_string();
+
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &transactions,
+                Some(&token),
+                Some(json!({ "amount": "30", "transaction_type": "Credit", "account_id": savings_id })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Each account only spends its own money, though the user holds 130 in total
+        for (account_id, amount) in [(Some(savings_id), "50"), (None, "120")] {
+            let (status, body) = app
+                .request(
+                    Method::POST,
+                    &transactions,
+                    Some(&token),
+                    Some(json!({ "amount": amount, "transaction_type": "Debit", "account_id": account_id })),
+                )
+                .await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(body["code"], "insufficient_funds");
+        }
+        for (account_id, balance) in [(main_id.as_str(), 100), (savings_id, 30)] {
+            let (status, body) = app.request(Method::GET, &format!("{}/{}/balance", accounts, account_id), Some(&token), None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(BigDecimal::from_str(body["available"].as_str().unwrap()).unwrap(), BigDecimal::from(balance));
+        }
+        let (status, body) = app.request(Method::GET, &format!("{}/{}/transactions", accounts, savings_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        // Only empty accounts other than the main one can be closed
+        let (status, _) = app.request(Method::DELETE, &format!("{}/{}", accounts, savings_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &transactions,
+                Some(&token),
+                Some(json!({ "amount": "30", "transaction_type": "Debit", "account_id": savings_id })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = app.request(Method::DELETE, &format!("{}/{}", accounts, savings_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body["closed_at"].is_null());
+        let (status, _) = app.request(Method::DELETE, &format!("{}/{}", accounts, main_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &transactions,
+                Some(&token),
+                Some(json!({ "amount": "5", "transaction_type": "Credit", "account_id": savings_id })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &transactions,
+                Some(&token),
+                Some(json!({ "amount": "5", "currency": "EUR", "transaction_type": "Credit", "account_id": main_id })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test]
+    async fn test_transfers_between_own_accounts(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-account-transfers@example.com").await;
+        let accounts = format!("/v1/users/{}/accounts", user_id);
+
+        let (status, main) = app
+            .request(Method::POST, &format!("/v1/users/{}/transactions", user_id), Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let main_id = main["account_id"].as_str().unwrap();
+        let (_, savings) = app
+            .request(Method::POST, &accounts, Some(&token), Some(json!({ "name": "Savings", "account_type": "savings" })))
+            .await;
+        let savings_id = savings["id"].as_str().unwrap();
+
+        let (status, body) = app
+            .request(
+                Method::POST,
+                &format!("{}/{}/transfer", accounts, main_id),
+                Some(&token),
+                Some(json!({ "to_account_id": savings_id, "amount": "60", "description": "Put aside" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transfer"]["from_user_id"], body["transfer"]["to_user_id"]);
+        assert_eq!(body["debit"]["account_id"].as_str(), Some(main_id));
+        assert_eq!(body["credit"]["account_id"].as_str(), Some(savings_id));
+        assert_eq!(body["debit"]["transfer_id"], body["transfer"]["id"]);
+        assert_eq!(body["credit"]["transfer_id"], body["transfer"]["id"]);
+
+        // Savings now holds 60, which is all it can send back
+        let (status, body) = app
+            .request(Method::POST, &format!("{}/{}/transfer", accounts, savings_id), Some(&token), Some(json!({ "to_account_id": main_id, "amount": "60.01" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "insufficient_funds");
+        let (status, _) = app
+            .request(Method::POST, &format!("{}/{}/transfer", accounts, savings_id), Some(&token), Some(json!({ "to_account_id": savings_id, "amount": "1" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, euros) = app
+            .request(Method::POST, &accounts, Some(&token), Some(json!({ "name": "Euros", "account_type": "wallet", "currency": "EUR" })))
+            .await;
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &format!("{}/{}/transfer", accounts, savings_id),
+                Some(&token),
+                Some(json!({ "to_account_id": euros["id"], "amount": "1" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        for (account_id, balance) in [(main_id, 40), (savings_id, 60)] {
+            let (_, body) = app.request(Method::GET, &format!("{}/{}/balance", accounts, account_id), Some(&token), None).await;
+            assert_eq!(BigDecimal::from_str(body["balance"].as_str().unwrap()).unwrap(), BigDecimal::from(balance));
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_savings_goal_tracks_progress_and_auto_transfers(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-savings-goals@example.com").await;
+        let goals = format!("/v1/users/{}/savings-goals", user_id);
+
+        let (_, main) = app
+            .request(Method::POST, &format!("/v1/users/{}/transactions", user_id), Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        let (_, savings) = app
+            .request(Method::POST, &format!("/v1/users/{}/accounts", user_id), Some(&token), Some(json!({ "name": "Bike", "account_type": "savings" })))
+            .await;
+
+        let (status, goal) = app
+            .request(
+                Method::POST,
+                &goals,
+                Some(&token),
+                Some(json!({
+                    "name": "New bike",
+                    "account_id": savings["id"],
+                    "target_amount": "50",
+                    "auto_transfer": { "from_account_id": main["account_id"], "amount": "30", "frequency": "Monthly" }
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(goal["progress"]["saved"], "0");
+        assert_eq!(goal["progress"]["achieved"], false);
+        let goal_url = format!("{}/{}", goals, goal["id"].as_str().unwrap());
+
+        // Goals need a dedicated account, one goal each
+        for (account_id, expected) in [(&main["account_id"], StatusCode::BAD_REQUEST), (&savings["id"], StatusCode::CONFLICT)] {
+            let (status, _) = app
+                .request(Method::POST, &goals, Some(&token), Some(json!({ "name": "Other", "account_id": account_id, "target_amount": "10" })))
+                .await;
+            assert_eq!(status, expected);
+        }
+
+        assert_eq!(crate::handlers::savings_goal::run_due_auto_transfers(&app.pool, &app.config).await.unwrap(), 1);
+        let (_, goal) = app.request(Method::GET, &goal_url, Some(&token), None).await;
+        assert_eq!(BigDecimal::from_str(goal["progress"]["saved"].as_str().unwrap()).unwrap(), BigDecimal::from(30));
+        assert_eq!(goal["progress"]["percent_complete"], "60.00");
+        assert!(!goal["next_auto_transfer_at"].is_null());
+
+        // The next transfer only tops the goal up to its target, then the rule stops
+        sqlx::query!("UPDATE savings_goals SET next_auto_transfer_at = NOW() WHERE user_id = $1", user_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(crate::handlers::savings_goal::run_due_auto_transfers(&app.pool, &app.config).await.unwrap(), 1);
+        let (_, goal) = app.request(Method::GET, &goal_url, Some(&token), None).await;
+        assert_eq!(goal["progress"]["achieved"], true);
+        assert!(goal["next_auto_transfer_at"].is_null());
+
+        let (_, balance) = app
+            .request(Method::GET, &format!("/v1/users/{}/accounts/{}/balance", user_id, main["account_id"].as_str().unwrap()), Some(&token), None)
+            .await;
+        assert_eq!(BigDecimal::from_str(balance["balance"].as_str().unwrap()).unwrap(), BigDecimal::from(50));
+
+        let (status, _) = app.request(Method::DELETE, &goal_url, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app.request(Method::GET, &goal_url, Some(&token), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test]
+    async fn test_round_ups_save_spare_change(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-round-ups@example.com").await;
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (_, deposit) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        let (_, savings) = app
+            .request(Method::POST, &format!("/v1/users/{}/accounts", user_id), Some(&token), Some(json!({ "name": "Spare change", "account_type": "savings" })))
+            .await;
+        let (status, rule) = app
+            .request(Method::PUT, &format!("/v1/users/{}/round-up", user_id), Some(&token), Some(json!({ "account_id": savings["id"], "enabled": true })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(rule["enabled"], true);
+
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "12.30", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let accounts = format!("/v1/users/{}/accounts", user_id);
+        for (account_id, balance) in [(&savings["id"], "0.70"), (&deposit["account_id"], "87")] {
+            let (_, body) = app.request(Method::GET, &format!("{}/{}/balance", accounts, account_id.as_str().unwrap()), Some(&token), None).await;
+            assert_eq!(BigDecimal::from_str(body["balance"].as_str().unwrap()).unwrap(), BigDecimal::from_str(balance).unwrap());
+        }
+
+        // Switched off, debits are left as they are
+        app.request(Method::PUT, &format!("/v1/users/{}/round-up", user_id), Some(&token), Some(json!({ "account_id": savings["id"], "enabled": false })))
+            .await;
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "1.50", "transaction_type": "Debit" })))
+            .await;
+        let (_, body) = app.request(Method::GET, &format!("{}/{}/balance", accounts, savings["id"].as_str().unwrap()), Some(&token), None).await;
+        assert_eq!(BigDecimal::from_str(body["balance"].as_str().unwrap()).unwrap(), BigDecimal::from_str("0.70").unwrap());
+    }
+
+    #[sqlx::test]
+    async fn test_budgets_report_spending_and_alert_at_thresholds(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-budgets@example.com").await;
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (status, budget) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/budgets", user_id),
+                Some(&token),
+                Some(json!({ "category": "Groceries", "period": "Monthly", "limit_amount": "200" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(budget["category"], "groceries");
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/budgets", user_id),
+                Some(&token),
+                Some(json!({ "category": "groceries", "period": "Monthly", "limit_amount": "300" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "500", "transaction_type": "Credit" })))
+            .await;
+        // Spending in other categories doesn't count
+        for (amount, category) in [("150", "groceries"), ("90", "rent"), ("20", "groceries"), ("40", "groceries")] {
+            let (status, _) = app
+                .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": amount, "transaction_type": "Debit", "category": category })))
+                .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let thresholds: Vec<i64> = sqlx::query_scalar!(
+            "SELECT (payload->'data'->>'threshold')::BIGINT FROM outbox_events WHERE user_id = $1 AND event_type = 'budget.threshold_reached' ORDER BY created_at",
+            user_id
+        )
+        .fetch_all(&app.pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect();
+        assert_eq!(thresholds, vec![80, 100]);
+
+        let (status, report) = app.request(Method::GET, &format!("/v1/users/{}/analytics/budgets", user_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let groceries = &report["budgets"][0];
+        assert_eq!(BigDecimal::from_str(groceries["spent"].as_str().unwrap()).unwrap(), BigDecimal::from(210));
+        assert_eq!(BigDecimal::from_str(groceries["remaining"].as_str().unwrap()).unwrap(), BigDecimal::from(0));
+        assert_eq!(groceries["percent_used"], "105.00");
+    }
+
+    #[sqlx::test]
+    async fn test_velocity_limits_refuse_transactions_over_them(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-velocity-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-velocity@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let limits = format!("/v1/admin/users/{}/velocity-limits", user_id);
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (status, _) = app.request(Method::GET, &limits, Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = app
+            .request(
+                Method::PUT,
+                &limits,
+                Some(&admin_token),
+                Some(json!({ "max_transaction_amount": "500", "max_daily_debit_total": "300", "max_transactions_per_hour": 4 })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["updated_by"], admin_id.to_string());
+
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "600", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "velocity_limit_exceeded");
+        assert_eq!(body["limit"]["limit"], "max_transaction_amount");
+
+        for (amount, transaction_type) in [("500", "Credit"), ("200", "Debit")] {
+            let (status, _) = app
+                .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": amount, "transaction_type": transaction_type })))
+                .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "150", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["limit"]["limit"], "max_daily_debit_total");
+        assert_eq!(body["limit"]["currency"], "USD");
+        assert_eq!(BigDecimal::from_str(body["limit"]["used"].as_str().unwrap()).unwrap(), BigDecimal::from(200));
+
+        // Refused transactions aren't recorded, so they don't use up the hourly count
+        for _ in 0..2 {
+            let (status, _) = app
+                .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "10", "transaction_type": "Credit" })))
+                .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "10", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["limit"]["limit"], "max_transactions_per_hour");
+        assert_eq!(body["message"], "At most 4 transactions can be made in an hour");
+
+        // Clearing the limits lifts them
+        let (status, _) = app.request(Method::PUT, &limits, Some(&admin_token), Some(json!({}))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "10", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn test_velocity_limits_count_debits_in_every_currency(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-fx-velocity-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-fx-velocity@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO fx_rates (base_currency, quote_currency, rate, source) VALUES ('EUR', 'USD', 1.25, 'test')")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        let transaction = |amount: &str, currency: &str, transaction_type: &str| {
+            json!({ "amount": amount, "currency": currency, "transaction_type": transaction_type })
+        };
+
+        let (status, body) = app
+            .request(
+                Method::PUT,
+                &format!("/v1/admin/users/{}/velocity-limits", user_id),
+                Some(&admin_token),
+                Some(json!({ "currency": "usd", "max_daily_debit_total": "300" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["currency"], "USD");
+
+        for (amount, currency, transaction_type) in [("500", "EUR", "Credit"), ("500", "USD", "Credit"), ("200", "EUR", "Debit"), ("50", "USD", "Debit")] {
+            let (status, _) = app
+                .request(Method::POST, &transactions, Some(&token), Some(transaction(amount, currency, transaction_type)))
+                .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        // 200 EUR is 250 USD, so the limit is used up across both wallets
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(transaction("1", "EUR", "Debit")))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["limit"]["limit"], "max_daily_debit_total");
+        assert_eq!(body["limit"]["currency"], "USD");
+        assert_eq!(BigDecimal::from_str(body["limit"]["used"].as_str().unwrap()).unwrap(), BigDecimal::from(300));
+        assert_eq!(BigDecimal::from_str(body["limit"]["requested"].as_str().unwrap()).unwrap(), BigDecimal::from_str("1.25").unwrap());
+
+        // Without a rate into the limits' currency, a transaction can't be checked and is refused
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(transaction("1", "GBP", "Credit")))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["message"], "No exchange rate from GBP to USD");
+    }
+
+    #[sqlx::test]
+    async fn test_large_transactions_wait_for_a_second_persons_approval(pool: PgPool) {
+        let config = crate::config::Config { approval_threshold: Some(BigDecimal::from(50000)), ..test_config() };
+        let app = TestApp::with_config(pool, config);
+        let (token, user_id) = app.sign_up("e2e-maker@example.com").await;
+        let (checker_token, checker_id) = app.sign_up("e2e-checker@example.com").await;
+        let (admin_token, admin_id) = app.sign_up("e2e-approving-admin@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "60000", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "AwaitingApproval");
+        let account_balance = format!("/v1/users/{}/accounts/{}/balance", user_id, body["account_id"].as_str().unwrap());
+        let balance = || async {
+            let (_, body) = app.request(Method::GET, &account_balance, Some(&token), None).await;
+            BigDecimal::from_str(body["balance"].as_str().unwrap()).unwrap()
+        };
+        assert_eq!(balance().await, BigDecimal::from(0));
+
+        // Only admins and approvers the maker named see it
+        let (_, body) = app.request(Method::GET, "/v1/approvals", Some(&checker_token), None).await;
+        assert_eq!(body, json!([]));
+        let (status, _) = app
+            .request(Method::PUT, &format!("/v1/users/{}/approvers/{}", user_id, checker_id), Some(&token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = app.request(Method::GET, "/v1/approvals", Some(&checker_token), None).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        let approval = format!("/v1/approvals/{}", body[0]["id"].as_str().unwrap());
+
+        let (status, _) = app.request(Method::POST, &approval, Some(&token), Some(json!({ "decision": "Approve" }))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = app
+            .request(Method::POST, &approval, Some(&checker_token), Some(json!({ "decision": "Approve", "note": "Salary" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["status"].as_str(), body["transaction_status"].as_str()), (Some("Approved"), Some("Settled")));
+        assert_eq!(balance().await, BigDecimal::from(60000));
+
+        let (_, debit) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "55000", "transaction_type": "Debit" })))
+            .await;
+        let (_, body) = app.request(Method::GET, "/v1/approvals", Some(&admin_token), None).await;
+        let pending = body.as_array().unwrap().iter().find(|approval| approval["transaction_id"] == debit["id"]).unwrap();
+        let approval = format!("/v1/approvals/{}", pending["id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &approval, Some(&admin_token), Some(json!({ "decision": "Reject" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transaction_status"], "Denied");
+        assert_eq!(balance().await, BigDecimal::from(60000));
+        let (status, _) = app.request(Method::POST, &approval, Some(&checker_token), Some(json!({ "decision": "Approve" }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // The maker hears about both requests and decisions, the named approver about requests
+        let events: Vec<(Option<uuid::Uuid>, String, i64)> = sqlx::query!(
+            r#"
+            SELECT user_id, event_type, COUNT(*) as "count!" FROM outbox_events
+            WHERE event_type LIKE 'approval.%' AND user_id IN ($1, $2)
+            GROUP BY user_id, event_type
+            ORDER BY user_id = $1 DESC, event_type
+            "#,
+            user_id,
+            checker_id
+        )
+        .fetch_all(&app.pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| (event.user_id, event.event_type, event.count))
+        .collect();
+        assert_eq!(
+            events,
+            vec![
+                (Some(user_id), "approval.decided".to_string(), 2),
+                (Some(user_id), "approval.requested".to_string(), 2),
+                (Some(checker_id), "approval.requested".to_string(), 1),
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_scheduled_transactions_over_the_threshold_wait_for_approval_when_due(pool: PgPool) {
+        let config = crate::config::Config { approval_threshold: Some(BigDecimal::from(50000)), ..test_config() };
+        let app = TestApp::with_config(pool, config);
+        let (token, user_id) = app.sign_up("e2e-scheduled-maker@example.com").await;
+        let (admin_token, admin_id) = app.sign_up("e2e-scheduled-admin@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let execute_at = (time::OffsetDateTime::now_utc() + time::Duration::hours(1))
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap();
+        let (status, scheduled) = app
+            .request(
+                Method::POST,
+                &transactions,
+                Some(&token),
+                Some(json!({ "amount": "60000", "transaction_type": "Credit", "execute_at": execute_at })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(scheduled["status"], "Scheduled");
+        let scheduled_id: uuid::Uuid = scheduled["id"].as_str().unwrap().parse().unwrap();
+        sqlx::query!("UPDATE transactions SET execute_at = NOW() WHERE id = $1", scheduled_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        assert_eq!(crate::services::ledger::execute_due_transactions(&app.pool, &app.config).await.unwrap(), 1);
+        let status = sqlx::query_scalar!(r#"SELECT status as "status: models::transaction::TransactionStatus" FROM transactions WHERE id = $1"#, scheduled_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(status, models::transaction::TransactionStatus::AwaitingApproval);
+
+        let (_, body) = app.request(Method::GET, "/v1/approvals", Some(&admin_token), None).await;
+        let pending = body.as_array().unwrap().iter().find(|approval| approval["transaction_id"] == scheduled["id"]).unwrap();
+        let approval = format!("/v1/approvals/{}", pending["id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &approval, Some(&admin_token), Some(json!({ "decision": "Approve" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transaction_status"], "Settled");
+    }
+
+    #[sqlx::test]
+    async fn test_fraud_rules_hold_and_block_transactions_for_review(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-risky@example.com").await;
+        let (admin_token, admin_id) = app.sign_up("e2e-risk-admin@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (status, credit) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Signing up counted as a login from a new device once the user has used another one before
+        sqlx::query!(
+            "INSERT INTO login_devices (user_id, fingerprint, first_seen_at, last_seen_at) VALUES ($1, 'old-phone', NOW() - INTERVAL '3 days', NOW() - INTERVAL '3 days')",
+            user_id
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+        let (status, debit) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "10", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(debit["status"], "Held");
+
+        let risk_events = format!("/v1/admin/risk-events?user_id={}", user_id);
+        let (_, events) = app.request(Method::GET, &risk_events, Some(&admin_token), None).await;
+        assert_eq!(events.as_array().unwrap().len(), 1);
+        assert_eq!((events[0]["rule"].as_str(), events[0]["action"].as_str()), (Some("new_device"), Some("hold")));
+        assert_eq!(events[0]["transaction_id"], debit["id"]);
+
+        let (_, queue) = app.request(Method::GET, "/v1/admin/held-debits", Some(&admin_token), None).await;
+        let held = queue.as_array().unwrap().iter().find(|held| held["transaction_id"] == debit["id"]).unwrap();
+        assert_eq!((&held["risk_event_id"], &held["hold_id"]), (&events[0]["id"], &serde_json::Value::Null));
+        let (status, body) = app
+            .request(Method::POST, &format!("/v1/admin/held-debits/{}/release", debit["id"].as_str().unwrap()), Some(&admin_token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Settled");
+
+        // A burst of transactions is blocked outright; nothing is stored but the risk event
+        let account_id: uuid::Uuid = credit["account_id"].as_str().unwrap().parse().unwrap();
+        sqlx::query!(
+            "INSERT INTO transactions (user_id, account_id, amount, transaction_type) SELECT $1, $2, 1, 'credit' FROM generate_series(1, 48)",
+            user_id,
+            account_id
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "5", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "transaction_declined");
+
+        let (_, events) = app.request(Method::GET, &risk_events, Some(&admin_token), None).await;
+        let blocked = events.as_array().unwrap().iter().find(|event| event["action"] == "block").unwrap();
+        assert_eq!((blocked["rule"].as_str(), &blocked["transaction_id"]), (Some("velocity_spike"), &serde_json::Value::Null));
+
+        let review = format!("/v1/admin/risk-events/{}/review", blocked["id"].as_str().unwrap());
+        let (status, _) = app.request(Method::POST, &review, Some(&token), Some(json!({}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = app
+            .request(Method::POST, &review, Some(&admin_token), Some(json!({ "note": "Load test by the user" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["reviewed_by"], admin_id.to_string());
+        let (status, _) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({}))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (_, reviewed) = app.request(Method::GET, &format!("{}&reviewed=true", risk_events), Some(&admin_token), None).await;
+        assert_eq!(reviewed.as_array().unwrap().len(), 1);
+        assert_eq!(reviewed[0]["review_note"], "Load test by the user");
+    }
+
+    #[sqlx::test]
+    async fn test_upheld_disputes_are_refunded_and_rejected_ones_stand(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-disputing@example.com").await;
+        let (admin_token, admin_id) = app.sign_up("e2e-dispute-admin@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (_, credit) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        let mut debits = Vec::new();
+        for amount in ["30", "20"] {
+            let (_, debit) = app
+                .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": amount, "transaction_type": "Debit" })))
+                .await;
+            debits.push(format!("{}/{}/dispute", transactions, debit["id"].as_str().unwrap()));
+        }
+        let account_balance = format!("/v1/users/{}/accounts/{}/balance", user_id, credit["account_id"].as_str().unwrap());
+        let balance = || async {
+            let (_, body) = app.request(Method::GET, &account_balance, Some(&token), None).await;
+            BigDecimal::from_str(body["balance"].as_str().unwrap()).unwrap()
+        };
+
+        let credit_dispute = format!("{}/{}/dispute", transactions, credit["id"].as_str().unwrap());
+        let (status, _) = app.request(Method::POST, &credit_dispute, Some(&token), Some(json!({ "reason": "Not mine" }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _) = app.request(Method::POST, &debits[0], Some(&token), Some(json!({ "reason": "" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, upheld) = app
+            .request(Method::POST, &debits[0], Some(&token), Some(json!({ "reason": "Charged twice" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((upheld["status"].as_str(), upheld["amount"].as_str()), (Some("Open"), Some("30")));
+        let (status, _) = app.request(Method::POST, &debits[0], Some(&token), Some(json!({ "reason": "Again" }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (_, rejected) = app
+            .request(Method::POST, &debits[1], Some(&token), Some(json!({ "reason": "Never arrived" })))
+            .await;
+
+        let (status, _) = app.request(Method::GET, "/v1/admin/disputes", Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (_, queue) = app.request(Method::GET, "/v1/admin/disputes", Some(&admin_token), None).await;
+        assert_eq!(queue.as_array().unwrap().len(), 2);
+
+        let upheld = format!("/v1/admin/disputes/{}", upheld["id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &format!("{}/review", upheld), Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "UnderReview");
+        let (status, _) = app.request(Method::POST, &format!("{}/review", upheld), Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // Upholding posts a compensating credit for the disputed amount
+        assert_eq!(balance().await, BigDecimal::from(50));
+        let resolve = format!("{}/resolve", upheld);
+        let (status, body) = app
+            .request(Method::POST, &resolve, Some(&admin_token), Some(json!({ "outcome": "Upheld", "note": "Duplicate charge" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Refunded");
+        assert_eq!(balance().await, BigDecimal::from(80));
+        let refund_id: uuid::Uuid = body["refund_transaction_id"].as_str().unwrap().parse().unwrap();
+        let reverses = sqlx::query_scalar!("SELECT reverses FROM transactions WHERE id = $1 AND transaction_type = 'credit'", refund_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(reverses.map(|id| id.to_string()).as_deref(), body["transaction_id"].as_str());
+        let (status, _) = app.request(Method::POST, &resolve, Some(&admin_token), Some(json!({ "outcome": "Rejected" }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let rejected = format!("/v1/admin/disputes/{}/resolve", rejected["id"].as_str().unwrap());
+        let (_, body) = app.request(Method::POST, &rejected, Some(&admin_token), Some(json!({ "outcome": "Rejected" }))).await;
+        assert_eq!((body["status"].as_str(), &body["refund_transaction_id"]), (Some("Resolved"), &serde_json::Value::Null));
+        assert_eq!(balance().await, BigDecimal::from(80));
+
+        let (_, disputes) = app.request(Method::GET, &format!("/v1/users/{}/disputes", user_id), Some(&token), None).await;
+        let statuses: Vec<&str> = disputes.as_array().unwrap().iter().map(|dispute| dispute["status"].as_str().unwrap()).collect();
+        assert_eq!(statuses, ["Resolved", "Refunded"]);
+    }
+
+    #[sqlx::test]
+    async fn test_attachments_are_validated_and_swept_when_transactions_are_voided(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-receipts@example.com").await;
+        let (other_token, _) = app.sign_up("e2e-receipts-other@example.com").await;
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+
+        let (_, settled) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        let (_, pending) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "10", "transaction_type": "Debit", "pending": true })))
+            .await;
+        let settled_attachments = format!("{}/{}/attachments", transactions, settled["id"].as_str().unwrap());
+        let pending_attachments = format!("{}/{}/attachments", transactions, pending["id"].as_str().unwrap());
+        let png = b"\x89PNG\r\n\x1a\nreceipt";
+
+        let (status, _) = app.upload(&settled_attachments, &token, "notes.txt", "text/plain", b"lunch").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _) = app.upload(&settled_attachments, &token, "receipt.png", "image/png", b"%PDF-1.7").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let oversized = vec![0u8; models::attachment::MAX_ATTACHMENT_BYTES + 1];
+        let (status, body) = app.upload(&settled_attachments, &token, "huge.pdf", "application/pdf", &oversized).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["code"], "payload_too_large");
+        let (status, _) = app.upload(&settled_attachments, &other_token, "receipt.png", "image/png", png).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, kept) = app.upload(&settled_attachments, &token, "receipts/lunch.png", "image/png", png).await;
+        assert_eq!(status, StatusCode::OK, "{}", kept);
+        assert_eq!(kept["filename"], "lunch.png");
+        assert_eq!(kept["size_bytes"], png.len());
+        assert!(kept.get("storage_key").is_none());
+        let kept_key = format!("attachments/{}/{}/{}", user_id, settled["id"].as_str().unwrap(), kept["id"].as_str().unwrap());
+        assert_eq!(app.blobs.get(&kept_key), Some(("image/png".to_string(), png.to_vec())));
+
+        let (status, listed) = app.request(Method::GET, &settled_attachments, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        let (status, download) = app
+            .request(Method::GET, &format!("{}/{}", settled_attachments, kept["id"].as_str().unwrap()), Some(&token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(download["download_url"].as_str().unwrap().starts_with(&format!("memory:///{}?", kept_key)));
+
+        // Cancelling the pending debit voids it, so its attachment is swept and it takes no more
+        let (status, voided) = app.upload(&pending_attachments, &token, "invoice.pdf", "application/pdf", b"%PDF-1.7\n").await;
+        assert_eq!(status, StatusCode::OK);
+        let voided_key = format!("attachments/{}/{}/{}", user_id, pending["id"].as_str().unwrap(), voided["id"].as_str().unwrap());
+        let (status, _) = app
+            .request(Method::POST, &format!("{}/{}/cancel", transactions, pending["id"].as_str().unwrap()), Some(&token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app.upload(&pending_attachments, &token, "invoice.pdf", "application/pdf", b"%PDF-1.7\n").await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let purged = handlers::attachment::purge_void_attachments(&app.pool, app.blobs.as_ref()).await.unwrap();
+        assert_eq!(purged, 1);
+        assert_eq!(app.blobs.get(&voided_key), None);
+        assert!(app.blobs.get(&kept_key).is_some());
+        let (_, listed) = app.request(Method::GET, &pending_attachments, Some(&token), None).await;
+        assert_eq!(listed, json!([]));
+    }
+
+    #[sqlx::test]
+    async fn test_payees_are_deduplicated_and_ordered_by_recent_use(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-payees@example.com").await;
+        let (_, friend_id) = app.sign_up("e2e-payees-friend@example.com").await;
+        let (other_token, other_id) = app.sign_up("e2e-payees-other@example.com").await;
+        let payees = format!("/v1/users/{}/payees", user_id);
+
+        let (status, landlord) = app
+            .request(Method::POST, &payees, Some(&token), Some(json!({ "name": "Landlord", "account_identifier": "gb29 nwbk-6016 1331 9268 19" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(landlord["account_identifier"], "GB29NWBK60161331926819");
+        let (status, friend) = app
+            .request(Method::POST, &payees, Some(&token), Some(json!({ "name": "Friend", "recipient_user_id": friend_id })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // The same account written differently is a duplicate
+        let (status, body) = app
+            .request(Method::POST, &payees, Some(&token), Some(json!({ "name": "Rent", "account_identifier": "GB29NWBK60161331926819" })))
+            .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(body["message"].as_str().unwrap().contains(landlord["id"].as_str().unwrap()));
+        let (status, _) = app
+            .request(Method::POST, &payees, Some(&token), Some(json!({ "name": "Both", "recipient_user_id": friend_id, "account_identifier": "12345678" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        app.request(Method::POST, &format!("/v1/users/{}/transactions", user_id), Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        let (status, transfer) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "payee_id": friend["id"], "amount": "10" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(transfer["transfer"]["to_user_id"], friend_id.to_string());
+        assert_eq!(transfer["transfer"]["payee_id"], friend["id"]);
+        // Outside accounts can't be paid by transfer
+        let (status, _) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "payee_id": landlord["id"], "amount": "10" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, rent) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/transactions", user_id),
+                Some(&token),
+                Some(json!({ "amount": "50", "transaction_type": "Debit", "payee_id": landlord["id"] })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(rent["payee_id"], landlord["id"]);
+        // Another user's payee is not found
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/transactions", other_id),
+                Some(&other_token),
+                Some(json!({ "amount": "5", "transaction_type": "Credit", "payee_id": landlord["id"] })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, listed) = app.request(Method::GET, &payees, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let names: Vec<&str> = listed.as_array().unwrap().iter().map(|payee| payee["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["Landlord", "Friend"]);
+
+        let landlord_uri = format!("{}/{}", payees, landlord["id"].as_str().unwrap());
+        let (status, renamed) = app.request(Method::PATCH, &landlord_uri, Some(&token), Some(json!({ "name": "Old landlord" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(renamed["name"], "Old landlord");
+        let (status, _) = app.request(Method::DELETE, &landlord_uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app.request(Method::GET, &landlord_uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let rent_id: uuid::Uuid = rent["id"].as_str().unwrap().parse().unwrap();
+        let payee_id = sqlx::query_scalar!("SELECT payee_id FROM transactions WHERE id = $1", rent_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(payee_id, None);
+    }
+
+    #[sqlx::test]
+    async fn test_payment_requests_are_paid_by_transfer_when_accepted(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (requester_token, requester_id) = app.sign_up("e2e-request-requester@example.com").await;
+        let (payer_token, payer_id) = app.sign_up("e2e-request-payer@example.com").await;
+        app.request(Method::POST, &format!("/v1/users/{}/transactions", payer_id), Some(&payer_token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+
+        let create = |amount: &str| json!({ "payer_id": payer_id, "amount": amount, "description": "Dinner" });
+        let (status, request) = app.request(Method::POST, "/v1/payment-requests", Some(&requester_token), Some(create("30"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(request["status"], "Pending");
+        let accept = format!("/v1/payment-requests/{}/accept", request["id"].as_str().unwrap());
+
+        let (status, received) = app.request(Method::GET, "/v1/payment-requests", Some(&payer_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(received[0]["id"], request["id"]);
+        let notified = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM outbox_events WHERE user_id = $1 AND event_type = 'payment_request.created'",
+            payer_id
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        assert_eq!(notified, Some(1));
+
+        // Only the payer can pay it
+        let (status, _) = app.request(Method::POST, &accept, Some(&requester_token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, accepted) = app.request(Method::POST, &accept, Some(&payer_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(accepted["payment_request"]["status"], "Accepted");
+        assert_eq!(accepted["payment_request"]["transfer_id"], accepted["transfer"]["transfer"]["id"]);
+        assert_eq!(accepted["transfer"]["credit"]["user_id"], requester_id.to_string());
+        let (status, _) = app.request(Method::POST, &accept, Some(&payer_token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // The payer can't be asked for more than they can pay
+        let (_, large) = app.request(Method::POST, "/v1/payment-requests", Some(&requester_token), Some(create("500"))).await;
+        let (status, _) = app
+            .request(Method::POST, &format!("/v1/payment-requests/{}/accept", large["id"].as_str().unwrap()), Some(&payer_token), None)
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, declined) = app
+            .request(Method::POST, &format!("/v1/payment-requests/{}/decline", large["id"].as_str().unwrap()), Some(&payer_token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(declined["status"], "Declined");
+
+        let (_, cancelled) = app.request(Method::POST, "/v1/payment-requests", Some(&requester_token), Some(create("5"))).await;
+        let (status, cancelled) = app
+            .request(Method::POST, &format!("/v1/payment-requests/{}/cancel", cancelled["id"].as_str().unwrap()), Some(&requester_token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(cancelled["status"], "Cancelled");
+
+        let (_, stale) = app.request(Method::POST, "/v1/payment-requests", Some(&requester_token), Some(create("5"))).await;
+        let stale_id: uuid::Uuid = stale["id"].as_str().unwrap().parse().unwrap();
+        sqlx::query!("UPDATE payment_requests SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1", stale_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(handlers::payment_request::expire_due_requests(&app.pool).await.unwrap(), 1);
+        let (status, _) = app.request(Method::POST, &format!("/v1/payment-requests/{}/accept", stale_id), Some(&payer_token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (_, stale) = app.request(Method::GET, &format!("/v1/payment-requests/{}", stale_id), Some(&requester_token), None).await;
+        assert_eq!(stale["status"], "Expired");
+
+        let (status, _) = app
+            .request(Method::POST, "/v1/payment-requests", Some(&requester_token), Some(json!({ "payer_id": requester_id, "amount": "5" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test]
+    async fn test_deposits_are_credited_once_when_stripe_reports_success(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-deposits@example.com").await;
+        let deposits = format!("/v1/users/{}/deposits", user_id);
+
+        let (status, deposit) = app.request(Method::POST, &deposits, Some(&token), Some(json!({ "amount": "25.50" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(deposit["status"], "Pending");
+        let intent_id = deposit["payment_intent_id"].as_str().unwrap().to_string();
+        assert_eq!(deposit["client_secret"], format!("{}_secret", intent_id));
+
+        let event = |id: &str, event_type: &str, amount_received: i64| {
+            json!({
+                "id": id,
+                "type": event_type,
+                "data": { "object": {
+                    "id": intent_id,
+                    "amount_received": amount_received,
+                    "currency": "usd",
+                    "metadata": { "deposit_id": deposit["id"], "user_id": user_id },
+                    "last_payment_error": if event_type == "payment_intent.payment_failed" { json!({ "message": "Your card was declined." }) } else { Value::Null },
+                } },
+            })
+        };
+
+        // A delivery that isn't signed with the endpoint's secret is refused
+        let (status, _) = app
+            .request(Method::POST, "/v1/webhooks/stripe", None, Some(event("evt_forged", "payment_intent.succeeded", 2550)))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = app.stripe_webhook(&event("evt_1", "payment_intent.payment_failed", 0)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, listed) = app.request(Method::GET, &deposits, Some(&token), None).await;
+        assert_eq!(listed[0]["status"], "Failed");
+        assert_eq!(listed[0]["failure_reason"], "Your card was declined.");
+
+        // Redelivering the success event doesn't credit the deposit twice
+        for _ in 0..2 {
+            let (status, body) = app.stripe_webhook(&event("evt_2", "payment_intent.succeeded", 2550)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["received"], true);
+        }
+        let (_, listed) = app.request(Method::GET, &deposits, Some(&token), None).await;
+        assert_eq!(listed[0]["status"], "Succeeded");
+        assert!(listed[0]["failure_reason"].is_null());
+        let (_, balance) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), Some(&token), None).await;
+        assert_eq!(BigDecimal::from_str(balance["balances"]["USD"].as_str().unwrap()).unwrap(), BigDecimal::from_str("25.50").unwrap());
+
+        // A success for a different amount than the deposit's isn't credited
+        let (_, short) = app.request(Method::POST, &deposits, Some(&token), Some(json!({ "amount": "10" }))).await;
+        let mut underpaid = event("evt_3", "payment_intent.succeeded", 500);
+        underpaid["data"]["object"]["id"] = short["payment_intent_id"].clone();
+        underpaid["data"]["object"]["metadata"]["deposit_id"] = short["id"].clone();
+        let (status, _) = app.stripe_webhook(&underpaid).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, listed) = app.request(Method::GET, &deposits, Some(&token), None).await;
+        assert_eq!(listed[0]["status"], "Pending");
+        assert!(listed[0]["transaction_id"].is_null());
+    }
+
+    #[sqlx::test]
+    async fn test_linked_accounts_pull_balances_and_transactions(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-linked@example.com").await;
+        let linked = format!("/v1/users/{}/linked-accounts", user_id);
+
+        let (status, body) = app.request(Method::POST, &format!("{}/link-token", linked), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["link_token"].as_str().unwrap().starts_with("link-sandbox-"));
+
+        let (status, _) = app.request(Method::POST, &linked, Some(&token), Some(json!({ "public_token": "made-up" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, accounts) = app.request(Method::POST, &linked, Some(&token), Some(json!({ "public_token": "public-bank" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(accounts.as_array().unwrap().len(), 1);
+        assert_eq!(accounts[0]["mask"], "0000");
+        assert_eq!(accounts[0]["available_balance"], "100");
+        assert!(!accounts.to_string().contains("access-bank"));
+        let account = format!("{}/{}", linked, accounts[0]["id"].as_str().unwrap());
+
+        // The access token is only stored encrypted
+        let stored = sqlx::query_scalar!("SELECT access_token FROM linked_items WHERE user_id = $1", user_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert!(!stored.contains("access-bank"));
+        assert_eq!(open_access_token(&app.config, &stored).unwrap(), "access-bank");
+
+        // The first refresh pulls everything; the next one swaps the pending transaction for its posted version
+        let (status, body) = app.request(Method::POST, &format!("{}/refresh", account), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transactions_added"], 2);
+        let (_, body) = app.request(Method::POST, &format!("{}/refresh", account), Some(&token), None).await;
+        assert_eq!((body["transactions_added"].as_u64(), body["transactions_removed"].as_u64()), (Some(1), Some(1)));
+        let (status, transactions) = app.request(Method::GET, &format!("{}/transactions", account), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let mut descriptions: Vec<_> = transactions.as_array().unwrap().iter().map(|t| t["description"].as_str().unwrap()).collect();
+        descriptions.sort();
+        assert_eq!(descriptions, ["Card payment txn_coffee", "Card payment txn_posted"]);
+
+        let (other_token, _) = app.sign_up("e2e-linked-other@example.com").await;
+        let (status, _) = app.request(Method::GET, &format!("{}/transactions", account), Some(&other_token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Unlinking the bank login's last account revokes access to it
+        let (status, _) = app.request(Method::DELETE, &account, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(*app.bank_data.removed.lock().unwrap(), ["access-bank"]);
+        let (_, accounts) = app.request(Method::GET, &linked, Some(&token), None).await;
+        assert_eq!(accounts, json!([]));
+    }
+
+    #[sqlx::test]
+    async fn test_transfers_convert_currency_at_the_latest_rate_less_the_spread(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, sender) = app.sign_up("e2e-fx-sender@example.com").await;
+        let (_, recipient) = app.sign_up("e2e-fx-recipient@example.com").await;
+        app.request(
+            Method::POST,
+            &format!("/v1/users/{}/transactions", sender),
+            Some(&token),
+            Some(json!({ "amount": "100.00", "transaction_type": "Credit" })),
+        )
+        .await;
+        // Only the euro's rate against the dollar is stored, as the ECB publishes it
+        sqlx::query("INSERT INTO fx_rates (base_currency, quote_currency, rate, source) VALUES ('EUR', 'USD', 1.25, 'test')")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let decimal = |value: &Value| BigDecimal::from_str(value.as_str().unwrap()).unwrap();
+
+        let (status, body) = app.request(Method::GET, "/v1/fx/rates?base=usd", Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["spread_bps"], TEST_FX_SPREAD_BPS);
+        assert_eq!(body["rates"][0]["currency"], "EUR");
+        assert_eq!(decimal(&body["rates"][0]["mid_rate"]), BigDecimal::from_str("0.8").unwrap());
+        assert_eq!(decimal(&body["rates"][0]["rate"]), BigDecimal::from_str("0.796").unwrap());
+
+        let transfer = |to_currency: &str| json!({ "to_user_id": recipient, "amount": "50", "currency": "USD", "to_currency": to_currency });
+        let (status, _) = app.request(Method::POST, "/v1/transfers", Some(&token), Some(transfer("GBP"))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, body) = app.request(Method::POST, "/v1/transfers", Some(&token), Some(transfer("eur"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(decimal(&body["debit"]["amount"]), BigDecimal::from_str("50").unwrap());
+        assert_eq!(body["debit"]["currency"], "USD");
+        assert_eq!(decimal(&body["credit"]["amount"]), BigDecimal::from_str("39.8").unwrap());
+        assert_eq!(body["credit"]["currency"], "EUR");
+        assert_eq!(decimal(&body["transfer"]["converted_amount"]), BigDecimal::from_str("39.8").unwrap());
+        assert_eq!(body["transfer"]["converted_currency"], "EUR");
+
+        // Converting into the transfer's own currency is a plain transfer
+        let (status, body) = app.request(Method::POST, "/v1/transfers", Some(&token), Some(transfer("USD"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(decimal(&body["credit"]["amount"]), BigDecimal::from_str("50").unwrap());
+        assert!(body["transfer"]["fx_rate"].is_null());
+    }
+
+    #[sqlx::test]
+    async fn test_quoted_rates_are_locked_for_one_transfer(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-quote@example.com").await;
+        let (other_token, _) = app.sign_up("e2e-quote-other@example.com").await;
+        app.request(
+            Method::POST,
+            &format!("/v1/users/{}/transactions", user_id),
+            Some(&token),
+            Some(json!({ "amount": "100.00", "transaction_type": "Credit" })),
+        )
+        .await;
+        sqlx::query("INSERT INTO fx_rates (base_currency, quote_currency, rate, source) VALUES ('EUR', 'USD', 1.25, 'test')")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let decimal = |value: &Value| BigDecimal::from_str(value.as_str().unwrap()).unwrap();
+        let quote = json!({ "amount": "50", "from_currency": "USD", "to_currency": "EUR" });
+
+        let (status, _) = app
+            .request(Method::POST, "/v1/fx/quotes", Some(&token), Some(json!({ "amount": "50", "to_currency": "USD" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = app.request(Method::POST, "/v1/fx/quotes", Some(&token), Some(quote.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(decimal(&body["rate"]), BigDecimal::from_str("0.796").unwrap());
+        assert_eq!(decimal(&body["converted_amount"]), BigDecimal::from_str("39.8").unwrap());
+        let quote_id = body["id"].clone();
+
+        // Later rates don't change a quote already given
+        sqlx::query("INSERT INTO fx_rates (base_currency, quote_currency, rate, source) VALUES ('EUR', 'USD', 2, 'test')")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let (status, _) = app
+            .request(Method::POST, "/v1/fx/transfers", Some(&other_token), Some(json!({ "quote_id": quote_id })))
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Without a recipient the user exchanges into their own euro wallet, which isn't a transfer
+        // fees are charged on
+        sqlx::query!(
+            "INSERT INTO fee_rules (name, event, currency, kind, flat_amount, created_by, updated_by)
+             VALUES ('Transfer fee', 'transfer', 'USD', 'flat', 1.50, $1, $1)",
+            user_id
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+        let (status, body) = app.request(Method::POST, "/v1/fx/transfers", Some(&token), Some(json!({ "quote_id": quote_id }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["fees"], json!([]));
+        assert_eq!(body["quote"]["transfer_id"], body["transfer"]["id"]);
+        assert_eq!(decimal(&body["debit"]["amount"]), BigDecimal::from_str("50").unwrap());
+        assert_eq!(body["debit"]["currency"], "USD");
+        assert_eq!(decimal(&body["credit"]["amount"]), BigDecimal::from_str("39.8").unwrap());
+        assert_eq!(body["credit"]["currency"], "EUR");
+        assert_eq!(body["credit"]["user_id"], json!(user_id));
+        assert_eq!(decimal(&body["debit"]["fx_rate"]), BigDecimal::from_str("0.796").unwrap());
+        assert_eq!(decimal(&body["credit"]["fx_rate"]), BigDecimal::from_str("0.796").unwrap());
+
+        let (status, _) = app.request(Method::POST, "/v1/fx/transfers", Some(&token), Some(json!({ "quote_id": quote_id }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (_, body) = app.request(Method::POST, "/v1/fx/quotes", Some(&token), Some(quote)).await;
+        sqlx::query("UPDATE fx_quotes SET expires_at = NOW() - INTERVAL '1 second' WHERE id = $1::text::uuid")
+            .bind(body["id"].as_str().unwrap())
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let (status, _) = app.request(Method::POST, "/v1/fx/transfers", Some(&token), Some(json!({ "quote_id": body["id"] }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[sqlx::test]
+    async fn test_interest_accrues_once_per_period(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-interest-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-interest@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let (status, _) = app
+            .request(Method::PUT, "/v1/admin/interest-rates/savings", Some(&token), Some(json!({ "apy": "5", "compounding": "daily" })))
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = app
+            .request(Method::PUT, "/v1/admin/interest-rates/savings", Some(&admin_token), Some(json!({ "apy": "101", "compounding": "daily" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = app
+            .request(Method::PUT, "/v1/admin/interest-rates/savings", Some(&admin_token), Some(json!({ "apy": "5", "compounding": "daily" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["compounding"], "daily");
+
+        // Only the savings account earns interest
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "1000", "transaction_type": "Credit" })))
+            .await;
+        let accounts = format!("/v1/users/{}/accounts", user_id);
+        let (_, savings) = app
+            .request(Method::POST, &accounts, Some(&token), Some(json!({ "name": "Savings", "account_type": "savings" })))
+            .await;
+        let savings_id = savings["id"].as_str().unwrap();
+        app.request(
+            Method::POST,
+            &transactions,
+            Some(&token),
+            Some(json!({ "amount": "1000", "transaction_type": "Credit", "account_id": savings_id })),
+        )
+        .await;
+
+        // An account with a currency that can't be paid in is passed over, even though it sorts first
+        sqlx::query!(
+            "INSERT INTO accounts (id, user_id, name, account_type, currency) VALUES ('00000000-0000-0000-0000-000000000001', $1, 'Broken', 'savings', '???')",
+            user_id
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+        // Three days on, today and the two days after have ended; running again pays nothing more
+        let today = time::OffsetDateTime::now_utc().date();
+        let later = today + time::Duration::days(3);
+        assert_eq!(crate::handlers::interest::accrue_due_interest(&app.pool, later).await.unwrap(), 3);
+        assert_eq!(crate::handlers::interest::accrue_due_interest(&app.pool, later).await.unwrap(), 0);
+
+        let (status, accruals) = app.request(Method::GET, &format!("{}/{}/interest", accounts, savings_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(accruals.as_array().unwrap().len(), 3);
+        let decimal = |value: &Value| BigDecimal::from_str(value.as_str().unwrap()).unwrap();
+        // 5% a year is about 0.0134% a day
+        for accrual in accruals.as_array().unwrap() {
+            assert_eq!(decimal(&accrual["amount"]), BigDecimal::from_str("0.13").unwrap());
+            assert!(accrual["transaction_id"].is_string());
+        }
+
+        let (_, balance) = app.request(Method::GET, &format!("{}/{}/balance", accounts, savings_id), Some(&token), None).await;
+        assert_eq!(decimal(&balance["balance"]), BigDecimal::from_str("1000.39").unwrap());
+        let (_, entries) = app.request(Method::GET, &format!("{}/{}/transactions", accounts, savings_id), Some(&token), None).await;
+        assert_eq!(entries[0]["category"], "interest");
+
+        // Interest is paid by the accrual job, so the user can't reverse it
+        let reverse = format!("{}/{}/reverse", transactions, entries[0]["id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &reverse, Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["message"], "Interest credits cannot be reversed");
+    }
+
+    #[sqlx::test]
+    async fn test_users_cannot_reverse_manual_adjustments(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-adjusted-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-adjusted@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        let chargeback = json!({ "amount": "40", "transaction_type": "Debit", "reason_code": "Chargeback", "note": "Card dispute lost" });
+        let (status, adjustment) = app
+            .request(Method::POST, &format!("/v1/admin/users/{}/adjustments", user_id), Some(&admin_token), Some(chargeback))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Reversing the chargeback's debit would hand the user the money back
+        let reverse = format!("{}/{}/reverse", transactions, adjustment["transaction_id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &reverse, Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["message"], "Manual adjustments cannot be reversed");
+        let (_, balance) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), Some(&token), None).await;
+        assert_eq!(BigDecimal::from_str(balance["balances"]["USD"].as_str().unwrap()).unwrap(), BigDecimal::from(60));
+    }
+
+    #[sqlx::test]
+    async fn test_adjustments_over_the_threshold_need_a_second_admin(pool: PgPool) {
+        let config = crate::config::Config { adjustment_approval_threshold: BigDecimal::from(1000), ..test_config() };
+        let app = TestApp::with_config(pool, config);
+        let (maker_token, maker_id) = app.sign_up("e2e-adjustment-maker@example.com").await;
+        let (checker_token, checker_id) = app.sign_up("e2e-adjustment-checker@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-adjustment-user@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = ANY($1)", &[maker_id, checker_id])
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let adjustments = format!("/v1/admin/users/{}/adjustments", user_id);
+        let credit = |amount: &str| json!({ "amount": amount, "transaction_type": "Credit", "reason_code": "GoodwillCredit", "note": "Service outage" });
+        let balance = || async {
+            let (_, balance) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), Some(&token), None).await;
+            balance["balances"]["USD"].as_str().map(|balance| BigDecimal::from_str(balance).unwrap())
+        };
+
+        // At or under the threshold an adjustment posts straight away
+        let (status, body) = app.request(Method::POST, &adjustments, Some(&maker_token), Some(credit("1000"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Posted");
+        assert!(body["transaction_id"].is_string());
+        assert_eq!(balance().await, Some(BigDecimal::from(1000)));
+
+        // Over it, the adjustment waits for a second admin without touching the balance
+        let (status, pending) = app.request(Method::POST, &adjustments, Some(&maker_token), Some(credit("1500"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(pending["status"], "PendingApproval");
+        assert!(pending["transaction_id"].is_null());
+        assert_eq!(balance().await, Some(BigDecimal::from(1000)));
+        let (status, queue) = app.request(Method::GET, "/v1/admin/adjustments/pending", Some(&checker_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(queue.as_array().unwrap().iter().any(|adjustment| adjustment["id"] == pending["id"]));
+
+        // The admin who asked for it can't approve it
+        let approve = format!("/v1/admin/adjustments/{}/approve", pending["id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &approve, Some(&maker_token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["message"], "Adjustments must be approved by a different admin");
+        let (status, body) = app.request(Method::POST, &approve, Some(&checker_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["status"].as_str(), body["decided_by"].as_str()), (Some("Posted"), Some(checker_id.to_string().as_str())));
+        assert_eq!(balance().await, Some(BigDecimal::from(2500)));
+
+        // A rejected adjustment is never posted, and can't be approved afterwards
+        let (_, pending) = app.request(Method::POST, &adjustments, Some(&maker_token), Some(credit("2000"))).await;
+        let id = pending["id"].as_str().unwrap();
+        let (status, body) = app.request(Method::POST, &format!("/v1/admin/adjustments/{}/reject", id), Some(&checker_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Rejected");
+        assert!(body["transaction_id"].is_null());
+        let (status, _) = app.request(Method::POST, &format!("/v1/admin/adjustments/{}/approve", id), Some(&checker_token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(balance().await, Some(BigDecimal::from(2500)));
+        let (_, queue) = app.request(Method::GET, "/v1/admin/adjustments/pending", Some(&checker_token), None).await;
+        assert!(queue.as_array().unwrap().iter().all(|adjustment| adjustment["id"] != pending["id"]));
+    }
+
+    #[sqlx::test]
+    async fn test_nobody_can_sign_in_as_the_house(pool: PgPool) {
+        let app = TestApp::new(pool);
+
+        // Its password hash isn't one bcrypt can check, so sign-in has to refuse it first
+        let credentials = json!({ "email": "house@dodo.internal", "password": TEST_PASSWORD });
+        let (status, body) = app.request(Method::POST, "/v1/auth", None, Some(credentials)).await;
+        assert_eq!((status, body["message"].as_str()), (StatusCode::UNAUTHORIZED, Some("Invalid credentials")));
+    }
+
+    #[sqlx::test]
+    async fn test_fee_rules_charge_transfers_and_withdrawals_to_the_house(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-fees-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-fees@example.com").await;
+        let (_, friend_id) = app.sign_up("e2e-fees-friend@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let transfer_fee = json!({ "name": "Transfer fee", "event": "transfer", "kind": "flat", "flat_amount": "1.50" });
+        let (status, _) = app.request(Method::POST, "/v1/admin/fee-rules", Some(&token), Some(transfer_fee.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = app
+            .request(Method::POST, "/v1/admin/fee-rules", Some(&admin_token), Some(json!({ "name": "Broken", "event": "transfer", "kind": "percentage" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = app.request(Method::POST, "/v1/admin/fee-rules", Some(&admin_token), Some(transfer_fee)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["currency"], "USD");
+        let transfer_rule = format!("/v1/admin/fee-rules/{}", body["id"].as_str().unwrap());
+
+        // 0.25 up to 100, then 1%
+        let withdrawal_fee = json!({
+            "name": "Withdrawal fee",
+            "event": "withdrawal",
+            "kind": "tiered",
+            "tiers": [{ "up_to": "100", "flat_amount": "0.25" }, { "up_to": null, "percentage_bps": 100 }]
+        });
+        let (status, _) = app.request(Method::POST, "/v1/admin/fee-rules", Some(&admin_token), Some(withdrawal_fee)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let decimal = |value: &Value| BigDecimal::from_str(value.as_str().unwrap()).unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": friend_id, "amount": "10" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["fees"].as_array().unwrap().len(), 1);
+        assert_eq!(decimal(&body["fees"][0]["amount"]), BigDecimal::from_str("1.50").unwrap());
+        assert_eq!(body["fees"][0]["category"], "fees");
+
+        // A fee can't be reversed on its own, which would leave the house its credit
+        let reverse = format!("{}/{}/reverse", transactions, body["fees"][0]["id"].as_str().unwrap());
+        let (status, _) = app.request(Method::POST, &reverse, Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "50", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, balance) = app.request(Method::GET, &format!("/v1/users/{}/balance", user_id), Some(&token), None).await;
+        assert_eq!(decimal(&balance["balances"]["USD"]), BigDecimal::from_str("38.25").unwrap());
+
+        // The whole balance would leave nothing for the fee
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "38.25", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "insufficient_funds");
+
+        let house = sqlx::query!(
+            r#"SELECT COALESCE(SUM(amount), 0) as "total!" FROM transactions WHERE user_id = $1"#,
+            crate::services::fees::HOUSE_USER_ID
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        assert_eq!(house.total, BigDecimal::from_str("1.75").unwrap());
+
+        // A switched-off rule stops charging but stays listed on request
+        let (status, body) = app.request(Method::DELETE, &transfer_rule, Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["active"], false);
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": friend_id, "amount": "10" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["fees"].as_array().unwrap().is_empty());
+        let (_, rules) = app.request(Method::GET, "/v1/admin/fee-rules", Some(&admin_token), None).await;
+        assert_eq!(rules.as_array().unwrap().len(), 1);
+        let (_, rules) = app.request(Method::GET, "/v1/admin/fee-rules?include_inactive=true", Some(&admin_token), None).await;
+        assert_eq!(rules.as_array().unwrap().len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn test_fees_are_charged_when_pending_and_held_debits_settle(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-settle-fees-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-settle-fees@example.com").await;
+        let (_, friend_id) = app.sign_up("e2e-settle-fees-friend@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        for (name, event) in [("Transfer fee", "transfer"), ("Withdrawal fee", "withdrawal")] {
+            let rule = json!({ "name": name, "event": event, "kind": "flat", "flat_amount": "1" });
+            let (status, _) = app.request(Method::POST, "/v1/admin/fee-rules", Some(&admin_token), Some(rule)).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let decimal = |value: &Value| BigDecimal::from_str(value.as_str().unwrap()).unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        let balance = format!("/v1/users/{}/balance", user_id);
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+
+        // A pending debit is charged once it settles
+        let (status, pending) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "20", "transaction_type": "Debit", "pending": true })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = app.request(Method::GET, &balance, Some(&token), None).await;
+        assert_eq!(decimal(&body["balances"]["USD"]), BigDecimal::from(100));
+        let (status, _) = app
+            .request(Method::POST, &format!("{}/{}/settle", transactions, pending["id"].as_str().unwrap()), Some(&token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = app.request(Method::GET, &balance, Some(&token), None).await;
+        assert_eq!(decimal(&body["balances"]["USD"]), BigDecimal::from_str("79").unwrap());
+
+        // A held transfer is charged when it's released
+        let (status, _) = app
+            .request(Method::POST, "/v1/admin/holds", Some(&admin_token), Some(json!({ "counterparty_id": friend_id, "reason": "Review" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": friend_id, "amount": "10" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["fees"].as_array().unwrap().is_empty());
+        let release = format!("/v1/admin/held-debits/{}/release", body["debit"]["id"].as_str().unwrap());
+        let (status, _) = app.request(Method::POST, &release, Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = app.request(Method::GET, &balance, Some(&token), None).await;
+        assert_eq!(decimal(&body["balances"]["USD"]), BigDecimal::from_str("68").unwrap());
+
+        let house = sqlx::query!(
+            r#"SELECT COALESCE(SUM(amount), 0) as "total!" FROM transactions WHERE user_id = $1"#,
+            crate::services::fees::HOUSE_USER_ID
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        assert_eq!(house.total, BigDecimal::from(2));
+    }
+
+    #[sqlx::test]
+    async fn test_kyc_review_lifts_the_limits_on_unverified_users(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-kyc-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-kyc@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let (status, _) = app
+            .request(Method::PUT, "/v1/admin/kyc-limits/unverified", Some(&token), Some(json!({ "max_transaction_amount": "500" })))
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = app
+            .request(Method::PUT, "/v1/admin/kyc-limits/unverified", Some(&admin_token), Some(json!({ "max_transaction_amount": "500" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app
+            .request(Method::PUT, "/v1/admin/kyc-limits/rejected", Some(&admin_token), Some(json!({ "max_transaction_amount": "500" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let kyc = format!("/v1/users/{}/kyc", user_id);
+        let (status, body) = app.request(Method::GET, &kyc, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "unverified");
+        assert_eq!(body["limits"]["max_transaction_amount"], "500");
+
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "1000", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "kyc_limit_exceeded");
+
+        // Proof of address alone isn't enough to submit
+        let documents = format!("{}/documents", kyc);
+        let png = b"\x89PNG\r\n\x1a\nbill";
+        let (status, _) = app.upload(&format!("{}?document_type=proof_of_address", documents), &token, "bill.png", "image/png", png).await;
+        assert_eq!(status, StatusCode::OK);
+        let submit = format!("{}/submit", kyc);
+        let (status, _) = app.request(Method::POST, &submit, Some(&token), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let pdf = b"%PDF-1.7\npassport";
+        let (status, passport) = app.upload(&format!("{}?document_type=passport", documents), &token, "passport.pdf", "application/pdf", pdf).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(passport["document_type"], "passport");
+        let (status, body) = app.request(Method::POST, &submit, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "pending");
+        let (status, _) = app.upload(&format!("{}?document_type=passport", documents), &token, "again.pdf", "application/pdf", pdf).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (_, pending) = app.request(Method::GET, "/v1/admin/kyc", Some(&admin_token), None).await;
+        assert_eq!(pending.as_array().unwrap().len(), 1);
+        let review = format!("/v1/admin/kyc/{}/review", user_id);
+        let (status, _) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "rejected" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = app
+            .request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "rejected", "reason": "Blurry photo" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["rejection_reason"], "Blurry photo");
+
+        // A resubmission needs a new identity document
+        let (status, _) = app.request(Method::POST, &submit, Some(&token), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        app.upload(&format!("{}?document_type=national_id", documents), &token, "id.png", "image/png", png).await;
+        let (status, _) = app.request(Method::POST, &submit, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = app.request(Method::GET, &format!("/v1/admin/kyc/{}", user_id), Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["documents"].as_array().unwrap().len(), 3);
+        assert!(body["documents"][0]["download_url"].is_string());
+        let (status, body) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "verified" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "verified");
+        let (status, _) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "verified" }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // Verified users have no limits set
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "1000", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn test_scheduled_debits_wait_while_withdrawals_are_switched_off(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-scheduled-switch-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-scheduled-switch@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+        let execute_at = (time::OffsetDateTime::now_utc() + time::Duration::hours(1))
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap();
+        let (status, scheduled) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "30", "transaction_type": "Debit", "execute_at": execute_at })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let scheduled_id: uuid::Uuid = scheduled["id"].as_str().unwrap().parse().unwrap();
+        sqlx::query!("UPDATE transactions SET execute_at = NOW() WHERE id = $1", scheduled_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let switch = |enabled: bool| json!({ "enabled": enabled, "reason": "Incident" });
+        let (status, _) = app.request(Method::PUT, "/v1/admin/feature-flags/withdrawals", Some(&admin_token), Some(switch(false))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(crate::services::ledger::execute_due_transactions(&app.pool, &app.config).await.unwrap(), 0);
+
+        app.request(Method::PUT, "/v1/admin/feature-flags/withdrawals", Some(&admin_token), Some(switch(true))).await;
+        assert_eq!(crate::services::ledger::execute_due_transactions(&app.pool, &app.config).await.unwrap(), 1);
+        let status = sqlx::query_scalar!(r#"SELECT status as "status: models::transaction::TransactionStatus" FROM transactions WHERE id = $1"#, scheduled_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(status, models::transaction::TransactionStatus::Settled);
+    }
+
+    #[sqlx::test]
+    async fn test_kyc_limits_cover_money_sent_in_transfers(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-kyc-transfer-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-kyc-transfer@example.com").await;
+        let (friend_token, friend_id) = app.sign_up("e2e-kyc-transfer-friend@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "1000", "transaction_type": "Credit" })))
+            .await;
+        let limits = json!({ "max_transaction_amount": "500", "max_daily_debit_total": "300" });
+        let (status, _) = app.request(Method::PUT, "/v1/admin/kyc-limits/unverified", Some(&admin_token), Some(limits)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": friend_id, "amount": "600" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "kyc_limit_exceeded");
+        let (status, _) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": friend_id, "amount": "200" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // The transfer counts towards the daily total, for debits and further transfers alike
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "150", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "kyc_limit_exceeded");
+        let (status, _) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": friend_id, "amount": "150" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Paying a payment request is a transfer too
+        let (status, request) = app
+            .request(Method::POST, "/v1/payment-requests", Some(&friend_token), Some(json!({ "payer_id": user_id, "amount": "150" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let accept = format!("/v1/payment-requests/{}/accept", request["id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &accept, Some(&token), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "kyc_limit_exceeded");
+
+        // Debits in another wallet count too, converted into the limits' currency
+        sqlx::query("INSERT INTO fx_rates (base_currency, quote_currency, rate, source) VALUES ('EUR', 'USD', 1.25, 'test')")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "300", "currency": "EUR", "transaction_type": "Credit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "currency": "EUR", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["message"], "Debits are limited to 300 USD in 24 hours until your identity is verified");
+        let (status, _) = app
+            .request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "80", "currency": "EUR", "transaction_type": "Debit" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn test_deleted_accounts_are_deactivated_and_refused(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-leaving@example.com").await;
+        let (other_token, _) = app.sign_up("e2e-staying@example.com").await;
+
+        let (status, body) = app.request(Method::DELETE, &format!("/v1/users/{}", user_id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Deactivated");
+        assert!(!body["deleted_at"].is_null());
+
+        // Sessions issued before the deletion stop working
+        let (status, body) = app.request(Method::GET, "/v1/me", Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "account_deactivated");
+
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(&other_token), Some(json!({ "to_user_id": user_id, "amount": "1.00" })))
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["message"], "Recipient account is deactivated");
+    }
+
+    #[sqlx::test]
+    async fn test_erasure_anonymizes_the_user_after_confirmation(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-erase@example.com").await;
+        let (_, friend_id) = app.sign_up("e2e-erase-friend@example.com").await;
+        let erase = format!("/v1/users/{}/erase", user_id);
+        let confirm = format!("{}/confirm", erase);
+
+        let (status, credit) = app
+            .request(
+                Method::POST,
+                &format!("/v1/users/{}/transactions", user_id),
+                Some(&token),
+                Some(json!({ "amount": "5.00", "transaction_type": "Credit" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Everything else the user gave us or we learned about them
+        let attachments = format!("/v1/users/{}/transactions/{}/attachments", user_id, credit["id"].as_str().unwrap());
+        let (status, _) = app.upload(&attachments, &token, "receipt.png", "image/png", b"\x89PNG\r\n\x1a\nreceipt").await;
+        assert_eq!(status, StatusCode::OK);
+        let documents = format!("/v1/users/{}/kyc/documents?document_type=passport", user_id);
+        let (status, _) = app.upload(&documents, &token, "passport.pdf", "application/pdf", b"%PDF-1.7\npassport").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app
+            .request(Method::POST, &format!("/v1/users/{}/payees", user_id), Some(&token), Some(json!({ "name": "Friend", "recipient_user_id": friend_id })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app
+            .request(Method::POST, &format!("/v1/users/{}/linked-accounts", user_id), Some(&token), Some(json!({ "public_token": "public-bank" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        sqlx::query!(
+            "INSERT INTO screening_results (operation, user_id, provider, outcome, reason) VALUES ('registration', $1, 'test', 'hit', 'Name matches a listed person')",
+            user_id
+        )
+        .execute(&app.pool)
+        .await
+        .unwrap();
+        let storage_keys = sqlx::query_scalar!(
+            r#"SELECT storage_key as "storage_key!" FROM attachments WHERE user_id = $1 UNION ALL SELECT storage_key FROM kyc_documents WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+        assert_eq!(storage_keys.len(), 2);
+
+        let (status, body) = app.request(Method::POST, &erase, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["request"]["status"], "Pending");
+        let confirmation_token = body["confirmation_token"].as_str().unwrap().to_string();
+
+        let (status, _) = app.request(Method::POST, &confirm, Some(&token), Some(json!({ "confirmation_token": "erase_wrong" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = app
+            .request(Method::POST, &confirm, Some(&token), Some(json!({ "confirmation_token": confirmation_token })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Confirmed");
+
+        // Nothing happens until the grace period is over
+        assert_eq!(crate::handlers::erasure::erase_due_accounts(&app.pool, app.blobs.as_ref()).await.unwrap(), 0);
+        sqlx::query!("UPDATE erasure_requests SET scheduled_for = NOW() WHERE user_id = $1", user_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(crate::handlers::erasure::erase_due_accounts(&app.pool, app.blobs.as_ref()).await.unwrap(), 1);
+
+        let user = sqlx::query!("SELECT email, name, status::text as \"status!\" FROM users WHERE id = $1", user_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(user.email, format!("erased-{}@erased.invalid", user_id));
+        assert_eq!(user.name, "Erased User");
+        assert_eq!(user.status, "deactivated");
+
+        // The ledger is kept for audit
+        let entries = sqlx::query_scalar!("SELECT COUNT(*) as \"count!\" FROM transactions WHERE user_id = $1", user_id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(entries, 1);
+
+        let left = sqlx::query!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM attachments WHERE user_id = $1) as "attachments!",
+                (SELECT COUNT(*) FROM kyc_documents WHERE user_id = $1) as "kyc_documents!",
+                (SELECT COUNT(*) FROM payees WHERE user_id = $1) as "payees!",
+                (SELECT COUNT(*) FROM linked_items WHERE user_id = $1) as "linked_items!",
+                (SELECT COUNT(*) FROM linked_accounts WHERE user_id = $1) as "linked_accounts!",
+                (SELECT COUNT(*) FROM screening_results WHERE user_id = $1 AND reason IS NOT NULL) as "screening_reasons!",
+                (SELECT COUNT(*) FROM screening_results WHERE user_id = $1) as "screening_results!"
+            "#,
+            user_id
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            (left.attachments, left.kyc_documents, left.payees, left.linked_items, left.linked_accounts, left.screening_reasons),
+            (0, 0, 0, 0, 0, 0)
+        );
+        assert!(left.screening_results > 0);
+        assert!(storage_keys.iter().all(|key| app.blobs.get(key).is_none()));
+
+        let (status, _) = app.request(Method::GET, "/v1/me", Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[sqlx::test]
+    async fn test_screening_holds_flagged_registrations_and_transfers_for_review(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (admin_token, admin_id) = app.sign_up("e2e-screening-admin@example.com").await;
+        let (token, user_id) = app.sign_up("e2e-screening@example.com").await;
+        let (friend_token, friend_id) = app.sign_up("e2e-screening-friend@example.com").await;
+        sqlx::query!("UPDATE users SET role = 'admin' WHERE id = $1", admin_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let transactions = format!("/v1/users/{}/transactions", user_id);
+        app.request(Method::POST, &transactions, Some(&token), Some(json!({ "amount": "100", "transaction_type": "Credit" })))
+            .await;
+
+        // A flagged registration goes through, but the account can't sign in until it's reviewed
+        let flagged_email = format!("e2e-flagged@{}", TEST_DENIED_DOMAIN);
+        let credentials = json!({ "email": flagged_email, "password": TEST_PASSWORD });
+        let registration = json!({ "email": flagged_email, "password": TEST_PASSWORD, "name": "Flagged User" });
+        let (status, body) = app.request(Method::POST, "/v1/register", None, Some(registration)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["message"].as_str(), body["user"]["status"].as_str()), (Some("Registration is pending review"), Some("PendingReview")));
+        let flagged_id = body["user"]["id"].as_str().unwrap().to_string();
+        let (status, body) = app.request(Method::POST, "/v1/auth", None, Some(credentials.clone())).await;
+        assert_eq!((status, body["code"].as_str()), (StatusCode::FORBIDDEN, Some("account_pending_review")));
+
+        let (status, _) = app.request(Method::GET, "/v1/admin/screening", Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (_, queue) = app.request(Method::GET, &format!("/v1/admin/screening?user_id={}", flagged_id), Some(&admin_token), None).await;
+        assert_eq!(queue.as_array().unwrap().len(), 1);
+        assert_eq!((queue[0]["operation"].as_str(), queue[0]["outcome"].as_str()), (Some("registration"), Some("hit")));
+        assert_eq!(queue[0]["reason"], format!("Email domain {} is on the deny list", TEST_DENIED_DOMAIN));
+
+        let review = format!("/v1/admin/screening/{}/review", queue[0]["id"].as_str().unwrap());
+        let (status, body) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "cleared", "note": "Different person" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["decision"].as_str(), body["review_note"].as_str()), (Some("cleared"), Some("Different person")));
+        let (status, _) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "confirmed" }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = app.request(Method::POST, "/v1/auth", None, Some(credentials)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Confirming a registrant's hit deactivates them
+        let confirmed_email = format!("e2e-confirmed@{}", TEST_DENIED_DOMAIN);
+        let registration = json!({ "email": confirmed_email, "password": TEST_PASSWORD, "name": "Confirmed User" });
+        let (_, body) = app.request(Method::POST, "/v1/register", None, Some(registration)).await;
+        let confirmed_id = body["user"]["id"].as_str().unwrap().to_string();
+        let (_, queue) = app.request(Method::GET, &format!("/v1/admin/screening?user_id={}", confirmed_id), Some(&admin_token), None).await;
+        let review = format!("/v1/admin/screening/{}/review", queue[0]["id"].as_str().unwrap());
+        let (status, _) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "confirmed" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        let credentials = json!({ "email": confirmed_email, "password": TEST_PASSWORD });
+        let (status, body) = app.request(Method::POST, "/v1/auth", None, Some(credentials)).await;
+        assert_eq!((status, body["code"].as_str()), (StatusCode::FORBIDDEN, Some("account_deactivated")));
+
+        // Transfers screen both parties again, but a hit an admin cleared isn't raised twice
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": flagged_id, "amount": "10" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["debit"]["status"], "Settled");
+
+        // A recipient who now matches the list holds the transfer in the held debit queue
+        app.request(Method::PATCH, "/v1/users/me", Some(&friend_token), Some(json!({ "name": TEST_DENIED_NAME })))
+            .await;
+        let (status, body) = app
+            .request(Method::POST, "/v1/transfers", Some(&token), Some(json!({ "to_user_id": friend_id, "amount": "20" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["debit"]["status"].as_str(), body["credit"]["status"].as_str()), (Some("Held"), Some("Held")));
+        let debit_id = body["debit"]["id"].clone();
+
+        let (_, queue) = app.request(Method::GET, &format!("/v1/admin/screening?user_id={}", friend_id), Some(&admin_token), None).await;
+        assert_eq!(queue.as_array().unwrap().len(), 1);
+        assert_eq!((queue[0]["operation"].as_str(), &queue[0]["transfer_id"]), (Some("transfer"), &body["transfer"]["id"]));
+        let (_, held) = app.request(Method::GET, "/v1/admin/held-debits", Some(&admin_token), None).await;
+        let held = held.as_array().unwrap().iter().find(|held| held["transaction_id"] == debit_id).unwrap();
+        assert_eq!(held["screening_result_id"], queue[0]["id"]);
+
+        // Confirming the hit denies the transfer
+        let review = format!("/v1/admin/screening/{}/review", queue[0]["id"].as_str().unwrap());
+        let (status, _) = app.request(Method::POST, &review, Some(&admin_token), Some(json!({ "decision": "confirmed" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, held) = app.request(Method::GET, "/v1/admin/held-debits", Some(&admin_token), None).await;
+        assert!(held.as_array().unwrap().iter().all(|held| held["transaction_id"] != debit_id));
+        let transfer_id: uuid::Uuid = body["transfer"]["id"].as_str().unwrap().parse().unwrap();
+        let statuses: Vec<String> = sqlx::query_scalar!(
+            r#"SELECT status::TEXT as "status!" FROM transactions WHERE transfer_id = $1"#,
+            transfer_id
+        )
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+        assert_eq!(statuses, vec!["denied", "denied"]);
+
+        let (_, reviewed) = app
+            .request(Method::GET, &format!("/v1/admin/screening?reviewed=true&user_id={}", friend_id), Some(&admin_token), None)
+            .await;
+        assert_eq!(reviewed[0]["decision"], "confirmed");
+    }
+
+    #[sqlx::test]
+    async fn test_api_keys_warn_before_expiring_and_are_refused_after(pool: PgPool) {
+        let app = TestApp::new(pool);
+        let (token, user_id) = app.sign_up("e2e-key-expiry@example.com").await;
+        let keys = format!("/v1/users/{}/api-keys", user_id);
+        let rfc3339 = |at: time::OffsetDateTime| at.format(&time::format_description::well_known::Rfc3339).unwrap();
+
+        let past = rfc3339(time::OffsetDateTime::now_utc() - time::Duration::hours(1));
+        let (status, _) = app
+            .request(Method::POST, &keys, Some(&token), Some(json!({ "name": "stale", "scopes": ["transactions:read"], "expires_at": past })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let soon = rfc3339(time::OffsetDateTime::now_utc() + time::Duration::days(3));
+        let (status, body) = app
+            .request(Method::POST, &keys, Some(&token), Some(json!({ "name": "ci", "scopes": ["transactions:read"], "expires_at": soon })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let key = body["key"].as_str().unwrap().to_string();
+        let (status, _) = app.request(Method::GET, &format!("/v1/users/{}/transactions", user_id), Some(&key), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Owners are warned once per key
+        assert_eq!(handlers::api_key::warn_expiring_keys(&app.pool).await.unwrap(), 1);
+        assert_eq!(handlers::api_key::warn_expiring_keys(&app.pool).await.unwrap(), 0);
+        let warned = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM outbox_events WHERE user_id = $1 AND event_type = 'api_key.expiring'",
+            user_id
+        )
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        assert_eq!(warned, Some(1));
+
+        sqlx::query!("UPDATE api_keys SET expires_at = NOW() - INTERVAL '1 minute' WHERE user_id = $1", user_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let (status, body) = app.request(Method::GET, &format!("/v1/users/{}/transactions", user_id), Some(&key), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "api_key_expired");
+    }
+}
@@ -0,0 +1,229 @@
+use async_trait::async_trait;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::models::screening::{ScreeningOperation, ScreeningOutcome, ScreeningResult};
+
+#[derive(Debug)]
+pub struct ScreeningError(pub String);
+
+impl fmt::Display for ScreeningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "screening provider request failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for ScreeningError {}
+
+// The party being screened, as they told us who they are
+#[derive(Debug, Clone, Copy)]
+pub struct ScreeningSubject<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+}
+
+// Checks people against sanctions and AML lists before they register or move money
+#[async_trait]
+pub trait Screening: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // The list entry the subject matches, written for the admin reviewing it; `None` when clear
+    async fn screen(&self, subject: &ScreeningSubject<'_>) -> Result<Option<String>, ScreeningError>;
+}
+
+// Used when no screening provider is configured: matches subjects against `SCREENING_DENY_LIST`.
+// Entries are full names, email addresses, or `@domain` for every address at a domain; all are
+// compared ignoring case and extra whitespace. An empty list clears everyone.
+pub struct DenyListScreening {
+    names: HashSet<String>,
+    emails: HashSet<String>,
+    domains: HashSet<String>,
+}
+
+impl DenyListScreening {
+    pub fn new(entries: &[String]) -> Self {
+        let mut list = Self { names: HashSet::new(), emails: HashSet::new(), domains: HashSet::new() };
+        for entry in entries.iter().map(|entry| normalize(entry)).filter(|entry| !entry.is_empty()) {
+            if let Some(domain) = entry.strip_prefix('@') {
+                list.domains.insert(domain.to_string());
+            } else if entry.contains('@') {
+                list.emails.insert(entry);
+            } else {
+                list.names.insert(entry);
+            }
+        }
+        list
+    }
+}
+
+#[async_trait]
+impl Screening for DenyListScreening {
+    fn name(&self) -> &'static str {
+        "deny_list"
+    }
+
+    async fn screen(&self, subject: &ScreeningSubject<'_>) -> Result<Option<String>, ScreeningError> {
+        let email = normalize(subject.email);
+        if self.emails.contains(&email) {
+            return Ok(Some(format!("Email {} is on the deny list", email)));
+        }
+        if let Some((_, domain)) = email.rsplit_once('@').filter(|(_, domain)| self.domains.contains(*domain)) {
+            return Ok(Some(format!("Email domain {} is on the deny list", domain)));
+        }
+        let name = normalize(subject.name);
+        if self.names.contains(&name) {
+            return Ok(Some(format!("Name {} is on the deny list", name)));
+        }
+        Ok(None)
+    }
+}
+
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// What screening one party came to, ready to record
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screened {
+    pub provider: &'static str,
+    pub outcome: ScreeningOutcome,
+    pub reason: Option<String>,
+}
+
+// Screens one party. A provider error doesn't fail the operation; it's recorded as an error outcome,
+// which is held for review like a hit.
+pub async fn screen(screening: &dyn Screening, subject: &ScreeningSubject<'_>) -> Screened {
+    let (outcome, reason) = match screening.screen(subject).await {
+        Ok(None) => (ScreeningOutcome::Clear, None),
+        Ok(Some(entry)) => (ScreeningOutcome::Hit, Some(entry)),
+        Err(e) => {
+            error!("Screening with {} failed: {}", screening.name(), e);
+            (ScreeningOutcome::Error, Some(e.to_string()))
+        }
+    };
+    Screened { provider: screening.name(), outcome, reason }
+}
+
+// Screens a user who already passed review once. A hit an admin cleared for them before isn't
+// raised again, so a false positive doesn't hold every transfer they make.
+pub async fn rescreen(
+    executor: impl PgExecutor<'_>,
+    screening: &dyn Screening,
+    user_id: Uuid,
+    subject: &ScreeningSubject<'_>,
+) -> Result<Screened, sqlx::Error> {
+    let mut screened = screen(screening, subject).await;
+    if screened.outcome == ScreeningOutcome::Hit {
+        let cleared = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM screening_results
+                WHERE user_id = $1 AND provider = $2 AND reason = $3 AND decision = 'cleared'
+            ) as "exists!"
+            "#,
+            user_id,
+            screened.provider,
+            screened.reason
+        )
+        .fetch_one(executor)
+        .await?;
+        if cleared {
+            info!("Screening hit on user {} was cleared before: {:?}", user_id, screened.reason);
+            screened.outcome = ScreeningOutcome::Clear;
+        }
+    }
+    Ok(screened)
+}
+
+// A transfer party as they were screened, so the transfer can tell if their details changed before
+// it locked them
+#[derive(Debug, Clone)]
+pub struct ScreenedParty {
+    pub user_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub screened: Screened,
+}
+
+// Screens the parties of a transfer again before its DB transaction opens, so a slow provider doesn't
+// keep their rows locked; the lists and their details may have changed since they registered. Users
+// that don't exist are left out for the transfer to refuse.
+pub async fn screen_transfer_parties(pool: &PgPool, screening: &dyn Screening, user_ids: &[Uuid]) -> Result<Vec<ScreenedParty>, sqlx::Error> {
+    let users = sqlx::query!("SELECT id, name, email FROM users WHERE id = ANY($1)", user_ids)
+        .fetch_all(pool)
+        .await?;
+    let mut parties = Vec::with_capacity(users.len());
+    for user in users {
+        let subject = ScreeningSubject { name: &user.name, email: &user.email };
+        let screened = rescreen(pool, screening, user.id, &subject).await?;
+        parties.push(ScreenedParty { user_id: user.id, name: user.name, email: user.email, screened });
+    }
+    Ok(parties)
+}
+
+pub async fn record(
+    conn: &mut PgConnection,
+    operation: ScreeningOperation,
+    user_id: Uuid,
+    transfer_id: Option<Uuid>,
+    screened: &Screened,
+) -> Result<ScreeningResult, sqlx::Error> {
+    let result = sqlx::query_as!(
+        ScreeningResult,
+        r#"
+        INSERT INTO screening_results (operation, user_id, transfer_id, provider, outcome, reason)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, operation as "operation: _", user_id, transfer_id, provider, outcome as "outcome: _", reason,
+            created_at, reviewed_by, reviewed_at, decision as "decision: _", review_note
+        "#,
+        operation as _,
+        user_id,
+        transfer_id,
+        screened.provider,
+        screened.outcome as _,
+        screened.reason
+    )
+    .fetch_one(conn)
+    .await?;
+
+    if result.outcome.is_flagged() {
+        info!(target: "audit", "Screening {:?} of user {} flagged for review: {:?}", operation, user_id, result.reason);
+    }
+    Ok(result)
+}
+
+// The deny list from `SCREENING_DENY_LIST`, the only provider so far
+pub fn from_config(config: &Config) -> Arc<dyn Screening> {
+    Arc::new(DenyListScreening::new(&config.screening_deny_list))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn hit(list: &DenyListScreening, name: &str, email: &str) -> Option<String> {
+        list.screen(&ScreeningSubject { name, email }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_deny_list_matches_names_emails_and_domains() {
+        let list = DenyListScreening::new(&[
+            " Ivan  Petrov ".to_string(),
+            "Bad.Actor@Example.com".to_string(),
+            "@sanctioned.example".to_string(),
+            "".to_string(),
+        ]);
+
+        assert_eq!(hit(&list, "IVAN PETROV", "ivan@example.com").await, Some("Name ivan petrov is on the deny list".to_string()));
+        assert_eq!(hit(&list, "Someone", "bad.actor@example.com").await, Some("Email bad.actor@example.com is on the deny list".to_string()));
+        assert_eq!(hit(&list, "Someone", "anyone@Sanctioned.example").await, Some("Email domain sanctioned.example is on the deny list".to_string()));
+        // Only whole names and exact domains match
+        assert_eq!(hit(&list, "Ivan Petrovich", "ivan@example.com").await, None);
+        assert_eq!(hit(&list, "Someone", "anyone@not-sanctioned.example").await, None);
+    }
+}
@@ -0,0 +1,41 @@
+use sqlx::PgConnection;
+use tracing::info;
+
+use crate::events::publish_approval_requested;
+use crate::models::approval::TransactionApproval;
+use crate::models::transaction::Transaction;
+
+// Queues a transaction already stored as awaiting approval, telling its owner and their approvers
+pub async fn request_approval(conn: &mut PgConnection, transaction: &Transaction, pending: bool) -> Result<TransactionApproval, sqlx::Error> {
+    let approval = sqlx::query_as!(
+        TransactionApproval,
+        r#"
+        WITH requested AS (
+            INSERT INTO transaction_approvals (transaction_id, user_id, pending)
+            VALUES ($1, $2, $3)
+            RETURNING id, transaction_id, status, created_at, decided_by, decided_at, note
+        )
+        SELECT r.id as "id!", r.transaction_id as "transaction_id!", t.user_id, t.account_id, t.amount, t.currency,
+            t.transaction_type as "transaction_type: _", t.description, t.category, t.status as "transaction_status: _",
+            r.status as "status!: _", r.created_at as "created_at!", r.decided_by, r.decided_at, r.note
+        FROM requested r
+        JOIN transactions t ON t.id = r.transaction_id
+        "#,
+        transaction.id,
+        transaction.user_id,
+        pending
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let approver_ids = sqlx::query_scalar!(
+        "SELECT approver_id FROM transaction_approvers WHERE user_id = $1 ORDER BY created_at",
+        transaction.user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    publish_approval_requested(conn, &approval, &approver_ids).await?;
+
+    info!(target: "audit", "Transaction {} is awaiting approval {}", transaction.id, approval.id);
+    Ok(approval)
+}
@@ -0,0 +1,255 @@
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgExecutor;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::models::audit::{AuditLogEntry, AuditLogQuery};
+use crate::models::transaction::TransactionCursor;
+
+// Operations written to the audit log
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditAction {
+    UserRegistered,
+    UserLoggedIn,
+    AccountDeleted,
+    TransactionCreated,
+    TransactionReversed,
+    UserTierChanged,
+    UserDeactivated,
+    UserReactivated,
+    AdjustmentCreated,
+    AdjustmentApproved,
+    AdjustmentRejected,
+    HoldPlaced,
+    HoldLifted,
+    HeldDebitReleased,
+    HeldDebitDenied,
+    FeatureFlagUpdated,
+    BalanceRecalculated,
+    JobRetried,
+    ProfileUpdated,
+    EmailChanged,
+    PasswordChanged,
+    AccountOpened,
+    AccountRenamed,
+    AccountClosed,
+    VelocityLimitsChanged,
+    TransactionApproved,
+    TransactionRejected,
+    ApproverAdded,
+    ApproverRemoved,
+    RiskEventReviewed,
+    DisputeOpened,
+    DisputeReviewStarted,
+    DisputeResolved,
+    InterestRateUpdated,
+    FeeRuleCreated,
+    FeeRuleUpdated,
+    FeeRuleDeactivated,
+    KycSubmitted,
+    KycReviewed,
+    KycLimitsUpdated,
+    ScreeningReviewed,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 41] = [
+        AuditAction::UserRegistered,
+        AuditAction::UserLoggedIn,
+        AuditAction::AccountDeleted,
+        AuditAction::TransactionCreated,
+        AuditAction::TransactionReversed,
+        AuditAction::UserTierChanged,
+        AuditAction::UserDeactivated,
+        AuditAction::UserReactivated,
+        AuditAction::AdjustmentCreated,
+        AuditAction::AdjustmentApproved,
+        AuditAction::AdjustmentRejected,
+        AuditAction::HoldPlaced,
+        AuditAction::HoldLifted,
+        AuditAction::HeldDebitReleased,
+        AuditAction::HeldDebitDenied,
+        AuditAction::FeatureFlagUpdated,
+        AuditAction::BalanceRecalculated,
+        AuditAction::JobRetried,
+        AuditAction::ProfileUpdated,
+        AuditAction::EmailChanged,
+        AuditAction::PasswordChanged,
+        AuditAction::AccountOpened,
+        AuditAction::AccountRenamed,
+        AuditAction::AccountClosed,
+        AuditAction::VelocityLimitsChanged,
+        AuditAction::TransactionApproved,
+        AuditAction::TransactionRejected,
+        AuditAction::ApproverAdded,
+        AuditAction::ApproverRemoved,
+        AuditAction::RiskEventReviewed,
+        AuditAction::DisputeOpened,
+        AuditAction::DisputeReviewStarted,
+        AuditAction::DisputeResolved,
+        AuditAction::InterestRateUpdated,
+        AuditAction::FeeRuleCreated,
+        AuditAction::FeeRuleUpdated,
+        AuditAction::FeeRuleDeactivated,
+        AuditAction::KycSubmitted,
+        AuditAction::KycReviewed,
+        AuditAction::KycLimitsUpdated,
+        AuditAction::ScreeningReviewed,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AuditAction::UserRegistered => "user.registered",
+            AuditAction::UserLoggedIn => "user.logged_in",
+            AuditAction::AccountDeleted => "user.deleted",
+            AuditAction::TransactionCreated => "transaction.created",
+            AuditAction::TransactionReversed => "transaction.reversed",
+            AuditAction::UserTierChanged => "user.tier_changed",
+            AuditAction::UserDeactivated => "user.deactivated",
+            AuditAction::UserReactivated => "user.reactivated",
+            AuditAction::AdjustmentCreated => "adjustment.created",
+            AuditAction::AdjustmentApproved => "adjustment.approved",
+            AuditAction::AdjustmentRejected => "adjustment.rejected",
+            AuditAction::HoldPlaced => "hold.placed",
+            AuditAction::HoldLifted => "hold.lifted",
+            AuditAction::HeldDebitReleased => "held_debit.released",
+            AuditAction::HeldDebitDenied => "held_debit.denied",
+            AuditAction::FeatureFlagUpdated => "feature_flag.updated",
+            AuditAction::BalanceRecalculated => "balance.recalculated",
+            AuditAction::JobRetried => "job.retried",
+            AuditAction::ProfileUpdated => "user.profile_updated",
+            AuditAction::EmailChanged => "user.email_changed",
+            AuditAction::PasswordChanged => "user.password_changed",
+            AuditAction::AccountOpened => "account.opened",
+            AuditAction::AccountRenamed => "account.renamed",
+            AuditAction::AccountClosed => "account.closed",
+            AuditAction::VelocityLimitsChanged => "user.velocity_limits_changed",
+            AuditAction::TransactionApproved => "transaction.approved",
+            AuditAction::TransactionRejected => "transaction.rejected",
+            AuditAction::ApproverAdded => "approver.added",
+            AuditAction::ApproverRemoved => "approver.removed",
+            AuditAction::RiskEventReviewed => "risk_event.reviewed",
+            AuditAction::DisputeOpened => "dispute.opened",
+            AuditAction::DisputeReviewStarted => "dispute.under_review",
+            AuditAction::DisputeResolved => "dispute.resolved",
+            AuditAction::InterestRateUpdated => "interest_rate.updated",
+            AuditAction::FeeRuleCreated => "fee_rule.created",
+            AuditAction::FeeRuleUpdated => "fee_rule.updated",
+            AuditAction::FeeRuleDeactivated => "fee_rule.deactivated",
+            AuditAction::KycSubmitted => "kyc.submitted",
+            AuditAction::KycReviewed => "kyc.reviewed",
+            AuditAction::KycLimitsUpdated => "kyc_limits.updated",
+            AuditAction::ScreeningReviewed => "screening.reviewed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+// One entry for the audit log: `actor_id` did `action` to `user_id`'s account, changing `target_id`
+// from `before` to `after`
+#[derive(Debug)]
+pub struct AuditRecord {
+    pub action: AuditAction,
+    pub actor_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditRecord {
+    pub fn new(action: AuditAction, actor_id: Uuid, user_id: Option<Uuid>) -> Self {
+        Self { action, actor_id, user_id, target_id: None, before: None, after: None }
+    }
+
+    pub fn target(mut self, target_id: Uuid) -> Self {
+        self.target_id = Some(target_id);
+        self
+    }
+
+    pub fn before(mut self, snapshot: &impl Serialize) -> Self {
+        self.before = serde_json::to_value(snapshot).ok();
+        self
+    }
+
+    pub fn after(mut self, snapshot: &impl Serialize) -> Self {
+        self.after = serde_json::to_value(snapshot).ok();
+        self
+    }
+}
+
+// Writes the entry; pass the DB transaction making the change so the entry commits or rolls back with it
+pub async fn record(executor: impl PgExecutor<'_>, record: AuditRecord) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (actor_id, user_id, action, target_id, before, after)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        record.actor_id,
+        record.user_id,
+        record.action.name(),
+        record.target_id,
+        record.before,
+        record.after
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        error!("Failed to write audit record for {}: {}", record.action.name(), e);
+        db_error(&e, "Failed to write audit log")
+    })?;
+    Ok(())
+}
+
+// Entries matching the query's filters, newest first, starting after `cursor`
+pub async fn search(
+    executor: impl PgExecutor<'_>,
+    query: &AuditLogQuery,
+    cursor: Option<&TransactionCursor>,
+    limit: i64,
+) -> Result<Vec<AuditLogEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        AuditLogEntry,
+        r#"
+        SELECT id, actor_id, user_id, action, target_id, before, after, created_at
+        FROM audit_log
+        WHERE ($1::uuid IS NULL OR user_id = $1)
+            AND ($2::uuid IS NULL OR actor_id = $2)
+            AND ($3::text IS NULL OR action = $3)
+            AND ($4::timestamptz IS NULL OR created_at >= $4)
+            AND ($5::timestamptz IS NULL OR created_at < $5)
+            AND ($6::timestamptz IS NULL OR (created_at, id) < ($6, $7))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $8
+        "#,
+        query.user_id,
+        query.actor_id,
+        query.action,
+        query.from,
+        query.to,
+        cursor.map(|c| c.created_at),
+        cursor.map(|c| c.id),
+        limit
+    )
+    .fetch_all(executor)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_names_round_trip() {
+        for action in AuditAction::ALL {
+            assert_eq!(AuditAction::from_name(action.name()), Some(action));
+        }
+        assert_eq!(AuditAction::from_name("user.unknown"), None);
+    }
+}
@@ -0,0 +1,319 @@
+use bcrypt::{hash, verify, DEFAULT_COST};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::email_policy::{DomainDecision, EmailPolicy};
+use crate::error::AppError;
+use crate::feature_flags::{ensure_enabled, KillSwitch};
+use crate::models::screening::ScreeningOperation;
+use crate::models::user::{CreateUser, User, UserStatus};
+use crate::outbox;
+use crate::repositories::user as users;
+use crate::screening::{self, Screening, ScreeningSubject};
+use crate::services::audit::{self, AuditAction, AuditRecord};
+use crate::services::fees::HOUSE_USER_ID;
+use crate::services::risk;
+
+// Tokens expire after 24 hours
+pub const TOKEN_LIFETIME_SECONDS: i64 = 24 * 3600;
+
+// Largest metadata object accepted on registration
+const MAX_METADATA_BYTES: usize = 4096;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String, // user id
+    pub exp: i64,    // expiration time
+    #[serde(default)]
+    pub auth_time: i64, // when the user last proved their password; 0 for tokens issued before step-up
+    #[serde(default)]
+    pub session_version: i32, // the user's session version at issue; tokens from older versions are refused
+}
+
+impl Claims {
+    pub fn user_id(&self) -> Result<Uuid, AppError> {
+        Uuid::parse_str(&self.sub).map_err(|e| {
+            tracing::error!("Invalid subject in token: {}", e);
+            AppError::Unauthorized("Invalid or expired token".to_string())
+        })
+    }
+}
+
+// Creates an account after checking the email isn't taken or blocked and the metadata is within
+// bounds; the email format and password policy are checked when the request is validated. Users
+// flagged by screening are created pending review until an admin reviews the result.
+pub async fn register(pool: &PgPool, email_policy: &EmailPolicy, screening: &dyn Screening, payload: CreateUser) -> Result<User, AppError> {
+    ensure_enabled(pool, KillSwitch::Registrations).await?;
+
+    // Check if user already exists
+    tracing::info!("Checking if user already exists");
+    let taken = users::email_taken(pool, &payload.email).await.map_err(|e| {
+        tracing::error!("Database error checking existing user: {:?}", e);
+        db_error(&e, "Failed to check existing user")
+    })?;
+
+    if taken {
+        tracing::error!("User already exists: {}", payload.email);
+        return Err(AppError::Conflict("User already exists".to_string()));
+    }
+
+    // Reject throwaway and otherwise blocked email domains
+    if email_policy.check(&payload.email) == DomainDecision::Blocked {
+        error!("Blocked email domain: {}", payload.email);
+        return Err(AppError::Unprocessable("Email domain is not allowed".to_string()));
+    }
+
+    let metadata = payload.metadata.unwrap_or_else(|| serde_json::json!({}));
+    if let Err(message) = validate_metadata(&metadata) {
+        error!("Invalid metadata: {}", message);
+        return Err(AppError::BadRequest(message));
+    }
+
+    let screened = screening::screen(screening, &ScreeningSubject { name: &payload.name, email: &payload.email }).await;
+
+    // Hash password
+    tracing::info!("Hashing password");
+    let password_hash = hash(payload.password.as_bytes(), DEFAULT_COST).map_err(|e| {
+        tracing::error!("Failed to hash password: {}", e);
+        AppError::Internal("Failed to hash password".to_string())
+    })?;
+
+    // Create user
+    tracing::info!("Creating new user in database");
+    let mut tx = pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+    let mut user = users::insert(&mut *tx, &payload.email, &password_hash, &payload.name, &metadata)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create user: {:?}", e);
+            db_error(&e, "Failed to create user")
+        })?;
+    screening::record(&mut tx, ScreeningOperation::Registration, user.id, None, &screened)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record screening result: {}", e);
+            db_error(&e, "Failed to create user")
+        })?;
+    if screened.outcome.is_flagged() {
+        user = users::set_status(&mut *tx, user.id, UserStatus::PendingReview)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to hold user for screening review: {}", e);
+                db_error(&e, "Failed to create user")
+            })?
+            .ok_or(AppError::Internal("Failed to create user".to_string()))?;
+    }
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::UserRegistered, user.id, Some(user.id)).target(user.id).after(&user))
+        .await?;
+    // Downstream systems get the ID only, so no personal data outlives an erasure in their copies
+    let event = json!({
+        "id": Uuid::new_v4(),
+        "type": outbox::EVENT_USER_REGISTERED,
+        "created_at": user.created_at.unix_timestamp(),
+        "data": { "user_id": user.id, "tier": user.tier },
+    });
+    outbox::enqueue(&mut tx, Some(user.id), outbox::EVENT_USER_REGISTERED, &event)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to queue registration event: {}", e);
+            db_error(&e, "Failed to create user")
+        })?;
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    tracing::info!("User created successfully: {}", user.email);
+    Ok(user)
+}
+
+// Metadata must be a JSON object no larger than `MAX_METADATA_BYTES` once serialized
+fn validate_metadata(metadata: &serde_json::Value) -> Result<(), String> {
+    if !metadata.is_object() {
+        return Err("Metadata must be a JSON object".to_string());
+    }
+    if metadata.to_string().len() > MAX_METADATA_BYTES {
+        return Err(format!("Metadata must not exceed {} bytes", MAX_METADATA_BYTES));
+    }
+    Ok(())
+}
+
+// Refuses a user who can't use their account, saying why
+pub fn ensure_active(status: UserStatus) -> Result<(), AppError> {
+    match status {
+        UserStatus::Active => Ok(()),
+        UserStatus::Deactivated => Err(AppError::AccountDeactivated),
+        UserStatus::PendingReview => Err(AppError::AccountPendingReview),
+    }
+}
+
+// Looks up the user by email and checks their password; an unknown email and a wrong password
+// fail the same way. The device signed in from is remembered for the fraud rules.
+pub async fn authenticate(pool: &PgPool, email: &str, password: &str, user_agent: Option<&str>) -> Result<User, AppError> {
+    tracing::info!("Querying database for user");
+    let user = users::find_by_email(pool, email)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error during user lookup: {:?}", e);
+            db_error(&e, "Failed to look up user")
+        })?
+        .ok_or_else(|| {
+            tracing::error!("User not found: {}", email);
+            AppError::Unauthorized("Invalid credentials".to_string())
+        })?;
+    // The house holds Dodo's own money and has no password; it fails like an unknown email
+    if user.id == HOUSE_USER_ID {
+        tracing::error!("Attempt to sign in as the house user");
+        return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+    }
+
+    verify_password(&user, password)?;
+    // Only reveal the account can't be used to someone who knows its password
+    ensure_active(user.status).inspect_err(|_| {
+        tracing::error!("User {} attempted to sign in while {:?}", user.id, user.status);
+    })?;
+
+    audit::record(pool, AuditRecord::new(AuditAction::UserLoggedIn, user.id, Some(user.id))).await?;
+    risk::remember_device(pool, user.id, user_agent).await.map_err(|e| {
+        tracing::error!("Failed to record login device: {}", e);
+        db_error(&e, "Failed to sign in")
+    })?;
+    Ok(user)
+}
+
+// Replaces the user's password once they've proven the current one, and signs out all their
+// sessions by bumping the session version. Returns the updated user, whose new version the
+// caller's fresh token should carry.
+pub async fn change_password(pool: &PgPool, user: &User, current_password: &str, new_password: &str) -> Result<User, AppError> {
+    verify_password(user, current_password)?;
+    if new_password == current_password {
+        return Err(AppError::BadRequest("New password must differ from the current one".to_string()));
+    }
+
+    let password_hash = hash(new_password.as_bytes(), DEFAULT_COST).map_err(|e| {
+        tracing::error!("Failed to hash password: {}", e);
+        AppError::Internal("Failed to hash password".to_string())
+    })?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to start transaction: {}", e);
+        db_error(&e, "Failed to start transaction")
+    })?;
+    let updated = users::set_password(&mut *tx, user.id, &password_hash)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to change password for user {}: {}", user.id, e);
+            db_error(&e, "Failed to change password")
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+    audit::record(&mut *tx, AuditRecord::new(AuditAction::PasswordChanged, user.id, Some(user.id)).target(user.id)).await?;
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit transaction: {}", e);
+        db_error(&e, "Failed to commit transaction")
+    })?;
+
+    Ok(updated)
+}
+
+pub fn verify_password(user: &User, password: &str) -> Result<(), AppError> {
+    match verify(password, &user.password_hash) {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            error!("Invalid password for user {}", user.id);
+            Err(AppError::Unauthorized("Invalid credentials".to_string()))
+        }
+        Err(e) => {
+            error!("Error verifying password: {}", e);
+            Err(AppError::Internal("Failed to verify password".to_string()))
+        }
+    }
+}
+
+// Signing and verification keys for session tokens, built once from the configured secret
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl JwtKeys {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+
+    // Issues a session token; `auth_time` and `session_version` are carried over unchanged when a
+    // session is merely refreshed
+    pub fn generate_token(&self, user_id: &Uuid, auth_time: i64, session_version: i32) -> Result<String, AppError> {
+        let expiration = OffsetDateTime::now_utc().unix_timestamp() + TOKEN_LIFETIME_SECONDS;
+
+        let claims = Claims {
+            sub: user_id.to_string(),
+            exp: expiration,
+            auth_time,
+            session_version,
+        };
+
+        match encode(&Header::default(), &claims, &self.encoding) {
+            Ok(token) => {
+                tracing::info!("Token generated successfully");
+                Ok(token)
+            },
+            Err(e) => {
+                tracing::error!("Failed to generate token: {:?}", e);
+                Err(AppError::Internal("Failed to generate token".to_string()))
+            }
+        }
+    }
+
+    pub fn decode_token(&self, token: &str) -> Result<Claims, AppError> {
+        decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map_err(|e| {
+                tracing::error!("Failed to decode token: {:?}", e);
+                AppError::Unauthorized("Invalid or expired token".to_string())
+            })
+            .map(|data| data.claims)
+    }
+}
+
+// Returns how close to expiry (in seconds) a session token must be before it is re-issued,
+// or `None` when sliding expiration is disabled for this deployment
+pub fn sliding_refresh_threshold(config: &Config) -> Option<i64> {
+    config.jwt_sliding_expiration.then_some(config.jwt_refresh_threshold_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_must_be_a_small_object() {
+        assert!(validate_metadata(&serde_json::json!({ "plan": "pro" })).is_ok());
+        assert!(validate_metadata(&serde_json::json!(["not", "an", "object"])).is_err());
+        assert!(validate_metadata(&serde_json::json!({ "blob": "x".repeat(MAX_METADATA_BYTES) })).is_err());
+    }
+
+    #[test]
+    fn test_tokens_round_trip_only_with_the_same_secret() {
+        let keys = JwtKeys::new("test_secret");
+        let user_id = Uuid::new_v4();
+        let token = keys.generate_token(&user_id, 42, 3).unwrap();
+
+        let claims = keys.decode_token(&token).unwrap();
+        assert_eq!(claims.user_id().unwrap(), user_id);
+        assert_eq!(claims.auth_time, 42);
+        assert_eq!(claims.session_version, 3);
+
+        assert!(JwtKeys::new("other_secret").decode_token(&token).is_err());
+    }
+}
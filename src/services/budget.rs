@@ -0,0 +1,92 @@
+use sqlx::{PgExecutor, PgPool};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::events::publish_budget_threshold_reached;
+use crate::models::budget::{Budget, BudgetPeriod, BudgetStatus};
+use crate::models::transaction::{Transaction, TransactionStatus, TransactionType};
+use crate::repositories::transaction as transactions;
+
+// The budget against spending in its period containing `at`, from the live ledger or the sandbox one
+pub async fn status(executor: impl PgExecutor<'_>, budget: Budget, livemode: bool, at: OffsetDateTime) -> Result<BudgetStatus, sqlx::Error> {
+    let spending = transactions::category_spending(
+        executor,
+        budget.user_id,
+        livemode,
+        &budget.category,
+        &budget.currency,
+        budget.period,
+        at,
+    )
+    .await?;
+    Ok(BudgetStatus::new(budget, spending))
+}
+
+// Runs after a debit has been committed: reports each budget on its category whose spending has
+// reached a higher alert threshold than already reported this period. Only live spending is
+// reported, once per threshold and period; a debit that jumps past several reports the highest.
+pub async fn check_thresholds(pool: &PgPool, debit: &Transaction) -> Result<usize, sqlx::Error> {
+    if debit.transaction_type != TransactionType::Debit || debit.status != TransactionStatus::Settled || !debit.livemode {
+        return Ok(0);
+    }
+    let Some(category) = debit.category.as_deref() else {
+        return Ok(0);
+    };
+
+    let mut tx = pool.begin().await?;
+
+    // Locked so concurrent debits report each threshold once
+    let budgets = sqlx::query!(
+        r#"
+        SELECT id, user_id, category, currency, period as "period: BudgetPeriod", limit_amount, created_at, updated_at,
+            alerted_period_start, alerted_threshold
+        FROM budgets
+        WHERE user_id = $1 AND category = $2 AND currency = $3
+        ORDER BY id
+        FOR UPDATE
+        "#,
+        debit.user_id,
+        category,
+        debit.currency
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut alerts = 0;
+    for row in budgets {
+        let budget = Budget {
+            id: row.id,
+            user_id: row.user_id,
+            category: row.category,
+            currency: row.currency,
+            period: row.period,
+            limit_amount: row.limit_amount,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        };
+        let status = status(&mut *tx, budget, true, debit.created_at).await?;
+        let Some(threshold) = status.threshold_reached() else {
+            continue;
+        };
+        let already_reported = row.alerted_period_start == Some(status.period_start)
+            && row.alerted_threshold.is_some_and(|alerted| alerted >= threshold);
+        if already_reported {
+            continue;
+        }
+
+        publish_budget_threshold_reached(&mut tx, &status, threshold).await?;
+        sqlx::query!(
+            "UPDATE budgets SET alerted_period_start = $2, alerted_threshold = $3 WHERE id = $1",
+            status.budget.id,
+            status.period_start,
+            threshold
+        )
+        .execute(&mut *tx)
+        .await?;
+        info!("Budget {} reached {}% of its limit", status.budget.id, threshold);
+        alerts += 1;
+    }
+
+    tx.commit().await?;
+    Ok(alerts)
+}
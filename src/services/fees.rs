@@ -0,0 +1,213 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::events::publish_transaction_created;
+use crate::models::fee::{FeeEvent, FeeKind, FeeRule, FeeTier};
+use crate::models::money::Money;
+use crate::models::transaction::{Transaction, TransactionStatus, TransactionType};
+use crate::repositories::transaction::{self as transactions, NewTransaction};
+
+// The user holding Dodo's own money, which fees are credited to
+pub const HOUSE_USER_ID: Uuid = Uuid::from_u128(1);
+
+// Category fee debits are filed under
+pub const FEE_CATEGORY: &str = "fees";
+
+const BASIS_POINTS: i64 = 10_000;
+
+// A fee a rule charges on an amount
+#[derive(Debug)]
+pub struct Fee {
+    pub rule: FeeRule,
+    pub amount: Money,
+}
+
+fn percentage(amount: &BigDecimal, bps: i32) -> BigDecimal {
+    amount * BigDecimal::from(bps) / BigDecimal::from(BASIS_POINTS)
+}
+
+// What `rule` charges on `amount`, before rounding: its flat amount, percentage or tier, kept
+// within the rule's bounds
+pub fn fee_amount(rule: &FeeRule, tiers: &[FeeTier], amount: &BigDecimal) -> BigDecimal {
+    let fee = match rule.kind {
+        FeeKind::Flat => rule.flat_amount.clone().unwrap_or_default(),
+        FeeKind::Percentage => percentage(amount, rule.percentage_bps.unwrap_or_default()),
+        FeeKind::Tiered => tiers
+            .iter()
+            .find(|tier| tier.up_to.as_ref().is_none_or(|up_to| amount <= up_to))
+            .map(|tier| &tier.flat_amount + percentage(amount, tier.percentage_bps))
+            .unwrap_or_default(),
+    };
+    let fee = match &rule.min_fee {
+        Some(min_fee) if fee < *min_fee => min_fee.clone(),
+        _ => fee,
+    };
+    match &rule.max_fee {
+        Some(max_fee) if fee > *max_fee => max_fee.clone(),
+        _ => fee,
+    }
+}
+
+// The fees the active rules for `event` charge on `amount`, rounded half up to the currency's minor
+// unit. Rules that come to nothing are left out.
+pub async fn assess(conn: &mut PgConnection, event: FeeEvent, amount: &Money) -> Result<Vec<Fee>, sqlx::Error> {
+    let currency = amount.currency();
+    let rules = sqlx::query_as!(
+        FeeRule,
+        r#"
+        SELECT id, name, event as "event: _", currency, kind as "kind: _", flat_amount, percentage_bps, tiers,
+            min_fee, max_fee, active, created_by, updated_by, created_at, updated_at
+        FROM fee_rules
+        WHERE active AND event = $1 AND currency = $2
+        ORDER BY created_at, id
+        "#,
+        event as _,
+        currency.as_str()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rules
+        .into_iter()
+        .filter_map(|rule| {
+            let tiers: Vec<FeeTier> = serde_json::from_value(rule.tiers.clone()).unwrap_or_default();
+            let fee = fee_amount(&rule, &tiers, &amount.to_decimal())
+                .with_scale_round(currency.minor_units(), RoundingMode::HalfUp);
+            let amount = Money::from_decimal(&fee, currency).ok().filter(|fee| fee.amount_minor() > 0)?;
+            Some(Fee { rule, amount })
+        })
+        .collect())
+}
+
+// What the fees come to together
+pub fn total(fees: &[Fee]) -> BigDecimal {
+    fees.iter().map(|fee| fee.amount.to_decimal()).sum()
+}
+
+// Posts each fee as a settled debit from the account `charged` was posted to and a credit to the
+// house, on the caller's DB transaction, and records what it was charged on. Returns the debits.
+pub async fn charge(conn: &mut PgConnection, fees: &[Fee], charged: &Transaction) -> Result<Vec<Transaction>, sqlx::Error> {
+    let mut debits = Vec::with_capacity(fees.len());
+
+    for fee in fees {
+        let currency = fee.amount.currency();
+        let description = format!("Fee: {}", fee.rule.name);
+        let entry = |user_id, account_id, transaction_type, category| NewTransaction {
+            user_id,
+            account_id,
+            amount: &fee.amount,
+            transaction_type,
+            description: Some(&description),
+            transfer_id: None,
+            status: TransactionStatus::Settled,
+            reverses: None,
+            execute_at: None,
+            livemode: true,
+            category,
+            payee_id: None,
+            fx_rate: None,
+        };
+
+        let debit = transactions::insert(
+            &mut *conn,
+            &entry(charged.user_id, Some(charged.account_id), TransactionType::Debit, Some(FEE_CATEGORY)),
+        )
+        .await?;
+        publish_transaction_created(&mut *conn, &debit).await?;
+        let credit = transactions::insert(&mut *conn, &entry(HOUSE_USER_ID, None, TransactionType::Credit, None)).await?;
+        publish_transaction_created(&mut *conn, &credit).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO fee_charges (rule_id, transaction_id, debit_id, credit_id, amount, currency)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            fee.rule.id,
+            charged.id,
+            debit.id,
+            credit.id,
+            fee.amount.to_decimal(),
+            currency.as_str()
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        debits.push(debit);
+    }
+
+    Ok(debits)
+}
+
+// Whether `transaction_id` is either leg of a fee charge
+pub async fn is_fee_entry(conn: &mut PgConnection, transaction_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM fee_charges WHERE debit_id = $1 OR credit_id = $1) as "exists!""#,
+        transaction_id
+    )
+    .fetch_one(conn)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use time::OffsetDateTime;
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn rule(kind: FeeKind) -> FeeRule {
+        FeeRule {
+            id: Uuid::new_v4(),
+            name: "Test".to_string(),
+            event: FeeEvent::Transfer,
+            currency: "USD".to_string(),
+            kind,
+            flat_amount: None,
+            percentage_bps: None,
+            tiers: serde_json::json!([]),
+            min_fee: None,
+            max_fee: None,
+            active: true,
+            created_by: Uuid::new_v4(),
+            updated_by: Uuid::new_v4(),
+            created_at: OffsetDateTime::now_utc(),
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    #[test]
+    fn test_flat_and_percentage_fees_stay_within_bounds() {
+        let flat = FeeRule { flat_amount: Some(decimal("1.50")), ..rule(FeeKind::Flat) };
+        assert_eq!(fee_amount(&flat, &[], &decimal("1000")), decimal("1.50"));
+
+        // 1.5% with a 0.50 floor and a 10.00 cap
+        let percentage = FeeRule {
+            percentage_bps: Some(150),
+            min_fee: Some(decimal("0.50")),
+            max_fee: Some(decimal("10")),
+            ..rule(FeeKind::Percentage)
+        };
+        assert_eq!(fee_amount(&percentage, &[], &decimal("100")), decimal("1.5"));
+        assert_eq!(fee_amount(&percentage, &[], &decimal("10")), decimal("0.50"));
+        assert_eq!(fee_amount(&percentage, &[], &decimal("5000")), decimal("10"));
+    }
+
+    #[test]
+    fn test_tiered_fees_use_the_first_band_covering_the_amount() {
+        let tiers = [
+            FeeTier { up_to: Some(decimal("100")), flat_amount: decimal("0.25"), percentage_bps: 0 },
+            FeeTier { up_to: Some(decimal("1000")), flat_amount: decimal("0"), percentage_bps: 100 },
+            FeeTier { up_to: None, flat_amount: decimal("5"), percentage_bps: 50 },
+        ];
+        let tiered = rule(FeeKind::Tiered);
+
+        assert_eq!(fee_amount(&tiered, &tiers, &decimal("100")), decimal("0.25"));
+        assert_eq!(fee_amount(&tiered, &tiers, &decimal("250")), decimal("2.5"));
+        assert_eq!(fee_amount(&tiered, &tiers, &decimal("2000")), decimal("15"));
+        assert_eq!(fee_amount(&tiered, &tiers[..1], &decimal("2000")), decimal("0"));
+    }
+}
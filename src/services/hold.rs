@@ -0,0 +1,192 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::config::Config;
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::events::publish_balance_updated;
+use crate::models::hold::HeldDebit;
+use crate::models::transaction::TransactionStatus;
+use crate::repositories::transaction as transactions;
+use crate::services::ledger::{charge_settled_debit, lock_account_balance};
+
+// Finds the oldest active hold catching a debit with this description, or paid to this counterparty
+pub async fn matching_hold(
+    conn: &mut PgConnection,
+    description: Option<&str>,
+    counterparty_id: Option<Uuid>,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT id
+        FROM debit_holds
+        WHERE lifted_at IS NULL
+            AND (counterparty_id IS NULL OR counterparty_id = $2)
+            AND (description_pattern IS NULL OR $1 ILIKE description_pattern)
+        ORDER BY created_at
+        LIMIT 1
+        "#,
+        description,
+        counterparty_id
+    )
+    .fetch_optional(conn)
+    .await
+}
+
+// Adds a debit already stored as held to the review queue
+pub async fn queue_held_debit(conn: &mut PgConnection, transaction_id: Uuid, hold_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO held_debits (transaction_id, hold_id) VALUES ($1, $2)",
+        transaction_id,
+        hold_id
+    )
+    .execute(conn)
+    .await?;
+
+    info!(target: "audit", "Debit {} held for review by hold {}", transaction_id, hold_id);
+    Ok(())
+}
+
+// Adds a transfer debit held over a screening hit to the review queue
+pub async fn queue_screening_held_debit(conn: &mut PgConnection, transaction_id: Uuid, screening_result_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO held_debits (transaction_id, screening_result_id) VALUES ($1, $2)",
+        transaction_id,
+        screening_result_id
+    )
+    .execute(conn)
+    .await?;
+
+    info!(target: "audit", "Debit {} held for review by screening result {}", transaction_id, screening_result_id);
+    Ok(())
+}
+
+// Adds a debit a fraud rule held to the review queue
+pub async fn queue_risk_held_debit(conn: &mut PgConnection, transaction_id: Uuid, risk_event_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO held_debits (transaction_id, risk_event_id) VALUES ($1, $2)",
+        transaction_id,
+        risk_event_id
+    )
+    .execute(conn)
+    .await?;
+
+    info!(target: "audit", "Debit {} held for review by risk event {}", transaction_id, risk_event_id);
+    Ok(())
+}
+
+pub async fn lock_held_debit(
+    conn: &mut PgConnection,
+    transaction_id: Uuid,
+) -> Result<HeldDebit, AppError> {
+    let held = sqlx::query_as!(
+        HeldDebit,
+        r#"
+        SELECT h.transaction_id, h.hold_id, h.risk_event_id, h.screening_result_id, t.user_id, t.account_id, t.amount, t.currency, t.transaction_type as "transaction_type: _",
+            t.description, t.transfer_id, t.status as "status: _", t.created_at, h.decided_by, h.decided_at
+        FROM held_debits h
+        JOIN transactions t ON t.id = h.transaction_id
+        WHERE h.transaction_id = $1
+        FOR UPDATE
+        "#,
+        transaction_id
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch held debit: {}", e);
+        db_error(&e, "Failed to fetch held debit")
+    })?
+    .ok_or(AppError::NotFound("Held debit not found".to_string()))?;
+
+    if held.decided_at.is_some() {
+        return Err(AppError::Conflict("Held debit has already been decided".to_string()));
+    }
+
+    Ok(held)
+}
+
+// Posts a locked held debit once the account can cover it and the fees it's charged on settling
+pub async fn settle_held_debit(conn: &mut PgConnection, config: &Config, held: &HeldDebit, decided_by: Uuid) -> Result<HeldDebit, AppError> {
+    let balance = lock_account_balance(&mut *conn, held.user_id, held.account_id, true).await
+        .map_err(|e| {
+            error!("Failed to compute balance: {}", e);
+            db_error(&e, "Failed to compute balance")
+        })?;
+    if &balance - &held.amount < -&config.overdraft_limit {
+        error!("Insufficient funds to release held debit {}: balance {}, amount {}", held.transaction_id, balance, held.amount);
+        return Err(AppError::InsufficientFunds);
+    }
+
+    let released = decide_held_debit(&mut *conn, held, TransactionStatus::Settled, decided_by).await?;
+    let debit = transactions::find_for_update(&mut *conn, held.user_id, held.transaction_id, true).await
+        .map_err(|e| {
+            error!("Failed to fetch held debit: {}", e);
+            db_error(&e, "Failed to update held debit")
+        })?
+        .ok_or(AppError::NotFound("Held debit not found".to_string()))?;
+    charge_settled_debit(conn, config, &debit).await?;
+    Ok(released)
+}
+
+// Moves a held debit, and any held transfer leg paired with it, to `status` and records the decision
+pub async fn decide_held_debit(
+    conn: &mut PgConnection,
+    held: &HeldDebit,
+    status: TransactionStatus,
+    decided_by: Uuid,
+) -> Result<HeldDebit, AppError> {
+    let legs = sqlx::query!(
+        r#"
+        UPDATE transactions
+        SET status = $3
+        WHERE status = 'held' AND (id = $1 OR transfer_id = $2)
+        RETURNING user_id, currency
+        "#,
+        held.transaction_id,
+        held.transfer_id,
+        status as _
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to update held transactions: {}", e);
+        db_error(&e, "Failed to update held debit")
+    })?;
+
+    if status == TransactionStatus::Settled {
+        for leg in legs {
+            publish_balance_updated(conn, leg.user_id, &leg.currency).await
+                .map_err(|e| {
+                    error!("Failed to queue balance events: {}", e);
+                    db_error(&e, "Failed to update held debit")
+                })?;
+        }
+    }
+
+    sqlx::query_as!(
+        HeldDebit,
+        r#"
+        WITH decided AS (
+            UPDATE held_debits
+            SET decided_by = $2, decided_at = NOW()
+            WHERE transaction_id = $1
+            RETURNING transaction_id, hold_id, risk_event_id, screening_result_id, decided_by, decided_at
+        )
+        SELECT d.transaction_id as "transaction_id!", d.hold_id, d.risk_event_id, d.screening_result_id, t.user_id, t.account_id, t.amount, t.currency,
+            t.transaction_type as "transaction_type: _", t.description, t.transfer_id, t.status as "status: _", t.created_at,
+            d.decided_by, d.decided_at
+        FROM decided d
+        JOIN transactions t ON t.id = d.transaction_id
+        "#,
+        held.transaction_id,
+        decided_by
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to record held debit decision: {}", e);
+        db_error(&e, "Failed to update held debit")
+    })
+}
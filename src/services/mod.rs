@@ -0,0 +1,13 @@
+// Business rules shared by handlers and background workers, kept free of HTTP types so they can be
+// exercised directly
+pub mod auth;
+pub mod ledger;
+pub mod audit;
+pub mod round_up;
+pub mod budget;
+pub mod velocity;
+pub mod risk;
+pub mod fees;
+pub mod hold;
+pub mod approval;
+pub mod payee;
@@ -0,0 +1,36 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+use tracing::error;
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::models::payee::Payee;
+
+// One of the user's payees; another user's is reported as not found
+pub async fn find_payee(executor: impl PgExecutor<'_>, user_id: Uuid, payee_id: Uuid) -> Result<Payee, AppError> {
+    sqlx::query_as!(
+        Payee,
+        r#"
+        SELECT id, user_id, name, recipient_user_id, account_identifier, last_used_at, created_at, updated_at
+        FROM payees
+        WHERE id = $1 AND user_id = $2
+        "#,
+        payee_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch payee: {}", e);
+        db_error(&e, "Failed to fetch payee")
+    })?
+    .ok_or(AppError::NotFound("Payee not found".to_string()))
+}
+
+// Moves the payee to the top of the user's recent payees
+pub async fn mark_payee_used(executor: impl PgExecutor<'_>, payee_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE payees SET last_used_at = NOW() WHERE id = $1", payee_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,332 @@
+use async_trait::async_trait;
+use bigdecimal::{BigDecimal, RoundingMode};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgExecutor};
+use time::{Duration, OffsetDateTime};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+use crate::models::risk::{RiskAction, RiskEvent};
+use crate::models::transaction::TransactionType;
+
+// Longest user agent kept for a login device; the fingerprint covers all of it
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+// A live transaction about to be created, as fraud rules see it
+pub struct RiskCandidate<'a> {
+    pub user_id: Uuid,
+    pub amount: &'a Money,
+    pub transaction_type: TransactionType,
+    pub at: OffsetDateTime,
+}
+
+// A rule the candidate tripped, with the action it calls for and why, written for the admin reviewing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskSignal {
+    pub rule: &'static str,
+    pub action: RiskAction,
+    pub reason: String,
+}
+
+// One check in the fraud pipeline. Rules run inside the DB transaction the candidate would be
+// created in, and return the action they call for along with the reason.
+#[async_trait]
+pub trait RiskRule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn evaluate(
+        &self,
+        conn: &mut PgConnection,
+        candidate: &RiskCandidate<'_>,
+    ) -> Result<Option<(RiskAction, String)>, sqlx::Error>;
+}
+
+// Every rule's verdict on one candidate
+#[derive(Debug, Default)]
+pub struct RiskAssessment {
+    pub signals: Vec<RiskSignal>,
+}
+
+impl RiskAssessment {
+    // The most severe action any rule called for
+    pub fn action(&self) -> Option<RiskAction> {
+        self.signals.iter().map(|signal| signal.action).max()
+    }
+}
+
+// Runs each rule in turn; every rule sees every candidate, so all the reasons are recorded
+pub struct RiskPipeline {
+    rules: Vec<Box<dyn RiskRule>>,
+}
+
+impl RiskPipeline {
+    pub fn new(rules: Vec<Box<dyn RiskRule>>) -> Self {
+        Self { rules }
+    }
+
+    // The rules live transactions go through
+    pub fn standard() -> Self {
+        Self::new(vec![
+            Box::new(VelocitySpike::default()),
+            Box::new(UnusualAmount::default()),
+            Box::new(NewDeviceLogin::default()),
+        ])
+    }
+
+    pub async fn evaluate(&self, conn: &mut PgConnection, candidate: &RiskCandidate<'_>) -> Result<RiskAssessment, sqlx::Error> {
+        let mut signals = Vec::new();
+        for rule in &self.rules {
+            let Some((mut action, reason)) = rule.evaluate(&mut *conn, candidate).await? else {
+                continue;
+            };
+            // Only debits can wait in the held debit queue, so a held credit is flagged instead
+            if action == RiskAction::Hold && candidate.transaction_type == TransactionType::Credit {
+                action = RiskAction::Flag;
+            }
+            signals.push(RiskSignal { rule: rule.name(), action, reason });
+        }
+        Ok(RiskAssessment { signals })
+    }
+}
+
+// A burst of transactions in a short window, this one included
+pub struct VelocitySpike {
+    pub window: Duration,
+    pub hold_at: i64,
+    pub block_at: i64,
+}
+
+impl Default for VelocitySpike {
+    fn default() -> Self {
+        Self { window: Duration::minutes(10), hold_at: 20, block_at: 50 }
+    }
+}
+
+#[async_trait]
+impl RiskRule for VelocitySpike {
+    fn name(&self) -> &'static str {
+        "velocity_spike"
+    }
+
+    async fn evaluate(
+        &self,
+        conn: &mut PgConnection,
+        candidate: &RiskCandidate<'_>,
+    ) -> Result<Option<(RiskAction, String)>, sqlx::Error> {
+        let recent = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM transactions
+            WHERE user_id = $1 AND livemode AND created_at > $2
+                AND transfer_id IS NULL AND reverses IS NULL
+            "#,
+            candidate.user_id,
+            candidate.at - self.window
+        )
+        .fetch_one(conn)
+        .await?;
+
+        let count = recent + 1;
+        let action = if count >= self.block_at {
+            RiskAction::Block
+        } else if count >= self.hold_at {
+            RiskAction::Hold
+        } else {
+            return Ok(None);
+        };
+        Ok(Some((action, format!("{} transactions within {} minutes", count, self.window.whole_minutes()))))
+    }
+}
+
+// A debit far larger than the user's usual ones in its currency, once there are enough to compare
+pub struct UnusualAmount {
+    pub multiple: BigDecimal,
+    pub min_history: i64,
+    pub lookback: Duration,
+}
+
+impl Default for UnusualAmount {
+    fn default() -> Self {
+        Self { multiple: BigDecimal::from(10), min_history: 5, lookback: Duration::days(90) }
+    }
+}
+
+#[async_trait]
+impl RiskRule for UnusualAmount {
+    fn name(&self) -> &'static str {
+        "unusual_amount"
+    }
+
+    async fn evaluate(
+        &self,
+        conn: &mut PgConnection,
+        candidate: &RiskCandidate<'_>,
+    ) -> Result<Option<(RiskAction, String)>, sqlx::Error> {
+        if candidate.transaction_type != TransactionType::Debit {
+            return Ok(None);
+        }
+
+        let currency = candidate.amount.currency();
+        let history = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!", AVG(amount) as average
+            FROM transactions
+            WHERE user_id = $1 AND livemode AND currency = $2 AND transaction_type = 'debit' AND status = 'settled'
+                AND created_at > $3 AND transfer_id IS NULL AND reverses IS NULL
+            "#,
+            candidate.user_id,
+            currency.as_str(),
+            candidate.at - self.lookback
+        )
+        .fetch_one(conn)
+        .await?;
+
+        let Some(average) = history.average.filter(|_| history.count >= self.min_history) else {
+            return Ok(None);
+        };
+        if candidate.amount.to_decimal() <= &average * &self.multiple {
+            return Ok(None);
+        }
+        let average = average.with_scale_round(currency.minor_units(), RoundingMode::HalfEven);
+        Ok(Some((
+            RiskAction::Flag,
+            format!("{} is over {}x the average debit of {} {}", candidate.amount, self.multiple, average, currency.as_str()),
+        )))
+    }
+}
+
+// A debit soon after the user signed in from a device they hadn't used before. Users who have only
+// ever used one device, such as new ones, don't trip it.
+pub struct NewDeviceLogin {
+    pub window: Duration,
+}
+
+impl Default for NewDeviceLogin {
+    fn default() -> Self {
+        Self { window: Duration::hours(24) }
+    }
+}
+
+#[async_trait]
+impl RiskRule for NewDeviceLogin {
+    fn name(&self) -> &'static str {
+        "new_device"
+    }
+
+    async fn evaluate(
+        &self,
+        conn: &mut PgConnection,
+        candidate: &RiskCandidate<'_>,
+    ) -> Result<Option<(RiskAction, String)>, sqlx::Error> {
+        if candidate.transaction_type != TransactionType::Debit {
+            return Ok(None);
+        }
+
+        let new_device = sqlx::query_scalar!(
+            r#"
+            SELECT
+                EXISTS(SELECT 1 FROM login_devices WHERE user_id = $1 AND first_seen_at > $2)
+                AND EXISTS(SELECT 1 FROM login_devices WHERE user_id = $1 AND first_seen_at <= $2) as "new_device!"
+            "#,
+            candidate.user_id,
+            candidate.at - self.window
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(new_device.then(|| {
+            (RiskAction::Hold, format!("Signed in from a new device within the last {} hours", self.window.whole_hours()))
+        }))
+    }
+}
+
+// Stores an event for each rule the candidate tripped, against `transaction_id` once it exists
+pub async fn record(
+    conn: &mut PgConnection,
+    candidate: &RiskCandidate<'_>,
+    transaction_id: Option<Uuid>,
+    assessment: &RiskAssessment,
+) -> Result<Vec<RiskEvent>, sqlx::Error> {
+    let amount = candidate.amount.to_decimal();
+    let currency = candidate.amount.currency();
+    let mut events = Vec::with_capacity(assessment.signals.len());
+    for signal in &assessment.signals {
+        let event = sqlx::query_as!(
+            RiskEvent,
+            r#"
+            INSERT INTO risk_events (user_id, transaction_id, rule, action, reason, amount, currency, transaction_type)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, user_id, transaction_id, rule, action as "action: _", reason, amount, currency,
+                transaction_type as "transaction_type: _", created_at, reviewed_by, reviewed_at, review_note
+            "#,
+            candidate.user_id,
+            transaction_id,
+            signal.rule,
+            signal.action as _,
+            signal.reason,
+            amount,
+            currency.as_str(),
+            candidate.transaction_type as _
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        info!(target: "audit", "Risk rule {} would {:?} a transaction for user {}: {}", event.rule, event.action, event.user_id, event.reason);
+        events.push(event);
+    }
+    Ok(events)
+}
+
+// Remembers the device a user signed in from, identified by its user agent
+pub async fn remember_device(executor: impl PgExecutor<'_>, user_id: Uuid, user_agent: Option<&str>) -> Result<(), sqlx::Error> {
+    let fingerprint = hex::encode(Sha256::digest(user_agent.unwrap_or_default().as_bytes()));
+    let user_agent = user_agent.map(|agent| agent.chars().take(MAX_USER_AGENT_LENGTH).collect::<String>());
+    sqlx::query!(
+        r#"
+        INSERT INTO login_devices (user_id, fingerprint, user_agent)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, fingerprint) DO UPDATE SET last_seen_at = NOW()
+        "#,
+        user_id,
+        fingerprint,
+        user_agent
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(RiskAction);
+
+    #[async_trait]
+    impl RiskRule for Fixed {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        async fn evaluate(&self, _: &mut PgConnection, _: &RiskCandidate<'_>) -> Result<Option<(RiskAction, String)>, sqlx::Error> {
+            Ok(Some((self.0, "always".to_string())))
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_pipeline_takes_the_most_severe_action_and_never_holds_credits(pool: sqlx::PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let amount = Money::from_minor(1000, crate::models::money::Currency::parse("USD").unwrap());
+        let pipeline = RiskPipeline::new(vec![Box::new(Fixed(RiskAction::Flag)), Box::new(Fixed(RiskAction::Hold))]);
+
+        let debit = RiskCandidate { user_id: Uuid::new_v4(), amount: &amount, transaction_type: TransactionType::Debit, at: OffsetDateTime::now_utc() };
+        let assessment = pipeline.evaluate(&mut conn, &debit).await.unwrap();
+        assert_eq!(assessment.signals.len(), 2);
+        assert_eq!(assessment.action(), Some(RiskAction::Hold));
+
+        let credit = RiskCandidate { transaction_type: TransactionType::Credit, ..debit };
+        assert_eq!(pipeline.evaluate(&mut conn, &credit).await.unwrap().action(), Some(RiskAction::Flag));
+        assert_eq!(RiskAssessment::default().action(), None);
+    }
+}
@@ -0,0 +1,88 @@
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::config::Config;
+use crate::handlers::transfer::post_account_transfer;
+use crate::models::money::Money;
+use crate::models::round_up::RoundUpRule;
+use crate::models::transaction::{Transaction, TransactionStatus, TransactionType};
+use crate::repositories::account as accounts;
+use crate::services::ledger::lock_account_balance;
+
+const ROUND_UP_DESCRIPTION: &str = "Round-up";
+
+// What rounding `amount` up to the next whole unit adds, e.g. 0.70 for 12.30 USD; None when the
+// amount is already whole or the currency has no minor unit
+pub fn spare_change(amount: Money) -> Option<Money> {
+    let unit = 10i64.pow(amount.currency().minor_units() as u32);
+    let spare = (unit - amount.amount_minor().rem_euclid(unit)) % unit;
+    (spare > 0).then(|| Money::from_minor(spare, amount.currency()))
+}
+
+// Runs after a debit has been committed: moves its spare change from the debit's account into the
+// user's round-up account as an ordinary transfer between their accounts. A round-up the account
+// can't cover is skipped; the debit itself stands either way.
+pub async fn apply(pool: &PgPool, config: &Config, debit: &Transaction) -> Result<Option<Money>, sqlx::Error> {
+    if debit.transaction_type != TransactionType::Debit || debit.status != TransactionStatus::Settled || !debit.livemode {
+        return Ok(None);
+    }
+
+    let Some(rule) = sqlx::query_as!(
+        RoundUpRule,
+        "SELECT user_id, account_id, enabled, created_at, updated_at FROM round_up_rules WHERE user_id = $1",
+        debit.user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    if !rule.enabled || rule.account_id == debit.account_id {
+        return Ok(None);
+    }
+
+    let Some(spare) = Money::from_stored(&debit.amount, &debit.currency).and_then(spare_change)
+    else {
+        return Ok(None);
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let balance = lock_account_balance(&mut tx, debit.user_id, debit.account_id, true).await?;
+    let from = accounts::find(&mut *tx, debit.user_id, debit.account_id).await?;
+    let to = accounts::find(&mut *tx, debit.user_id, rule.account_id).await?;
+    let (Some(from), Some(to)) = (from, to) else {
+        return Ok(None);
+    };
+    if from.closed_at.is_some() || to.closed_at.is_some() || to.currency != from.currency {
+        return Ok(None);
+    }
+    if &balance - spare.to_decimal() < -&config.overdraft_limit {
+        info!("Skipping round-up of transaction {}: the account can't cover {}", debit.id, spare);
+        return Ok(None);
+    }
+
+    let response = post_account_transfer(&mut tx, debit.user_id, &from, &to, &spare, Some(ROUND_UP_DESCRIPTION)).await?;
+    tx.commit().await?;
+
+    info!("Rounded up transaction {} by {} as transfer {}", debit.id, spare, response.transfer.id);
+    Ok(Some(spare))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::money::Currency;
+
+    #[test]
+    fn test_spare_change_rounds_up_to_the_next_whole_unit() {
+        let usd = Currency::parse("USD").unwrap();
+        assert_eq!(spare_change(Money::from_minor(1230, usd)), Some(Money::from_minor(70, usd)));
+        assert_eq!(spare_change(Money::from_minor(1299, usd)), Some(Money::from_minor(1, usd)));
+        assert_eq!(spare_change(Money::from_minor(1200, usd)), None);
+
+        let kwd = Currency::parse("KWD").unwrap();
+        assert_eq!(spare_change(Money::from_minor(1250, kwd)), Some(Money::from_minor(750, kwd)));
+        assert_eq!(spare_change(Money::from_minor(1250, Currency::parse("JPY").unwrap())), None);
+    }
+}
@@ -0,0 +1,62 @@
+use sqlx::PgConnection;
+use time::OffsetDateTime;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::db::db_error;
+use crate::error::AppError;
+use crate::fx;
+use crate::models::money::Money;
+use crate::models::transaction::TransactionType;
+use crate::models::velocity_limit::{VelocityLimits, VelocityUsage};
+use crate::repositories::transaction as transactions;
+use crate::repositories::user as users;
+
+// Checks a new live transaction against the user's velocity limits, if they have any, converting it
+// and the user's debits in other currencies into the limits' currency. Locks the user's row for the rest of the DB transaction so concurrent requests can't both fit under a limit.
+pub async fn enforce(conn: &mut PgConnection, user_id: Uuid, amount: &Money, transaction_type: TransactionType) -> Result<(), AppError> {
+    let limits = sqlx::query_as!(
+        VelocityLimits,
+        r#"
+        SELECT user_id, currency, max_transaction_amount, max_daily_debit_total, max_transactions_per_hour, updated_by,
+            created_at, updated_at
+        FROM velocity_limits
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch velocity limits: {}", e);
+        db_error(&e, "Failed to create transaction")
+    })?;
+    let Some(limits) = limits else {
+        return Ok(());
+    };
+
+    users::lock(&mut *conn, user_id).await.map_err(|e| {
+        error!("Failed to lock user: {}", e);
+        db_error(&e, "Failed to create transaction")
+    })?;
+    let usage = transactions::velocity_usage(&mut *conn, user_id, OffsetDateTime::now_utc())
+        .await
+        .map_err(|e| {
+            error!("Failed to compute velocity usage: {}", e);
+            db_error(&e, "Failed to create transaction")
+        })?;
+    let used: Vec<_> = usage.iter().map(|row| (row.currency.as_str(), &row.daily_debit_total)).collect();
+    let (requested, daily_debit_total) = fx::limit_values(&mut *conn, &limits.currency, amount, &used).await?;
+    let usage = VelocityUsage {
+        daily_debit_total,
+        hourly_transaction_count: usage.iter().map(|row| row.hourly_transaction_count).sum(),
+    };
+
+    match limits.check(&requested, transaction_type == TransactionType::Debit, &usage) {
+        Some(violation) => {
+            info!("Transaction for user {} refused by velocity limit {:?}", user_id, violation.limit);
+            Err(AppError::VelocityLimitExceeded(Box::new(violation)))
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,177 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Interval;
+
+// Resolves on the first SIGINT or SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+// Waits for the next tick, or returns false once the worker has been told to stop. A run that has
+// already started always finishes, so no batch is abandoned halfway.
+pub async fn tick(ticker: &mut Interval, stop: &mut watch::Receiver<bool>) -> bool {
+    if *stop.borrow() {
+        return false;
+    }
+    tokio::select! {
+        biased;
+        _ = stop.changed() => false,
+        _ = ticker.tick() => true,
+    }
+}
+
+struct Worker {
+    name: &'static str,
+    stop: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+struct RunningFlag {
+    name: &'static str,
+    running: Arc<AtomicBool>,
+}
+
+// Which background workers are still running, shared with the readiness probe
+#[derive(Clone, Default)]
+pub struct WorkerStatus {
+    flags: Arc<Mutex<Vec<RunningFlag>>>,
+}
+
+impl WorkerStatus {
+    // Names of the workers that have exited, whether they returned or panicked
+    pub fn stopped(&self) -> Vec<&'static str> {
+        self.flags
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|flag| !flag.running.load(Ordering::SeqCst))
+            .map(|flag| flag.name)
+            .collect()
+    }
+}
+
+// Clears a worker's running flag when its task ends, including by panic
+struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+// Background workers, stopped one at a time in the order they were started
+#[derive(Default)]
+pub struct Workers {
+    workers: Vec<Worker>,
+    status: WorkerStatus,
+}
+
+impl Workers {
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, run: F)
+    where
+        F: FnOnce(watch::Receiver<bool>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (stop, stopped) = watch::channel(false);
+        let running = Arc::new(AtomicBool::new(true));
+        self.status.flags.lock().unwrap().push(RunningFlag { name, running: running.clone() });
+
+        let worker = run(stopped);
+        let handle = tokio::spawn(async move {
+            let _guard = RunningGuard(running);
+            worker.await;
+        });
+        self.workers.push(Worker { name, stop, handle });
+    }
+
+    pub fn status(&self) -> WorkerStatus {
+        self.status.clone()
+    }
+
+    pub async fn stop(self) {
+        for worker in self.workers {
+            tracing::info!("Stopping {}", worker.name);
+            let _ = worker.stop.send(true);
+            if let Err(e) = worker.handle.await {
+                tracing::error!("{} exited abnormally: {}", worker.name, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_workers_finish_their_run_and_stop_in_order() {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut workers = Workers::default();
+
+        for name in ["first", "second"] {
+            let order = order.clone();
+            let runs = runs.clone();
+            workers.spawn(name, move |mut stop| async move {
+                let mut ticker = tokio::time::interval(Duration::from_millis(10));
+                while tick(&mut ticker, &mut stop).await {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                }
+                order.lock().unwrap().push(name);
+            });
+        }
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        workers.stop().await;
+
+        assert!(runs.load(Ordering::SeqCst) >= 2);
+        assert_eq!(*order.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_workers_that_exited() {
+        let mut workers = Workers::default();
+        let status = workers.status();
+        workers.spawn("steady", |mut stop| async move {
+            let mut ticker = tokio::time::interval(Duration::from_millis(10));
+            while tick(&mut ticker, &mut stop).await {}
+        });
+        workers.spawn("crashing", |_| async { panic!("worker failed") });
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(status.stopped(), vec!["crashing"]);
+
+        workers.stop().await;
+        assert_eq!(status.stopped(), vec!["steady", "crashing"]);
+    }
+}
@@ -0,0 +1,173 @@
+use axum::extract::FromRef;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::blob_store::BlobStore;
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::email_policy::EmailPolicy;
+use crate::fx::RateCache;
+use crate::services::auth::JwtKeys;
+use crate::handlers::realtime::RealtimeHub;
+use crate::handlers::webhooks::WebhookProcessors;
+use crate::middleware::latency::LatencyTracker;
+use crate::linked_accounts::{BankDataProvider, NoBankDataProvider};
+use crate::middleware::user_rate::UserRateLimiter;
+use crate::payments::{NoPaymentGateway, PaymentGateway};
+use crate::screening::{self, Screening};
+use crate::shutdown::WorkerStatus;
+
+// Everything the router shares with handlers and middleware. Each piece can be extracted on its
+// own, e.g. `State<PgPool>`, so a new subsystem only needs a field and a `FromRef` impl here and
+// only the handlers that use it change.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub realtime: Arc<RealtimeHub>,
+    pub user_rate: Arc<UserRateLimiter>,
+    pub cache: Arc<dyn Cache>,
+    pub blobs: Arc<dyn BlobStore>,
+    pub payments: Arc<dyn PaymentGateway>,
+    pub bank_data: Arc<dyn BankDataProvider>,
+    // Which email domains may register
+    pub email_policy: Arc<EmailPolicy>,
+    // Screens registrations and transfers against sanctions and AML lists
+    pub screening: Arc<dyn Screening>,
+    // Inbound webhook providers with credentials configured
+    pub webhooks: Arc<WebhookProcessors>,
+    pub fx_rates: Arc<RateCache>,
+    pub latency: Arc<LatencyTracker>,
+    pub workers: WorkerStatus,
+}
+
+impl AppState {
+    pub fn new(pool: PgPool, config: Arc<Config>, cache: Arc<dyn Cache>, blobs: Arc<dyn BlobStore>) -> Self {
+        Self {
+            pool,
+            jwt_keys: Arc::new(JwtKeys::new(&config.jwt_secret)),
+            latency: Arc::new(LatencyTracker::new(&config.latency_slos)),
+            webhooks: Arc::new(WebhookProcessors::from_config(&config)),
+            email_policy: Arc::new(EmailPolicy::from_config(&config)),
+            screening: screening::from_config(&config),
+            fx_rates: Arc::new(RateCache::default()),
+            config,
+            realtime: Arc::new(RealtimeHub::default()),
+            user_rate: Arc::new(UserRateLimiter::default()),
+            cache,
+            blobs,
+            payments: Arc::new(NoPaymentGateway),
+            bank_data: Arc::new(NoBankDataProvider),
+            workers: WorkerStatus::default(),
+        }
+    }
+
+    // Takes card deposits through `payments`; without it deposits are refused
+    pub fn with_payments(mut self, payments: Arc<dyn PaymentGateway>) -> Self {
+        self.payments = payments;
+        self
+    }
+
+    // Links users' bank accounts through `bank_data`; without it linking is refused
+    pub fn with_bank_data(mut self, bank_data: Arc<dyn BankDataProvider>) -> Self {
+        self.bank_data = bank_data;
+        self
+    }
+
+    // Lets the readiness probe see the background workers, including ones started later
+    pub fn with_workers(mut self, workers: WorkerStatus) -> Self {
+        self.workers = workers;
+        self
+    }
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<JwtKeys> {
+    fn from_ref(state: &AppState) -> Self {
+        state.jwt_keys.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RealtimeHub> {
+    fn from_ref(state: &AppState) -> Self {
+        state.realtime.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<UserRateLimiter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.user_rate.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Cache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.cache.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn BlobStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.blobs.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn PaymentGateway> {
+    fn from_ref(state: &AppState) -> Self {
+        state.payments.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn BankDataProvider> {
+    fn from_ref(state: &AppState) -> Self {
+        state.bank_data.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<EmailPolicy> {
+    fn from_ref(state: &AppState) -> Self {
+        state.email_policy.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Screening> {
+    fn from_ref(state: &AppState) -> Self {
+        state.screening.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<WebhookProcessors> {
+    fn from_ref(state: &AppState) -> Self {
+        state.webhooks.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RateCache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.fx_rates.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<LatencyTracker> {
+    fn from_ref(state: &AppState) -> Self {
+        state.latency.clone()
+    }
+}
+
+impl FromRef<AppState> for WorkerStatus {
+    fn from_ref(state: &AppState) -> Self {
+        state.workers.clone()
+    }
+}
@@ -0,0 +1,128 @@
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::{Request, Response};
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::env;
+use std::time::Duration;
+use tracing::{field, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::middleware::request_id::REQUEST_ID_HEADER;
+
+// Spans are only exported when one of these names an OTLP/HTTP collector
+const OTLP_ENDPOINT_ENV: [&str; 2] = ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"];
+
+// Reported as `service.name` unless `OTEL_SERVICE_NAME` overrides it
+const SERVICE_NAME: &str = "dodo";
+
+// What gets exported, independent of `RUST_LOG`: request spans and the logs inside them, plus the
+// statement sqlx logs for every query
+const EXPORT_FILTER: &str = "info,sqlx::query=debug";
+
+// Installs log output and, when a collector is configured, span export over OTLP. The returned
+// provider buffers spans, so it has to be shut down on exit to flush them.
+pub fn init() -> Option<SdkTracerProvider> {
+    // Continue traces started upstream via W3C `traceparent` / `tracestate` headers
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let exporter = OTLP_ENDPOINT_ENV
+        .iter()
+        .any(|name| env::var(name).is_ok())
+        .then(|| SpanExporter::builder().with_http().build());
+    let (provider, exporter_error) = match exporter {
+        Some(Ok(exporter)) => {
+            let provider = SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(resource())
+                .build();
+            (Some(provider), None)
+        }
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer()
+            .with_target(true)
+            .with_thread_ids(true)
+            .with_file(true)
+            .with_line_number(true)
+            .with_thread_names(true)
+            .with_level(true)
+            .with_ansi(true)
+            .with_filter(EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("debug,tower_http=debug,axum=debug,sqlx=debug"))))
+        .with(provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer(SERVICE_NAME))
+                .with_filter(EnvFilter::new(EXPORT_FILTER))
+        }))
+        .init();
+
+    if let Some(e) = exporter_error {
+        tracing::error!("Failed to create OTLP span exporter, spans will not be exported: {}", e);
+    }
+    provider
+}
+
+fn resource() -> Resource {
+    let builder = Resource::builder();
+    if env::var("OTEL_SERVICE_NAME").is_ok() {
+        builder.build()
+    } else {
+        builder.with_service_name(SERVICE_NAME).build()
+    }
+}
+
+// Flushes spans still buffered for export
+pub fn shutdown(provider: SdkTracerProvider) {
+    if let Err(e) = provider.shutdown() {
+        tracing::error!("Failed to flush spans: {}", e);
+    }
+}
+
+// Span covering a whole request, named after its route. Handler logs and sqlx query events are
+// recorded inside it, and it joins the caller's trace when the request carries a `traceparent`.
+// Runs after the request id is assigned, so the span carries it.
+pub fn make_span(request: &Request<Body>) -> Span {
+    let path = request.uri().path();
+    let request_id = request.headers().get(REQUEST_ID_HEADER).and_then(|value| value.to_str().ok());
+    let route = request.extensions().get::<MatchedPath>().map_or(path, MatchedPath::as_str);
+    let span = tracing::info_span!(
+        "request",
+        otel.name = format!("{} {}", request.method(), route),
+        otel.kind = "server",
+        otel.status_code = field::Empty,
+        http.request.method = %request.method(),
+        http.route = route,
+        url.path = path,
+        http.response.status_code = field::Empty,
+        request_id,
+    );
+
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(request.headers())));
+    let _ = span.set_parent(parent);
+    span
+}
+
+pub fn on_request(request: &Request<Body>, _span: &Span) {
+    tracing::info!("{} {}", request.method(), request.uri());
+}
+
+pub fn on_response(response: &Response<Body>, latency: Duration, span: &Span) {
+    let status = response.status();
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+        tracing::error!("{} in {:?}", status, latency);
+    } else {
+        tracing::info!("{} in {:?}", status, latency);
+    }
+}
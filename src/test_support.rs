@@ -0,0 +1,172 @@
+// End-to-end test harness. Pair it with `#[sqlx::test]`, which gives every test its own freshly
+// migrated database, so tests can run in parallel without seeing each other's rows:
+//
+//     #[sqlx::test]
+//     async fn test_something(pool: PgPool) {
+//         let app = TestApp::new(pool);
+//         let (token, user_id) = app.sign_up("someone@example.com").await;
+//         ...
+//     }
+//
+// Requests go through the full router, middleware included, via `tower::ServiceExt::oneshot`.
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::blob_store::MemoryBlobStore;
+use crate::cache::NoCache;
+use crate::config::Config;
+use crate::linked_accounts::FakeBankDataProvider;
+use crate::middleware::auth::API_KEY_HEADER;
+use crate::middleware::rate_limit::{IpRateLimiters, IpRateLimits};
+use crate::payments::{stripe, FakePaymentGateway};
+use crate::routes;
+use crate::state::AppState;
+
+pub const TEST_PASSWORD: &str = "correct horse battery";
+pub const TEST_STRIPE_WEBHOOK_SECRET: &str = "whsec_test";
+pub const TEST_PLAID_TOKEN_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+pub const TEST_FX_SPREAD_BPS: u32 = 50;
+// Registrations from this domain, and users by this name, are flagged by screening
+pub const TEST_DENIED_DOMAIN: &str = "denied.example.com";
+pub const TEST_DENIED_NAME: &str = "Sanctioned Person";
+
+// The settings every test app runs with unless a test gives its own
+pub fn test_config() -> Config {
+    Config {
+        jwt_secret: "test_secret".to_string(),
+        stripe_webhook_secret: Some(TEST_STRIPE_WEBHOOK_SECRET.to_string()),
+        plaid_token_key: Some(TEST_PLAID_TOKEN_KEY.to_string()),
+        fx_spread_bps: TEST_FX_SPREAD_BPS,
+        screening_deny_list: vec![format!("@{}", TEST_DENIED_DOMAIN), TEST_DENIED_NAME.to_string()],
+        ..Config::default()
+    }
+}
+
+pub struct TestApp {
+    pub pool: PgPool,
+    // What the app runs with, for calling background workers directly
+    pub config: Arc<Config>,
+    // Where attachments are uploaded to
+    pub blobs: Arc<MemoryBlobStore>,
+    // Where bank accounts are linked from
+    pub bank_data: Arc<FakeBankDataProvider>,
+    router: Router,
+}
+
+impl TestApp {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_config(pool, test_config())
+    }
+
+    // An app with settings of its own, e.g. `Config { approval_threshold: .., ..test_config() }`
+    pub fn with_config(pool: PgPool, config: Config) -> Self {
+        let config = Arc::new(config);
+        let blobs = Arc::new(MemoryBlobStore::default());
+        let bank_data = Arc::new(FakeBankDataProvider::default());
+        let state = AppState::new(pool.clone(), config.clone(), Arc::new(NoCache), blobs.clone())
+            .with_payments(Arc::new(FakePaymentGateway))
+            .with_bank_data(bank_data.clone());
+        // Loose enough that no test trips them by accident
+        let limits = IpRateLimits { per_minute: 10_000, auth_per_minute: 10_000, trust_proxy: false };
+        let router = routes::router(state, Arc::new(IpRateLimiters::new(limits)));
+        Self { pool, config, blobs, bank_data, router }
+    }
+
+    // Sends one request, authenticated with a session token or API key when `credential` is set,
+    // and returns the status and JSON body (`Null` when the body is empty or not JSON)
+    pub async fn request(&self, method: Method, uri: &str, credential: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(credential) = credential {
+            builder = if credential.starts_with("dodo_") {
+                builder.header(API_KEY_HEADER, credential)
+            } else {
+                builder.header(header::AUTHORIZATION, format!("Bearer {}", credential))
+            };
+        }
+        let body = match body {
+            Some(body) => {
+                builder = builder.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+
+        self.send(builder.body(body).unwrap()).await
+    }
+
+    // Posts `bytes` as the `file` field of a multipart form, authenticated with a session token
+    pub async fn upload(&self, uri: &str, credential: &str, filename: &str, content_type: &str, bytes: &[u8]) -> (StatusCode, Value) {
+        let boundary = "dodo-test-boundary";
+        let mut body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary, filename, content_type
+        )
+        .into_bytes();
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", credential))
+            .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+            .body(Body::from(body))
+            .unwrap();
+        self.send(request).await
+    }
+
+    // Delivers a Stripe webhook event signed with the test secret, as Stripe would
+    pub async fn stripe_webhook(&self, event: &Value) -> (StatusCode, Value) {
+        let body = event.to_string();
+        let signature = stripe::sign(body.as_bytes(), TEST_STRIPE_WEBHOOK_SECRET, OffsetDateTime::now_utc().unix_timestamp());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/v1/webhooks/stripe")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(stripe::SIGNATURE_HEADER, signature)
+            .body(Body::from(body))
+            .unwrap();
+        self.send(request).await
+    }
+
+    async fn send(&self, mut request: Request<Body>) -> (StatusCode, Value) {
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    // Registers a user through the API and signs them in, returning their session token and id
+    pub async fn sign_up(&self, email: &str) -> (String, Uuid) {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                "/v1/register",
+                None,
+                Some(json!({ "email": email, "password": TEST_PASSWORD, "name": "Test User" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "registration failed: {}", body);
+
+        let (status, body) = self
+            .request(Method::POST, "/v1/auth", None, Some(json!({ "email": email, "password": TEST_PASSWORD })))
+            .await;
+        assert_eq!(status, StatusCode::OK, "sign-in failed: {}", body);
+
+        let token = body["token"].as_str().unwrap().to_string();
+        let user_id = body["user"]["id"].as_str().unwrap().parse().unwrap();
+        (token, user_id)
+    }
+}
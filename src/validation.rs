@@ -0,0 +1,91 @@
+use axum::extract::{FromRequest, Request};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::error::AppError;
+
+// Bounds on passwords; bcrypt ignores everything past the first 72 bytes, so a longer password
+// would be weaker than it looks
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_BYTES: usize = 72;
+
+// A JSON body that has also passed its `Validate` rules. Bodies that aren't valid JSON for `T` are
+// rejected as `Json` would reject them; bodies that break a rule get `400 Bad Request` listing
+// every failing field.
+pub struct ValidatedJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await.map_err(IntoResponse::into_response)?;
+        value.validate().map_err(|errors| AppError::Validation(field_errors(&errors)).into_response())?;
+        Ok(Self(value))
+    }
+}
+
+// Messages per field, e.g. `{"password": ["Password must be at least 8 characters long"]}`
+fn field_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| match &error.message {
+                    Some(message) => message.to_string(),
+                    None => format!("Failed the {} check", error.code),
+                })
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect()
+}
+
+fn invalid(code: &'static str, message: String) -> ValidationError {
+    ValidationError::new(code).with_message(Cow::Owned(message))
+}
+
+// The password policy, applied wherever a password is set
+pub fn password_policy(password: &str) -> Result<(), ValidationError> {
+    if password.len() < MIN_PASSWORD_LENGTH {
+        return Err(invalid("password_too_short", format!("Password must be at least {} characters long", MIN_PASSWORD_LENGTH)));
+    }
+    if password.len() > MAX_PASSWORD_BYTES {
+        return Err(invalid("password_too_long", format!("Password must be at most {} bytes long", MAX_PASSWORD_BYTES)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::CreateUser;
+
+    #[test]
+    fn test_every_failing_field_is_reported() {
+        let user = CreateUser {
+            email: "not-an-email".to_string(),
+            password: "short".to_string(),
+            name: "Test User".to_string(),
+            metadata: None,
+        };
+        let errors = field_errors(&user.validate().unwrap_err());
+        assert_eq!(errors.keys().collect::<Vec<_>>(), ["email", "password"]);
+        assert_eq!(errors["password"], ["Password must be at least 8 characters long"]);
+    }
+
+    #[test]
+    fn test_passwords_are_bounded() {
+        assert!(password_policy("correct horse battery").is_ok());
+        assert!(password_policy(&"x".repeat(MAX_PASSWORD_BYTES + 1)).is_err());
+    }
+}